@@ -0,0 +1,277 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "std")]
+mod utils;
+
+use core::cell::{Cell, RefCell};
+use core::pin::Pin;
+use core::str;
+use core::task::Poll;
+use log::{debug, info};
+
+use smoltcp::iface::{Interface, InterfaceBuilder, NeighborCache, SocketHandle};
+use smoltcp::phy::{Loopback, Medium};
+use smoltcp::socket::tcp;
+use smoltcp::wire::{EthernetAddress, IpAddress, IpCidr};
+
+use executor::{poll_fn, Executor};
+
+/// A minimal single-threaded executor driven by a software timer.
+///
+/// Time only advances when no task is ready to run, so the whole exchange below completes
+/// instantly, and a missed wakeup shows up as a panic rather than as a hang.
+mod executor {
+    use core::cell::Cell;
+    use core::future::Future;
+    use core::pin::Pin;
+    use core::sync::atomic::{AtomicBool, Ordering};
+    use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+    use smoltcp::time::Instant;
+
+    // Each waker points at the `AtomicBool` that marks its task as ready to be polled.
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, wake, wake, drop);
+
+    unsafe fn clone(flag: *const ()) -> RawWaker {
+        RawWaker::new(flag, &VTABLE)
+    }
+
+    unsafe fn wake(flag: *const ()) {
+        (*(flag as *const AtomicBool)).store(true, Ordering::SeqCst)
+    }
+
+    unsafe fn drop(_flag: *const ()) {}
+
+    pub struct Executor {
+        now: Cell<Instant>,
+        deadline: Cell<Option<Instant>>,
+    }
+
+    impl Executor {
+        pub fn new() -> Executor {
+            Executor {
+                now: Cell::new(Instant::from_millis(0)),
+                deadline: Cell::new(None),
+            }
+        }
+
+        /// Return the current time of the software clock.
+        pub fn now(&self) -> Instant {
+            self.now.get()
+        }
+
+        /// Ask for every pending task to be woken up once the clock reaches `at`.
+        pub fn arm_timer(&self, at: Instant) {
+            match self.deadline.get() {
+                Some(deadline) if deadline <= at => {}
+                _ => self.deadline.set(Some(at)),
+            }
+        }
+
+        /// Run all tasks to completion.
+        pub fn run<const N: usize>(&self, mut tasks: [Pin<&mut dyn Future<Output = ()>>; N]) {
+            #[allow(clippy::declare_interior_mutable_const)]
+            const READY: AtomicBool = AtomicBool::new(true);
+            let ready = [READY; N];
+            let mut done = [false; N];
+
+            while done.iter().any(|done| !done) {
+                let mut polled = false;
+                for (index, task) in tasks.iter_mut().enumerate() {
+                    if done[index] || !ready[index].swap(false, Ordering::SeqCst) {
+                        continue;
+                    }
+                    polled = true;
+
+                    let raw =
+                        RawWaker::new(&ready[index] as *const AtomicBool as *const (), &VTABLE);
+                    let waker = unsafe { Waker::from_raw(raw) };
+                    let mut cx = Context::from_waker(&waker);
+                    if task.as_mut().poll(&mut cx).is_ready() {
+                        done[index] = true;
+                    }
+                }
+
+                if !polled {
+                    // Nothing is ready, so sleep until the timer fires. Timer wakeups are not
+                    // associated with a particular task; waking all of them is merely spurious.
+                    let deadline = self
+                        .deadline
+                        .take()
+                        .expect("all tasks are waiting, but no timer is armed");
+                    if deadline > self.now.get() {
+                        self.now.set(deadline);
+                    }
+                    for flag in ready.iter() {
+                        flag.store(true, Ordering::SeqCst);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Create a future out of a closure that is polled with the task context.
+    pub fn poll_fn<T, F: FnMut(&mut Context<'_>) -> Poll<T>>(f: F) -> PollFn<F> {
+        PollFn(f)
+    }
+
+    pub struct PollFn<F>(F);
+
+    impl<F> Unpin for PollFn<F> {}
+
+    impl<T, F: FnMut(&mut Context<'_>) -> Poll<T>> Future for PollFn<F> {
+        type Output = T;
+
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+            (self.get_mut().0)(cx)
+        }
+    }
+}
+
+type Iface<'a> = RefCell<Interface<'a, Loopback>>;
+
+/// Poll the interface whenever it has something to do.
+async fn net_task(iface: &Iface<'_>, executor: &Executor, done: &Cell<bool>) {
+    poll_fn(|cx| {
+        if done.get() {
+            return Poll::Ready(());
+        }
+
+        let mut iface = iface.borrow_mut();
+        let timestamp = executor.now();
+        match iface.poll(timestamp) {
+            Ok(_) => {}
+            Err(e) => {
+                debug!("poll error: {}", e);
+            }
+        }
+
+        // Register the waker before querying the deadline: a task that touches a socket after
+        // this point will wake us, and `poll_at` will then be queried again.
+        iface.register_poll_waker(cx.waker());
+        match iface.poll_at(timestamp) {
+            Some(at) if at <= timestamp => cx.waker().wake_by_ref(),
+            Some(at) => executor.arm_timer(at),
+            None => {}
+        }
+        Poll::Pending
+    })
+    .await
+}
+
+async fn server_task(iface: &Iface<'_>, handle: SocketHandle, done: &Cell<bool>) {
+    debug!("listening");
+    iface
+        .borrow_mut()
+        .get_socket::<tcp::Socket>(handle)
+        .listen(1234)
+        .unwrap();
+
+    let mut data = [0; 64];
+    let mut len = 0;
+    loop {
+        let received = poll_fn(|cx| {
+            let mut iface = iface.borrow_mut();
+            let socket = iface.get_socket::<tcp::Socket>(handle);
+            if socket.can_recv() {
+                Poll::Ready(Some(socket.recv_slice(&mut data[len..]).unwrap()))
+            } else if socket.may_recv()
+                || matches!(socket.state(), tcp::State::Listen | tcp::State::SynReceived)
+            {
+                // Checking readiness and registering the waker under the same borrow of the
+                // interface means that no packet can be processed in between.
+                socket.register_recv_waker(cx.waker());
+                Poll::Pending
+            } else {
+                Poll::Ready(None)
+            }
+        })
+        .await;
+
+        match received {
+            Some(size) => len += size,
+            None => break,
+        }
+    }
+    debug!("got {:?}", str::from_utf8(&data[..len]).unwrap());
+
+    iface.borrow_mut().get_socket::<tcp::Socket>(handle).close();
+    done.set(true);
+}
+
+async fn client_task(iface: &Iface<'_>, handle: SocketHandle) {
+    debug!("connecting");
+    {
+        let mut iface = iface.borrow_mut();
+        let (socket, cx) = iface.get_socket_and_context::<tcp::Socket>(handle);
+        socket
+            .connect(cx, (IpAddress::v4(127, 0, 0, 1), 1234), 65000)
+            .unwrap();
+    }
+
+    poll_fn(|cx| {
+        let mut iface = iface.borrow_mut();
+        let socket = iface.get_socket::<tcp::Socket>(handle);
+        if socket.can_send() {
+            debug!("sending");
+            socket.send_slice(b"0123456789abcdef").unwrap();
+            socket.close();
+            Poll::Ready(())
+        } else {
+            socket.register_send_waker(cx.waker());
+            Poll::Pending
+        }
+    })
+    .await
+}
+
+fn main() {
+    #[cfg(feature = "std")]
+    utils::setup_logging("");
+
+    let device = Loopback::new(Medium::Ethernet);
+
+    let mut neighbor_cache_entries = [None; 8];
+    let neighbor_cache = NeighborCache::new(&mut neighbor_cache_entries[..]);
+
+    let ip_addrs = [IpCidr::new(IpAddress::v4(127, 0, 0, 1), 8)];
+    let mut sockets: [_; 2] = Default::default();
+    let mut iface = InterfaceBuilder::new(device, &mut sockets[..])
+        .hardware_addr(EthernetAddress::default().into())
+        .neighbor_cache(neighbor_cache)
+        .ip_addrs(ip_addrs)
+        .finalize();
+
+    let server_socket = {
+        static mut TCP_SERVER_RX_DATA: [u8; 1024] = [0; 1024];
+        static mut TCP_SERVER_TX_DATA: [u8; 1024] = [0; 1024];
+        let tcp_rx_buffer = tcp::SocketBuffer::new(unsafe { &mut TCP_SERVER_RX_DATA[..] });
+        let tcp_tx_buffer = tcp::SocketBuffer::new(unsafe { &mut TCP_SERVER_TX_DATA[..] });
+        tcp::Socket::new(tcp_rx_buffer, tcp_tx_buffer)
+    };
+
+    let client_socket = {
+        static mut TCP_CLIENT_RX_DATA: [u8; 1024] = [0; 1024];
+        static mut TCP_CLIENT_TX_DATA: [u8; 1024] = [0; 1024];
+        let tcp_rx_buffer = tcp::SocketBuffer::new(unsafe { &mut TCP_CLIENT_RX_DATA[..] });
+        let tcp_tx_buffer = tcp::SocketBuffer::new(unsafe { &mut TCP_CLIENT_TX_DATA[..] });
+        tcp::Socket::new(tcp_rx_buffer, tcp_tx_buffer)
+    };
+
+    let server_handle = iface.add_socket(server_socket);
+    let client_handle = iface.add_socket(client_socket);
+
+    let iface = RefCell::new(iface);
+    let executor = Executor::new();
+    let done = Cell::new(false);
+
+    let mut net = net_task(&iface, &executor, &done);
+    let mut server = server_task(&iface, server_handle, &done);
+    let mut client = client_task(&iface, client_handle);
+    // The futures are shadowed by the pinned references, so they can never be moved again.
+    let net = unsafe { Pin::new_unchecked(&mut net) };
+    let server = unsafe { Pin::new_unchecked(&mut server) };
+    let client = unsafe { Pin::new_unchecked(&mut client) };
+    executor.run([net, server, client]);
+
+    info!("done at {}", executor.now())
+}
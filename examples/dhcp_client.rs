@@ -6,7 +6,7 @@ use std::collections::BTreeMap;
 use std::os::unix::io::AsRawFd;
 
 use smoltcp::iface::{Interface, InterfaceBuilder, NeighborCache, Routes};
-use smoltcp::socket::dhcpv4;
+use smoltcp::socket::{dhcpv4, dns};
 use smoltcp::time::Instant;
 use smoltcp::wire::{EthernetAddress, IpCidr, Ipv4Address, Ipv4Cidr};
 use smoltcp::{
@@ -53,6 +53,7 @@ fn main() {
     dhcp_socket.set_max_lease_duration(Some(Duration::from_secs(10)));
 
     let dhcp_handle = iface.add_socket(dhcp_socket);
+    let dns_handle = iface.add_socket(dns::Socket::new(&[], vec![]));
 
     loop {
         let timestamp = Instant::now();
@@ -61,6 +62,12 @@ fn main() {
         }
 
         let event = iface.get_socket::<dhcpv4::Socket>(dhcp_handle).poll();
+        if let Some(event) = &event {
+            // Keep the resolver in sync with the lease.
+            iface
+                .get_socket::<dns::Socket>(dns_handle)
+                .update_servers_from_dhcpv4(event);
+        }
         match event {
             None => {}
             Some(dhcpv4::Event::Configured(config)) => {
@@ -82,6 +89,14 @@ fn main() {
                         debug!("DNS server {}:    {}", i, s);
                     }
                 }
+                for (i, s) in config.ntp_servers.iter().enumerate() {
+                    if let Some(s) = s {
+                        debug!("NTP server {}:    {}", i, s);
+                    }
+                }
+                if let Some(domain_name) = config.domain_name {
+                    debug!("Domain name:     {}", domain_name);
+                }
             }
             Some(dhcpv4::Event::Deconfigured) => {
                 debug!("DHCP lost config!");
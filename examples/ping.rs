@@ -203,7 +203,7 @@ fn main() {
         }
 
         if socket.can_recv() {
-            let (payload, _) = socket.recv().unwrap();
+            let (payload, _, received_at) = socket.recv_with_timestamp().unwrap();
 
             match remote_addr {
                 IpAddress::Ipv4(_) => {
@@ -215,7 +215,7 @@ fn main() {
                         payload,
                         waiting_queue,
                         remote_addr,
-                        timestamp,
+                        received_at,
                         received
                     );
                 }
@@ -234,7 +234,7 @@ fn main() {
                         payload,
                         waiting_queue,
                         remote_addr,
-                        timestamp,
+                        received_at,
                         received
                     );
                 }
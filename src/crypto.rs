@@ -0,0 +1,227 @@
+//! Cryptographic primitives for the TCP Authentication Option and SYN cookies.
+//!
+//! The MACs of TCP-AO are those of the RustCrypto crates, built with the `socket-tcp-ao`
+//! feature; SipHash-2-4, which keys SYN cookies, is implemented here.
+
+use byteorder::{ByteOrder, LittleEndian};
+
+#[cfg(feature = "socket-tcp-ao")]
+pub(crate) use self::mac::{Aes128Cmac, HmacSha1, AES_BLOCK_LEN, SHA1_LEN};
+
+#[cfg(feature = "socket-tcp-ao")]
+mod mac {
+    use aes::Aes128;
+    use cmac::Cmac;
+    use hmac::{Hmac, Mac};
+    use sha1::Sha1;
+
+    /// The length of a SHA-1 digest.
+    pub(crate) const SHA1_LEN: usize = 20;
+    /// The length of an AES block.
+    pub(crate) const AES_BLOCK_LEN: usize = 16;
+
+    /// HMAC (RFC 2104) instantiated with SHA-1.
+    pub(crate) struct HmacSha1(Hmac<Sha1>);
+
+    impl HmacSha1 {
+        pub(crate) fn new(key: &[u8]) -> HmacSha1 {
+            // HMAC accepts keys of any length.
+            HmacSha1(Hmac::new_from_slice(key).unwrap())
+        }
+
+        pub(crate) fn update(&mut self, data: &[u8]) {
+            self.0.update(data)
+        }
+
+        pub(crate) fn finalize(self) -> [u8; SHA1_LEN] {
+            self.0.finalize().into_bytes().into()
+        }
+    }
+
+    /// AES-CMAC (RFC 4493).
+    pub(crate) struct Aes128Cmac(Cmac<Aes128>);
+
+    impl Aes128Cmac {
+        pub(crate) fn new(key: &[u8; AES_BLOCK_LEN]) -> Aes128Cmac {
+            Aes128Cmac(Mac::new(key.into()))
+        }
+
+        pub(crate) fn update(&mut self, data: &[u8]) {
+            self.0.update(data)
+        }
+
+        pub(crate) fn finalize(self) -> [u8; AES_BLOCK_LEN] {
+            self.0.finalize().into_bytes().into()
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        fn hmac_sha1(key: &[u8], data: &[u8]) -> [u8; SHA1_LEN] {
+            let mut mac = HmacSha1::new(key);
+            mac.update(data);
+            mac.finalize()
+        }
+
+        fn cmac(key: &[u8; AES_BLOCK_LEN], data: &[u8]) -> [u8; AES_BLOCK_LEN] {
+            let mut mac = Aes128Cmac::new(key);
+            mac.update(data);
+            mac.finalize()
+        }
+
+        #[test]
+        fn test_hmac_sha1() {
+            // RFC 2202, test cases 1, 2 and 6.
+            assert_eq!(
+                hmac_sha1(&[0x0b; 20], b"Hi There"),
+                [
+                    0xb6, 0x17, 0x31, 0x86, 0x55, 0x05, 0x72, 0x64, 0xe2, 0x8b, 0xc0, 0xb6, 0xfb,
+                    0x37, 0x8c, 0x8e, 0xf1, 0x46, 0xbe, 0x00
+                ]
+            );
+            assert_eq!(
+                hmac_sha1(b"Jefe", b"what do ya want for nothing?"),
+                [
+                    0xef, 0xfc, 0xdf, 0x6a, 0xe5, 0xeb, 0x2f, 0xa2, 0xd2, 0x74, 0x16, 0xd5, 0xf1,
+                    0x84, 0xdf, 0x9c, 0x25, 0x9a, 0x7c, 0x79
+                ]
+            );
+            assert_eq!(
+                hmac_sha1(
+                    &[0xaa; 80],
+                    b"Test Using Larger Than Block-Size Key - Hash Key First"
+                ),
+                [
+                    0xaa, 0x4a, 0xe5, 0xe1, 0x52, 0x72, 0xd0, 0x0e, 0x95, 0x70, 0x56, 0x37, 0xce,
+                    0x8a, 0x3b, 0x55, 0xed, 0x40, 0x21, 0x12
+                ]
+            );
+        }
+
+        static CMAC_KEY: [u8; 16] = [
+            0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6, 0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf,
+            0x4f, 0x3c,
+        ];
+
+        static CMAC_MESSAGE: [u8; 64] = [
+            0x6b, 0xc1, 0xbe, 0xe2, 0x2e, 0x40, 0x9f, 0x96, 0xe9, 0x3d, 0x7e, 0x11, 0x73, 0x93,
+            0x17, 0x2a, 0xae, 0x2d, 0x8a, 0x57, 0x1e, 0x03, 0xac, 0x9c, 0x9e, 0xb7, 0x6f, 0xac,
+            0x45, 0xaf, 0x8e, 0x51, 0x30, 0xc8, 0x1c, 0x46, 0xa3, 0x5c, 0xe4, 0x11, 0xe5, 0xfb,
+            0xc1, 0x19, 0x1a, 0x0a, 0x52, 0xef, 0xf6, 0x9f, 0x24, 0x45, 0xdf, 0x4f, 0x9b, 0x17,
+            0xad, 0x2b, 0x41, 0x7b, 0xe6, 0x6c, 0x37, 0x10,
+        ];
+
+        #[test]
+        fn test_aes128_cmac() {
+            // RFC 4493, examples 1 to 4.
+            assert_eq!(
+                cmac(&CMAC_KEY, &CMAC_MESSAGE[..0]),
+                [
+                    0xbb, 0x1d, 0x69, 0x29, 0xe9, 0x59, 0x37, 0x28, 0x7f, 0xa3, 0x7d, 0x12, 0x9b,
+                    0x75, 0x67, 0x46
+                ]
+            );
+            assert_eq!(
+                cmac(&CMAC_KEY, &CMAC_MESSAGE[..16]),
+                [
+                    0x07, 0x0a, 0x16, 0xb4, 0x6b, 0x4d, 0x41, 0x44, 0xf7, 0x9b, 0xdd, 0x9d, 0xd0,
+                    0x4a, 0x28, 0x7c
+                ]
+            );
+            assert_eq!(
+                cmac(&CMAC_KEY, &CMAC_MESSAGE[..40]),
+                [
+                    0xdf, 0xa6, 0x67, 0x47, 0xde, 0x9a, 0xe6, 0x30, 0x30, 0xca, 0x32, 0x61, 0x14,
+                    0x97, 0xc8, 0x27
+                ]
+            );
+            assert_eq!(
+                cmac(&CMAC_KEY, &CMAC_MESSAGE[..]),
+                [
+                    0x51, 0xf0, 0xbe, 0xbf, 0x7e, 0x3b, 0x9d, 0x92, 0xfc, 0x49, 0x74, 0x17, 0x79,
+                    0x36, 0x3c, 0xfe
+                ]
+            );
+        }
+
+        #[test]
+        fn test_aes128_cmac_split_updates() {
+            let mut mac = Aes128Cmac::new(&CMAC_KEY);
+            for chunk in CMAC_MESSAGE[..40].chunks(3) {
+                mac.update(chunk);
+            }
+            assert_eq!(mac.finalize(), cmac(&CMAC_KEY, &CMAC_MESSAGE[..40]));
+        }
+    }
+}
+
+/// Compute the SipHash-2-4 pseudorandom function of `data` under a 128-bit `key`.
+pub(crate) fn siphash24(key: &[u8; 16], data: &[u8]) -> u64 {
+    fn round(v: &mut [u64; 4]) {
+        v[0] = v[0].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(13) ^ v[0];
+        v[0] = v[0].rotate_left(32);
+        v[2] = v[2].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(16) ^ v[2];
+        v[0] = v[0].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(21) ^ v[0];
+        v[2] = v[2].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(17) ^ v[2];
+        v[2] = v[2].rotate_left(32);
+    }
+
+    fn compress(v: &mut [u64; 4], m: u64) {
+        v[3] ^= m;
+        round(v);
+        round(v);
+        v[0] ^= m;
+    }
+
+    let k0 = LittleEndian::read_u64(&key[..8]);
+    let k1 = LittleEndian::read_u64(&key[8..]);
+    let mut v = [
+        k0 ^ 0x736f6d6570736575,
+        k1 ^ 0x646f72616e646f6d,
+        k0 ^ 0x6c7967656e657261,
+        k1 ^ 0x7465646279746573,
+    ];
+
+    let mut chunks = data.chunks_exact(8);
+    for chunk in &mut chunks {
+        compress(&mut v, LittleEndian::read_u64(chunk));
+    }
+    // The final word holds the remaining octets and the low byte of the message length.
+    let mut last = [0; 8];
+    last[..chunks.remainder().len()].copy_from_slice(chunks.remainder());
+    last[7] = data.len() as u8;
+    compress(&mut v, LittleEndian::read_u64(&last));
+
+    v[2] ^= 0xff;
+    for _ in 0..4 {
+        round(&mut v);
+    }
+    v[0] ^ v[1] ^ v[2] ^ v[3]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_siphash24() {
+        // Test vectors from the reference implementation, with the key 00 01 02 ... 0f.
+        let key = [
+            0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d,
+            0x0e, 0x0f,
+        ];
+        let message: [u8; 15] = [
+            0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d,
+            0x0e,
+        ];
+        assert_eq!(siphash24(&key, &[]), 0x726fdb47dd0e0e31);
+        assert_eq!(siphash24(&key, &message[..8]), 0x93f5f5799a932462);
+        assert_eq!(siphash24(&key, &message), 0xa129ca6149be45e5);
+    }
+}
@@ -70,6 +70,57 @@ impl<'a> PacketAssembler<'a> {
         Ok(())
     }
 
+    /// Start with saving fragments of a packet whose total size isn't known yet, but is at
+    /// most `max_size`. The buffer is used up to that size, and the total size is set once it
+    /// is known with [Self::set_total_size].
+    ///
+    /// An owned buffer is not allocated up front, but grows with the fragments added.
+    pub(crate) fn start_with_max_size(
+        &mut self,
+        max_size: usize,
+        start_time: Instant,
+    ) -> Result<()> {
+        let size = match &mut self.buffer {
+            ManagedSlice::Borrowed(b) => b.len().min(max_size),
+            #[cfg(any(feature = "std", feature = "alloc"))]
+            ManagedSlice::Owned(b) => {
+                b.clear();
+                max_size
+            }
+        };
+
+        self.assembler = AssemblerState::Assembling {
+            assembler: Assembler::new(size),
+            total_size: size,
+            last_updated: start_time,
+            started_on: start_time,
+        };
+
+        Ok(())
+    }
+
+    /// Set the total size of the packet that is being reassembled.
+    ///
+    /// # Errors
+    ///
+    /// - Returns [`Error::PacketAssemblerNotInit`] when the assembler was not initialized.
+    /// - Returns [`Error::PacketAssemblerBufferTooSmall`] when the size is larger than the size
+    ///   the assembler was started with.
+    pub(crate) fn set_total_size(&mut self, size: usize) -> Result<()> {
+        match self.assembler {
+            AssemblerState::NotInit => Err(Error::PacketAssemblerNotInit),
+            AssemblerState::Assembling {
+                ref mut total_size, ..
+            } => {
+                if size > *total_size {
+                    return Err(Error::PacketAssemblerBufferTooSmall);
+                }
+                *total_size = size;
+                Ok(())
+            }
+        }
+    }
+
     /// Add a fragment into the packet that is being reassembled.
     ///
     /// # Errors
@@ -93,6 +144,15 @@ impl<'a> PacketAssembler<'a> {
                 }
 
                 let len = data.len();
+                match &mut self.buffer {
+                    ManagedSlice::Borrowed(_) => (),
+                    #[cfg(any(feature = "std", feature = "alloc"))]
+                    ManagedSlice::Owned(b) => {
+                        if b.len() < offset + len {
+                            b.resize(offset + len, 0);
+                        }
+                    }
+                }
                 self.buffer[offset..][..len].copy_from_slice(data);
 
                 match assembler.add(offset, data.len()) {
@@ -162,6 +222,11 @@ impl<'a> PacketAssembler<'a> {
         self.assembler == AssemblerState::NotInit
     }
 
+    /// Returns the number of octets of buffer the packet assembler holds.
+    fn buffered_len(&self) -> usize {
+        self.buffer.len()
+    }
+
     /// Returns the [`Instant`] when the packet assembler was started.
     ///
     /// # Errors
@@ -199,6 +264,8 @@ impl<'a> PacketAssembler<'a> {
 pub struct PacketAssemblerSet<'a, Key: Eq + Ord + Clone + Copy> {
     packet_buffer: ManagedSlice<'a, PacketAssembler<'a>>,
     index_buffer: ManagedMap<'a, Key, usize>,
+    max_packets: usize,
+    max_bytes: usize,
 }
 
 impl<'a, K: Eq + Ord + Clone + Copy> PacketAssemblerSet<'a, K> {
@@ -244,9 +311,23 @@ impl<'a, K: Eq + Ord + Clone + Copy> PacketAssemblerSet<'a, K> {
         Self {
             packet_buffer,
             index_buffer,
+            max_packets: u8::MAX as usize,
+            max_bytes: usize::MAX,
         }
     }
 
+    /// Limit the number of packets reassembled at once, and the octets of buffer they hold
+    /// altogether.
+    ///
+    /// The limits only apply to [add_fragment], which discards the oldest packets to stay
+    /// within them.
+    ///
+    /// [add_fragment]: #method.add_fragment
+    pub(crate) fn set_limits(&mut self, max_packets: usize, max_bytes: usize) {
+        self.max_packets = max_packets;
+        self.max_bytes = max_bytes;
+    }
+
     /// Reserve a [`PacketAssembler`], which is linked to a specific key.
     /// Returns the reserved fragments assembler.
     ///
@@ -255,20 +336,10 @@ impl<'a, K: Eq + Ord + Clone + Copy> PacketAssemblerSet<'a, K> {
     /// - Returns [`Error::PacketAssemblerSetFull`] when every [`PacketAssembler`] in the buffer is used (only
     /// when the non allocating version of is used).
     pub(crate) fn reserve_with_key(&mut self, key: &K) -> Result<&mut PacketAssembler<'a>> {
-        // Check how many WIP reassemblies we have.
-        // The limit is currently set to 255.
-        if self.index_buffer.len() == u8::MAX as usize {
+        if self.is_full() {
             return Err(Error::PacketAssemblerSetFull);
         }
 
-        if self.packet_buffer.len() == self.index_buffer.len() {
-            match &mut self.packet_buffer {
-                ManagedSlice::Borrowed(_) => return Err(Error::PacketAssemblerSetFull),
-                #[cfg(any(feature = "std", feature = "alloc"))]
-                ManagedSlice::Owned(b) => (),
-            }
-        }
-
         let i = self
             .get_free_packet_assembler()
             .ok_or(Error::PacketAssemblerSetFull)?;
@@ -280,19 +351,159 @@ impl<'a, K: Eq + Ord + Clone + Copy> PacketAssemblerSet<'a, K> {
         }
     }
 
-    /// Return the first free packet assembler available from the cache.
-    fn get_free_packet_assembler(&mut self) -> Option<usize> {
-        match &mut self.packet_buffer {
-            ManagedSlice::Borrowed(_) => (),
+    /// Add the fragment `data` at `offset` to the packet linked to `key`, which is at most
+    /// `max_size` octets long, and return whether the packet is complete.
+    ///
+    /// The last fragment of the packet is added together with the `header` placed at its
+    /// start, which sets the size of the packet. The oldest packets are discarded as needed to
+    /// stay within the limits of the set, and the packet is discarded if the fragment can't be
+    /// added to it.
+    pub(crate) fn add_fragment(
+        &mut self,
+        key: &K,
+        max_size: usize,
+        header: Option<&[u8]>,
+        data: &[u8],
+        offset: usize,
+        now: Instant,
+    ) -> Result<bool> {
+        let res = (|| {
+            self.reserve_evicting(key, max_size, now)?;
+            self.make_room(key, offset + data.len())?;
+            let assembler = self.get_packet_assembler_mut(key)?;
+            if let Some(header) = header {
+                assembler.set_total_size(offset + data.len())?;
+                assembler.add(header, 0, now)?;
+            }
+            assembler.add(data, offset, now)
+        })();
+
+        if res.is_err() {
+            if let Ok(assembler) = self.get_packet_assembler_mut(key) {
+                assembler.mark_discarded();
+            }
+            self.remove_discarded();
+        }
+        res
+    }
+
+    /// Return the packet assembler linked to `key`, or reserve one for it and start it with
+    /// [PacketAssembler::start_with_max_size].
+    ///
+    /// When the set already holds as many packets as it is limited to, the oldest one is
+    /// discarded to make room for the new one.
+    ///
+    /// # Errors
+    ///
+    /// - Returns [`Error::PacketAssemblerSetFull`] when the set is limited to no packets.
+    fn reserve_evicting(
+        &mut self,
+        key: &K,
+        max_size: usize,
+        now: Instant,
+    ) -> Result<&mut PacketAssembler<'a>> {
+        if self.index_buffer.get(key).is_none() {
+            while self.is_full() {
+                if !self.remove_oldest(key) {
+                    return Err(Error::PacketAssemblerSetFull);
+                }
+            }
+            self.reserve_with_key(key)?
+                .start_with_max_size(max_size, now)?;
+        }
+        self.get_packet_assembler_mut(key)
+    }
+
+    /// Make sure the packet linked to `key` can hold the octets up to `end`, without the set
+    /// holding more octets than it is limited to.
+    ///
+    /// The oldest other packets are discarded until the buffer fits.
+    ///
+    /// # Errors
+    ///
+    /// - Returns [`Error::PacketAssemblerSetKeyNotFound`] when the `key` was not found.
+    /// - Returns [`Error::PacketAssemblerBufferTooSmall`] when the packet doesn't fit even
+    ///   without any other packets.
+    fn make_room(&mut self, key: &K, end: usize) -> Result<()> {
+        let held = self.get_packet_assembler_mut(key)?.buffered_len();
+        if end <= held {
+            return Ok(());
+        }
+        while self.buffered_len() + (end - held) > self.max_bytes {
+            if !self.remove_oldest(key) {
+                return Err(Error::PacketAssemblerBufferTooSmall);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns `true` when no more packets can be reassembled at once.
+    fn is_full(&self) -> bool {
+        // Check how many WIP reassemblies we have.
+        // The limit is 255, unless set lower with `set_limits`.
+        if self.index_buffer.len() >= self.max_packets.min(u8::MAX as usize) {
+            return true;
+        }
+
+        match &self.packet_buffer {
+            ManagedSlice::Borrowed(b) => b.len() == self.index_buffer.len(),
             #[cfg(any(feature = "std", feature = "alloc"))]
-            ManagedSlice::Owned(b) => b.push(PacketAssembler::new(vec![])),
+            ManagedSlice::Owned(_) => false,
         }
+    }
+
+    /// Returns the number of octets of buffer held by the packets being reassembled.
+    fn buffered_len(&self) -> usize {
+        self.index_buffer
+            .iter()
+            .map(|(_, i)| self.packet_buffer[*i].buffered_len())
+            .sum()
+    }
+
+    /// Discard the packet that started being reassembled first, other than the one linked to
+    /// `key`, and return whether there was one.
+    fn remove_oldest(&mut self, key: &K) -> bool {
+        let mut oldest: Option<(K, Instant)> = None;
+        for (k, i) in self.index_buffer.iter() {
+            if k == key {
+                continue;
+            }
+            if let Ok(started_on) = self.packet_buffer[*i].start_time() {
+                if !matches!(oldest, Some((_, oldest)) if oldest <= started_on) {
+                    oldest = Some((*k, started_on));
+                }
+            }
+        }
+
+        match oldest {
+            Some((k, _)) => {
+                net_debug!("discarding the oldest packet being reassembled");
+                if let Some(i) = self.index_buffer.remove(&k) {
+                    self.packet_buffer[i].mark_discarded();
+                }
+                true
+            }
+            None => false,
+        }
+    }
 
-        self.packet_buffer
+    /// Return the first free packet assembler available from the cache.
+    fn get_free_packet_assembler(&mut self) -> Option<usize> {
+        let free = self
+            .packet_buffer
             .iter()
             .enumerate()
             .find(|(_, b)| b.is_free())
-            .map(|(i, _)| i)
+            .map(|(i, _)| i);
+
+        match &mut self.packet_buffer {
+            ManagedSlice::Borrowed(_) => free,
+            #[cfg(any(feature = "std", feature = "alloc"))]
+            ManagedSlice::Owned(b) => free.or_else(|| {
+                b.push(PacketAssembler::new(alloc::vec::Vec::new()));
+                Some(b.len() - 1)
+            }),
+        }
     }
 
     /// Return a mutable slice to a packet assembler.
@@ -404,6 +615,24 @@ mod tests {
         );
     }
 
+    #[test]
+    fn packet_assembler_unknown_size() {
+        let mut storage = [0u8; 16];
+        let mut p_assembler = PacketAssembler::new(&mut storage[..]);
+
+        p_assembler
+            .start_with_max_size(usize::MAX, Instant::now())
+            .unwrap();
+        assert_eq!(p_assembler.add(b"Wor", 6, Instant::now()), Ok(false));
+        assert_eq!(
+            p_assembler.set_total_size(17),
+            Err(Error::PacketAssemblerBufferTooSmall)
+        );
+        assert_eq!(p_assembler.set_total_size(9), Ok(()));
+        assert_eq!(p_assembler.add(b"Hello ", 0, Instant::now()), Ok(true));
+        assert_eq!(p_assembler.assemble(), Ok(&b"Hello Wor"[..]));
+    }
+
     #[test]
     fn packet_assembler_overlap() {
         let mut storage = [0u8; 5];
@@ -470,6 +699,55 @@ mod tests {
         assert!(set.reserve_with_key(&key).is_ok());
     }
 
+    #[test]
+    fn packet_assembler_owned_grows() {
+        let mut p_assembler = PacketAssembler::new(vec![]);
+
+        p_assembler
+            .start_with_max_size(usize::MAX, Instant::now())
+            .unwrap();
+        assert_eq!(p_assembler.buffered_len(), 0);
+        assert_eq!(p_assembler.add(b"Hello ", 0, Instant::now()), Ok(false));
+        assert_eq!(p_assembler.buffered_len(), 6);
+        assert_eq!(p_assembler.set_total_size(9), Ok(()));
+        assert_eq!(p_assembler.add(b"Wor", 6, Instant::now()), Ok(true));
+        assert_eq!(p_assembler.assemble(), Ok(&b"Hello Wor"[..]));
+    }
+
+    #[test]
+    fn packet_assembler_set_evicts_oldest() {
+        let mut set = PacketAssemblerSet::<'_, _>::new(vec![], std::collections::BTreeMap::new());
+        set.set_limits(2, 10);
+
+        // Only two packets are reassembled at once.
+        for id in 0..3 {
+            let now = Instant::from_millis(id as i64);
+            set.reserve_evicting(&Key { id }, usize::MAX, now).unwrap();
+            set.make_room(&Key { id }, 4).unwrap();
+            set.get_packet_assembler_mut(&Key { id })
+                .unwrap()
+                .add(b"Rust", 0, now)
+                .unwrap();
+        }
+        assert!(set.get_packet_assembler_mut(&Key { id: 0 }).is_err());
+        assert!(set.get_packet_assembler_mut(&Key { id: 1 }).is_ok());
+        assert!(set.get_packet_assembler_mut(&Key { id: 2 }).is_ok());
+        assert_eq!(set.buffered_len(), 8);
+
+        // Growing a packet past the octet limit discards the others.
+        set.make_room(&Key { id: 2 }, 10).unwrap();
+        assert!(set.get_packet_assembler_mut(&Key { id: 1 }).is_err());
+        assert_eq!(
+            set.make_room(&Key { id: 2 }, 11),
+            Err(Error::PacketAssemblerBufferTooSmall)
+        );
+
+        // The assemblers of discarded packets are reused.
+        set.reserve_evicting(&Key { id: 3 }, usize::MAX, Instant::from_millis(3))
+            .unwrap();
+        assert_eq!(set.packet_buffer.len(), 2);
+    }
+
     #[test]
     fn packet_assembler_set_assembling_many() {
         let mut buf = [0u8, 127];
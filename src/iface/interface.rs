@@ -2,14 +2,21 @@
 // of RFC 1122 that discuss Ethernet, ARP and IP for any IPv4 work
 // and RFCs 8200 and 4861 for any IPv6 and NDISC work.
 
-use core::cmp;
+#[cfg(feature = "async")]
+use core::task::Waker;
+use core::{cmp, mem};
 use managed::{ManagedMap, ManagedSlice};
 
 #[allow(unused)]
-#[cfg(feature = "proto-sixlowpan")]
-use super::fragmentation::PacketAssemblerSet;
+#[cfg(any(
+    feature = "proto-sixlowpan",
+    feature = "proto-ipv4-fragmentation",
+    feature = "proto-ipv6-fragmentation"
+))]
+use super::fragmentation::{PacketAssembler, PacketAssemblerSet};
+use super::rate_limit::RateLimiter;
 use super::socket_set::SocketSet;
-use super::{SocketHandle, SocketStorage};
+use super::{SocketHandle, SocketSetFull, SocketStorage};
 use crate::iface::Routes;
 #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
 use crate::iface::{NeighborAnswer, NeighborCache};
@@ -38,6 +45,68 @@ macro_rules! check {
             }
         }
     };
+    // Also report the dropped packet to the drop observer, if there is one.
+    ($e:expr, $iface:ident, $reason:expr, $ip_repr:expr) => {
+        match $e {
+            Ok(x) => x,
+            Err(_) => {
+                #[cfg(not(feature = "defmt"))]
+                net_trace!(concat!("iface: malformed ", stringify!($e)));
+                #[cfg(feature = "defmt")]
+                net_trace!("iface: malformed");
+                if $iface.drop_observer.is_some() {
+                    $iface.observe_drop($reason, $ip_repr);
+                }
+                return Default::default();
+            }
+        }
+    };
+}
+
+/// The reason why an incoming packet was dropped.
+///
+/// See [Interface::set_drop_observer].
+///
+/// IPv4 and IPv6 fragments that can't be reassembled are reported as [Fragment](#variant.Fragment);
+/// packets whose reassembly times out are not reported.
+///
+/// [Interface::set_drop_observer]: struct.Interface.html#method.set_drop_observer
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum DropReason {
+    /// The packet is not addressed to the interface, and no socket accepts it anyway.
+    NoRoute,
+    /// No socket accepts the packet, and the protocol isn't handled by the interface itself.
+    NoSocket,
+    /// The checksum of the IP header or of the payload is wrong.
+    Checksum,
+    /// The IP header, an extension header or the payload is malformed or truncated, or has
+    /// an unrecognized option that requires the packet to be discarded.
+    Malformed,
+    /// The source address is not a valid unicast address.
+    Filtered,
+    /// The socket accepting the packet has no room for it in its receive buffer.
+    BufferFull,
+    /// The packet is a TCP SYN, and there are too many half-open connections already.
+    HalfOpenLimit,
+    /// The packet is an IPv4 or IPv6 fragment that can't be reassembled, and that no raw
+    /// socket accepts.
+    Fragment,
+}
+
+/// A function called with the dropped incoming packets.
+type DropObserver<'a> = &'a mut dyn FnMut(DropReason, &IpRepr);
+
+impl DropReason {
+    /// The reason for a packet that failed to parse, given whether its checksum is wrong.
+    fn parse_failure(bad_checksum: bool) -> DropReason {
+        if bad_checksum {
+            DropReason::Checksum
+        } else {
+            DropReason::Malformed
+        }
+    }
 }
 
 /// A  network interface.
@@ -49,6 +118,42 @@ pub struct Interface<'a, DeviceT: for<'d> Device<'d>> {
     device: DeviceT,
     sockets: SocketSet<'a>,
     inner: InterfaceInner<'a>,
+    #[cfg(feature = "async")]
+    poll_waker: WakerRegistration,
+}
+
+/// An IPv4 packet larger than the MTU, whose fragments are being sent.
+#[cfg(feature = "proto-ipv4-fragmentation")]
+struct Ipv4OutPacket<'a> {
+    /// The whole packet, header included.
+    buffer: ManagedSlice<'a, u8>,
+    /// Length of the packet, or zero if there is none.
+    packet_len: usize,
+    /// Octets of the payload sent so far.
+    sent_len: usize,
+    /// Identification shared by the fragments.
+    ident: u16,
+    #[cfg(feature = "medium-ethernet")]
+    dst_hardware_addr: EthernetAddress,
+}
+
+#[cfg(feature = "proto-ipv4-fragmentation")]
+impl<'a> Ipv4OutPacket<'a> {
+    fn new(buffer: ManagedSlice<'a, u8>) -> Self {
+        Ipv4OutPacket {
+            buffer,
+            packet_len: 0,
+            sent_len: 0,
+            ident: 0,
+            #[cfg(feature = "medium-ethernet")]
+            dst_hardware_addr: EthernetAddress::default(),
+        }
+    }
+
+    /// Return whether there are fragments left to send.
+    fn is_pending(&self) -> bool {
+        self.packet_len != 0
+    }
 }
 
 /// The device independent part of an Ethernet network interface.
@@ -63,8 +168,26 @@ pub struct InterfaceInner<'a> {
     now: Instant,
     #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
     neighbor_cache: Option<NeighborCache<'a>>,
+    /// Limits how many ARP requests and neighbor solicitations are processed.
+    #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
+    neighbor_request_limiter: RateLimiter,
+    /// Number of ARP requests and neighbor solicitations dropped by the limiter.
+    #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
+    neighbor_requests_limited: u64,
     #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
     hardware_addr: Option<HardwareAddress>,
+    /// The only neighbor on a point-to-point link, which all unicast packets are sent to.
+    #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
+    peer_hardware_addr: Option<HardwareAddress>,
+    /// If the hardware address changed and has to be announced with gratuitous ARP, the
+    /// index of the next address to announce.
+    #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+    arp_announce_next: Option<usize>,
+    /// The header of the Ethernet frame being processed, for raw sockets.
+    #[cfg(all(feature = "medium-ethernet", feature = "socket-raw"))]
+    rx_ethernet_repr: Option<EthernetRepr>,
+    #[cfg(feature = "medium-ethernet")]
+    vlan_tags: EthernetVlanTags,
     #[cfg(feature = "medium-ieee802154")]
     sequence_no: u8,
     #[cfg(feature = "medium-ieee802154")]
@@ -73,11 +196,48 @@ pub struct InterfaceInner<'a> {
     #[cfg(feature = "proto-ipv4")]
     any_ip: bool,
     routes: Routes<'a>,
+    policy_routes: Routes<'a>,
+    accept_redirects: bool,
+    icmp_echo_reply: bool,
+    egress_limit: Option<usize>,
+    /// Maximum number of TCP sockets in the SYN-RECEIVED state.
+    #[cfg(feature = "socket-tcp")]
+    half_open_limit: Option<usize>,
+    /// Key of the SYN cookies, generated when the first one is sent.
+    #[cfg(feature = "socket-tcp")]
+    syn_cookie_secret: Option<[u8; 16]>,
+    #[cfg(feature = "proto-ipv6")]
+    ipv6_param_problems: bool,
     #[cfg(feature = "proto-igmp")]
     ipv4_multicast_groups: ManagedMap<'a, Ipv4Address, ()>,
     /// When to report for (all or) the next multicast group membership via IGMP
     #[cfg(feature = "proto-igmp")]
     igmp_report_state: IgmpReportState,
+    #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+    rarp_table: ManagedMap<'a, EthernetAddress, Ipv4Address>,
+    /// Number of packets the sockets emitted during the current call to `poll`.
+    egress_count: usize,
+    /// Storage slot of the socket to offer the first transmit opportunity to.
+    egress_next: usize,
+    /// Called for the packets dropped while processing ingress.
+    drop_observer: Option<DropObserver<'a>>,
+    /// Limits how many dropped packets are reported to the observer.
+    drop_report_limiter: RateLimiter,
+    /// Storage for the IPv4 packets being reassembled, if reassembly is enabled.
+    #[cfg(feature = "proto-ipv4-fragmentation")]
+    ipv4_fragments: Option<PacketAssemblerSet<'a, Ipv4FragKey>>,
+    /// The IPv4 packet that was just reassembled, and is yet to be processed.
+    #[cfg(feature = "proto-ipv4-fragmentation")]
+    ipv4_reassembled: Option<Ipv4FragKey>,
+    /// The IPv4 packet being sent in fragments.
+    #[cfg(feature = "proto-ipv4-fragmentation")]
+    ipv4_out_packet: Ipv4OutPacket<'a>,
+    /// Storage for the IPv6 packets being reassembled, if reassembly is enabled.
+    #[cfg(feature = "proto-ipv6-fragmentation")]
+    ipv6_fragments: Option<PacketAssemblerSet<'a, Ipv6FragKey>>,
+    /// The IPv6 packet that was just reassembled, and is yet to be processed.
+    #[cfg(feature = "proto-ipv6-fragmentation")]
+    ipv6_reassembled: Option<Ipv6FragKey>,
     rand: Rand,
 }
 
@@ -87,7 +247,13 @@ pub struct InterfaceBuilder<'a, DeviceT: for<'d> Device<'d>> {
     #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
     hardware_addr: Option<HardwareAddress>,
     #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
+    peer_hardware_addr: Option<HardwareAddress>,
+    #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
     neighbor_cache: Option<NeighborCache<'a>>,
+    #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
+    neighbor_request_limiter: RateLimiter,
+    #[cfg(feature = "medium-ethernet")]
+    vlan_tags: EthernetVlanTags,
     #[cfg(feature = "medium-ieee802154")]
     pan_id: Option<Ieee802154Pan>,
     ip_addrs: ManagedSlice<'a, IpCidr>,
@@ -95,9 +261,26 @@ pub struct InterfaceBuilder<'a, DeviceT: for<'d> Device<'d>> {
     #[cfg(feature = "proto-ipv4")]
     any_ip: bool,
     routes: Routes<'a>,
+    policy_routes: Routes<'a>,
+    accept_redirects: bool,
+    icmp_echo_reply: bool,
+    egress_limit: Option<usize>,
+    #[cfg(feature = "socket-tcp")]
+    half_open_limit: Option<usize>,
+    #[cfg(feature = "proto-ipv6")]
+    ipv6_param_problems: bool,
     /// Does not share storage with `ipv6_multicast_groups` to avoid IPv6 size overhead.
     #[cfg(feature = "proto-igmp")]
     ipv4_multicast_groups: ManagedMap<'a, Ipv4Address, ()>,
+    #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+    rarp_table: ManagedMap<'a, EthernetAddress, Ipv4Address>,
+    drop_report_limiter: RateLimiter,
+    #[cfg(feature = "proto-ipv4-fragmentation")]
+    ipv4_fragments: Option<PacketAssemblerSet<'a, Ipv4FragKey>>,
+    #[cfg(feature = "proto-ipv4-fragmentation")]
+    ipv4_out_buffer: ManagedSlice<'a, u8>,
+    #[cfg(feature = "proto-ipv6-fragmentation")]
+    ipv6_fragments: Option<PacketAssemblerSet<'a, Ipv6FragKey>>,
     random_seed: u64,
 }
 
@@ -145,17 +328,47 @@ let iface = InterfaceBuilder::new(device, vec![])
             #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
             hardware_addr: None,
             #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
+            peer_hardware_addr: None,
+            #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
             neighbor_cache: None,
+            #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
+            neighbor_request_limiter: RateLimiter::new(
+                InterfaceInner::NEIGHBOR_REQUEST_LIMIT,
+                InterfaceInner::NEIGHBOR_REQUEST_INTERVAL,
+            ),
+            #[cfg(feature = "medium-ethernet")]
+            vlan_tags: EthernetVlanTags::Untagged,
 
             #[cfg(feature = "medium-ieee802154")]
             pan_id: None,
 
+            drop_report_limiter: RateLimiter::new(
+                InterfaceInner::DROP_REPORT_LIMIT,
+                InterfaceInner::DROP_REPORT_INTERVAL,
+            ),
+
             ip_addrs: ManagedSlice::Borrowed(&mut []),
             #[cfg(feature = "proto-ipv4")]
             any_ip: false,
             routes: Routes::new(ManagedMap::Borrowed(&mut [])),
+            policy_routes: Routes::new(ManagedMap::Borrowed(&mut [])),
+            accept_redirects: true,
+            icmp_echo_reply: true,
+            egress_limit: None,
+            #[cfg(feature = "socket-tcp")]
+            half_open_limit: None,
+            #[cfg(feature = "proto-ipv6")]
+            ipv6_param_problems: true,
             #[cfg(feature = "proto-igmp")]
             ipv4_multicast_groups: ManagedMap::Borrowed(&mut []),
+            #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+            rarp_table: ManagedMap::Borrowed(&mut []),
+            #[cfg(feature = "proto-ipv4-fragmentation")]
+            ipv4_fragments: None,
+            #[cfg(feature = "proto-ipv4-fragmentation")]
+            ipv4_out_buffer: ManagedSlice::Borrowed(&mut []),
+            #[cfg(feature = "proto-ipv6-fragmentation")]
+            ipv6_fragments: None,
             random_seed: 0,
         }
     }
@@ -185,6 +398,80 @@ let iface = InterfaceBuilder::new(device, vec![])
         self
     }
 
+    /// Treat the link as point-to-point, with the given hardware address as the only neighbor.
+    ///
+    /// All unicast packets are sent to `addr`, whatever their destination, without consulting
+    /// the routing table or resolving the next hop with ARP or NDISC. Broadcast and multicast
+    /// packets are sent to their usual hardware addresses. This is meant for back-to-back links
+    /// where the peer accepts everything sent to it, and avoids delaying the first packet for
+    /// neighbor resolution; see also [set_point_to_point_peer].
+    ///
+    /// # Panics
+    /// This function panics if the address is not unicast, and `finalize` panics if the
+    /// device medium is IP.
+    ///
+    /// [set_point_to_point_peer]: struct.Interface.html#method.set_point_to_point_peer
+    #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
+    pub fn point_to_point_peer(mut self, addr: HardwareAddress) -> Self {
+        InterfaceInner::check_hardware_addr(&addr);
+        self.peer_hardware_addr = Some(addr);
+        self
+    }
+
+    /// Limit how many ARP requests and NDISC neighbor solicitations the interface processes.
+    ///
+    /// At most `max_requests` requests are processed per `interval`, and any excess ones are
+    /// dropped without being answered. See also [neighbor_requests_limited].
+    ///
+    /// The default is 100 requests per second.
+    ///
+    /// [neighbor_requests_limited]: struct.Interface.html#method.neighbor_requests_limited
+    #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
+    pub fn neighbor_request_limit(mut self, max_requests: u32, interval: Duration) -> Self {
+        self.neighbor_request_limiter = RateLimiter::new(max_requests, interval);
+        self
+    }
+
+    /// Limit how many dropped packets are reported to the drop observer.
+    ///
+    /// At most `max_reports` packets are reported per `interval`, and any excess ones are
+    /// dropped silently.
+    ///
+    /// The default is 100 packets per second. See also [Interface::set_drop_observer].
+    ///
+    /// [Interface::set_drop_observer]: struct.Interface.html#method.set_drop_observer
+    pub fn drop_report_limit(mut self, max_reports: u32, interval: Duration) -> Self {
+        self.drop_report_limiter = RateLimiter::new(max_reports, interval);
+        self
+    }
+
+    /// Limit the number of sockets the interface may hold at once.
+    ///
+    /// Once the limit is reached, [add_socket] panics and [try_add_socket] returns an error,
+    /// even if the socket storage could grow further. By default, the number of sockets is only
+    /// limited by the socket storage. See also [socket_capacity].
+    ///
+    /// [add_socket]: struct.Interface.html#method.add_socket
+    /// [try_add_socket]: struct.Interface.html#method.try_add_socket
+    /// [socket_capacity]: struct.Interface.html#method.socket_capacity
+    pub fn max_sockets(mut self, max_sockets: usize) -> Self {
+        self.sockets.set_limit(Some(max_sockets));
+        self
+    }
+
+    /// Set the 802.1Q VLAN tags the interface sends and receives frames with.
+    ///
+    /// Outgoing frames are tagged accordingly, and incoming frames whose tags do not match
+    /// exactly are dropped. Use [EthernetVlanTags::Double] to operate as a QinQ customer.
+    /// The default is to send and receive untagged frames only.
+    ///
+    /// [EthernetVlanTags::Double]: ../wire/enum.EthernetVlanTags.html#variant.Double
+    #[cfg(feature = "medium-ethernet")]
+    pub fn vlan_tags(mut self, vlan_tags: EthernetVlanTags) -> Self {
+        self.vlan_tags = vlan_tags;
+        self
+    }
+
     /// Set the IEEE802.15.4 PAN ID the interface will use.
     ///
     /// **NOTE**: we use the same PAN ID for destination and source.
@@ -230,6 +517,78 @@ let iface = InterfaceBuilder::new(device, vec![])
         self
     }
 
+    /// Enable or disable the acceptance of ICMP and NDISC redirect messages.
+    ///
+    /// When enabled (the default), a redirect sent by the router that is currently the first
+    /// hop towards some destination installs a host route to that destination via the better
+    /// next hop it names, provided that next hop is on-link. Such routes are added to [routes]
+    /// and expire after ten minutes.
+    ///
+    /// [routes]: struct.Interface.html#method.routes
+    pub fn accept_redirects(mut self, enabled: bool) -> Self {
+        self.accept_redirects = enabled;
+        self
+    }
+
+    /// Enable or disable answering ICMP and ICMPv6 Echo Requests.
+    ///
+    /// When enabled (the default), the interface answers every Echo Request sent to it, in
+    /// addition to passing it to any ICMP socket bound to it. When disabled, the requests are
+    /// only passed to the sockets, so that the application can decide which ones to answer,
+    /// and with what data. See also [Icmpv4Repr::echo_reply] and [Icmpv6Repr::echo_reply].
+    ///
+    /// [Icmpv4Repr::echo_reply]: ../wire/enum.Icmpv4Repr.html#method.echo_reply
+    /// [Icmpv6Repr::echo_reply]: ../wire/enum.Icmpv6Repr.html#method.echo_reply
+    pub fn icmp_echo_reply(mut self, enabled: bool) -> Self {
+        self.icmp_echo_reply = enabled;
+        self
+    }
+
+    /// Limit the number of packets the sockets may emit during a single call to [poll].
+    ///
+    /// This bounds the work done per call, and keeps a single busy socket from starving the
+    /// rest of the application. Once the limit is reached, the remaining sockets are left for
+    /// the next call, and [poll_at] returns a deadline that has already passed. Packets sent
+    /// in direct response to received ones, such as ARP replies, are not counted.
+    ///
+    /// By default, the number of packets is unlimited.
+    ///
+    /// [poll]: struct.Interface.html#method.poll
+    /// [poll_at]: struct.Interface.html#method.poll_at
+    pub fn egress_limit(mut self, limit: usize) -> Self {
+        self.egress_limit = Some(limit);
+        self
+    }
+
+    /// Limit the number of TCP sockets that may be in the SYN-RECEIVED state at once.
+    ///
+    /// Once the limit is reached, SYNs to listening sockets are dropped until one of the
+    /// half-open connections completes its handshake or is given up on, so that a SYN flood
+    /// can't take over every listening socket. See also [tcp::Socket::set_handshake_timeout].
+    /// Listening sockets with [SYN cookies] enabled answer these SYNs with a cookie instead.
+    ///
+    /// By default, the number of half-open connections is unlimited.
+    ///
+    /// [tcp::Socket::set_handshake_timeout]: ../socket/tcp/struct.Socket.html#method.set_handshake_timeout
+    /// [SYN cookies]: ../socket/tcp/struct.Socket.html#method.set_syn_cookies
+    #[cfg(feature = "socket-tcp")]
+    pub fn half_open_limit(mut self, limit: usize) -> Self {
+        self.half_open_limit = Some(limit);
+        self
+    }
+
+    /// Enable or disable sending ICMPv6 Parameter Problem messages.
+    ///
+    /// When enabled (the default), an IPv6 packet with an unrecognized next header, or with an
+    /// unrecognized hop-by-hop option whose type asks for the sender to be notified, is answered
+    /// with a Parameter Problem message as required by RFC 8200. When disabled, such packets are
+    /// silently dropped, which hides the presence of the host from scans.
+    #[cfg(feature = "proto-ipv6")]
+    pub fn ipv6_param_problems(mut self, enabled: bool) -> Self {
+        self.ipv6_param_problems = enabled;
+        self
+    }
+
     /// Set the IP routes the interface will use. See also
     /// [routes].
     ///
@@ -242,6 +601,18 @@ let iface = InterfaceBuilder::new(device, vec![])
         self
     }
 
+    /// Set the policy routes the interface will use. See also
+    /// [policy_routes].
+    ///
+    /// [policy_routes]: struct.Interface.html#method.policy_routes
+    pub fn policy_routes<T>(mut self, policy_routes: T) -> InterfaceBuilder<'a, DeviceT>
+    where
+        T: Into<Routes<'a>>,
+    {
+        self.policy_routes = policy_routes.into();
+        self
+    }
+
     /// Provide storage for multicast groups.
     ///
     /// Join multicast groups by calling [`join_multicast_group()`] on an `Interface`.
@@ -261,6 +632,81 @@ let iface = InterfaceBuilder::new(device, vec![])
         self
     }
 
+    /// Provide the MAC to IPv4 address mappings the interface answers RARP requests for.
+    ///
+    /// By default the table is empty, and RARP requests are ignored.
+    #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+    pub fn rarp_table<T>(mut self, rarp_table: T) -> Self
+    where
+        T: Into<ManagedMap<'a, EthernetAddress, Ipv4Address>>,
+    {
+        self.rarp_table = rarp_table.into();
+        self
+    }
+
+    /// Provide storage for reassembling incoming IPv4 fragments.
+    ///
+    /// Each packet being reassembled takes up one of the assemblers, which must be large
+    /// enough for the whole packet; packets whose fragments don't all arrive within 60
+    /// seconds are discarded. Without this storage, fragments are only passed to the raw
+    /// sockets that [accept them][set_pass_fragments].
+    ///
+    /// At most `max_packets` packets are reassembled at once, holding at most `max_bytes`
+    /// octets altogether; owned assemblers grow with the fragments they are given. The oldest
+    /// packets are discarded to make room for new fragments beyond these limits.
+    ///
+    /// Options are dropped from the header of a reassembled packet.
+    ///
+    /// [set_pass_fragments]: ../socket/raw/struct.Socket.html#method.set_pass_fragments
+    #[cfg(feature = "proto-ipv4-fragmentation")]
+    pub fn ipv4_fragments(
+        mut self,
+        mut storage: PacketAssemblerSet<'a, Ipv4FragKey>,
+        max_packets: usize,
+        max_bytes: usize,
+    ) -> Self {
+        storage.set_limits(max_packets, max_bytes);
+        self.ipv4_fragments = Some(storage);
+        self
+    }
+
+    /// Provide a buffer for sending IPv4 packets larger than the MTU in fragments.
+    ///
+    /// A packet is written to the buffer whole, and its fragments are sent over the following
+    /// transmit opportunities; until they have all been sent, packets that would need the
+    /// buffer as well are dropped. An owned buffer grows to fit the packets; packets that
+    /// don't fit a borrowed one are dropped. Without this buffer, packets larger than the
+    /// MTU are sent as they are.
+    #[cfg(feature = "proto-ipv4-fragmentation")]
+    pub fn ipv4_fragmentation_buffer<T>(mut self, storage: T) -> Self
+    where
+        T: Into<ManagedSlice<'a, u8>>,
+    {
+        self.ipv4_out_buffer = storage.into();
+        self
+    }
+
+    /// Provide storage for reassembling incoming IPv6 fragments.
+    ///
+    /// The storage is used and bounded like the [IPv4 fragment storage][ipv4_fragments].
+    /// Without it, fragments are only passed to the raw sockets for the Fragment header.
+    ///
+    /// Extension headers in front of the Fragment header are dropped from a reassembled
+    /// packet. Outgoing IPv6 packets are never fragmented.
+    ///
+    /// [ipv4_fragments]: #method.ipv4_fragments
+    #[cfg(feature = "proto-ipv6-fragmentation")]
+    pub fn ipv6_fragments(
+        mut self,
+        mut storage: PacketAssemblerSet<'a, Ipv6FragKey>,
+        max_packets: usize,
+        max_bytes: usize,
+    ) -> Self {
+        storage.set_limits(max_packets, max_bytes);
+        self.ipv6_fragments = Some(storage);
+        self
+    }
+
     /// Set the Neighbor Cache the interface will use.
     #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
     pub fn neighbor_cache(mut self, neighbor_cache: NeighborCache<'a>) -> Self {
@@ -301,6 +747,11 @@ let iface = InterfaceBuilder::new(device, vec![])
                     self.hardware_addr.is_none(),
                     "hardware_addr is set, but device medium is IP"
                 );
+                #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
+                assert!(
+                    self.peer_hardware_addr.is_none(),
+                    "point_to_point_peer is set, but device medium is IP"
+                );
                 assert!(
                     self.neighbor_cache.is_none(),
                     "neighbor_cache is set, but device medium is IP"
@@ -340,25 +791,65 @@ let iface = InterfaceBuilder::new(device, vec![])
         Interface {
             device: self.device,
             sockets: self.sockets,
+            #[cfg(feature = "async")]
+            poll_waker: WakerRegistration::new(),
             inner: InterfaceInner {
                 now: Instant::from_secs(0),
                 caps,
                 #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
                 hardware_addr,
+                #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
+                peer_hardware_addr: self.peer_hardware_addr,
+                #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+                arp_announce_next: None,
+                #[cfg(all(feature = "medium-ethernet", feature = "socket-raw"))]
+                rx_ethernet_repr: None,
                 ip_addrs: self.ip_addrs,
                 #[cfg(feature = "proto-ipv4")]
                 any_ip: self.any_ip,
                 routes: self.routes,
+                policy_routes: self.policy_routes,
+                #[cfg(feature = "proto-ipv6")]
+                ipv6_param_problems: self.ipv6_param_problems,
+                accept_redirects: self.accept_redirects,
+                icmp_echo_reply: self.icmp_echo_reply,
+                egress_limit: self.egress_limit,
+                #[cfg(feature = "socket-tcp")]
+                half_open_limit: self.half_open_limit,
+                #[cfg(feature = "socket-tcp")]
+                syn_cookie_secret: None,
+                egress_count: 0,
+                egress_next: 0,
+                drop_observer: None,
+                drop_report_limiter: self.drop_report_limiter,
                 #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
                 neighbor_cache,
+                #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
+                neighbor_request_limiter: self.neighbor_request_limiter,
+                #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
+                neighbor_requests_limited: 0,
                 #[cfg(feature = "proto-igmp")]
                 ipv4_multicast_groups: self.ipv4_multicast_groups,
                 #[cfg(feature = "proto-igmp")]
                 igmp_report_state: IgmpReportState::Inactive,
+                #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+                rarp_table: self.rarp_table,
+                #[cfg(feature = "proto-ipv4-fragmentation")]
+                ipv4_fragments: self.ipv4_fragments,
+                #[cfg(feature = "proto-ipv4-fragmentation")]
+                ipv4_reassembled: None,
+                #[cfg(feature = "proto-ipv4-fragmentation")]
+                ipv4_out_packet: Ipv4OutPacket::new(self.ipv4_out_buffer),
+                #[cfg(feature = "proto-ipv6-fragmentation")]
+                ipv6_fragments: self.ipv6_fragments,
+                #[cfg(feature = "proto-ipv6-fragmentation")]
+                ipv6_reassembled: None,
                 #[cfg(feature = "medium-ieee802154")]
                 sequence_no,
                 #[cfg(feature = "medium-ieee802154")]
                 pan_id: self.pan_id,
+                #[cfg(feature = "medium-ethernet")]
+                vlan_tags: self.vlan_tags,
                 rand,
             },
         }
@@ -385,8 +876,9 @@ pub(crate) enum IpPacket<'a> {
     Icmpv6((Ipv6Repr, Icmpv6Repr<'a>)),
     #[cfg(feature = "socket-raw")]
     Raw((IpRepr, &'a [u8])),
+    /// The flag is cleared to omit the UDP checksum, which is only honored over IPv4.
     #[cfg(any(feature = "socket-udp", feature = "socket-dns"))]
-    Udp((IpRepr, UdpRepr, &'a [u8])),
+    Udp((IpRepr, UdpRepr, &'a [u8], bool)),
     #[cfg(feature = "socket-tcp")]
     Tcp((IpRepr, TcpRepr<'a>)),
     #[cfg(feature = "socket-dhcpv4")]
@@ -405,7 +897,7 @@ impl<'a> IpPacket<'a> {
             #[cfg(feature = "socket-raw")]
             IpPacket::Raw((ip_repr, _)) => ip_repr.clone(),
             #[cfg(any(feature = "socket-udp", feature = "socket-dns"))]
-            IpPacket::Udp((ip_repr, _, _)) => ip_repr.clone(),
+            IpPacket::Udp((ip_repr, _, _, _)) => ip_repr.clone(),
             #[cfg(feature = "socket-tcp")]
             IpPacket::Tcp((ip_repr, _)) => ip_repr.clone(),
             #[cfg(feature = "socket-dhcpv4")]
@@ -413,12 +905,44 @@ impl<'a> IpPacket<'a> {
         }
     }
 
-    pub(crate) fn emit_payload(
-        &self,
-        _ip_repr: IpRepr,
-        payload: &mut [u8],
-        caps: &DeviceCapabilities,
-    ) {
+    /// Return the length of the packet, including the IP header.
+    pub(crate) fn total_len(&self, ip_repr: &IpRepr) -> usize {
+        match self {
+            #[cfg(feature = "socket-raw")]
+            IpPacket::Raw((_, raw_packet)) => raw_packet.len(),
+            #[cfg(feature = "proto-igmp")]
+            IpPacket::Igmp(_) => ip_repr.total_len() + IPV4_ROUTER_ALERT_OPTION.len(),
+            _ => ip_repr.total_len(),
+        }
+    }
+
+    /// Emit the IP header followed by the payload into `buffer`.
+    ///
+    /// Packets from raw sockets already include their IP header, and are emitted verbatim.
+    pub(crate) fn emit(&self, ip_repr: IpRepr, buffer: &mut [u8], caps: &DeviceCapabilities) {
+        #[cfg(feature = "socket-raw")]
+        if let IpPacket::Raw((_, raw_packet)) = self {
+            buffer.copy_from_slice(raw_packet);
+            return;
+        }
+
+        // Snooping switches and multicast routers only examine IGMP messages that carry
+        // the Router Alert option (RFC 2236 § 2).
+        #[cfg(feature = "proto-igmp")]
+        if let (IpPacket::Igmp(_), IpRepr::Ipv4(ipv4_repr)) = (self, &ip_repr) {
+            let mut packet = Ipv4Packet::new_unchecked(&mut *buffer);
+            ipv4_repr.emit_with_options(&mut packet, &IPV4_ROUTER_ALERT_OPTION, &caps.checksum);
+            let header_len = packet.header_len() as usize;
+            self.emit_payload(ip_repr, &mut buffer[header_len..], caps);
+            return;
+        }
+
+        let header_len = ip_repr.buffer_len();
+        ip_repr.emit(&mut *buffer, &caps.checksum);
+        self.emit_payload(ip_repr, &mut buffer[header_len..], caps);
+    }
+
+    fn emit_payload(&self, _ip_repr: IpRepr, payload: &mut [u8], caps: &DeviceCapabilities) {
         match self {
             #[cfg(feature = "proto-ipv4")]
             IpPacket::Icmpv4((_, icmpv4_repr)) => {
@@ -436,16 +960,33 @@ impl<'a> IpPacket<'a> {
                 &caps.checksum,
             ),
             #[cfg(feature = "socket-raw")]
-            IpPacket::Raw((_, raw_packet)) => payload.copy_from_slice(raw_packet),
+            IpPacket::Raw(_) => unreachable!(),
             #[cfg(any(feature = "socket-udp", feature = "socket-dns"))]
-            IpPacket::Udp((_, udp_repr, inner_payload)) => udp_repr.emit(
-                &mut UdpPacket::new_unchecked(payload),
-                &_ip_repr.src_addr(),
-                &_ip_repr.dst_addr(),
-                inner_payload.len(),
-                |buf| buf.copy_from_slice(inner_payload),
-                &caps.checksum,
-            ),
+            IpPacket::Udp((_, udp_repr, inner_payload, checksum)) => {
+                // A zero checksum means "no checksum" over IPv4, but is invalid over IPv6.
+                #[cfg(feature = "proto-ipv4")]
+                let checksum_caps = {
+                    let mut checksum_caps = caps.checksum.clone();
+                    if !checksum && _ip_repr.version() == IpVersion::Ipv4 {
+                        checksum_caps.udp = crate::phy::Checksum::None;
+                    }
+                    checksum_caps
+                };
+                #[cfg(not(feature = "proto-ipv4"))]
+                let checksum_caps = {
+                    let _ = checksum;
+                    caps.checksum.clone()
+                };
+
+                udp_repr.emit(
+                    &mut UdpPacket::new_unchecked(payload),
+                    &_ip_repr.src_addr(),
+                    &_ip_repr.dst_addr(),
+                    inner_payload.len(),
+                    |buf| buf.copy_from_slice(inner_payload),
+                    &checksum_caps,
+                )
+            }
             #[cfg(feature = "socket-tcp")]
             IpPacket::Tcp((_, mut tcp_repr)) => {
                 // This is a terrible hack to make TCP performance more acceptable on systems
@@ -486,6 +1027,122 @@ impl<'a> IpPacket<'a> {
     }
 }
 
+/// The offset of the Next Header field in an IPv6 header.
+#[cfg(feature = "proto-ipv6")]
+const IPV6_NEXT_HEADER_OFFSET: usize = 6;
+
+/// Return the scope of an IPv6 address, as defined in [RFC 4291 § 2.7].
+///
+/// Following [RFC 6724 § 3.1], the loopback address has link-local scope, and unique local
+/// addresses have global scope; they are told apart from global addresses by their label.
+///
+/// [RFC 4291 § 2.7]: https://tools.ietf.org/html/rfc4291#section-2.7
+/// [RFC 6724 § 3.1]: https://tools.ietf.org/html/rfc6724#section-3.1
+#[cfg(feature = "proto-ipv6")]
+fn ipv6_scope(addr: &Ipv6Address) -> u8 {
+    if addr.is_multicast() {
+        addr.as_bytes()[1] & 0x0f
+    } else if addr.is_link_local() || addr.is_loopback() {
+        0x2
+    } else if addr.as_bytes()[0] == 0xfe && addr.as_bytes()[1] & 0xc0 == 0xc0 {
+        // Deprecated site-local addresses, fec0::/10.
+        0x5
+    } else {
+        0xe
+    }
+}
+
+/// Return the label of an IPv6 address in the [default policy table].
+///
+/// [default policy table]: https://tools.ietf.org/html/rfc6724#section-2.1
+#[cfg(feature = "proto-ipv6")]
+fn ipv6_label(addr: &Ipv6Address) -> u8 {
+    let bytes = addr.as_bytes();
+    if addr.is_loopback() {
+        0
+    } else if addr.is_ipv4_mapped() {
+        4
+    } else if bytes[0..2] == [0x20, 0x02] {
+        2
+    } else if bytes[0..4] == [0x20, 0x01, 0x00, 0x00] {
+        5
+    } else if addr.is_unique_local() {
+        13
+    } else if bytes[0..12] == [0; 12] {
+        3
+    } else if bytes[0] == 0xfe && bytes[1] & 0xc0 == 0xc0 {
+        11
+    } else if bytes[0..2] == [0x3f, 0xfe] {
+        12
+    } else {
+        1
+    }
+}
+
+/// Return the length of the longest prefix shared by two IPv6 addresses.
+#[cfg(feature = "proto-ipv6")]
+fn ipv6_common_prefix_len(lhs: &Ipv6Address, rhs: &Ipv6Address) -> u8 {
+    let mut len = 0;
+    for (l, r) in lhs.as_bytes().iter().zip(rhs.as_bytes()) {
+        let diff = l ^ r;
+        len += diff.leading_zeros() as u8;
+        if diff != 0 {
+            break;
+        }
+    }
+    len
+}
+
+/// Compare two candidate source addresses for `dst_addr`, following the rules of
+/// [RFC 6724 § 5] that apply to statically configured addresses, and return whether
+/// `lhs` is to be preferred over `rhs`.
+///
+/// [RFC 6724 § 5]: https://tools.ietf.org/html/rfc6724#section-5
+#[cfg(feature = "proto-ipv6")]
+fn ipv6_source_is_better(lhs: &Ipv6Cidr, rhs: &Ipv6Cidr, dst_addr: &Ipv6Address) -> bool {
+    let (lhs_addr, rhs_addr) = (lhs.address(), rhs.address());
+
+    // Rule 1: prefer the destination address itself.
+    if lhs_addr == *dst_addr || rhs_addr == *dst_addr {
+        return lhs_addr == *dst_addr && rhs_addr != *dst_addr;
+    }
+
+    // Rule 2: prefer the smallest scope that is at least the destination's scope.
+    let (lhs_scope, rhs_scope) = (ipv6_scope(&lhs_addr), ipv6_scope(&rhs_addr));
+    let dst_scope = ipv6_scope(dst_addr);
+    if lhs_scope != rhs_scope {
+        return if lhs_scope < rhs_scope {
+            lhs_scope >= dst_scope
+        } else {
+            rhs_scope < dst_scope
+        };
+    }
+
+    // Rule 6: prefer an address with the same label as the destination.
+    let dst_label = ipv6_label(dst_addr);
+    let lhs_matches = ipv6_label(&lhs_addr) == dst_label;
+    if lhs_matches != (ipv6_label(&rhs_addr) == dst_label) {
+        return lhs_matches;
+    }
+
+    // Rule 8: prefer the longest prefix matching the destination, within the subnet.
+    let lhs_len = ipv6_common_prefix_len(&lhs_addr, dst_addr).min(lhs.prefix_len());
+    let rhs_len = ipv6_common_prefix_len(&rhs_addr, dst_addr).min(rhs.prefix_len());
+    lhs_len > rhs_len
+}
+
+/// Return the IPv4 header of a packet that was not, or could not be, parsed by `Ipv4Repr`.
+#[cfg(feature = "proto-ipv4")]
+fn ipv4_header_repr<T: AsRef<[u8]> + ?Sized>(ipv4_packet: &Ipv4Packet<&T>) -> Ipv4Repr {
+    Ipv4Repr {
+        src_addr: ipv4_packet.src_addr(),
+        dst_addr: ipv4_packet.dst_addr(),
+        next_header: ipv4_packet.next_header(),
+        payload_len: ipv4_packet.payload().len(),
+        hop_limit: ipv4_packet.hop_limit(),
+    }
+}
+
 #[cfg(any(feature = "proto-ipv4", feature = "proto-ipv6"))]
 fn icmp_reply_payload_len(len: usize, mtu: usize, header_len: usize) -> usize {
     // Send back as much of the original payload as will fit within
@@ -522,17 +1179,46 @@ where
     /// Add a socket to the interface, and return its handle.
     ///
     /// # Panics
-    /// This function panics if the storage is fixed-size (not a `Vec`) and is full.
+    /// This function panics if the interface already holds as many sockets as its
+    /// [socket_capacity](#method.socket_capacity). Use [try_add_socket](#method.try_add_socket)
+    /// to handle that case gracefully instead.
     pub fn add_socket<T: AnySocket<'a>>(&mut self, socket: T) -> SocketHandle {
+        self.wake_poller();
         self.sockets.add(socket)
     }
 
+    /// Add a socket to the interface, and return its handle.
+    ///
+    /// This function returns the socket back as `Err(SocketSetFull(socket))` if the interface
+    /// already holds as many sockets as its [socket_capacity](#method.socket_capacity);
+    /// removing another socket makes room for it.
+    pub fn try_add_socket<T: AnySocket<'a>>(
+        &mut self,
+        socket: T,
+    ) -> core::result::Result<SocketHandle, SocketSetFull<T>> {
+        let handle = self.sockets.try_add(socket)?;
+        self.wake_poller();
+        Ok(handle)
+    }
+
+    /// Return the maximum number of sockets the interface may hold at once.
+    ///
+    /// This is the smaller of the size of the socket storage, if the storage is fixed-size
+    /// (not a `Vec`), and the limit set with [InterfaceBuilder::max_sockets]. `None` means
+    /// that the number of sockets is only limited by the available memory.
+    ///
+    /// [InterfaceBuilder::max_sockets]: struct.InterfaceBuilder.html#method.max_sockets
+    pub fn socket_capacity(&self) -> Option<usize> {
+        self.sockets.capacity()
+    }
+
     /// Get a socket from the interface by its handle, as mutable.
     ///
     /// # Panics
     /// This function may panic if the handle does not belong to this socket set
     /// or the socket has the wrong type.
     pub fn get_socket<T: AnySocket<'a>>(&mut self, handle: SocketHandle) -> &mut T {
+        self.wake_poller();
         self.sockets.get(handle)
     }
 
@@ -547,41 +1233,119 @@ where
         &mut self,
         handle: SocketHandle,
     ) -> (&mut T, &mut InterfaceInner<'a>) {
+        self.wake_poller();
         (self.sockets.get(handle), &mut self.inner)
     }
 
-    /// Remove a socket from the set, without changing its state.
+    /// Get a socket by handle, and the user data attached to it.
     ///
     /// # Panics
-    /// This function may panic if the handle does not belong to this socket set.
-    pub fn remove_socket(&mut self, handle: SocketHandle) -> Socket<'a> {
-        self.sockets.remove(handle)
+    /// This function may panic if the handle does not belong to this socket set
+    /// or the socket has the wrong type.
+    #[cfg(feature = "socket-user-data")]
+    pub fn get_socket_and_user_data<T: AnySocket<'a>>(
+        &mut self,
+        handle: SocketHandle,
+    ) -> (&mut T, usize) {
+        let user_data = self.sockets.user_data(handle);
+        (self.get_socket(handle), user_data)
     }
 
-    /// Get the HardwareAddress address of the interface.
+    /// Get the user data attached to a socket.
+    ///
+    /// With the `socket-user-data` feature, every socket carries a `usize` the application
+    /// may use e.g. as an index or token identifying its own per-connection state. The value
+    /// is zero until set with [set_socket_user_data](#method.set_socket_user_data), and is
+    /// otherwise never interpreted or changed by the interface.
     ///
     /// # Panics
-    /// This function panics if the medium is not Ethernet or Ieee802154.
-    #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
-    pub fn hardware_addr(&self) -> HardwareAddress {
-        #[cfg(all(feature = "medium-ethernet", not(feature = "medium-ieee802154")))]
-        assert!(self.device().capabilities().medium == Medium::Ethernet);
-        #[cfg(all(feature = "medium-ieee802154", not(feature = "medium-ethernet")))]
-        assert!(self.device().capabilities().medium == Medium::Ieee802154);
-
-        #[cfg(all(feature = "medium-ieee802154", feature = "medium-ethernet"))]
-        assert!(
-            self.device().capabilities().medium == Medium::Ethernet
-                || self.device().capabilities().medium == Medium::Ieee802154
-        );
-
-        self.inner.hardware_addr.unwrap()
+    /// This function may panic if the handle does not belong to this socket set.
+    #[cfg(feature = "socket-user-data")]
+    pub fn socket_user_data(&self, handle: SocketHandle) -> usize {
+        self.sockets.user_data(handle)
     }
 
-    /// Set the HardwareAddress address of the interface.
+    /// Attach user data to a socket, replacing the previous value.
     ///
     /// # Panics
-    /// This function panics if the address is not unicast, and if the medium is not Ethernet or
+    /// This function may panic if the handle does not belong to this socket set.
+    #[cfg(feature = "socket-user-data")]
+    pub fn set_socket_user_data(&mut self, handle: SocketHandle, user_data: usize) {
+        self.sockets.set_user_data(handle, user_data)
+    }
+
+    /// Remove a socket from the set, without changing its state.
+    ///
+    /// # Panics
+    /// This function may panic if the handle does not belong to this socket set.
+    pub fn remove_socket(&mut self, handle: SocketHandle) -> Socket<'a> {
+        self.wake_poller();
+        self.sockets.remove(handle)
+    }
+
+    /// Register a waker for the task that polls this interface.
+    ///
+    /// The waker is woken whenever a socket is added, removed or accessed mutably through the
+    /// interface, since any of these may make the deadline returned by [poll_at] earlier than
+    /// it was when last queried. An executor-driven poll loop should therefore:
+    ///
+    /// 1. call [poll],
+    /// 2. register its waker,
+    /// 3. arm a timer for [poll_at], and wait for either the timer or the waker.
+    ///
+    /// Registering the waker before querying [poll_at] ensures that a socket operation
+    /// performed by another task in between is not missed. Accessing sockets through the
+    /// interface after registering the waker wakes it immediately.
+    ///
+    /// Notes:
+    ///
+    /// - Only one waker can be registered at a time. If another waker was previously registered,
+    ///   it is overwritten and will no longer be woken.
+    /// - The Waker is woken only once. Once woken, you must register it again to receive more wakes.
+    ///
+    /// [poll]: #method.poll
+    /// [poll_at]: #method.poll_at
+    #[cfg(feature = "async")]
+    pub fn register_poll_waker(&mut self, waker: &Waker) {
+        self.poll_waker.register(waker)
+    }
+
+    fn wake_poller(&mut self) {
+        #[cfg(feature = "async")]
+        self.poll_waker.wake();
+    }
+
+    /// Get the HardwareAddress address of the interface.
+    ///
+    /// # Panics
+    /// This function panics if the medium is not Ethernet or Ieee802154.
+    #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
+    pub fn hardware_addr(&self) -> HardwareAddress {
+        #[cfg(all(feature = "medium-ethernet", not(feature = "medium-ieee802154")))]
+        assert!(self.device().capabilities().medium == Medium::Ethernet);
+        #[cfg(all(feature = "medium-ieee802154", not(feature = "medium-ethernet")))]
+        assert!(self.device().capabilities().medium == Medium::Ieee802154);
+
+        #[cfg(all(feature = "medium-ieee802154", feature = "medium-ethernet"))]
+        assert!(
+            self.device().capabilities().medium == Medium::Ethernet
+                || self.device().capabilities().medium == Medium::Ieee802154
+        );
+
+        self.inner.hardware_addr.unwrap()
+    }
+
+    /// Set the HardwareAddress address of the interface.
+    ///
+    /// The address is used both as the source of outgoing frames and in ARP replies, so
+    /// it can be changed at runtime to take over a virtual MAC address on failover. When
+    /// an Ethernet address changes, the next [poll] announces it for every IPv4 address
+    /// of the interface with a gratuitous ARP request, so that neighbors update their caches.
+    ///
+    /// [poll]: #method.poll
+    ///
+    /// # Panics
+    /// This function panics if the address is not unicast, and if the medium is not Ethernet or
     /// Ieee802154.
     #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
     pub fn set_hardware_addr(&mut self, addr: HardwareAddress) {
@@ -597,9 +1361,77 @@ where
         );
 
         InterfaceInner::check_hardware_addr(&addr);
+        #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+        if self.inner.hardware_addr != Some(addr) {
+            self.inner.arp_announce_next = Some(0);
+        }
         self.inner.hardware_addr = Some(addr);
     }
 
+    /// Get the hardware address of the peer on a point-to-point link, if the link is treated
+    /// as such.
+    ///
+    /// See also the [point_to_point_peer] builder option.
+    ///
+    /// [point_to_point_peer]: struct.InterfaceBuilder.html#method.point_to_point_peer
+    #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
+    pub fn point_to_point_peer(&self) -> Option<HardwareAddress> {
+        self.inner.peer_hardware_addr
+    }
+
+    /// Set the hardware address of the peer on a point-to-point link, or `None` to resolve
+    /// neighbors and route packets as usual.
+    ///
+    /// # Panics
+    /// This function panics if the address is not unicast, or if the medium is IP.
+    #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
+    pub fn set_point_to_point_peer(&mut self, addr: Option<HardwareAddress>) {
+        #[cfg(feature = "medium-ip")]
+        assert!(self.device().capabilities().medium != Medium::Ip);
+
+        if let Some(addr) = addr {
+            InterfaceInner::check_hardware_addr(&addr);
+        }
+        self.inner.peer_hardware_addr = addr;
+    }
+
+    /// Get the 802.1Q VLAN tags the interface sends and receives frames with.
+    #[cfg(feature = "medium-ethernet")]
+    pub fn vlan_tags(&self) -> EthernetVlanTags {
+        self.inner.vlan_tags
+    }
+
+    /// Set the 802.1Q VLAN tags the interface sends and receives frames with.
+    /// See also [InterfaceBuilder::vlan_tags].
+    ///
+    /// [InterfaceBuilder::vlan_tags]: struct.InterfaceBuilder.html#method.vlan_tags
+    #[cfg(feature = "medium-ethernet")]
+    pub fn set_vlan_tags(&mut self, vlan_tags: EthernetVlanTags) {
+        self.inner.vlan_tags = vlan_tags;
+    }
+
+    /// Get whether the interface answers ICMP and ICMPv6 Echo Requests itself.
+    pub fn icmp_echo_reply(&self) -> bool {
+        self.inner.icmp_echo_reply
+    }
+
+    /// Enable or disable answering ICMP and ICMPv6 Echo Requests.
+    /// See also [InterfaceBuilder::icmp_echo_reply].
+    ///
+    /// [InterfaceBuilder::icmp_echo_reply]: struct.InterfaceBuilder.html#method.icmp_echo_reply
+    pub fn set_icmp_echo_reply(&mut self, enabled: bool) {
+        self.inner.icmp_echo_reply = enabled;
+    }
+
+    /// Get the number of bytes queued for transmission within the device, if the device
+    /// reports it.
+    ///
+    /// Unlike the send buffers of sockets, this only counts packets the interface has already
+    /// emitted. See [Device::tx_queue_len](../phy/trait.Device.html#method.tx_queue_len).
+    pub fn device_tx_queue_len(&self) -> Option<usize> {
+        self.device.tx_queue_len()
+    }
+
     /// Get a reference to the inner device.
     pub fn device(&self) -> &DeviceT {
         &self.device
@@ -621,13 +1453,86 @@ where
         self.sockets.iter().map(|i| (i.meta.handle, &i.socket))
     }
 
+    /// Get an iterator to the inner sockets, along with the user data attached to them.
+    /// See also [socket_user_data](#method.socket_user_data).
+    #[cfg(feature = "socket-user-data")]
+    pub fn sockets_with_user_data(
+        &mut self,
+    ) -> impl Iterator<Item = (SocketHandle, &mut Socket<'a>, usize)> {
+        self.wake_poller();
+        self.sockets
+            .iter_mut()
+            .map(|i| (i.meta.handle, &mut i.socket, i.meta.user_data))
+    }
+
     /// Get a mutable iterator to the inner sockets.
     pub fn sockets_mut(&mut self) -> impl Iterator<Item = (SocketHandle, &mut Socket<'a>)> {
+        self.wake_poller();
         self.sockets
             .iter_mut()
             .map(|i| (i.meta.handle, &mut i.socket))
     }
 
+    /// Check whether a TCP or UDP socket is bound to a local endpoint overlapping with
+    /// `endpoint`.
+    ///
+    /// An endpoint without an address overlaps with every endpoint on the same port. This lets
+    /// the application pick a free port, or report a conflict, before binding a socket. Sockets
+    /// that are listening or connected count as bound, including TCP sockets in the
+    /// `TIME-WAIT` state. Other protocols have no ports, and are never in use.
+    #[cfg(any(feature = "socket-tcp", feature = "socket-udp"))]
+    pub fn is_local_endpoint_in_use<T>(&self, protocol: IpProtocol, endpoint: T) -> bool
+    where
+        T: Into<IpListenEndpoint>,
+    {
+        let endpoint = endpoint.into();
+        self.sockets
+            .iter()
+            .any(|item| match (&item.socket, protocol) {
+                #[cfg(feature = "socket-tcp")]
+                (Socket::Tcp(socket), IpProtocol::Tcp) => socket.is_bound_to(&endpoint),
+                #[cfg(feature = "socket-udp")]
+                (Socket::Udp(socket), IpProtocol::Udp) => socket.is_bound_to(&endpoint),
+                _ => false,
+            })
+    }
+
+    /// Return the number of TCP sockets in the `TIME-WAIT` state.
+    ///
+    /// These keep occupying their slots in the socket set until the delay expires, which can
+    /// exhaust it on a server that churns through connections.
+    #[cfg(feature = "socket-tcp")]
+    pub fn time_wait_count(&self) -> usize {
+        self.sockets
+            .iter()
+            .filter(|item| match item.socket {
+                Socket::Tcp(ref socket) => socket.state() == tcp::State::TimeWait,
+                #[allow(unreachable_patterns)]
+                _ => false,
+            })
+            .count()
+    }
+
+    /// Close every TCP socket in the `TIME-WAIT` state right away, and return how many
+    /// were closed.
+    ///
+    /// This is meant as an escape hatch, e.g. for a controlled restart; see
+    /// [tcp::Socket::reap_time_wait] for why it risks accepting old duplicate segments.
+    ///
+    /// [tcp::Socket::reap_time_wait]: ../socket/tcp/struct.Socket.html#method.reap_time_wait
+    #[cfg(feature = "socket-tcp")]
+    pub fn reap_time_wait(&mut self) -> usize {
+        let mut reaped = 0;
+        for item in self.sockets.iter_mut() {
+            if let Some(socket) = tcp::Socket::downcast(&mut item.socket) {
+                if socket.reap_time_wait() {
+                    reaped += 1;
+                }
+            }
+        }
+        reaped
+    }
+
     /// Add an address to a list of subscribed multicast IP addresses.
     ///
     /// Returns `Ok(announce_sent)` if the address was added successfully, where `annouce_sent`
@@ -731,6 +1636,58 @@ where
         InterfaceInner::check_ip_addrs(&self.inner.ip_addrs)
     }
 
+    /// Remove an IP address from the interface, and return whether it was assigned.
+    ///
+    /// The remaining addresses keep their order. If the addresses are held in borrowed
+    /// storage, the list is shortened, and the slot of the removed address can only be
+    /// reused by passing new storage to [update_ip_addrs](#method.update_ip_addrs).
+    ///
+    /// TCP connections using the address are reset without sending anything, and report
+    /// [`CloseReason::LocalAddressRemoved`]; UDP sockets bound to it drop their queued
+    /// packets and refuse to send more until they are bound again.
+    ///
+    /// [`CloseReason::LocalAddressRemoved`]: ../socket/tcp/enum.CloseReason.html#variant.LocalAddressRemoved
+    pub fn remove_ip_addr<T: Into<IpAddress>>(&mut self, addr: T) -> bool {
+        let addr = addr.into();
+        if !self.inner.has_ip_addr(addr) {
+            return false;
+        }
+        let ip_addrs = mem::replace(&mut self.inner.ip_addrs, ManagedSlice::Borrowed(&mut []));
+        self.inner.ip_addrs = match ip_addrs {
+            ManagedSlice::Borrowed(cidrs) => {
+                // Move the addresses that are kept to the front, in order.
+                let mut len = 0;
+                for index in 0..cidrs.len() {
+                    if cidrs[index].address() != addr {
+                        cidrs.swap(len, index);
+                        len += 1;
+                    }
+                }
+                ManagedSlice::Borrowed(&mut cidrs[..len])
+            }
+            #[cfg(any(feature = "std", feature = "alloc"))]
+            ManagedSlice::Owned(mut cidrs) => {
+                cidrs.retain(|cidr| cidr.address() != addr);
+                ManagedSlice::Owned(cidrs)
+            }
+        };
+        InterfaceInner::flush_cache(&mut self.inner);
+
+        #[allow(clippy::match_single_binding)]
+        for item in self.sockets.iter_mut() {
+            match item.socket {
+                #[cfg(feature = "socket-tcp")]
+                Socket::Tcp(ref mut socket) => socket.local_address_removed(addr),
+                #[cfg(feature = "socket-udp")]
+                Socket::Udp(ref mut socket) => socket.local_address_removed(addr),
+                #[allow(unreachable_patterns)]
+                _ => {}
+            }
+        }
+        self.wake_poller();
+        true
+    }
+
     /// Check whether the interface has the given IP address assigned.
     pub fn has_ip_addr<T: Into<IpAddress>>(&self, addr: T) -> bool {
         self.inner.has_ip_addr(addr)
@@ -750,6 +1707,80 @@ where
         &mut self.inner.routes
     }
 
+    /// Get the MAC to IPv4 address mappings the interface answers RARP requests for.
+    #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+    pub fn rarp_table(&self) -> &ManagedMap<'a, EthernetAddress, Ipv4Address> {
+        &self.inner.rarp_table
+    }
+
+    /// Get the MAC to IPv4 address mappings the interface answers RARP requests for, mutably.
+    #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+    pub fn rarp_table_mut(&mut self) -> &mut ManagedMap<'a, EthernetAddress, Ipv4Address> {
+        &mut self.inner.rarp_table
+    }
+
+    /// Get the policy routes of the interface.
+    ///
+    /// Policy routes are consulted before anything else when choosing the next hop towards
+    /// a unicast destination, so that they override both [routes] and directly-connected
+    /// subnets. This allows e.g. pinning some destinations to a specific uplink.
+    /// Sockets that may only reach directly-connected destinations, such as those with
+    /// `dont_route` set, take policy routes into account too.
+    ///
+    /// [routes]: #method.routes
+    pub fn policy_routes(&self) -> &Routes<'a> {
+        &self.inner.policy_routes
+    }
+
+    pub fn policy_routes_mut(&mut self) -> &mut Routes<'a> {
+        &mut self.inner.policy_routes
+    }
+
+    /// Return the next hop packets to `dst_addr` are sent to, and whether it is `dst_addr`
+    /// itself, i.e. whether the destination is reached without going through a router.
+    ///
+    /// The result reflects the [policy_routes], addresses and [routes] of the interface as
+    /// of the last call to [poll], which determines route expiry. `None` means that there is
+    /// no route to the destination.
+    ///
+    /// [policy_routes]: #method.policy_routes
+    /// [routes]: #method.routes
+    /// [poll]: #method.poll
+    pub fn next_hop(&self, dst_addr: IpAddress) -> Option<(IpAddress, bool)> {
+        if dst_addr.is_multicast() {
+            return Some((dst_addr, true));
+        }
+        if !dst_addr.is_unicast() && !dst_addr.is_broadcast() {
+            return None;
+        }
+
+        let next_hop = self.inner.route(&dst_addr, self.inner.now).ok()?;
+        Some((next_hop, next_hop == dst_addr))
+    }
+
+    /// Return the number of ARP requests and NDISC neighbor solicitations that were dropped
+    /// because they exceeded the configured limit.
+    ///
+    /// See also [InterfaceBuilder::neighbor_request_limit].
+    ///
+    /// [InterfaceBuilder::neighbor_request_limit]: struct.InterfaceBuilder.html#method.neighbor_request_limit
+    #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
+    pub fn neighbor_requests_limited(&self) -> u64 {
+        self.inner.neighbor_requests_limited
+    }
+
+    /// Set the observer called for incoming packets that are dropped.
+    ///
+    /// The observer is given the reason and the IP header of every packet dropped after the
+    /// IP header was parsed, so frames dropped at the link layer are not reported. Neither are
+    /// well-formed ICMP, IGMP and NDISC messages that the interface itself handles by ignoring
+    /// them. Reports are rate limited, see [InterfaceBuilder::drop_report_limit].
+    ///
+    /// [InterfaceBuilder::drop_report_limit]: struct.InterfaceBuilder.html#method.drop_report_limit
+    pub fn set_drop_observer(&mut self, observer: Option<DropObserver<'a>>) {
+        self.inner.drop_observer = observer;
+    }
+
     /// Transmit packets queued in the given sockets, and receive packets queued
     /// in the device.
     ///
@@ -769,21 +1800,41 @@ where
     /// be logged.
     pub fn poll(&mut self, timestamp: Instant) -> Result<bool> {
         self.inner.now = timestamp;
+        self.inner.egress_count = 0;
+
+        #[cfg(any(
+            feature = "proto-ipv4-fragmentation",
+            feature = "proto-ipv6-fragmentation"
+        ))]
+        self.inner.expire_fragments();
 
         let mut readiness_may_have_changed = false;
         loop {
             let processed_any = self.socket_ingress();
+            #[cfg(feature = "proto-ipv4-fragmentation")]
+            let sent_fragments = self.ipv4_egress();
+            #[cfg(not(feature = "proto-ipv4-fragmentation"))]
+            let sent_fragments = false;
             let emitted_any = self.socket_egress();
 
             #[cfg(feature = "proto-igmp")]
             self.igmp_egress()?;
 
-            if processed_any || emitted_any {
+            #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+            self.arp_announce_egress()?;
+
+            if processed_any || sent_fragments || emitted_any {
                 readiness_may_have_changed = true;
             } else {
                 break;
             }
         }
+
+        #[cfg(feature = "paranoid")]
+        for item in self.sockets.iter_mut() {
+            item.socket.check_invariants(timestamp);
+        }
+
         Ok(readiness_may_have_changed)
     }
 
@@ -798,6 +1849,16 @@ where
     pub fn poll_at(&mut self, timestamp: Instant) -> Option<Instant> {
         self.inner.now = timestamp;
 
+        #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+        if self.inner.arp_announce_next.is_some() {
+            return Some(Instant::from_millis(0));
+        }
+
+        #[cfg(feature = "proto-ipv4-fragmentation")]
+        if self.inner.ipv4_out_packet.is_pending() {
+            return Some(Instant::from_millis(0));
+        }
+
         let inner = &mut self.inner;
 
         self.sockets
@@ -832,6 +1893,26 @@ where
         }
     }
 
+    /// Send the remaining fragments of the IPv4 packet being fragmented, for as long as the
+    /// device has room for them, and return whether any were sent.
+    #[cfg(feature = "proto-ipv4-fragmentation")]
+    fn ipv4_egress(&mut self) -> bool {
+        let mut sent_any = false;
+        while self.inner.ipv4_out_packet.is_pending() {
+            let tx_token = match self.device.transmit() {
+                Some(tx_token) => tx_token,
+                None => break,
+            };
+            if let Err(err) = self.inner.dispatch_ipv4_fragment(tx_token) {
+                net_debug!("failed to send IPv4 fragment: {}", err);
+                self.inner.ipv4_out_packet.packet_len = 0;
+                break;
+            }
+            sent_any = true;
+        }
+        sent_any
+    }
+
     fn socket_ingress(&mut self) -> bool {
         let mut processed_any = false;
         let Self {
@@ -849,6 +1930,14 @@ where
                             if let Err(err) = inner.dispatch(tx_token, packet) {
                                 net_debug!("Failed to send response: {}", err);
                             }
+                        } else {
+                            #[cfg(any(
+                                feature = "proto-ipv4-fragmentation",
+                                feature = "proto-ipv6-fragmentation"
+                            ))]
+                            if let Err(err) = inner.process_reassembled(sockets, tx_token) {
+                                net_debug!("Failed to send response: {}", err);
+                            }
                         }
                     }
                     #[cfg(feature = "medium-ip")]
@@ -857,6 +1946,14 @@ where
                             if let Err(err) = inner.dispatch_ip(tx_token, packet) {
                                 net_debug!("Failed to send response: {}", err);
                             }
+                        } else {
+                            #[cfg(any(
+                                feature = "proto-ipv4-fragmentation",
+                                feature = "proto-ipv6-fragmentation"
+                            ))]
+                            if let Err(err) = inner.process_reassembled(sockets, tx_token) {
+                                net_debug!("Failed to send response: {}", err);
+                            }
                         }
                     }
                     #[cfg(feature = "medium-ieee802154")]
@@ -889,8 +1986,14 @@ where
         } = self;
         let _caps = device.capabilities();
 
+        // Sockets are offered a transmit opportunity in turn, starting after the last one that
+        // sent something, so that a socket with a lot of data to send can't claim all of them
+        // when the device or the egress limit runs out first.
         let mut emitted_any = false;
-        for item in sockets.iter_mut() {
+        for item in sockets.iter_mut_from(inner.egress_next) {
+            if matches!(inner.egress_limit, Some(limit) if inner.egress_count >= limit) {
+                break;
+            }
             if !item
                 .meta
                 .egress_permitted(inner.now, |ip_addr| inner.has_neighbor(&ip_addr))
@@ -898,11 +2001,14 @@ where
                 continue;
             }
 
+            let handle = item.meta.handle;
             let mut neighbor_addr = None;
             let mut respond = |inner: &mut InterfaceInner, response: IpPacket| {
                 neighbor_addr = Some(response.ip_repr().dst_addr());
                 let tx_token = device.transmit().ok_or(Error::Exhausted)?;
                 inner.dispatch_ip(tx_token, response)?;
+                inner.egress_count += 1;
+                inner.egress_next = handle.index() + 1;
                 emitted_any = true;
                 Ok(())
             };
@@ -926,9 +2032,12 @@ where
                     _ => unreachable!(),
                 }),
                 #[cfg(feature = "socket-udp")]
-                Socket::Udp(socket) => socket.dispatch(inner, |inner, response| {
-                    respond(inner, IpPacket::Udp(response))
-                }),
+                Socket::Udp(socket) => {
+                    let checksum = socket.udp_checksum();
+                    socket.dispatch(inner, |inner, (ip_repr, udp_repr, payload)| {
+                        respond(inner, IpPacket::Udp((ip_repr, udp_repr, payload, checksum)))
+                    })
+                }
                 #[cfg(feature = "socket-tcp")]
                 Socket::Tcp(socket) => socket.dispatch(inner, |inner, response| {
                     respond(inner, IpPacket::Tcp(response))
@@ -938,9 +2047,11 @@ where
                     respond(inner, IpPacket::Dhcpv4(response))
                 }),
                 #[cfg(feature = "socket-dns")]
-                Socket::Dns(ref mut socket) => socket.dispatch(inner, |inner, response| {
-                    respond(inner, IpPacket::Udp(response))
-                }),
+                Socket::Dns(ref mut socket) => {
+                    socket.dispatch(inner, |inner, (ip_repr, udp_repr, payload)| {
+                        respond(inner, IpPacket::Udp((ip_repr, udp_repr, payload, true)))
+                    })
+                }
             };
 
             match result {
@@ -954,6 +2065,9 @@ where
                         inner.now,
                         neighbor_addr.expect("non-IP response packet"),
                     );
+                    // A neighbor discovery request may have been emitted in place of the
+                    // packet, and the answer to it may already be waiting to be received.
+                    emitted_any = true;
                     break;
                 }
                 Err(err) => {
@@ -969,6 +2083,56 @@ where
         emitted_any
     }
 
+    /// Send a gratuitous ARP request for every IPv4 address of the interface, if the hardware
+    /// address changed since the last announcement.
+    ///
+    /// If the device runs out of transmit buffers, the announcement resumes from the address
+    /// that couldn't be announced the next time this function is called.
+    #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+    fn arp_announce_egress(&mut self) -> Result<bool> {
+        let next_index = match self.inner.arp_announce_next {
+            Some(next_index) => next_index,
+            None => return Ok(false),
+        };
+
+        let hardware_addr = match self.inner.hardware_addr {
+            Some(HardwareAddress::Ethernet(addr)) if self.inner.caps.medium == Medium::Ethernet => {
+                addr
+            }
+            _ => {
+                self.inner.arp_announce_next = None;
+                return Ok(false);
+            }
+        };
+
+        for index in next_index..self.inner.ip_addrs.len() {
+            let addr = match self.inner.ip_addrs[index].address() {
+                IpAddress::Ipv4(addr) if !addr.is_unspecified() => addr,
+                _ => continue,
+            };
+
+            net_debug!(
+                "announcing {} at {} with gratuitous ARP",
+                addr,
+                hardware_addr
+            );
+            self.inner.arp_announce_next = Some(index);
+            let tx_token = self.device.transmit().ok_or(Error::Exhausted)?;
+            let arp_repr = ArpRepr::EthernetIpv4 {
+                operation: ArpOperation::Request,
+                source_hardware_addr: hardware_addr,
+                source_protocol_addr: addr,
+                target_hardware_addr: EthernetAddress::BROADCAST,
+                target_protocol_addr: addr,
+            };
+            self.inner
+                .dispatch(tx_token, EthernetPacket::Arp(arp_repr))?;
+        }
+
+        self.inner.arp_announce_next = None;
+        Ok(true)
+    }
+
     /// Depending on `igmp_report_state` and the therein contained
     /// timeouts, send IGMP membership reports.
     #[cfg(feature = "proto-igmp")]
@@ -1031,11 +2195,36 @@ where
 }
 
 impl<'a> InterfaceInner<'a> {
+    /// Default number of neighbor requests processed per `NEIGHBOR_REQUEST_INTERVAL`.
+    #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
+    const NEIGHBOR_REQUEST_LIMIT: u32 = 100;
+    #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
+    const NEIGHBOR_REQUEST_INTERVAL: Duration = Duration::from_secs(1);
+    /// Default number of dropped packets reported per `DROP_REPORT_INTERVAL`.
+    const DROP_REPORT_LIMIT: u32 = 100;
+    const DROP_REPORT_INTERVAL: Duration = Duration::from_secs(1);
+    /// How long the fragments of a packet are kept waiting for the rest.
+    #[cfg(any(
+        feature = "proto-ipv4-fragmentation",
+        feature = "proto-ipv6-fragmentation"
+    ))]
+    const REASSEMBLY_TIMEOUT: Duration = Duration::from_secs(60);
+
     #[allow(unused)] // unused depending on which sockets are enabled
     pub(crate) fn now(&self) -> Instant {
         self.now
     }
 
+    /// Report a dropped incoming packet to the drop observer, if there is one.
+    #[inline]
+    pub(crate) fn observe_drop(&mut self, reason: DropReason, ip_repr: &IpRepr) {
+        if let Some(observer) = self.drop_observer.as_mut() {
+            if self.drop_report_limiter.try_acquire(self.now) {
+                observer(reason, ip_repr)
+            }
+        }
+    }
+
     #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
     #[allow(unused)] // unused depending on which sockets are enabled
     pub(crate) fn hardware_addr(&self) -> Option<HardwareAddress> {
@@ -1049,7 +2238,12 @@ impl<'a> InterfaceInner<'a> {
 
     #[allow(unused)] // unused depending on which sockets are enabled
     pub(crate) fn ip_mtu(&self) -> usize {
-        self.caps.ip_mtu()
+        match self.caps.medium {
+            #[cfg(feature = "medium-ethernet")]
+            Medium::Ethernet => self.caps.ip_mtu() - self.vlan_tags.header_len(),
+            #[allow(unreachable_patterns)]
+            _ => self.caps.ip_mtu(),
+        }
     }
 
     #[allow(unused)] // unused depending on which sockets are enabled, and in tests
@@ -1057,8 +2251,27 @@ impl<'a> InterfaceInner<'a> {
         &mut self.rand
     }
 
+    /// Return the key of the SYN cookies, generating it on first use.
+    #[cfg(feature = "socket-tcp")]
+    pub(crate) fn syn_cookie_secret(&mut self) -> [u8; 16] {
+        if let Some(secret) = self.syn_cookie_secret {
+            return secret;
+        }
+        let mut secret = [0; 16];
+        for chunk in secret.chunks_mut(4) {
+            chunk.copy_from_slice(&self.rand.rand_u32().to_ne_bytes());
+        }
+        self.syn_cookie_secret = Some(secret);
+        secret
+    }
+
     #[allow(unused)] // unused depending on which sockets are enabled
     pub(crate) fn get_source_address(&mut self, dst_addr: IpAddress) -> Option<IpAddress> {
+        #[cfg(feature = "proto-ipv6")]
+        if let IpAddress::Ipv6(dst_addr) = dst_addr {
+            return self.get_source_address_ipv6(dst_addr).map(IpAddress::Ipv6);
+        }
+
         let v = dst_addr.version();
         for cidr in self.ip_addrs.iter() {
             let addr = cidr.address();
@@ -1069,6 +2282,15 @@ impl<'a> InterfaceInner<'a> {
         None
     }
 
+    /// Check whether the given address can be reached without going through a router.
+    ///
+    /// This uses the same next hop selection as egress, so that a destination routed
+    /// through a gateway is never considered to be on-link.
+    #[allow(unused)] // unused depending on which sockets are enabled
+    pub(crate) fn is_on_link(&self, addr: &IpAddress) -> bool {
+        addr.is_multicast() || self.route(addr, self.now).ok() == Some(*addr)
+    }
+
     #[cfg(feature = "proto-ipv4")]
     #[allow(unused)]
     pub(crate) fn get_source_address_ipv4(
@@ -1086,17 +2308,18 @@ impl<'a> InterfaceInner<'a> {
 
     #[cfg(feature = "proto-ipv6")]
     #[allow(unused)]
-    pub(crate) fn get_source_address_ipv6(
-        &mut self,
-        _dst_addr: Ipv6Address,
-    ) -> Option<Ipv6Address> {
+    pub(crate) fn get_source_address_ipv6(&mut self, dst_addr: Ipv6Address) -> Option<Ipv6Address> {
+        let mut best: Option<Ipv6Cidr> = None;
         for cidr in self.ip_addrs.iter() {
             #[allow(irrefutable_let_patterns)] // if only ipv6 is enabled
             if let IpCidr::Ipv6(cidr) = cidr {
-                return Some(cidr.address());
+                match best {
+                    Some(best_cidr) if !ipv6_source_is_better(cidr, &best_cidr, &dst_addr) => {}
+                    _ => best = Some(*cidr),
+                }
             }
         }
-        None
+        best.map(|cidr| cidr.address())
     }
 
     #[cfg(test)]
@@ -1134,7 +2357,19 @@ impl<'a> InterfaceInner<'a> {
                 )),
             ]),
             rand: Rand::new(1234),
+            #[cfg(feature = "socket-tcp")]
+            syn_cookie_secret: None,
+            egress_count: 0,
+            egress_next: 0,
             routes: Routes::new(&mut [][..]),
+            policy_routes: Routes::new(&mut [][..]),
+            accept_redirects: true,
+            icmp_echo_reply: true,
+            egress_limit: None,
+            #[cfg(feature = "socket-tcp")]
+            half_open_limit: None,
+            #[cfg(feature = "proto-ipv6")]
+            ipv6_param_problems: true,
 
             #[cfg(feature = "proto-ipv4")]
             any_ip: false,
@@ -1143,21 +2378,59 @@ impl<'a> InterfaceInner<'a> {
             pan_id: Some(crate::wire::Ieee802154Pan(0xabcd)),
             #[cfg(feature = "medium-ieee802154")]
             sequence_no: 0,
+            #[cfg(feature = "medium-ethernet")]
+            vlan_tags: EthernetVlanTags::Untagged,
 
             #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
             hardware_addr: Some(crate::wire::HardwareAddress::Ethernet(
                 crate::wire::EthernetAddress([0x02, 0x02, 0x02, 0x02, 0x02, 0x02]),
             )),
             #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
+            peer_hardware_addr: None,
+            #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+            arp_announce_next: None,
+            #[cfg(all(feature = "medium-ethernet", feature = "socket-raw"))]
+            rx_ethernet_repr: None,
+            #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
             neighbor_cache: None,
+            #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
+            neighbor_request_limiter: RateLimiter::new(
+                Self::NEIGHBOR_REQUEST_LIMIT,
+                Self::NEIGHBOR_REQUEST_INTERVAL,
+            ),
+            #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
+            neighbor_requests_limited: 0,
+            drop_observer: None,
+            drop_report_limiter: RateLimiter::new(
+                Self::DROP_REPORT_LIMIT,
+                Self::DROP_REPORT_INTERVAL,
+            ),
 
             #[cfg(feature = "proto-igmp")]
             igmp_report_state: IgmpReportState::Inactive,
             #[cfg(feature = "proto-igmp")]
             ipv4_multicast_groups: ManagedMap::Borrowed(&mut []),
+            #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+            rarp_table: ManagedMap::Borrowed(&mut []),
+            #[cfg(feature = "proto-ipv4-fragmentation")]
+            ipv4_fragments: None,
+            #[cfg(feature = "proto-ipv4-fragmentation")]
+            ipv4_reassembled: None,
+            #[cfg(feature = "proto-ipv4-fragmentation")]
+            ipv4_out_packet: Ipv4OutPacket::new(ManagedSlice::Borrowed(&mut [])),
+            #[cfg(feature = "proto-ipv6-fragmentation")]
+            ipv6_fragments: None,
+            #[cfg(feature = "proto-ipv6-fragmentation")]
+            ipv6_reassembled: None,
         }
     }
 
+    /// Return the header of the Ethernet frame being processed, if any.
+    #[cfg(all(feature = "medium-ethernet", feature = "socket-raw"))]
+    pub(crate) fn rx_ethernet_repr(&self) -> Option<EthernetRepr> {
+        self.rx_ethernet_repr
+    }
+
     #[cfg(test)]
     #[allow(unused)] // unused depending on which sockets are enabled
     pub(crate) fn set_now(&mut self, now: Instant) {
@@ -1206,7 +2479,7 @@ impl<'a> InterfaceInner<'a> {
     }
 
     /// Check whether the interface has the given IP address assigned.
-    fn has_ip_addr<T: Into<IpAddress>>(&self, addr: T) -> bool {
+    pub(crate) fn has_ip_addr<T: Into<IpAddress>>(&self, addr: T) -> bool {
         let addr = addr.into();
         self.ip_addrs.iter().any(|probe| probe.address() == addr)
     }
@@ -1246,6 +2519,10 @@ impl<'a> InterfaceInner<'a> {
         sockets: &mut SocketSet,
         frame: &'frame T,
     ) -> Option<EthernetPacket<'frame>> {
+        #[cfg(feature = "socket-raw")]
+        {
+            self.rx_ethernet_repr = None;
+        }
         let eth_frame = check!(EthernetFrame::new_checked(frame));
 
         // Ignore any packets not directed to our hardware address or any of the multicast groups.
@@ -1256,18 +2533,37 @@ impl<'a> InterfaceInner<'a> {
             return None;
         }
 
-        match eth_frame.ethertype() {
+        // Ignore any packets not on our VLAN.
+        if check!(eth_frame.vlan_tags()) != self.vlan_tags {
+            net_trace!("iface: ignoring frame with mismatched VLAN tags");
+            return None;
+        }
+        let payload = check!(eth_frame.tagged_payload());
+        let ethertype = check!(eth_frame.tagged_ethertype());
+
+        #[cfg(feature = "socket-raw")]
+        {
+            self.rx_ethernet_repr = Some(EthernetRepr {
+                src_addr: eth_frame.src_addr(),
+                dst_addr: eth_frame.dst_addr(),
+                ethertype,
+            });
+        }
+
+        match ethertype {
+            #[cfg(feature = "proto-ipv4")]
+            EthernetProtocol::Arp => self.process_arp(self.now, payload),
             #[cfg(feature = "proto-ipv4")]
-            EthernetProtocol::Arp => self.process_arp(self.now, &eth_frame),
+            EthernetProtocol::Rarp => self.process_rarp(payload),
             #[cfg(feature = "proto-ipv4")]
             EthernetProtocol::Ipv4 => {
-                let ipv4_packet = check!(Ipv4Packet::new_checked(eth_frame.payload()));
+                let ipv4_packet = check!(Ipv4Packet::new_checked(payload));
                 self.process_ipv4(sockets, &ipv4_packet)
                     .map(EthernetPacket::Ip)
             }
             #[cfg(feature = "proto-ipv6")]
             EthernetProtocol::Ipv6 => {
-                let ipv6_packet = check!(Ipv6Packet::new_checked(eth_frame.payload()));
+                let ipv6_packet = check!(Ipv6Packet::new_checked(payload));
                 self.process_ipv6(sockets, &ipv6_packet)
                     .map(EthernetPacket::Ip)
             }
@@ -1358,26 +2654,39 @@ impl<'a> InterfaceInner<'a> {
         // Currently we assume the next header is a UDP, so we ignore everything else.
         match iphc_repr.next_header {
             SixlowpanNextHeader::Compressed => {
-                match check!(SixlowpanNhcPacket::dispatch(payload)) {
+                match check!(
+                    SixlowpanNhcPacket::dispatch(payload),
+                    self,
+                    DropReason::Malformed,
+                    &IpRepr::Ipv6(ipv6_repr)
+                ) {
                     SixlowpanNhcPacket::ExtensionHeader(_) => {
                         net_debug!("Extension headers are currently not supported for 6LoWPAN");
+                        self.observe_drop(DropReason::NoSocket, &IpRepr::Ipv6(ipv6_repr));
                         None
                     }
                     #[cfg(not(feature = "socket-udp"))]
                     SixlowpanNhcPacket::UdpHeader(_) => {
                         net_debug!("UDP support is disabled, enable cargo feature `socket-udp`.");
+                        ipv6_repr.next_header = IpProtocol::Udp;
+                        self.observe_drop(DropReason::NoSocket, &IpRepr::Ipv6(ipv6_repr));
                         None
                     }
                     #[cfg(feature = "socket-udp")]
                     SixlowpanNhcPacket::UdpHeader(udp_packet) => {
                         ipv6_repr.next_header = IpProtocol::Udp;
                         // Handle the UDP
-                        let udp_repr = check!(SixlowpanUdpRepr::parse(
-                            &udp_packet,
-                            &iphc_repr.src_addr,
-                            &iphc_repr.dst_addr,
-                            udp_packet.checksum(),
-                        ));
+                        let udp_repr = check!(
+                            SixlowpanUdpRepr::parse(
+                                &udp_packet,
+                                &iphc_repr.src_addr,
+                                &iphc_repr.dst_addr,
+                                udp_packet.checksum(),
+                            ),
+                            self,
+                            DropReason::Malformed,
+                            &IpRepr::Ipv6(ipv6_repr)
+                        );
 
                         // Look for UDP sockets that will accept the UDP packet.
                         // If it does not accept the packet, then send an ICMP message.
@@ -1396,6 +2705,7 @@ impl<'a> InterfaceInner<'a> {
                             }
                         }
 
+                        self.observe_drop(DropReason::NoSocket, &IpRepr::Ipv6(ipv6_repr));
                         let payload_len = icmp_reply_payload_len(
                             payload.len(),
                             IPV6_MIN_MTU,
@@ -1417,6 +2727,8 @@ impl<'a> InterfaceInner<'a> {
                 }
                 _ => {
                     net_debug!("Headers other than ICMPv6 and compressed headers are currently not supported for 6LoWPAN");
+                    ipv6_repr.next_header = nxt_hdr;
+                    self.observe_drop(DropReason::NoSocket, &IpRepr::Ipv6(ipv6_repr));
                     None
                 }
             },
@@ -1424,12 +2736,12 @@ impl<'a> InterfaceInner<'a> {
     }
 
     #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
-    fn process_arp<'frame, T: AsRef<[u8]>>(
+    fn process_arp<'frame>(
         &mut self,
         timestamp: Instant,
-        eth_frame: &EthernetFrame<&'frame T>,
+        arp_payload: &'frame [u8],
     ) -> Option<EthernetPacket<'frame>> {
-        let arp_packet = check!(ArpPacket::new_checked(eth_frame.payload()));
+        let arp_packet = check!(ArpPacket::new_checked(arp_payload));
         let arp_repr = check!(ArpRepr::parse(&arp_packet));
 
         match arp_repr {
@@ -1445,9 +2757,17 @@ impl<'a> InterfaceInner<'a> {
                     return None;
                 }
 
-                // Only process REQUEST and RESPONSE.
-                if let ArpOperation::Unknown(_) = operation {
-                    net_debug!("arp: unknown operation code");
+                // Only process REQUEST and RESPONSE; RARP operations have no business here.
+                match operation {
+                    ArpOperation::Request | ArpOperation::Reply => {}
+                    _ => {
+                        net_debug!("arp: unsupported operation code");
+                        return None;
+                    }
+                }
+
+                if operation == ArpOperation::Request && !self.neighbor_request_permitted() {
+                    net_debug!("arp: request rate limit exceeded");
                     return None;
                 }
 
@@ -1492,12 +2812,74 @@ impl<'a> InterfaceInner<'a> {
         }
     }
 
+    #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+    fn process_rarp<'frame>(
+        &mut self,
+        rarp_payload: &'frame [u8],
+    ) -> Option<EthernetPacket<'frame>> {
+        let rarp_packet = check!(ArpPacket::new_checked(rarp_payload));
+        let rarp_repr = check!(ArpRepr::parse(&rarp_packet));
+
+        match rarp_repr {
+            ArpRepr::EthernetIpv4 {
+                operation,
+                source_hardware_addr,
+                target_hardware_addr,
+                ..
+            } => {
+                // We are only ever a RARP server, so replies are of no interest.
+                if operation != ArpOperation::RarpRequest {
+                    net_debug!("rarp: unsupported operation code");
+                    return None;
+                }
+
+                // Only answer for hosts we have been told about.
+                let mapped_protocol_addr = match self.rarp_table.get(&target_hardware_addr) {
+                    Some(addr) => *addr,
+                    None => return None,
+                };
+
+                if !source_hardware_addr.is_unicast() {
+                    net_debug!("rarp: non-unicast source address");
+                    return None;
+                }
+
+                let src_protocol_addr = match self.ipv4_address() {
+                    Some(addr) => addr,
+                    None => {
+                        net_debug!("rarp: no IPv4 address to reply from");
+                        return None;
+                    }
+                };
+
+                if !self.neighbor_request_permitted() {
+                    net_debug!("rarp: request rate limit exceeded");
+                    return None;
+                }
+
+                let src_hardware_addr = match self.hardware_addr {
+                    Some(HardwareAddress::Ethernet(addr)) => addr,
+                    _ => unreachable!(),
+                };
+
+                Some(EthernetPacket::Arp(ArpRepr::EthernetIpv4 {
+                    operation: ArpOperation::RarpReply,
+                    source_hardware_addr: src_hardware_addr,
+                    source_protocol_addr: src_protocol_addr,
+                    target_hardware_addr,
+                    target_protocol_addr: mapped_protocol_addr,
+                }))
+            }
+        }
+    }
+
     #[cfg(feature = "socket-raw")]
     fn raw_socket_filter<'frame>(
         &mut self,
         sockets: &mut SocketSet,
         ip_repr: &IpRepr,
-        ip_payload: &'frame [u8],
+        ip_packet: &'frame [u8],
+        is_fragment: bool,
     ) -> bool {
         let mut handled_by_raw_socket = false;
 
@@ -1506,8 +2888,11 @@ impl<'a> InterfaceInner<'a> {
             .iter_mut()
             .filter_map(|i| raw::Socket::downcast(&mut i.socket))
         {
+            if is_fragment && !raw_socket.pass_fragments() {
+                continue;
+            }
             if raw_socket.accepts(ip_repr) {
-                raw_socket.process(self, ip_repr, ip_payload);
+                raw_socket.process(self, ip_repr, ip_packet);
                 handled_by_raw_socket = true;
             }
         }
@@ -1520,41 +2905,80 @@ impl<'a> InterfaceInner<'a> {
         sockets: &mut SocketSet,
         ipv6_packet: &Ipv6Packet<&'frame T>,
     ) -> Option<IpPacket<'frame>> {
-        let ipv6_repr = check!(Ipv6Repr::parse(ipv6_packet));
+        let ipv6_repr = check!(
+            Ipv6Repr::parse(ipv6_packet),
+            self,
+            DropReason::Malformed,
+            &IpRepr::Ipv6(Ipv6Repr {
+                src_addr: ipv6_packet.src_addr(),
+                dst_addr: ipv6_packet.dst_addr(),
+                next_header: ipv6_packet.next_header(),
+                payload_len: ipv6_packet.payload_len() as usize,
+                hop_limit: ipv6_packet.hop_limit(),
+            })
+        );
 
         if !ipv6_repr.src_addr.is_unicast() {
             // Discard packets with non-unicast source addresses.
             net_debug!("non-unicast source address");
+            self.observe_drop(DropReason::Filtered, &ipv6_repr.into());
             return None;
         }
 
         let ip_payload = ipv6_packet.payload();
 
         #[cfg(feature = "socket-raw")]
-        let handled_by_raw_socket = self.raw_socket_filter(sockets, &ipv6_repr.into(), ip_payload);
+        let handled_by_raw_socket = self.raw_socket_filter(
+            sockets,
+            &ipv6_repr.into(),
+            &ipv6_packet.as_ref()[..ipv6_packet.total_len()],
+            false,
+        );
         #[cfg(not(feature = "socket-raw"))]
         let handled_by_raw_socket = false;
 
+        if ipv6_repr.dst_addr.is_unicast() && !self.has_ip_addr(ipv6_repr.dst_addr) {
+            // Ignore unicast packets not directed at us, though sockets in transparent mode
+            // may still accept them.
+            #[cfg(any(feature = "socket-tcp", feature = "socket-udp"))]
+            return self.process_transparent(sockets, ipv6_repr.into(), ip_payload);
+            #[cfg(not(any(feature = "socket-tcp", feature = "socket-udp")))]
+            {
+                self.observe_drop(DropReason::NoRoute, &ipv6_repr.into());
+                return None;
+            }
+        }
+
         self.process_nxt_hdr(
             sockets,
             ipv6_repr,
             ipv6_repr.next_header,
+            IPV6_NEXT_HEADER_OFFSET,
             handled_by_raw_socket,
             ip_payload,
+            0,
         )
     }
 
     /// Given the next header value forward the payload onto the correct process
     /// function.
+    ///
+    /// The header identified by `nxt_hdr` starts `offset` octets into `ipv6_payload`, and
+    /// `nxt_hdr_pointer` is the offset of the field it was read from, counting from the start
+    /// of the IPv6 header.
     #[cfg(feature = "proto-ipv6")]
+    #[allow(clippy::too_many_arguments)]
     fn process_nxt_hdr<'frame>(
         &mut self,
         sockets: &mut SocketSet,
         ipv6_repr: Ipv6Repr,
         nxt_hdr: IpProtocol,
+        nxt_hdr_pointer: usize,
         handled_by_raw_socket: bool,
-        ip_payload: &'frame [u8],
+        ipv6_payload: &'frame [u8],
+        offset: usize,
     ) -> Option<IpPacket<'frame>> {
+        let ip_payload = &ipv6_payload[offset..];
         match nxt_hdr {
             IpProtocol::Icmpv6 => self.process_icmpv6(sockets, ipv6_repr.into(), ip_payload),
 
@@ -1566,48 +2990,260 @@ impl<'a> InterfaceInner<'a> {
             #[cfg(feature = "socket-tcp")]
             IpProtocol::Tcp => self.process_tcp(sockets, ipv6_repr.into(), ip_payload),
 
-            IpProtocol::HopByHop => {
-                self.process_hopbyhop(sockets, ipv6_repr, handled_by_raw_socket, ip_payload)
+            IpProtocol::HopByHop => self.process_hopbyhop(
+                sockets,
+                ipv6_repr,
+                handled_by_raw_socket,
+                ipv6_payload,
+                offset,
+            ),
+
+            #[cfg(feature = "proto-ipv6-fragmentation")]
+            IpProtocol::Ipv6Frag => {
+                self.process_ipv6_fragment(ipv6_repr, handled_by_raw_socket, ipv6_payload, offset)
             }
 
             #[cfg(feature = "socket-raw")]
             _ if handled_by_raw_socket => None,
 
             _ => {
-                // Send back as much of the original payload as we can.
-                let payload_len =
-                    icmp_reply_payload_len(ip_payload.len(), IPV6_MIN_MTU, ipv6_repr.buffer_len());
-                let icmp_reply_repr = Icmpv6Repr::ParamProblem {
-                    reason: Icmpv6ParamProblem::UnrecognizedNxtHdr,
-                    // The offending packet is after the IPv6 header.
-                    pointer: ipv6_repr.buffer_len() as u32,
-                    header: ipv6_repr,
-                    data: &ip_payload[0..payload_len],
-                };
-                self.icmpv6_reply(ipv6_repr, icmp_reply_repr)
+                self.observe_drop(DropReason::NoSocket, &ipv6_repr.into());
+                self.icmpv6_param_problem(
+                    ipv6_repr,
+                    Icmpv6ParamProblem::UnrecognizedNxtHdr,
+                    nxt_hdr_pointer,
+                    ipv6_payload,
+                    false,
+                )
+            }
+        }
+    }
+
+    /// Deliver an IPv4 fragment to the raw sockets that asked for fragments, and add it to
+    /// the packet being reassembled, if reassembly is enabled.
+    ///
+    /// A reassembled packet is processed by [process_reassembled].
+    ///
+    /// [process_reassembled]: #method.process_reassembled
+    #[cfg(all(
+        feature = "proto-ipv4",
+        any(feature = "socket-raw", feature = "proto-ipv4-fragmentation")
+    ))]
+    #[cfg_attr(not(feature = "socket-raw"), allow(unused_variables))]
+    fn process_ipv4_fragment<'frame, T: AsRef<[u8]> + ?Sized>(
+        &mut self,
+        sockets: &mut SocketSet,
+        ipv4_packet: &Ipv4Packet<&'frame T>,
+    ) -> Option<IpPacket<'frame>> {
+        let ip_repr = IpRepr::Ipv4(ipv4_header_repr(ipv4_packet));
+        let bad_checksum = self.caps.checksum.ipv4.rx() && !ipv4_packet.verify_checksum();
+        if ipv4_packet.version() != 4 || bad_checksum {
+            net_debug!("malformed IPv4 fragment");
+            self.observe_drop(DropReason::parse_failure(bad_checksum), &ip_repr);
+            return None;
+        }
+
+        let (src_addr, dst_addr) = (ipv4_packet.src_addr(), ipv4_packet.dst_addr());
+        if !self.is_unicast_v4(src_addr) {
+            net_debug!("non-unicast source address");
+            self.observe_drop(DropReason::Filtered, &ip_repr);
+            return None;
+        }
+        if !self.has_ip_addr(dst_addr)
+            && !self.has_multicast_group(dst_addr)
+            && !self.is_broadcast_v4(dst_addr)
+        {
+            self.observe_drop(DropReason::NoRoute, &ip_repr);
+            return None;
+        }
+
+        #[cfg(feature = "socket-raw")]
+        let handled_by_raw_socket = {
+            let ip_packet = &ipv4_packet.as_ref()[..ipv4_packet.total_len() as usize];
+            self.raw_socket_filter(sockets, &ip_repr, ip_packet, true)
+        };
+        #[cfg(not(feature = "socket-raw"))]
+        let handled_by_raw_socket = false;
+
+        #[cfg(feature = "proto-ipv4-fragmentation")]
+        let reassembling = self.reassemble_ipv4_fragment(ipv4_packet);
+        #[cfg(not(feature = "proto-ipv4-fragmentation"))]
+        let reassembling = false;
+
+        if !handled_by_raw_socket && !reassembling {
+            net_debug!("dropping IPv4 fragment");
+            self.observe_drop(DropReason::Fragment, &ip_repr);
+        }
+        None
+    }
+
+    /// Add an IPv4 fragment to the packet it belongs to, and return whether it was added.
+    ///
+    /// The packet is reassembled with a header without options, in front of the payload,
+    /// which is written once the last fragment arrives.
+    #[cfg(feature = "proto-ipv4-fragmentation")]
+    fn reassemble_ipv4_fragment<T: AsRef<[u8]> + ?Sized>(
+        &mut self,
+        ipv4_packet: &Ipv4Packet<&T>,
+    ) -> bool {
+        let now = self.now;
+        let fragments = match self.ipv4_fragments.as_mut() {
+            Some(fragments) => fragments,
+            None => return false,
+        };
+
+        let key = ipv4_packet.get_key();
+        let offset = IPV4_HEADER_LEN + ipv4_packet.frag_offset() as usize;
+        let payload = ipv4_packet.payload();
+        let mut header = [0; IPV4_HEADER_LEN];
+        if !ipv4_packet.more_frags() {
+            let mut repr = ipv4_header_repr(ipv4_packet);
+            repr.payload_len = offset + payload.len() - IPV4_HEADER_LEN;
+            repr.emit(
+                &mut Ipv4Packet::new_unchecked(&mut header[..]),
+                &ChecksumCapabilities::default(),
+            );
+        }
+        let header = if ipv4_packet.more_frags() {
+            None
+        } else {
+            Some(&header[..])
+        };
+
+        match fragments.add_fragment(&key, u16::MAX as usize, header, payload, offset, now) {
+            Ok(true) => {
+                self.ipv4_reassembled = Some(key);
+                true
+            }
+            Ok(false) => true,
+            Err(err) => {
+                net_debug!("cannot reassemble IPv4 fragment: {}", err);
+                false
             }
         }
     }
 
+    /// Process the IPv4 or IPv6 packet that was just reassembled, if any, and send the
+    /// response to it with `tx_token`.
+    #[cfg(any(
+        feature = "proto-ipv4-fragmentation",
+        feature = "proto-ipv6-fragmentation"
+    ))]
+    fn process_reassembled<Tx: TxToken>(
+        &mut self,
+        sockets: &mut SocketSet,
+        tx_token: Tx,
+    ) -> Result<()> {
+        // The reassembled packet is borrowed from the storage while it is processed.
+        #[cfg(feature = "proto-ipv4-fragmentation")]
+        if let Some(key) = self.ipv4_reassembled.take() {
+            let mut fragments = match self.ipv4_fragments.take() {
+                Some(fragments) => fragments,
+                None => return Ok(()),
+            };
+            let res = match fragments.get_assembled_packet(&key) {
+                Ok(packet) => {
+                    match self.process_ipv4(sockets, &Ipv4Packet::new_unchecked(packet)) {
+                        Some(response) => self.dispatch_ip(tx_token, response),
+                        None => Ok(()),
+                    }
+                }
+                Err(err) => Err(err),
+            };
+            self.ipv4_fragments = Some(fragments);
+            return res;
+        }
+
+        #[cfg(feature = "proto-ipv6-fragmentation")]
+        if let Some(key) = self.ipv6_reassembled.take() {
+            let mut fragments = match self.ipv6_fragments.take() {
+                Some(fragments) => fragments,
+                None => return Ok(()),
+            };
+            let res = match fragments.get_assembled_packet(&key) {
+                Ok(packet) => {
+                    match self.process_ipv6(sockets, &Ipv6Packet::new_unchecked(packet)) {
+                        Some(response) => self.dispatch_ip(tx_token, response),
+                        None => Ok(()),
+                    }
+                }
+                Err(err) => Err(err),
+            };
+            self.ipv6_fragments = Some(fragments);
+            return res;
+        }
+
+        Ok(())
+    }
+
+    /// Discard the packets whose fragments didn't all arrive in time.
+    #[cfg(any(
+        feature = "proto-ipv4-fragmentation",
+        feature = "proto-ipv6-fragmentation"
+    ))]
+    fn expire_fragments(&mut self) {
+        let now = self.now;
+        let expired = |assembler: &mut PacketAssembler<'_>| {
+            Ok(assembler
+                .start_time()
+                .map_or(true, |start| now >= start + Self::REASSEMBLY_TIMEOUT))
+        };
+        #[cfg(feature = "proto-ipv4-fragmentation")]
+        if let Some(fragments) = self.ipv4_fragments.as_mut() {
+            let _ = fragments.remove_when(expired);
+        }
+        #[cfg(feature = "proto-ipv6-fragmentation")]
+        if let Some(fragments) = self.ipv6_fragments.as_mut() {
+            let _ = fragments.remove_when(expired);
+        }
+    }
+
     #[cfg(feature = "proto-ipv4")]
     fn process_ipv4<'frame, T: AsRef<[u8]> + ?Sized>(
         &mut self,
         sockets: &mut SocketSet,
         ipv4_packet: &Ipv4Packet<&'frame T>,
     ) -> Option<IpPacket<'frame>> {
-        let ipv4_repr = check!(Ipv4Repr::parse(ipv4_packet, &self.caps.checksum));
+        if ipv4_packet.more_frags() || ipv4_packet.frag_offset() != 0 {
+            #[cfg(any(feature = "socket-raw", feature = "proto-ipv4-fragmentation"))]
+            return self.process_ipv4_fragment(sockets, ipv4_packet);
+            #[cfg(not(any(feature = "socket-raw", feature = "proto-ipv4-fragmentation")))]
+            {
+                net_debug!("dropping IPv4 fragment");
+                self.observe_drop(
+                    DropReason::Fragment,
+                    &IpRepr::Ipv4(ipv4_header_repr(ipv4_packet)),
+                );
+                return None;
+            }
+        }
+
+        let ipv4_repr = check!(
+            Ipv4Repr::parse(ipv4_packet, &self.caps.checksum),
+            self,
+            DropReason::parse_failure(
+                self.caps.checksum.ipv4.rx() && !ipv4_packet.verify_checksum()
+            ),
+            &IpRepr::Ipv4(ipv4_header_repr(ipv4_packet))
+        );
+        let ip_repr = IpRepr::Ipv4(ipv4_repr);
 
         if !self.is_unicast_v4(ipv4_repr.src_addr) {
             // Discard packets with non-unicast source addresses.
             net_debug!("non-unicast source address");
+            self.observe_drop(DropReason::Filtered, &ip_repr);
             return None;
         }
 
-        let ip_repr = IpRepr::Ipv4(ipv4_repr);
         let ip_payload = ipv4_packet.payload();
 
         #[cfg(feature = "socket-raw")]
-        let handled_by_raw_socket = self.raw_socket_filter(sockets, &ip_repr, ip_payload);
+        let handled_by_raw_socket = self.raw_socket_filter(
+            sockets,
+            &ip_repr,
+            &ipv4_packet.as_ref()[..ipv4_packet.total_len() as usize],
+            false,
+        );
         #[cfg(not(feature = "socket-raw"))]
         let handled_by_raw_socket = false;
 
@@ -1616,7 +3252,12 @@ impl<'a> InterfaceInner<'a> {
             if ipv4_repr.next_header == IpProtocol::Udp && self.hardware_addr.is_some() {
                 // First check for source and dest ports, then do `UdpRepr::parse` if they match.
                 // This way we avoid validating the UDP checksum twice for all non-DHCP UDP packets (one here, one in `process_udp`)
-                let udp_packet = check!(UdpPacket::new_checked(ip_payload));
+                let udp_packet = check!(
+                    UdpPacket::new_checked(ip_payload),
+                    self,
+                    DropReason::Malformed,
+                    &ip_repr
+                );
                 if udp_packet.src_port() == DHCP_SERVER_PORT
                     && udp_packet.dst_port() == DHCP_CLIENT_PORT
                 {
@@ -1626,12 +3267,15 @@ impl<'a> InterfaceInner<'a> {
                         .next()
                     {
                         let (src_addr, dst_addr) = (ip_repr.src_addr(), ip_repr.dst_addr());
-                        let udp_repr = check!(UdpRepr::parse(
-                            &udp_packet,
-                            &src_addr,
-                            &dst_addr,
-                            &self.caps.checksum
-                        ));
+                        let udp_repr = check!(
+                            UdpRepr::parse(&udp_packet, &src_addr, &dst_addr, &self.caps.checksum),
+                            self,
+                            DropReason::parse_failure(
+                                self.caps.checksum.udp.rx()
+                                    && !udp_packet.verify_checksum(&src_addr, &dst_addr)
+                            ),
+                            &ip_repr
+                        );
                         let udp_payload = udp_packet.payload();
 
                         dhcp_socket.process(self, &ipv4_repr, &udp_repr, udp_payload);
@@ -1654,6 +3298,12 @@ impl<'a> InterfaceInner<'a> {
                     .lookup(&IpAddress::Ipv4(ipv4_repr.dst_addr), self.now)
                     .map_or(true, |router_addr| !self.has_ip_addr(router_addr))
             {
+                // Sockets in transparent mode may still accept the packet.
+                #[cfg(any(feature = "socket-tcp", feature = "socket-udp"))]
+                if ipv4_repr.dst_addr.is_unicast() {
+                    return self.process_transparent(sockets, ip_repr, ip_payload);
+                }
+                self.observe_drop(DropReason::NoRoute, &ip_repr);
                 return None;
             }
         }
@@ -1675,6 +3325,7 @@ impl<'a> InterfaceInner<'a> {
             _ if handled_by_raw_socket => None,
 
             _ => {
+                self.observe_drop(DropReason::NoSocket, &ip_repr);
                 // Send back as much of the original payload as we can.
                 let payload_len =
                     icmp_reply_payload_len(ip_payload.len(), IPV4_MIN_MTU, ipv4_repr.buffer_len());
@@ -1725,8 +3376,18 @@ impl<'a> InterfaceInner<'a> {
         ipv4_repr: Ipv4Repr,
         ip_payload: &'frame [u8],
     ) -> Option<IpPacket<'frame>> {
-        let igmp_packet = check!(IgmpPacket::new_checked(ip_payload));
-        let igmp_repr = check!(IgmpRepr::parse(&igmp_packet));
+        let igmp_packet = check!(
+            IgmpPacket::new_checked(ip_payload),
+            self,
+            DropReason::Malformed,
+            &IpRepr::Ipv4(ipv4_repr)
+        );
+        let igmp_repr = check!(
+            IgmpRepr::parse(&igmp_packet),
+            self,
+            DropReason::parse_failure(!igmp_packet.verify_checksum()),
+            &IpRepr::Ipv4(ipv4_repr)
+        );
 
         // FIXME: report membership after a delay
         match igmp_repr {
@@ -1787,13 +3448,22 @@ impl<'a> InterfaceInner<'a> {
         ip_repr: IpRepr,
         ip_payload: &'frame [u8],
     ) -> Option<IpPacket<'frame>> {
-        let icmp_packet = check!(Icmpv6Packet::new_checked(ip_payload));
-        let icmp_repr = check!(Icmpv6Repr::parse(
-            &ip_repr.src_addr(),
-            &ip_repr.dst_addr(),
-            &icmp_packet,
-            &self.caps.checksum,
-        ));
+        let (src_addr, dst_addr) = (ip_repr.src_addr(), ip_repr.dst_addr());
+        let icmp_packet = check!(
+            Icmpv6Packet::new_checked(ip_payload),
+            self,
+            DropReason::Malformed,
+            &ip_repr
+        );
+        let icmp_repr = check!(
+            Icmpv6Repr::parse(&src_addr, &dst_addr, &icmp_packet, &self.caps.checksum),
+            self,
+            DropReason::parse_failure(
+                self.caps.checksum.icmpv6.rx()
+                    && !icmp_packet.verify_checksum(&src_addr, &dst_addr)
+            ),
+            &ip_repr
+        );
 
         #[cfg(feature = "socket-icmp")]
         let mut handled_by_icmp_socket = false;
@@ -1810,7 +3480,8 @@ impl<'a> InterfaceInner<'a> {
         }
 
         match icmp_repr {
-            // Respond to echo requests.
+            // Respond to echo requests, unless the application does.
+            Icmpv6Repr::EchoRequest { .. } if !self.icmp_echo_reply => None,
             Icmpv6Repr::EchoRequest {
                 ident,
                 seq_no,
@@ -1866,7 +3537,12 @@ impl<'a> InterfaceInner<'a> {
             } => {
                 let ip_addr = ip_repr.src_addr.into();
                 if let Some(lladdr) = lladdr {
-                    let lladdr = check!(lladdr.parse(self.caps.medium));
+                    let lladdr = check!(
+                        lladdr.parse(self.caps.medium),
+                        self,
+                        DropReason::Malformed,
+                        &IpRepr::Ipv6(ip_repr)
+                    );
                     if !lladdr.is_unicast() || !target_addr.is_unicast() {
                         return None;
                     }
@@ -1891,8 +3567,21 @@ impl<'a> InterfaceInner<'a> {
                 lladdr,
                 ..
             } => {
+                // Only solicitations for our addresses are answered, and rate limited.
+                let for_us =
+                    self.has_solicited_node(ip_repr.dst_addr) && self.has_ip_addr(target_addr);
+                if for_us && !self.neighbor_request_permitted() {
+                    net_debug!("ndisc: solicitation rate limit exceeded");
+                    return None;
+                }
+
                 if let Some(lladdr) = lladdr {
-                    let lladdr = check!(lladdr.parse(self.caps.medium));
+                    let lladdr = check!(
+                        lladdr.parse(self.caps.medium),
+                        self,
+                        DropReason::Malformed,
+                        &IpRepr::Ipv6(ip_repr)
+                    );
                     if !lladdr.is_unicast() || !target_addr.is_unicast() {
                         return None;
                     }
@@ -1903,7 +3592,7 @@ impl<'a> InterfaceInner<'a> {
                     );
                 }
 
-                if self.has_solicited_node(ip_repr.dst_addr) && self.has_ip_addr(target_addr) {
+                if for_us {
                     let advert = Icmpv6Repr::Ndisc(NdiscRepr::NeighborAdvert {
                         flags: NdiscNeighborFlags::SOLICITED,
                         target_addr,
@@ -1922,6 +3611,44 @@ impl<'a> InterfaceInner<'a> {
                     None
                 }
             }
+            NdiscRepr::Redirect {
+                target_addr,
+                dest_addr,
+                lladdr,
+                ..
+            } => {
+                // RFC 4861 § 8.1: redirects come from a router's link-local address, and point
+                // either at another router (by its link-local address) or at the destination
+                // itself, which is then on-link.
+                if !ip_repr.src_addr.is_link_local()
+                    || !dest_addr.is_unicast()
+                    || !(target_addr.is_link_local() || target_addr == dest_addr)
+                {
+                    net_debug!("ignoring invalid redirect from {}", ip_repr.src_addr);
+                    return None;
+                }
+                let accepted = self.process_redirect(
+                    ip_repr.src_addr.into(),
+                    dest_addr.into(),
+                    target_addr.into(),
+                );
+                if let (true, Some(lladdr)) = (accepted, lladdr) {
+                    let lladdr = check!(
+                        lladdr.parse(self.caps.medium),
+                        self,
+                        DropReason::Malformed,
+                        &IpRepr::Ipv6(ip_repr)
+                    );
+                    if lladdr.is_unicast() {
+                        self.neighbor_cache.as_mut().unwrap().fill(
+                            target_addr.into(),
+                            lladdr,
+                            self.now,
+                        );
+                    }
+                }
+                None
+            }
             _ => None,
         }
     }
@@ -1932,38 +3659,132 @@ impl<'a> InterfaceInner<'a> {
         sockets: &mut SocketSet,
         ipv6_repr: Ipv6Repr,
         handled_by_raw_socket: bool,
-        ip_payload: &'frame [u8],
+        ipv6_payload: &'frame [u8],
+        offset: usize,
     ) -> Option<IpPacket<'frame>> {
-        let hbh_pkt = check!(Ipv6HopByHopHeader::new_checked(ip_payload));
-        let hbh_repr = check!(Ipv6HopByHopRepr::parse(&hbh_pkt));
+        let hbh_pkt = check!(
+            Ipv6HopByHopHeader::new_checked(&ipv6_payload[offset..]),
+            self,
+            DropReason::Malformed,
+            &ipv6_repr.into()
+        );
+        let hbh_repr = check!(
+            Ipv6HopByHopRepr::parse(&hbh_pkt),
+            self,
+            DropReason::Malformed,
+            &ipv6_repr.into()
+        );
+        // The options follow the Next Header and Hdr Ext Len fields.
+        let mut opt_offset = ipv6_repr.buffer_len() + offset + 2;
         for opt_repr in hbh_repr.options() {
-            let opt_repr = check!(opt_repr);
+            let opt_repr = check!(opt_repr, self, DropReason::Malformed, &ipv6_repr.into());
             match opt_repr {
                 Ipv6OptionRepr::Pad1 | Ipv6OptionRepr::PadN(_) => (),
-                Ipv6OptionRepr::Unknown { type_, .. } => {
-                    match Ipv6OptionFailureType::from(type_) {
-                        Ipv6OptionFailureType::Skip => (),
-                        Ipv6OptionFailureType::Discard => {
-                            return None;
-                        }
-                        _ => {
-                            // FIXME(dlrobertson): Send an ICMPv6 parameter problem message
-                            // here.
-                            return None;
-                        }
+                Ipv6OptionRepr::Unknown { type_, .. } => match Ipv6OptionFailureType::from(type_) {
+                    Ipv6OptionFailureType::Skip => (),
+                    Ipv6OptionFailureType::Discard => {
+                        self.observe_drop(DropReason::Malformed, &ipv6_repr.into());
+                        return None;
                     }
-                }
+                    failure_type => {
+                        net_debug!("unrecognized IPv6 option: {}", failure_type);
+                        self.observe_drop(DropReason::Malformed, &ipv6_repr.into());
+                        return self.icmpv6_param_problem(
+                            ipv6_repr,
+                            Icmpv6ParamProblem::UnrecognizedOption,
+                            opt_offset,
+                            ipv6_payload,
+                            failure_type == Ipv6OptionFailureType::DiscardSendAll,
+                        );
+                    }
+                },
             }
+            opt_offset += opt_repr.buffer_len();
         }
         self.process_nxt_hdr(
             sockets,
             ipv6_repr,
             hbh_repr.next_header,
+            ipv6_repr.buffer_len() + offset,
             handled_by_raw_socket,
-            &ip_payload[hbh_repr.buffer_len()..],
+            ipv6_payload,
+            offset + hbh_repr.buffer_len(),
         )
     }
 
+    /// Add an IPv6 fragment to the packet it belongs to, if reassembly is enabled.
+    ///
+    /// The Fragment header starts `offset` octets into `ipv6_payload`. The packet is
+    /// reassembled with an IPv6 header without extension headers in front of the
+    /// Fragmentable Part, which is written once the last fragment arrives, and processed by
+    /// [process_reassembled].
+    ///
+    /// [process_reassembled]: #method.process_reassembled
+    #[cfg(feature = "proto-ipv6-fragmentation")]
+    fn process_ipv6_fragment<'frame>(
+        &mut self,
+        ipv6_repr: Ipv6Repr,
+        handled_by_raw_socket: bool,
+        ipv6_payload: &'frame [u8],
+        offset: usize,
+    ) -> Option<IpPacket<'frame>> {
+        let frag_header = check!(
+            Ipv6FragmentHeader::new_checked(&ipv6_payload[offset..]),
+            self,
+            DropReason::Malformed,
+            &ipv6_repr.into()
+        );
+        let frag_repr = check!(
+            Ipv6FragmentRepr::parse(&frag_header),
+            self,
+            DropReason::Malformed,
+            &ipv6_repr.into()
+        );
+
+        let now = self.now;
+        let fragments = match self.ipv6_fragments.as_mut() {
+            Some(fragments) => fragments,
+            None => {
+                if !handled_by_raw_socket {
+                    net_debug!("dropping IPv6 fragment");
+                    self.observe_drop(DropReason::Fragment, &ipv6_repr.into());
+                }
+                return None;
+            }
+        };
+
+        let key = frag_repr.get_key(&ipv6_repr);
+        let data_offset = IPV6_HEADER_LEN + frag_repr.frag_offset as usize * 8;
+        let data = &ipv6_payload[offset + frag_repr.buffer_len()..];
+        let mut header = [0; IPV6_HEADER_LEN];
+        if !frag_repr.more_frags {
+            let repr = Ipv6Repr {
+                next_header: frag_repr.next_header,
+                payload_len: data_offset + data.len() - IPV6_HEADER_LEN,
+                ..ipv6_repr
+            };
+            repr.emit(&mut Ipv6Packet::new_unchecked(&mut header[..]));
+        }
+        let header = if frag_repr.more_frags {
+            None
+        } else {
+            Some(&header[..])
+        };
+
+        let max_size = IPV6_HEADER_LEN + u16::MAX as usize;
+        match fragments.add_fragment(&key, max_size, header, data, data_offset, now) {
+            Ok(true) => self.ipv6_reassembled = Some(key),
+            Ok(false) => (),
+            Err(err) => {
+                net_debug!("cannot reassemble IPv6 fragment: {}", err);
+                if !handled_by_raw_socket {
+                    self.observe_drop(DropReason::Fragment, &ipv6_repr.into());
+                }
+            }
+        }
+        None
+    }
+
     #[cfg(feature = "proto-ipv4")]
     fn process_icmpv4<'frame>(
         &mut self,
@@ -1971,8 +3792,20 @@ impl<'a> InterfaceInner<'a> {
         ip_repr: IpRepr,
         ip_payload: &'frame [u8],
     ) -> Option<IpPacket<'frame>> {
-        let icmp_packet = check!(Icmpv4Packet::new_checked(ip_payload));
-        let icmp_repr = check!(Icmpv4Repr::parse(&icmp_packet, &self.caps.checksum));
+        let icmp_packet = check!(
+            Icmpv4Packet::new_checked(ip_payload),
+            self,
+            DropReason::Malformed,
+            &ip_repr
+        );
+        let icmp_repr = check!(
+            Icmpv4Repr::parse(&icmp_packet, &self.caps.checksum),
+            self,
+            DropReason::parse_failure(
+                self.caps.checksum.icmpv4.rx() && !icmp_packet.verify_checksum()
+            ),
+            &ip_repr
+        );
 
         #[cfg(feature = "socket-icmp")]
         let mut handled_by_icmp_socket = false;
@@ -1989,7 +3822,9 @@ impl<'a> InterfaceInner<'a> {
         }
 
         match icmp_repr {
-            // Respond to echo requests.
+            // Respond to echo requests, unless the application does.
+            #[cfg(feature = "proto-ipv4")]
+            Icmpv4Repr::EchoRequest { .. } if !self.icmp_echo_reply => None,
             #[cfg(feature = "proto-ipv4")]
             Icmpv4Repr::EchoRequest {
                 ident,
@@ -2011,6 +3846,27 @@ impl<'a> InterfaceInner<'a> {
             // Ignore any echo replies.
             Icmpv4Repr::EchoReply { .. } => None,
 
+            // Use a better first hop if the router we sent a packet through tells us about one.
+            Icmpv4Repr::Redirect {
+                gateway, header, ..
+            } => {
+                match ip_repr {
+                    IpRepr::Ipv4(ipv4_repr)
+                        if self.has_ip_addr(header.src_addr)
+                            && self.is_unicast_v4(gateway)
+                            && self.in_same_network(&gateway.into()) =>
+                    {
+                        self.process_redirect(
+                            ipv4_repr.src_addr.into(),
+                            header.dst_addr.into(),
+                            gateway.into(),
+                        );
+                    }
+                    _ => net_debug!("ignoring redirect to off-link gateway {}", gateway),
+                }
+                None
+            }
+
             // Don't report an error if a packet with unknown type
             // has been handled by an ICMP socket
             #[cfg(feature = "socket-icmp")]
@@ -2084,6 +3940,49 @@ impl<'a> InterfaceInner<'a> {
         }
     }
 
+    /// Answer an IPv6 packet with a Parameter Problem message pointing at the octet `pointer`
+    /// of the packet, counting from the start of its IPv6 header.
+    ///
+    /// Like other ICMPv6 errors, the message is not sent to packets destined to a multicast
+    /// address, unless `to_multicast` is set (RFC 4443 § 2.4 (e)).
+    #[cfg(feature = "proto-ipv6")]
+    fn icmpv6_param_problem<'frame>(
+        &mut self,
+        ipv6_repr: Ipv6Repr,
+        reason: Icmpv6ParamProblem,
+        pointer: usize,
+        ipv6_payload: &'frame [u8],
+        to_multicast: bool,
+    ) -> Option<IpPacket<'frame>> {
+        if !self.ipv6_param_problems {
+            return None;
+        }
+
+        // Send back as much of the original payload as we can.
+        let payload_len =
+            icmp_reply_payload_len(ipv6_payload.len(), IPV6_MIN_MTU, ipv6_repr.buffer_len());
+        let icmp_reply_repr = Icmpv6Repr::ParamProblem {
+            reason,
+            pointer: pointer as u32,
+            header: ipv6_repr,
+            data: &ipv6_payload[..payload_len],
+        };
+
+        if to_multicast && ipv6_repr.dst_addr.is_multicast() {
+            let src_addr = self.get_source_address_ipv6(ipv6_repr.src_addr)?;
+            let ipv6_reply_repr = Ipv6Repr {
+                src_addr,
+                dst_addr: ipv6_repr.src_addr,
+                next_header: IpProtocol::Icmpv6,
+                payload_len: icmp_reply_repr.buffer_len(),
+                hop_limit: 64,
+            };
+            Some(IpPacket::Icmpv6((ipv6_reply_repr, icmp_reply_repr)))
+        } else {
+            self.icmpv6_reply(ipv6_repr, icmp_reply_repr)
+        }
+    }
+
     #[cfg(any(feature = "socket-udp", feature = "socket-dns"))]
     fn process_udp<'frame>(
         &mut self,
@@ -2093,13 +3992,20 @@ impl<'a> InterfaceInner<'a> {
         ip_payload: &'frame [u8],
     ) -> Option<IpPacket<'frame>> {
         let (src_addr, dst_addr) = (ip_repr.src_addr(), ip_repr.dst_addr());
-        let udp_packet = check!(UdpPacket::new_checked(ip_payload));
-        let udp_repr = check!(UdpRepr::parse(
-            &udp_packet,
-            &src_addr,
-            &dst_addr,
-            &self.caps.checksum
-        ));
+        let udp_packet = check!(
+            UdpPacket::new_checked(ip_payload),
+            self,
+            DropReason::Malformed,
+            &ip_repr
+        );
+        let udp_repr = check!(
+            UdpRepr::parse(&udp_packet, &src_addr, &dst_addr, &self.caps.checksum),
+            self,
+            DropReason::parse_failure(
+                self.caps.checksum.udp.rx() && !udp_packet.verify_checksum(&src_addr, &dst_addr)
+            ),
+            &ip_repr
+        );
         let udp_payload = udp_packet.payload();
 
         #[cfg(feature = "socket-udp")]
@@ -2125,6 +4031,9 @@ impl<'a> InterfaceInner<'a> {
         }
 
         // The packet wasn't handled by a socket, send an ICMP port unreachable packet.
+        if !handled_by_raw_socket {
+            self.observe_drop(DropReason::NoSocket, &ip_repr);
+        }
         match ip_repr {
             #[cfg(feature = "proto-ipv4")]
             IpRepr::Ipv4(_) if handled_by_raw_socket => None,
@@ -2163,25 +4072,26 @@ impl<'a> InterfaceInner<'a> {
         ip_payload: &'frame [u8],
     ) -> Option<IpPacket<'frame>> {
         let (src_addr, dst_addr) = (ip_repr.src_addr(), ip_repr.dst_addr());
-        let tcp_packet = check!(TcpPacket::new_checked(ip_payload));
-        let tcp_repr = check!(TcpRepr::parse(
-            &tcp_packet,
-            &src_addr,
-            &dst_addr,
-            &self.caps.checksum
-        ));
+        let tcp_packet = check!(
+            TcpPacket::new_checked(ip_payload),
+            self,
+            DropReason::Malformed,
+            &ip_repr
+        );
+        let tcp_repr = check!(
+            TcpRepr::parse(&tcp_packet, &src_addr, &dst_addr, &self.caps.checksum),
+            self,
+            DropReason::parse_failure(
+                self.caps.checksum.tcp.rx() && !tcp_packet.verify_checksum(&src_addr, &dst_addr)
+            ),
+            &ip_repr
+        );
 
-        for tcp_socket in sockets
-            .iter_mut()
-            .filter_map(|i| tcp::Socket::downcast(&mut i.socket))
-        {
-            if tcp_socket.accepts(self, &ip_repr, &tcp_repr) {
-                return tcp_socket
-                    .process(self, &ip_repr, &tcp_repr)
-                    .map(IpPacket::Tcp);
-            }
+        if let Some(handle) = self.tcp_socket_for(sockets, &ip_repr, &tcp_repr, false) {
+            return self.process_tcp_socket(sockets, handle, &ip_repr, &tcp_repr, ip_payload);
         }
 
+        self.observe_drop(DropReason::NoSocket, &ip_repr);
         if tcp_repr.control == TcpControl::Rst {
             // Never reply to a TCP RST packet with another TCP RST packet.
             None
@@ -2191,6 +4101,157 @@ impl<'a> InterfaceInner<'a> {
         }
     }
 
+    /// Pass a TCP segment to the socket that accepted it, given the octets it was parsed from.
+    #[cfg(feature = "socket-tcp")]
+    #[cfg_attr(not(feature = "socket-tcp-ao"), allow(unused_variables))]
+    fn process_tcp_socket<'frame>(
+        &mut self,
+        sockets: &mut SocketSet,
+        handle: SocketHandle,
+        ip_repr: &IpRepr,
+        tcp_repr: &TcpRepr,
+        segment: &[u8],
+    ) -> Option<IpPacket<'frame>> {
+        if let Some(limit) = self.half_open_limit {
+            let is_syn = tcp_repr.control == TcpControl::Syn && tcp_repr.ack_number.is_none();
+            if is_syn
+                && sockets.get::<tcp::Socket>(handle).state() == tcp::State::Listen
+                && Self::half_open_count(sockets) >= limit
+            {
+                let tcp_socket = sockets.get::<tcp::Socket>(handle);
+                if tcp_socket.syn_cookies() {
+                    if let Some(reply) = tcp_socket.syn_cookie_reply(self, ip_repr, tcp_repr) {
+                        net_debug!("too many half-open connections, answering SYN with a cookie");
+                        return Some(IpPacket::Tcp(reply));
+                    }
+                }
+                net_debug!("too many half-open connections, dropping SYN");
+                self.observe_drop(DropReason::HalfOpenLimit, ip_repr);
+                return None;
+            }
+        }
+
+        let tcp_socket = sockets.get::<tcp::Socket>(handle);
+        #[cfg(feature = "socket-tcp-ao")]
+        let reply = tcp_socket.process_authenticated(self, ip_repr, tcp_repr, segment);
+        #[cfg(not(feature = "socket-tcp-ao"))]
+        let reply = tcp_socket.process(self, ip_repr, tcp_repr);
+        reply.map(IpPacket::Tcp)
+    }
+
+    /// Return the number of TCP sockets in the SYN-RECEIVED state.
+    #[cfg(feature = "socket-tcp")]
+    fn half_open_count(sockets: &SocketSet) -> usize {
+        sockets
+            .iter()
+            .filter(|item| {
+                matches!(&item.socket, Socket::Tcp(socket) if socket.state() == tcp::State::SynReceived)
+            })
+            .count()
+    }
+
+    /// Find the TCP socket that accepts a segment, considering only sockets in transparent
+    /// mode if `transparent` is set.
+    #[cfg(feature = "socket-tcp")]
+    fn tcp_socket_for(
+        &mut self,
+        sockets: &mut SocketSet,
+        ip_repr: &IpRepr,
+        tcp_repr: &TcpRepr,
+        transparent: bool,
+    ) -> Option<SocketHandle> {
+        let mut range_listener = None;
+        for item in sockets.iter_mut() {
+            let tcp_socket = match tcp::Socket::downcast(&mut item.socket) {
+                Some(tcp_socket) => tcp_socket,
+                None => continue,
+            };
+            if transparent && !tcp_socket.transparent() {
+                continue;
+            }
+            if !tcp_socket.accepts(self, ip_repr, tcp_repr) {
+                continue;
+            }
+            if tcp_socket.is_range_listener() {
+                // Connections and single port listeners take precedence over port ranges.
+                range_listener.get_or_insert(item.meta.handle);
+                continue;
+            }
+            return Some(item.meta.handle);
+        }
+        range_listener
+    }
+
+    /// Pass a TCP segment or UDP datagram sent to an address that isn't ours to the sockets
+    /// in transparent mode.
+    ///
+    /// Nothing is sent back if none of them accepts the packet: we would be answering on
+    /// behalf of another host.
+    #[cfg(any(feature = "socket-tcp", feature = "socket-udp"))]
+    fn process_transparent<'frame>(
+        &mut self,
+        sockets: &mut SocketSet,
+        ip_repr: IpRepr,
+        ip_payload: &'frame [u8],
+    ) -> Option<IpPacket<'frame>> {
+        let (src_addr, dst_addr) = (ip_repr.src_addr(), ip_repr.dst_addr());
+        match ip_repr.next_header() {
+            #[cfg(feature = "socket-udp")]
+            IpProtocol::Udp => {
+                let udp_packet = check!(
+                    UdpPacket::new_checked(ip_payload),
+                    self,
+                    DropReason::Malformed,
+                    &ip_repr
+                );
+                let udp_repr = check!(
+                    UdpRepr::parse(&udp_packet, &src_addr, &dst_addr, &self.caps.checksum),
+                    self,
+                    DropReason::parse_failure(
+                        self.caps.checksum.udp.rx()
+                            && !udp_packet.verify_checksum(&src_addr, &dst_addr)
+                    ),
+                    &ip_repr
+                );
+                for udp_socket in sockets
+                    .iter_mut()
+                    .filter_map(|i| udp::Socket::downcast(&mut i.socket))
+                {
+                    if udp_socket.transparent() && udp_socket.accepts(self, &ip_repr, &udp_repr) {
+                        udp_socket.process(self, &ip_repr, &udp_repr, udp_packet.payload());
+                        return None;
+                    }
+                }
+            }
+            #[cfg(feature = "socket-tcp")]
+            IpProtocol::Tcp => {
+                let tcp_packet = check!(
+                    TcpPacket::new_checked(ip_payload),
+                    self,
+                    DropReason::Malformed,
+                    &ip_repr
+                );
+                let tcp_repr = check!(
+                    TcpRepr::parse(&tcp_packet, &src_addr, &dst_addr, &self.caps.checksum),
+                    self,
+                    DropReason::parse_failure(
+                        self.caps.checksum.tcp.rx()
+                            && !tcp_packet.verify_checksum(&src_addr, &dst_addr)
+                    ),
+                    &ip_repr
+                );
+                if let Some(handle) = self.tcp_socket_for(sockets, &ip_repr, &tcp_repr, true) {
+                    return self
+                        .process_tcp_socket(sockets, handle, &ip_repr, &tcp_repr, ip_payload);
+                }
+            }
+            _ => {}
+        }
+
+        self.observe_drop(DropReason::NoRoute, &ip_repr);
+        None
+    }
+
     #[cfg(feature = "medium-ethernet")]
     fn dispatch<Tx>(&mut self, tx_token: Tx, packet: EthernetPacket) -> Result<()>
     where
@@ -2199,16 +4260,22 @@ impl<'a> InterfaceInner<'a> {
         match packet {
             #[cfg(feature = "proto-ipv4")]
             EthernetPacket::Arp(arp_repr) => {
-                let dst_hardware_addr = match arp_repr {
+                let (dst_hardware_addr, ethertype) = match arp_repr {
                     ArpRepr::EthernetIpv4 {
+                        operation,
                         target_hardware_addr,
                         ..
-                    } => target_hardware_addr,
+                    } => match operation {
+                        ArpOperation::RarpRequest | ArpOperation::RarpReply => {
+                            (target_hardware_addr, EthernetProtocol::Rarp)
+                        }
+                        _ => (target_hardware_addr, EthernetProtocol::Arp),
+                    },
                 };
 
                 self.dispatch_ethernet(tx_token, arp_repr.buffer_len(), |mut frame| {
                     frame.set_dst_addr(dst_hardware_addr);
-                    frame.set_ethertype(EthernetProtocol::Arp);
+                    frame.set_ethertype(ethertype);
 
                     let mut packet = ArpPacket::new_unchecked(frame.payload_mut());
                     arp_repr.emit(&mut packet);
@@ -2224,10 +4291,13 @@ impl<'a> InterfaceInner<'a> {
         Tx: TxToken,
         F: FnOnce(EthernetFrame<&mut [u8]>),
     {
-        let tx_len = EthernetFrame::<&[u8]>::buffer_len(buffer_len);
+        let vlan_len = self.vlan_tags.header_len();
+        let tx_len = EthernetFrame::<&[u8]>::buffer_len(buffer_len) + vlan_len;
         tx_token.consume(self.now, tx_len, |tx_buffer| {
             debug_assert!(tx_buffer.as_ref().len() == tx_len);
-            let mut frame = EthernetFrame::new_unchecked(tx_buffer);
+            // Build an untagged frame after room for the tags, then move the addresses
+            // in front of them; the EtherType then ends up right after the tags.
+            let mut frame = EthernetFrame::new_unchecked(&mut tx_buffer[vlan_len..]);
 
             let src_addr = if let Some(HardwareAddress::Ethernet(addr)) = self.hardware_addr {
                 addr
@@ -2239,6 +4309,13 @@ impl<'a> InterfaceInner<'a> {
 
             f(frame);
 
+            if vlan_len > 0 {
+                // Destination and source addresses.
+                let addrs_len = ETHERNET_HEADER_LEN - 2;
+                tx_buffer.copy_within(vlan_len..vlan_len + addrs_len, 0);
+                EthernetFrame::new_unchecked(tx_buffer).set_vlan_tags(self.vlan_tags);
+            }
+
             Ok(())
         })
     }
@@ -2248,6 +4325,13 @@ impl<'a> InterfaceInner<'a> {
     }
 
     fn route(&self, addr: &IpAddress, timestamp: Instant) -> Result<IpAddress> {
+        // Policy routes override everything else.
+        if addr.is_unicast() {
+            if let Some(router_addr) = self.policy_routes.lookup(addr, timestamp) {
+                return Ok(router_addr);
+            }
+        }
+
         // Send directly.
         if self.in_same_network(addr) || addr.is_broadcast() {
             return Ok(*addr);
@@ -2260,7 +4344,67 @@ impl<'a> InterfaceInner<'a> {
         }
     }
 
+    /// Check the neighbor request rate limit, counting the request as dropped if it is exceeded.
+    #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
+    fn neighbor_request_permitted(&mut self) -> bool {
+        if self.neighbor_request_limiter.try_acquire(self.now) {
+            true
+        } else {
+            self.neighbor_requests_limited += 1;
+            false
+        }
+    }
+
+    /// Install a host route to `dst_addr` via `new_router`, as requested by a redirect
+    /// received from `router`. The caller must check that `new_router` is on-link.
+    ///
+    /// Returns whether the redirect was accepted.
+    fn process_redirect(
+        &mut self,
+        router: IpAddress,
+        dst_addr: IpAddress,
+        new_router: IpAddress,
+    ) -> bool {
+        if !self.accept_redirects {
+            net_debug!("ignoring redirect from {}: redirects are disabled", router);
+            return false;
+        }
+
+        // Only the router we are currently sending these packets through may redirect them.
+        if !dst_addr.is_unicast()
+            || self.in_same_network(&dst_addr)
+            || self.route(&dst_addr, self.now).ok() != Some(router)
+        {
+            net_debug!(
+                "ignoring redirect for {} from {}: not the first hop",
+                dst_addr,
+                router
+            );
+            return false;
+        }
+        if self.has_ip_addr(new_router) {
+            net_debug!("ignoring redirect for {} to ourselves", dst_addr);
+            return false;
+        }
+
+        match self.routes.add_redirect(dst_addr, new_router, self.now) {
+            Ok(()) => {
+                net_debug!("redirected {} from {} to {}", dst_addr, router, new_router);
+                true
+            }
+            Err(_) => {
+                net_debug!("cannot add redirect for {}: route table full", dst_addr);
+                false
+            }
+        }
+    }
+
     fn has_neighbor(&self, addr: &IpAddress) -> bool {
+        #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
+        if self.peer_hardware_addr.is_some() {
+            return true;
+        }
+
         match self.route(addr, self.now) {
             Ok(_routed_addr) => match self.caps.medium {
                 #[cfg(feature = "medium-ethernet")]
@@ -2340,6 +4484,10 @@ impl<'a> InterfaceInner<'a> {
             return Ok((hardware_addr, tx_token));
         }
 
+        if let Some(peer_hardware_addr) = self.peer_hardware_addr {
+            return Ok((peer_hardware_addr, tx_token));
+        }
+
         let dst_addr = self.route(dst_addr, self.now)?;
 
         match self
@@ -2441,8 +4589,14 @@ impl<'a> InterfaceInner<'a> {
                     (HardwareAddress::Ieee802154(_), _) => unreachable!(),
                 };
 
+                #[cfg(feature = "proto-ipv4-fragmentation")]
+                if self.needs_ipv4_fragmentation(&packet) {
+                    self.ipv4_out_packet.dst_hardware_addr = dst_hardware_addr;
+                    return self.dispatch_ipv4_fragmented(tx_token, packet);
+                }
+
                 let caps = self.caps.clone();
-                self.dispatch_ethernet(tx_token, ip_repr.total_len(), |mut frame| {
+                self.dispatch_ethernet(tx_token, packet.total_len(&ip_repr), |mut frame| {
                     frame.set_dst_addr(dst_hardware_addr);
                     match ip_repr {
                         #[cfg(feature = "proto-ipv4")]
@@ -2451,22 +4605,21 @@ impl<'a> InterfaceInner<'a> {
                         IpRepr::Ipv6(_) => frame.set_ethertype(EthernetProtocol::Ipv6),
                     }
 
-                    ip_repr.emit(frame.payload_mut(), &caps.checksum);
-
-                    let payload = &mut frame.payload_mut()[ip_repr.buffer_len()..];
-                    packet.emit_payload(ip_repr, payload, &caps);
+                    packet.emit(ip_repr, frame.payload_mut(), &caps);
                 })
             }
             #[cfg(feature = "medium-ip")]
             Medium::Ip => {
-                let tx_len = ip_repr.total_len();
-                tx_token.consume(self.now, tx_len, |mut tx_buffer| {
-                    debug_assert!(tx_buffer.as_ref().len() == tx_len);
+                #[cfg(feature = "proto-ipv4-fragmentation")]
+                if self.needs_ipv4_fragmentation(&packet) {
+                    return self.dispatch_ipv4_fragmented(tx_token, packet);
+                }
 
-                    ip_repr.emit(&mut tx_buffer, &self.caps.checksum);
+                let tx_len = packet.total_len(&ip_repr);
+                tx_token.consume(self.now, tx_len, |tx_buffer| {
+                    debug_assert!(tx_buffer.as_ref().len() == tx_len);
 
-                    let payload = &mut tx_buffer[ip_repr.buffer_len()..];
-                    packet.emit_payload(ip_repr, payload, &self.caps);
+                    packet.emit(ip_repr, tx_buffer, &self.caps);
 
                     Ok(())
                 })
@@ -2476,6 +4629,125 @@ impl<'a> InterfaceInner<'a> {
         }
     }
 
+    /// Return whether `packet` is an IPv4 packet larger than the MTU, that has to be sent in
+    /// fragments because a buffer was provided for them.
+    #[cfg(feature = "proto-ipv4-fragmentation")]
+    fn needs_ipv4_fragmentation(&self, packet: &IpPacket) -> bool {
+        let ip_repr = packet.ip_repr();
+        let has_buffer = match &self.ipv4_out_packet.buffer {
+            ManagedSlice::Borrowed(buffer) => !buffer.is_empty(),
+            #[cfg(any(feature = "std", feature = "alloc"))]
+            ManagedSlice::Owned(_) => true,
+        };
+        matches!(ip_repr, IpRepr::Ipv4(_))
+            && has_buffer
+            && packet.total_len(&ip_repr) > self.ip_mtu()
+    }
+
+    /// Write an IPv4 packet larger than the MTU to the fragmentation buffer, and send its
+    /// first fragment with `tx_token`. The other fragments are sent by `ipv4_egress`.
+    #[cfg(feature = "proto-ipv4-fragmentation")]
+    fn dispatch_ipv4_fragmented<Tx: TxToken>(
+        &mut self,
+        tx_token: Tx,
+        packet: IpPacket,
+    ) -> Result<()> {
+        let ip_repr = packet.ip_repr();
+        let packet_len = packet.total_len(&ip_repr);
+        let out_packet = &mut self.ipv4_out_packet;
+        if out_packet.is_pending() {
+            net_debug!("dropping IPv4 packet: still sending the fragments of another one");
+            return Err(Error::Exhausted);
+        }
+        match &mut out_packet.buffer {
+            ManagedSlice::Borrowed(buffer) if buffer.len() < packet_len => {
+                net_debug!("dropping IPv4 packet: too large for the fragmentation buffer");
+                return Err(Error::Exhausted);
+            }
+            ManagedSlice::Borrowed(_) => (),
+            #[cfg(any(feature = "std", feature = "alloc"))]
+            ManagedSlice::Owned(buffer) => {
+                if buffer.len() < packet_len {
+                    buffer.resize(packet_len, 0);
+                }
+            }
+        }
+
+        packet.emit(ip_repr, &mut out_packet.buffer[..packet_len], &self.caps);
+        out_packet.packet_len = packet_len;
+        out_packet.sent_len = 0;
+        out_packet.ident = self.rand.rand_u16();
+        self.dispatch_ipv4_fragment(tx_token)
+    }
+
+    /// Send the next fragment of the IPv4 packet in the fragmentation buffer.
+    #[cfg(feature = "proto-ipv4-fragmentation")]
+    fn dispatch_ipv4_fragment<Tx: TxToken>(&mut self, tx_token: Tx) -> Result<()> {
+        // Only the buffer is borrowed from `self` while the fragment is emitted.
+        let buffer = mem::replace(
+            &mut self.ipv4_out_packet.buffer,
+            ManagedSlice::Borrowed(&mut []),
+        );
+        let Ipv4OutPacket {
+            packet_len,
+            sent_len,
+            ident,
+            ..
+        } = self.ipv4_out_packet;
+
+        let header_len = Ipv4Packet::new_unchecked(&buffer[..]).header_len() as usize;
+        let payload_len = packet_len - header_len;
+        // The payload of every fragment but the last is a multiple of 8 octets long.
+        let frag_len = cmp::min(payload_len - sent_len, (self.ip_mtu() - header_len) & !7);
+        let more_frags = sent_len + frag_len < payload_len;
+        let checksum_tx = self.caps.checksum.ipv4.tx();
+        let emit = |tx_buffer: &mut [u8]| {
+            tx_buffer[..header_len].copy_from_slice(&buffer[..header_len]);
+            tx_buffer[header_len..].copy_from_slice(&buffer[header_len + sent_len..][..frag_len]);
+
+            let mut packet = Ipv4Packet::new_unchecked(tx_buffer);
+            packet.set_total_len((header_len + frag_len) as u16);
+            packet.set_ident(ident);
+            packet.set_dont_frag(false);
+            packet.set_more_frags(more_frags);
+            packet.set_frag_offset(sent_len as u16);
+            if checksum_tx {
+                packet.fill_checksum();
+            } else {
+                packet.set_checksum(0);
+            }
+        };
+
+        let tx_len = header_len + frag_len;
+        let res = match self.caps.medium {
+            #[cfg(feature = "medium-ethernet")]
+            Medium::Ethernet => {
+                let dst_hardware_addr = self.ipv4_out_packet.dst_hardware_addr;
+                self.dispatch_ethernet(tx_token, tx_len, |mut frame| {
+                    frame.set_dst_addr(dst_hardware_addr);
+                    frame.set_ethertype(EthernetProtocol::Ipv4);
+                    emit(frame.payload_mut());
+                })
+            }
+            #[cfg(feature = "medium-ip")]
+            Medium::Ip => tx_token.consume(self.now, tx_len, |tx_buffer| {
+                emit(tx_buffer);
+                Ok(())
+            }),
+            #[cfg(feature = "medium-ieee802154")]
+            Medium::Ieee802154 => Err(Error::Unaddressable),
+        };
+
+        self.ipv4_out_packet.buffer = buffer;
+        res?;
+        if more_frags {
+            self.ipv4_out_packet.sent_len += frag_len;
+        } else {
+            self.ipv4_out_packet.packet_len = 0;
+        }
+        Ok(())
+    }
+
     #[cfg(feature = "medium-ieee802154")]
     fn dispatch_ieee802154<Tx: TxToken>(&mut self, tx_token: Tx, packet: IpPacket) -> Result<()> {
         let ip_repr = packet.ip_repr();
@@ -2558,7 +4830,7 @@ impl<'a> InterfaceInner<'a> {
                 #[allow(unreachable_patterns)]
                 match &packet {
                     #[cfg(feature = "socket-udp")]
-                    IpPacket::Udp((_, udp_repr, payload)) => {
+                    IpPacket::Udp((_, udp_repr, payload, _)) => {
                         let udp_repr = SixlowpanUdpRepr(*udp_repr);
                         tx_len += udp_repr.header_len() + payload.len();
                     }
@@ -2584,7 +4856,7 @@ impl<'a> InterfaceInner<'a> {
                     #[allow(unreachable_patterns)]
                     match packet {
                         #[cfg(feature = "socket-udp")]
-                        IpPacket::Udp((_, udp_repr, payload)) => {
+                        IpPacket::Udp((_, udp_repr, payload, _)) => {
                             // 3. Create the header for 6LoWPAN UDP
                             let mut udp_packet =
                                 SixlowpanUdpPacket::new_unchecked(&mut tx_buffer[start..tx_len]);
@@ -2640,8 +4912,6 @@ impl<'a> InterfaceInner<'a> {
                 next_header: IpProtocol::Igmp,
                 payload_len: igmp_repr.buffer_len(),
                 hop_limit: 1,
-                // TODO: add Router Alert IPv4 header option. See
-                // [#183](https://github.com/m-labs/smoltcp/issues/183).
             },
             igmp_repr,
         ));
@@ -2738,7 +5008,11 @@ mod test {
         iface_builder.finalize()
     }
 
-    #[cfg(feature = "proto-igmp")]
+    #[cfg(any(
+        feature = "proto-igmp",
+        feature = "proto-ipv4-fragmentation",
+        all(feature = "medium-ethernet", feature = "proto-ipv4")
+    ))]
     fn recv_all(iface: &mut Interface<'_, Loopback>, timestamp: Instant) -> Vec<Vec<u8>> {
         let mut pkts = Vec::new();
         while let Some((rx, _tx)) = iface.device.receive() {
@@ -2751,6 +5025,108 @@ mod test {
         pkts
     }
 
+    /// Build an IPv4 packet carrying a UDP datagram from 127.0.0.2, port 67.
+    #[cfg(all(feature = "proto-ipv4", feature = "socket-udp"))]
+    fn udp_datagram_ipv4(dst_addr: Ipv4Address, dst_port: u16, payload: &[u8]) -> Vec<u8> {
+        use crate::wire::Ipv4Packet;
+
+        let src_addr = Ipv4Address::new(127, 0, 0, 2);
+        let udp_repr = UdpRepr {
+            src_port: 67,
+            dst_port,
+        };
+        let ipv4_repr = Ipv4Repr {
+            src_addr,
+            dst_addr,
+            next_header: IpProtocol::Udp,
+            payload_len: udp_repr.header_len() + payload.len(),
+            hop_limit: 0x40,
+        };
+        let mut bytes = vec![0; ipv4_repr.buffer_len() + ipv4_repr.payload_len];
+        let mut packet = Ipv4Packet::new_unchecked(&mut bytes);
+        ipv4_repr.emit(&mut packet, &ChecksumCapabilities::default());
+        udp_repr.emit(
+            &mut UdpPacket::new_unchecked(packet.payload_mut()),
+            &src_addr.into(),
+            &dst_addr.into(),
+            payload.len(),
+            |buf| buf.copy_from_slice(payload),
+            &ChecksumCapabilities::default(),
+        );
+        bytes
+    }
+
+    /// Process a TCP segment without payload from 127.0.0.2 to port 80 of 127.0.0.1, and
+    /// return the control flag and sequence number of the reply, if any.
+    #[cfg(all(feature = "proto-ipv4", feature = "socket-tcp"))]
+    fn tcp_segment_ipv4(
+        iface: &mut Interface<'_, Loopback>,
+        src_port: u16,
+        control: TcpControl,
+        seq_number: crate::wire::TcpSeqNumber,
+        ack_number: Option<crate::wire::TcpSeqNumber>,
+    ) -> Option<(TcpControl, crate::wire::TcpSeqNumber)> {
+        use crate::wire::TcpPacket;
+
+        let src_addr = Ipv4Address::new(127, 0, 0, 2);
+        let dst_addr = Ipv4Address::new(127, 0, 0, 1);
+        let tcp_repr = TcpRepr {
+            src_port,
+            dst_port: 80,
+            control,
+            seq_number,
+            ack_number,
+            window_len: 256,
+            window_scale: None,
+            max_seg_size: None,
+            sack_permitted: false,
+            sack_ranges: [None, None, None],
+            auth: None,
+            payload: &[],
+        };
+        let mut bytes = vec![0; tcp_repr.buffer_len()];
+        tcp_repr.emit(
+            &mut TcpPacket::new_unchecked(&mut bytes[..]),
+            &src_addr.into(),
+            &dst_addr.into(),
+            &ChecksumCapabilities::default(),
+        );
+        let ip_repr = IpRepr::Ipv4(Ipv4Repr {
+            src_addr,
+            dst_addr,
+            next_header: IpProtocol::Tcp,
+            payload_len: bytes.len(),
+            hop_limit: 64,
+        });
+        match iface.inner.process_tcp(&mut iface.sockets, ip_repr, &bytes) {
+            Some(IpPacket::Tcp((_, reply))) => Some((reply.control, reply.seq_number)),
+            None => None,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Create a UDP socket with room for `packets` datagrams of up to 16 octets each way.
+    #[cfg(feature = "socket-udp")]
+    fn udp_socket<'a>(packets: usize) -> udp::Socket<'a> {
+        let rx_buffer = udp::PacketBuffer::new(
+            vec![udp::PacketMetadata::EMPTY; packets],
+            vec![0; 16 * packets],
+        );
+        let tx_buffer = udp::PacketBuffer::new(
+            vec![udp::PacketMetadata::EMPTY; packets],
+            vec![0; 16 * packets],
+        );
+        udp::Socket::new(rx_buffer, tx_buffer)
+    }
+
+    /// Create a TCP socket with 64 octet buffers.
+    #[cfg(all(feature = "proto-ipv4", feature = "socket-tcp"))]
+    fn tcp_socket<'a>() -> tcp::Socket<'a> {
+        let rx_buffer = tcp::SocketBuffer::new(vec![0; 64]);
+        let tx_buffer = tcp::SocketBuffer::new(vec![0; 64]);
+        tcp::Socket::new(rx_buffer, tx_buffer)
+    }
+
     #[derive(Debug, PartialEq)]
     #[cfg_attr(feature = "defmt", derive(defmt::Format))]
     struct MockTxToken;
@@ -2934,67 +5310,410 @@ mod test {
     }
 
     #[test]
-    #[cfg(all(feature = "socket-udp", feature = "proto-ipv4"))]
-    fn test_icmp_error_port_unreachable() {
-        static UDP_PAYLOAD: [u8; 12] = [
-            0x48, 0x65, 0x6c, 0x6c, 0x6f, 0x2c, 0x20, 0x57, 0x6f, 0x6c, 0x64, 0x21,
-        ];
-        let mut iface = create_loopback();
+    #[cfg(all(
+        feature = "proto-ipv4-fragmentation",
+        feature = "medium-ip",
+        feature = "socket-icmp"
+    ))]
+    fn test_icmpv4_fragmented_echo() {
+        use crate::wire::{Icmpv4Packet, Ipv4Packet};
+
+        // Split a packet like a router on a path with a 1500 octet MTU would.
+        let fragment = |bytes: &[u8], ident: u16| {
+            let header_len = Ipv4Packet::new_checked(bytes).unwrap().header_len() as usize;
+            let payload = &bytes[header_len..];
+            payload
+                .chunks(1480)
+                .enumerate()
+                .map(|(index, chunk)| {
+                    let mut fragment = bytes[..header_len].to_vec();
+                    fragment.extend_from_slice(chunk);
+                    let mut packet = Ipv4Packet::new_unchecked(&mut fragment[..]);
+                    packet.set_total_len((header_len + chunk.len()) as u16);
+                    packet.set_ident(ident);
+                    packet.set_dont_frag(false);
+                    packet.set_more_frags((index + 1) * 1480 < payload.len());
+                    packet.set_frag_offset((index * 1480) as u16);
+                    packet.fill_checksum();
+                    fragment
+                })
+                .collect::<Vec<_>>()
+        };
+        let echo_packet = |repr: &Icmpv4Repr, src_addr, dst_addr| {
+            let ipv4_repr = Ipv4Repr {
+                src_addr,
+                dst_addr,
+                next_header: IpProtocol::Icmp,
+                payload_len: repr.buffer_len(),
+                hop_limit: 64,
+            };
+            let mut bytes = vec![0; ipv4_repr.buffer_len() + repr.buffer_len()];
+            let mut packet = Ipv4Packet::new_unchecked(&mut bytes[..]);
+            ipv4_repr.emit(&mut packet, &ChecksumCapabilities::default());
+            repr.emit(
+                &mut Icmpv4Packet::new_unchecked(packet.payload_mut()),
+                &ChecksumCapabilities::default(),
+            );
+            bytes
+        };
+        // Queue the fragments on the device, for `poll` to receive them.
+        let receive = |iface: &mut Interface<'_, Loopback>, fragments: &[Vec<u8>]| {
+            for bytes in fragments {
+                let tx_token = iface.device.transmit().unwrap();
+                tx_token
+                    .consume(Instant::from_millis(0), bytes.len(), |buffer| {
+                        buffer.copy_from_slice(bytes);
+                        Ok(())
+                    })
+                    .unwrap();
+            }
+        };
 
-        let mut udp_bytes_unicast = vec![0u8; 20];
-        let mut udp_bytes_broadcast = vec![0u8; 20];
-        let mut packet_unicast = UdpPacket::new_unchecked(&mut udp_bytes_unicast);
-        let mut packet_broadcast = UdpPacket::new_unchecked(&mut udp_bytes_broadcast);
+        let mut iface = create_loopback_ip();
+        iface.inner.ipv4_fragments = Some(PacketAssemblerSet::new(vec![], BTreeMap::new()));
+        iface.inner.ipv4_out_packet = Ipv4OutPacket::new(ManagedSlice::Owned(vec![]));
+        iface.inner.caps.max_transmission_unit = 1500;
+        let local_addr = Ipv4Address::new(127, 0, 0, 1);
+        let payload = (0..4000).map(|i| i as u8).collect::<Vec<_>>();
 
-        let udp_repr = UdpRepr {
-            src_port: 67,
-            dst_port: 68,
+        // The echo request and the reply to it both reach a socket bound to their identifier.
+        let rx_buffer =
+            icmp::PacketBuffer::new(vec![icmp::PacketMetadata::EMPTY; 2], vec![0; 2 * 4008]);
+        let tx_buffer = icmp::PacketBuffer::new(vec![icmp::PacketMetadata::EMPTY], vec![0; 4008]);
+        let socket_handle = iface.add_socket(icmp::Socket::new(rx_buffer, tx_buffer));
+        let socket = iface.get_socket::<icmp::Socket>(socket_handle);
+        socket.bind(icmp::Endpoint::Ident(0x1234)).unwrap();
+        let recv_echo = |iface: &mut Interface<'_, Loopback>, expected: &Icmpv4Repr| {
+            let socket = iface.get_socket::<icmp::Socket>(socket_handle);
+            let (bytes, addr) = socket.recv().unwrap();
+            assert_eq!(addr, local_addr.into());
+            let icmp_packet = Icmpv4Packet::new_checked(bytes).unwrap();
+            assert_eq!(
+                Icmpv4Repr::parse(&icmp_packet, &ChecksumCapabilities::default()).as_ref(),
+                Ok(expected)
+            );
         };
 
-        let ip_repr = IpRepr::Ipv4(Ipv4Repr {
-            src_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x02]),
-            dst_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x01]),
-            next_header: IpProtocol::Udp,
-            payload_len: udp_repr.header_len() + UDP_PAYLOAD.len(),
-            hop_limit: 64,
-        });
-
-        // Emit the representations to a packet
-        udp_repr.emit(
-            &mut packet_unicast,
-            &ip_repr.src_addr(),
-            &ip_repr.dst_addr(),
-            UDP_PAYLOAD.len(),
-            |buf| buf.copy_from_slice(&UDP_PAYLOAD),
+        // An echo request larger than the MTU is sent in fragments.
+        let request = Icmpv4Repr::EchoRequest {
+            ident: 0x1234,
+            seq_no: 1,
+            data: &payload,
+        };
+        let reply = Icmpv4Repr::EchoReply {
+            ident: 0x1234,
+            seq_no: 1,
+            data: &payload,
+        };
+        let mut bytes = vec![0; request.buffer_len()];
+        request.emit(
+            &mut Icmpv4Packet::new_unchecked(&mut bytes[..]),
             &ChecksumCapabilities::default(),
         );
+        let socket = iface.get_socket::<icmp::Socket>(socket_handle);
+        socket.send_slice(&bytes, local_addr.into()).unwrap();
+        assert!(iface.socket_egress());
+        assert!(iface.ipv4_egress());
+        let mut fragments = recv_all(&mut iface, Instant::from_millis(0));
+        assert_eq!(fragments.len(), 3);
+        let ident = Ipv4Packet::new_checked(&fragments[0][..]).unwrap().ident();
+        for (index, bytes) in fragments.iter().enumerate() {
+            assert!(bytes.len() <= 1500);
+            let packet = Ipv4Packet::new_checked(&bytes[..]).unwrap();
+            assert!(packet.verify_checksum());
+            assert_eq!(packet.ident(), ident);
+            assert!(!packet.dont_frag());
+            assert_eq!(packet.more_frags(), index < 2);
+            assert_eq!(packet.frag_offset() as usize, index * 1480);
+        }
 
-        let data = packet_unicast.into_inner();
-
-        // The expected Destination Unreachable ICMPv4 error response due
-        // to no sockets listening on the destination port.
-        let icmp_repr = Icmpv4Repr::DstUnreachable {
-            reason: Icmpv4DstUnreachable::PortUnreachable,
-            header: Ipv4Repr {
-                src_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x02]),
-                dst_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x01]),
-                next_header: IpProtocol::Udp,
-                payload_len: udp_repr.header_len() + UDP_PAYLOAD.len(),
-                hop_limit: 64,
-            },
-            data: data,
+        // A fragmented echo request is reassembled and answered in full, the answer being
+        // fragmented as well.
+        // Fragments may arrive in any order.
+        fragments.reverse();
+        receive(&mut iface, &fragments);
+        assert_eq!(iface.poll(Instant::from_millis(0)), Ok(true));
+        recv_echo(&mut iface, &request);
+        recv_echo(&mut iface, &reply);
+        assert!(!iface.inner.ipv4_out_packet.is_pending());
+
+        // Only one packet is reassembled at once; the fragments of another one discard it.
+        iface
+            .inner
+            .ipv4_fragments
+            .as_mut()
+            .unwrap()
+            .set_limits(1, usize::MAX);
+        let discarded = Icmpv4Repr::EchoRequest {
+            ident: 0x1234,
+            seq_no: 2,
+            data: &payload,
         };
-        let expected_repr = IpPacket::Icmpv4((
-            Ipv4Repr {
-                src_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x01]),
-                dst_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x02]),
-                next_header: IpProtocol::Icmp,
-                payload_len: icmp_repr.buffer_len(),
-                hop_limit: 64,
-            },
-            icmp_repr,
-        ));
-
+        let request = Icmpv4Repr::EchoRequest {
+            ident: 0x1234,
+            seq_no: 3,
+            data: &payload,
+        };
+        let reply = Icmpv4Repr::EchoReply {
+            ident: 0x1234,
+            seq_no: 3,
+            data: &payload,
+        };
+        let discarded_fragments = fragment(&echo_packet(&discarded, local_addr, local_addr), 2);
+        receive(&mut iface, &discarded_fragments[..1]);
+        receive(
+            &mut iface,
+            &fragment(&echo_packet(&request, local_addr, local_addr), 3),
+        );
+        receive(&mut iface, &discarded_fragments[1..]);
+        assert_eq!(iface.poll(Instant::from_millis(0)), Ok(true));
+        recv_echo(&mut iface, &request);
+        recv_echo(&mut iface, &reply);
+        let socket = iface.get_socket::<icmp::Socket>(socket_handle);
+        assert!(!socket.can_recv());
+
+        // A packet missing fragments is given up on once the reassembly timeout expires.
+        let key = Ipv4Packet::new_checked(&discarded_fragments[1][..])
+            .unwrap()
+            .get_key();
+        receive(&mut iface, &discarded_fragments[1..]);
+        assert_eq!(iface.poll(Instant::from_millis(0)), Ok(true));
+        let fragments = iface.inner.ipv4_fragments.as_mut().unwrap();
+        assert!(fragments.get_packet_assembler_mut(&key).is_ok());
+        assert_eq!(iface.poll(Instant::from_secs(60)), Ok(false));
+        let fragments = iface.inner.ipv4_fragments.as_mut().unwrap();
+        assert!(fragments.get_packet_assembler_mut(&key).is_err());
+    }
+
+    #[test]
+    #[cfg(all(
+        feature = "proto-ipv6-fragmentation",
+        feature = "medium-ip",
+        feature = "socket-icmp"
+    ))]
+    fn test_icmpv6_fragmented_echo() {
+        use crate::wire::{Icmpv6Packet, Ipv6FragmentHeader, Ipv6FragmentRepr, Ipv6Packet};
+
+        let local_addr = Ipv6Address::LOOPBACK;
+        // Split a packet in fragments that fit the minimum IPv6 MTU.
+        let fragment = |bytes: &[u8], ident: u32| {
+            let packet = Ipv6Packet::new_checked(bytes).unwrap();
+            let payload = packet.payload();
+            payload
+                .chunks(1232)
+                .enumerate()
+                .map(|(index, chunk)| {
+                    let frag_repr = Ipv6FragmentRepr {
+                        next_header: packet.next_header(),
+                        frag_offset: (index * 1232 / 8) as u16,
+                        more_frags: (index + 1) * 1232 < payload.len(),
+                        ident,
+                    };
+                    let ipv6_repr = Ipv6Repr {
+                        src_addr: local_addr,
+                        dst_addr: local_addr,
+                        next_header: IpProtocol::Ipv6Frag,
+                        payload_len: frag_repr.buffer_len() + chunk.len(),
+                        hop_limit: 64,
+                    };
+                    let mut fragment = vec![0; ipv6_repr.buffer_len() + ipv6_repr.payload_len];
+                    let mut packet = Ipv6Packet::new_unchecked(&mut fragment[..]);
+                    ipv6_repr.emit(&mut packet);
+                    let payload = packet.payload_mut();
+                    frag_repr.emit(&mut Ipv6FragmentHeader::new_unchecked(&mut payload[..]));
+                    payload[frag_repr.buffer_len()..].copy_from_slice(chunk);
+                    fragment
+                })
+                .collect::<Vec<_>>()
+        };
+        let echo_packet = |repr: &Icmpv6Repr| {
+            let ipv6_repr = Ipv6Repr {
+                src_addr: local_addr,
+                dst_addr: local_addr,
+                next_header: IpProtocol::Icmpv6,
+                payload_len: repr.buffer_len(),
+                hop_limit: 64,
+            };
+            let mut bytes = vec![0; ipv6_repr.buffer_len() + repr.buffer_len()];
+            let mut packet = Ipv6Packet::new_unchecked(&mut bytes[..]);
+            ipv6_repr.emit(&mut packet);
+            repr.emit(
+                &local_addr.into(),
+                &local_addr.into(),
+                &mut Icmpv6Packet::new_unchecked(packet.payload_mut()),
+                &ChecksumCapabilities::default(),
+            );
+            bytes
+        };
+        // Queue the fragments on the device, for `poll` to receive them.
+        let receive = |iface: &mut Interface<'_, Loopback>, fragments: &[Vec<u8>]| {
+            for bytes in fragments {
+                let tx_token = iface.device.transmit().unwrap();
+                tx_token
+                    .consume(Instant::from_millis(0), bytes.len(), |buffer| {
+                        buffer.copy_from_slice(bytes);
+                        Ok(())
+                    })
+                    .unwrap();
+            }
+        };
+
+        let mut iface = create_loopback_ip();
+        iface.inner.ipv6_fragments = Some(PacketAssemblerSet::new(vec![], BTreeMap::new()));
+        let payload = (0..4000).map(|i| i as u8).collect::<Vec<_>>();
+
+        // The echo request and the reply to it both reach a socket bound to their identifier.
+        let rx_buffer =
+            icmp::PacketBuffer::new(vec![icmp::PacketMetadata::EMPTY; 2], vec![0; 2 * 4008]);
+        let tx_buffer = icmp::PacketBuffer::new(vec![icmp::PacketMetadata::EMPTY], vec![0; 4008]);
+        let socket_handle = iface.add_socket(icmp::Socket::new(rx_buffer, tx_buffer));
+        let socket = iface.get_socket::<icmp::Socket>(socket_handle);
+        socket.bind(icmp::Endpoint::Ident(0x1234)).unwrap();
+        let recv_echo = |iface: &mut Interface<'_, Loopback>, expected: &Icmpv6Repr| {
+            let socket = iface.get_socket::<icmp::Socket>(socket_handle);
+            let (bytes, addr) = socket.recv().unwrap();
+            assert_eq!(addr, local_addr.into());
+            let icmp_packet = Icmpv6Packet::new_checked(bytes).unwrap();
+            assert_eq!(
+                Icmpv6Repr::parse(
+                    &local_addr.into(),
+                    &local_addr.into(),
+                    &icmp_packet,
+                    &ChecksumCapabilities::default()
+                )
+                .as_ref(),
+                Ok(expected)
+            );
+        };
+
+        // A fragmented echo request is reassembled and answered in full.
+        // Fragments may arrive in any order.
+        let request = Icmpv6Repr::EchoRequest {
+            ident: 0x1234,
+            seq_no: 1,
+            data: &payload,
+        };
+        let reply = Icmpv6Repr::EchoReply {
+            ident: 0x1234,
+            seq_no: 1,
+            data: &payload,
+        };
+        let mut fragments = fragment(&echo_packet(&request), 1);
+        assert_eq!(fragments.len(), 4);
+        fragments.reverse();
+        receive(&mut iface, &fragments);
+        assert_eq!(iface.poll(Instant::from_millis(0)), Ok(true));
+        recv_echo(&mut iface, &request);
+        recv_echo(&mut iface, &reply);
+
+        // Only one packet is reassembled at once; the fragments of another one discard it.
+        iface
+            .inner
+            .ipv6_fragments
+            .as_mut()
+            .unwrap()
+            .set_limits(1, usize::MAX);
+        let discarded = Icmpv6Repr::EchoRequest {
+            ident: 0x1234,
+            seq_no: 2,
+            data: &payload,
+        };
+        let request = Icmpv6Repr::EchoRequest {
+            ident: 0x1234,
+            seq_no: 3,
+            data: &payload,
+        };
+        let reply = Icmpv6Repr::EchoReply {
+            ident: 0x1234,
+            seq_no: 3,
+            data: &payload,
+        };
+        let discarded_fragments = fragment(&echo_packet(&discarded), 2);
+        receive(&mut iface, &discarded_fragments[..1]);
+        receive(&mut iface, &fragment(&echo_packet(&request), 3));
+        receive(&mut iface, &discarded_fragments[1..]);
+        assert_eq!(iface.poll(Instant::from_millis(0)), Ok(true));
+        recv_echo(&mut iface, &request);
+        recv_echo(&mut iface, &reply);
+        let socket = iface.get_socket::<icmp::Socket>(socket_handle);
+        assert!(!socket.can_recv());
+
+        // A packet missing fragments is given up on once the reassembly timeout expires.
+        let packet = Ipv6Packet::new_checked(&discarded_fragments[1][..]).unwrap();
+        let ipv6_repr = Ipv6Repr::parse(&packet).unwrap();
+        let frag_header = Ipv6FragmentHeader::new_checked(packet.payload()).unwrap();
+        let key = Ipv6FragmentRepr::parse(&frag_header)
+            .unwrap()
+            .get_key(&ipv6_repr);
+        let fragments = iface.inner.ipv6_fragments.as_mut().unwrap();
+        assert!(fragments.get_packet_assembler_mut(&key).is_ok());
+        assert_eq!(iface.poll(Instant::from_secs(60)), Ok(false));
+        let fragments = iface.inner.ipv6_fragments.as_mut().unwrap();
+        assert!(fragments.get_packet_assembler_mut(&key).is_err());
+    }
+
+    #[test]
+    #[cfg(all(feature = "socket-udp", feature = "proto-ipv4"))]
+    fn test_icmp_error_port_unreachable() {
+        static UDP_PAYLOAD: [u8; 12] = [
+            0x48, 0x65, 0x6c, 0x6c, 0x6f, 0x2c, 0x20, 0x57, 0x6f, 0x6c, 0x64, 0x21,
+        ];
+        let mut iface = create_loopback();
+
+        let mut udp_bytes_unicast = vec![0u8; 20];
+        let mut udp_bytes_broadcast = vec![0u8; 20];
+        let mut packet_unicast = UdpPacket::new_unchecked(&mut udp_bytes_unicast);
+        let mut packet_broadcast = UdpPacket::new_unchecked(&mut udp_bytes_broadcast);
+
+        let udp_repr = UdpRepr {
+            src_port: 67,
+            dst_port: 68,
+        };
+
+        let ip_repr = IpRepr::Ipv4(Ipv4Repr {
+            src_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x02]),
+            dst_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x01]),
+            next_header: IpProtocol::Udp,
+            payload_len: udp_repr.header_len() + UDP_PAYLOAD.len(),
+            hop_limit: 64,
+        });
+
+        // Emit the representations to a packet
+        udp_repr.emit(
+            &mut packet_unicast,
+            &ip_repr.src_addr(),
+            &ip_repr.dst_addr(),
+            UDP_PAYLOAD.len(),
+            |buf| buf.copy_from_slice(&UDP_PAYLOAD),
+            &ChecksumCapabilities::default(),
+        );
+
+        let data = packet_unicast.into_inner();
+
+        // The expected Destination Unreachable ICMPv4 error response due
+        // to no sockets listening on the destination port.
+        let icmp_repr = Icmpv4Repr::DstUnreachable {
+            reason: Icmpv4DstUnreachable::PortUnreachable,
+            header: Ipv4Repr {
+                src_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x02]),
+                dst_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x01]),
+                next_header: IpProtocol::Udp,
+                payload_len: udp_repr.header_len() + UDP_PAYLOAD.len(),
+                hop_limit: 64,
+            },
+            data: data,
+        };
+        let expected_repr = IpPacket::Icmpv4((
+            Ipv4Repr {
+                src_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x01]),
+                dst_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x02]),
+                next_header: IpProtocol::Icmp,
+                payload_len: icmp_repr.buffer_len(),
+                hop_limit: 64,
+            },
+            icmp_repr,
+        ));
+
         // Ensure that the unknown protocol triggers an error response.
         // And we correctly handle no payload.
         assert_eq!(
@@ -3115,6 +5834,155 @@ mod test {
         );
     }
 
+    #[test]
+    #[cfg(all(feature = "proto-ipv4", feature = "socket-udp"))]
+    fn test_handle_ipv4_options() {
+        use crate::wire::{IpEndpoint, Ipv4Packet};
+
+        static UDP_PAYLOAD: [u8; 5] = [0x48, 0x65, 0x6c, 0x6c, 0x6f];
+
+        let mut iface = create_loopback();
+
+        let rx_buffer = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY], vec![0; 15]);
+        let tx_buffer = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY], vec![0; 15]);
+        let socket_handle = iface.add_socket(udp::Socket::new(rx_buffer, tx_buffer));
+        let socket = iface.get_socket::<udp::Socket>(socket_handle);
+        assert_eq!(socket.bind(68), Ok(()));
+
+        let src_addr = Ipv4Address::new(127, 0, 0, 2);
+        let dst_addr = Ipv4Address::new(127, 0, 0, 1);
+        let udp_repr = UdpRepr {
+            src_port: 67,
+            dst_port: 68,
+        };
+        let ipv4_repr = Ipv4Repr {
+            src_addr,
+            dst_addr,
+            next_header: IpProtocol::Udp,
+            payload_len: udp_repr.header_len() + UDP_PAYLOAD.len(),
+            hop_limit: 0x40,
+        };
+
+        // Two no-operation options, padded to a multiple of four octets by the
+        // end-of-options list, followed by a Router Alert option.
+        let options = [0x01, 0x01, 0x00, 0x00, 0x94, 0x04, 0x00, 0x00];
+        let mut bytes = vec![0; ipv4_repr.buffer_len() + options.len() + ipv4_repr.payload_len];
+        let mut packet = Ipv4Packet::new_unchecked(&mut bytes);
+        ipv4_repr.emit_with_options(&mut packet, &options, &ChecksumCapabilities::default());
+        udp_repr.emit(
+            &mut UdpPacket::new_unchecked(packet.payload_mut()),
+            &src_addr.into(),
+            &dst_addr.into(),
+            UDP_PAYLOAD.len(),
+            |buf| buf.copy_from_slice(&UDP_PAYLOAD),
+            &ChecksumCapabilities::default(),
+        );
+
+        let frame = Ipv4Packet::new_checked(&bytes).unwrap();
+        assert_eq!(frame.header_len() as usize, 28);
+        assert_eq!(iface.inner.process_ipv4(&mut iface.sockets, &frame), None);
+
+        let socket = iface.get_socket::<udp::Socket>(socket_handle);
+        assert_eq!(
+            socket.recv(),
+            Ok((&UDP_PAYLOAD[..], IpEndpoint::new(src_addr.into(), 67)))
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "proto-ipv4", feature = "socket-udp"))]
+    fn test_drop_observer() {
+        use crate::wire::Ipv4Packet;
+        use std::cell::RefCell;
+
+        let udp_datagram =
+            |dst_addr: Ipv4Address, dst_port: u16| udp_datagram_ipv4(dst_addr, dst_port, b"Hello");
+
+        let drops = RefCell::new(Vec::new());
+        let mut observer = |reason: DropReason, ip_repr: &IpRepr| {
+            drops.borrow_mut().push((reason, ip_repr.dst_addr()))
+        };
+        let mut iface = create_loopback();
+        iface.set_drop_observer(Some(&mut observer));
+
+        let rx_buffer = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY], vec![0; 15]);
+        let tx_buffer = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY], vec![0; 15]);
+        let socket_handle = iface.add_socket(udp::Socket::new(rx_buffer, tx_buffer));
+        let socket = iface.get_socket::<udp::Socket>(socket_handle);
+        assert_eq!(socket.bind(68), Ok(()));
+
+        let local_addr = Ipv4Address::new(127, 0, 0, 1);
+        let other_addr = Ipv4Address::new(192, 168, 1, 1);
+        let mut process = |bytes: &[u8]| {
+            let packet = Ipv4Packet::new_checked(bytes).unwrap();
+            iface.inner.process_ipv4(&mut iface.sockets, &packet);
+        };
+
+        // The first datagram is received, but there is no room for the second one.
+        process(&udp_datagram(local_addr, 68));
+        assert!(drops.borrow().is_empty());
+        process(&udp_datagram(local_addr, 68));
+        // Nothing listens on port 69.
+        process(&udp_datagram(local_addr, 69));
+        // Nor are we the destination.
+        process(&udp_datagram(other_addr, 68));
+        // A corrupted payload, and then a corrupted header.
+        let mut bytes = udp_datagram(local_addr, 68);
+        bytes[30] ^= 0xff;
+        process(&bytes);
+        bytes[8] ^= 0xff;
+        process(&bytes);
+        // A UDP length that doesn't fit in the packet.
+        let mut bytes = udp_datagram(local_addr, 68);
+        bytes[24] = 0xff;
+        process(&bytes);
+        // A fragment, which isn't reassembled.
+        let mut bytes = udp_datagram(local_addr, 68);
+        let mut packet = Ipv4Packet::new_unchecked(&mut bytes);
+        packet.set_more_frags(true);
+        packet.fill_checksum();
+        process(&bytes);
+
+        assert_eq!(
+            drops.borrow()[..],
+            [
+                (DropReason::BufferFull, local_addr.into()),
+                (DropReason::NoSocket, local_addr.into()),
+                (DropReason::NoRoute, other_addr.into()),
+                (DropReason::Checksum, local_addr.into()),
+                (DropReason::Checksum, local_addr.into()),
+                (DropReason::Malformed, local_addr.into()),
+                (DropReason::Fragment, local_addr.into()),
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "proto-ipv4", feature = "socket-udp"))]
+    fn test_drop_observer_rate_limit() {
+        use crate::wire::Ipv4Packet;
+        use std::cell::Cell;
+
+        let reports = Cell::new(0);
+        let mut observer = |_: DropReason, _: &IpRepr| reports.set(reports.get() + 1);
+        let mut iface = create_loopback();
+        iface.set_drop_observer(Some(&mut observer));
+        iface.inner.drop_report_limiter = RateLimiter::new(2, Duration::from_secs(1));
+
+        // A UDP datagram to a closed port.
+        let bytes = udp_datagram_ipv4(Ipv4Address::new(127, 0, 0, 1), 69, &[]);
+        let packet = Ipv4Packet::new_checked(&bytes).unwrap();
+
+        for _ in 0..4 {
+            iface.inner.process_ipv4(&mut iface.sockets, &packet);
+        }
+        assert_eq!(reports.get(), 2);
+
+        iface.inner.now = crate::time::Instant::from_secs(1);
+        iface.inner.process_ipv4(&mut iface.sockets, &packet);
+        assert_eq!(reports.get(), 3);
+    }
+
     #[test]
     #[cfg(feature = "proto-ipv4")]
     fn test_handle_ipv4_broadcast() {
@@ -3349,75 +6217,465 @@ mod test {
     }
 
     #[test]
-    #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv6"))]
-    fn test_handle_valid_ndisc_request() {
+    #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+    fn test_arp_request_rate_limit() {
         let mut iface = create_loopback_ethernet();
+        iface.inner.neighbor_request_limiter = RateLimiter::new(2, Duration::from_secs(1));
 
-        let mut eth_bytes = vec![0u8; 86];
-
-        let local_ip_addr = Ipv6Address::new(0xfdbe, 0, 0, 0, 0, 0, 0, 1);
-        let remote_ip_addr = Ipv6Address::new(0xfdbe, 0, 0, 0, 0, 0, 0, 2);
-        let local_hw_addr = EthernetAddress([0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        let mut eth_bytes = vec![0u8; 42];
         let remote_hw_addr = EthernetAddress([0x52, 0x54, 0x00, 0x00, 0x00, 0x00]);
-
-        let solicit = Icmpv6Repr::Ndisc(NdiscRepr::NeighborSolicit {
-            target_addr: local_ip_addr,
-            lladdr: Some(remote_hw_addr.into()),
-        });
-        let ip_repr = IpRepr::Ipv6(Ipv6Repr {
-            src_addr: remote_ip_addr,
-            dst_addr: local_ip_addr.solicited_node(),
-            next_header: IpProtocol::Icmpv6,
-            hop_limit: 0xff,
-            payload_len: solicit.buffer_len(),
-        });
-
+        let repr = ArpRepr::EthernetIpv4 {
+            operation: ArpOperation::Request,
+            source_hardware_addr: remote_hw_addr,
+            source_protocol_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x02]),
+            target_hardware_addr: EthernetAddress::default(),
+            target_protocol_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x01]),
+        };
         let mut frame = EthernetFrame::new_unchecked(&mut eth_bytes);
-        frame.set_dst_addr(EthernetAddress([0x33, 0x33, 0x00, 0x00, 0x00, 0x00]));
+        frame.set_dst_addr(EthernetAddress::BROADCAST);
         frame.set_src_addr(remote_hw_addr);
-        frame.set_ethertype(EthernetProtocol::Ipv6);
-        ip_repr.emit(frame.payload_mut(), &ChecksumCapabilities::default());
-        solicit.emit(
-            &remote_ip_addr.into(),
-            &local_ip_addr.solicited_node().into(),
-            &mut Icmpv6Packet::new_unchecked(&mut frame.payload_mut()[ip_repr.buffer_len()..]),
-            &ChecksumCapabilities::default(),
-        );
-
-        let icmpv6_expected = Icmpv6Repr::Ndisc(NdiscRepr::NeighborAdvert {
-            flags: NdiscNeighborFlags::SOLICITED,
-            target_addr: local_ip_addr,
-            lladdr: Some(local_hw_addr.into()),
-        });
-
-        let ipv6_expected = Ipv6Repr {
-            src_addr: local_ip_addr,
-            dst_addr: remote_ip_addr,
-            next_header: IpProtocol::Icmpv6,
-            hop_limit: 0xff,
-            payload_len: icmpv6_expected.buffer_len(),
-        };
+        frame.set_ethertype(EthernetProtocol::Arp);
+        repr.emit(&mut ArpPacket::new_unchecked(frame.payload_mut()));
+        let frame = frame.into_inner();
 
-        // Ensure an Neighbor Solicitation triggers a Neighbor Advertisement
-        assert_eq!(
-            iface
+        for _ in 0..2 {
+            assert!(iface
                 .inner
-                .process_ethernet(&mut iface.sockets, frame.into_inner()),
-            Some(EthernetPacket::Ip(IpPacket::Icmpv6((
-                ipv6_expected,
-                icmpv6_expected
-            ))))
+                .process_ethernet(&mut iface.sockets, &frame)
+                .is_some());
+        }
+        assert_eq!(
+            iface.inner.process_ethernet(&mut iface.sockets, &frame),
+            None
         );
+        assert_eq!(iface.neighbor_requests_limited(), 1);
 
-        // Ensure the address of the requestor was entered in the cache
+        iface.inner.now = Instant::from_millis(500);
+        assert!(iface
+            .inner
+            .process_ethernet(&mut iface.sockets, &frame)
+            .is_some());
         assert_eq!(
-            iface.inner.lookup_hardware_addr(
-                MockTxToken,
-                &IpAddress::Ipv6(local_ip_addr),
-                &IpAddress::Ipv6(remote_ip_addr)
-            ),
-            Ok((HardwareAddress::Ethernet(remote_hw_addr), MockTxToken))
+            iface.inner.process_ethernet(&mut iface.sockets, &frame),
+            None
         );
+        assert_eq!(iface.neighbor_requests_limited(), 2);
+    }
+
+    #[test]
+    #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+    fn test_set_hardware_addr_announce() {
+        let mut iface = create_loopback_ethernet();
+        let timestamp = Instant::from_millis(0);
+
+        // Setting the same address again announces nothing.
+        iface.set_hardware_addr(EthernetAddress::default().into());
+        assert_eq!(iface.poll_at(timestamp), None);
+        assert_eq!(iface.arp_announce_egress(), Ok(false));
+
+        let virtual_addr = EthernetAddress([0x00, 0x00, 0x5e, 0x00, 0x01, 0x01]);
+        iface.set_hardware_addr(virtual_addr.into());
+        assert_eq!(iface.poll_at(timestamp), Some(Instant::from_millis(0)));
+        assert_eq!(iface.arp_announce_egress(), Ok(true));
+        assert_eq!(iface.arp_announce_egress(), Ok(false));
+
+        let frames = recv_all(&mut iface, timestamp);
+        assert_eq!(frames.len(), 1);
+        let eth_frame = EthernetFrame::new_checked(&frames[0][..]).unwrap();
+        assert_eq!(eth_frame.src_addr(), virtual_addr);
+        assert_eq!(eth_frame.dst_addr(), EthernetAddress::BROADCAST);
+        assert_eq!(eth_frame.ethertype(), EthernetProtocol::Arp);
+        let arp_packet = ArpPacket::new_checked(eth_frame.payload()).unwrap();
+        assert_eq!(
+            ArpRepr::parse(&arp_packet),
+            Ok(ArpRepr::EthernetIpv4 {
+                operation: ArpOperation::Request,
+                source_hardware_addr: virtual_addr,
+                source_protocol_addr: Ipv4Address([127, 0, 0, 1]),
+                target_hardware_addr: EthernetAddress::BROADCAST,
+                target_protocol_addr: Ipv4Address([127, 0, 0, 1]),
+            })
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+    fn test_set_hardware_addr_announce_resume() {
+        let mut iface = create_loopback_ethernet();
+        let timestamp = Instant::from_millis(0);
+        iface.update_ip_addrs(|addrs| {
+            *addrs = vec![
+                IpCidr::new(IpAddress::v4(127, 0, 0, 1), 8),
+                IpCidr::new(IpAddress::v4(0, 0, 0, 0), 0),
+                IpCidr::new(IpAddress::v4(10, 0, 0, 1), 8),
+            ]
+            .into();
+        });
+
+        let announced = |iface: &mut Interface<'_, Loopback>| {
+            recv_all(iface, timestamp)
+                .iter()
+                .map(|frame| {
+                    let eth_frame = EthernetFrame::new_checked(&frame[..]).unwrap();
+                    let arp_packet = ArpPacket::new_checked(eth_frame.payload()).unwrap();
+                    match ArpRepr::parse(&arp_packet).unwrap() {
+                        ArpRepr::EthernetIpv4 {
+                            source_protocol_addr,
+                            ..
+                        } => source_protocol_addr,
+                        #[allow(unreachable_patterns)]
+                        _ => unreachable!(),
+                    }
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // The unspecified address is never announced.
+        let virtual_addr = EthernetAddress([0x00, 0x00, 0x5e, 0x00, 0x01, 0x01]);
+        iface.set_hardware_addr(virtual_addr.into());
+        assert_eq!(iface.arp_announce_egress(), Ok(true));
+        assert_eq!(
+            announced(&mut iface),
+            [
+                Ipv4Address::new(127, 0, 0, 1),
+                Ipv4Address::new(10, 0, 0, 1)
+            ]
+        );
+
+        // An interrupted announcement resumes from the address that wasn't announced.
+        iface.inner.arp_announce_next = Some(1);
+        assert_eq!(iface.poll_at(timestamp), Some(Instant::from_millis(0)));
+        assert_eq!(iface.arp_announce_egress(), Ok(true));
+        assert_eq!(announced(&mut iface), [Ipv4Address::new(10, 0, 0, 1)]);
+        assert_eq!(iface.poll_at(timestamp), None);
+    }
+
+    #[test]
+    #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+    fn test_point_to_point_peer() {
+        let mut iface = create_loopback_ethernet();
+        let peer_hw_addr = EthernetAddress([0x52, 0x54, 0x00, 0x00, 0x00, 0x01]);
+        let local_addr = IpAddress::v4(127, 0, 0, 1);
+        let on_link_addr = IpAddress::v4(127, 0, 0, 2);
+        let remote_addr = IpAddress::v4(192, 168, 1, 1);
+
+        // There is no route to the remote address.
+        assert!(!iface.inner.has_neighbor(&remote_addr));
+        assert_eq!(
+            iface
+                .inner
+                .lookup_hardware_addr(MockTxToken, &local_addr, &remote_addr),
+            Err(Error::Unaddressable)
+        );
+
+        iface.set_point_to_point_peer(Some(peer_hw_addr.into()));
+        assert_eq!(iface.point_to_point_peer(), Some(peer_hw_addr.into()));
+        for addr in [on_link_addr, remote_addr].iter() {
+            assert!(iface.inner.has_neighbor(addr));
+            assert_eq!(
+                iface
+                    .inner
+                    .lookup_hardware_addr(MockTxToken, &local_addr, addr),
+                Ok((HardwareAddress::Ethernet(peer_hw_addr), MockTxToken))
+            );
+        }
+        assert_eq!(
+            iface.inner.lookup_hardware_addr(
+                MockTxToken,
+                &local_addr,
+                &IpAddress::Ipv4(Ipv4Address::BROADCAST)
+            ),
+            Ok((
+                HardwareAddress::Ethernet(EthernetAddress::BROADCAST),
+                MockTxToken
+            ))
+        );
+        // Nothing was resolved.
+        assert!(iface.device.receive().is_none());
+
+        iface.set_point_to_point_peer(None);
+        assert!(!iface.inner.has_neighbor(&remote_addr));
+    }
+
+    #[test]
+    #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+    fn test_ignore_rarp_operation_in_arp() {
+        let mut iface = create_loopback_ethernet();
+
+        let mut eth_bytes = vec![0u8; 42];
+        let remote_hw_addr = EthernetAddress([0x52, 0x54, 0x00, 0x00, 0x00, 0x00]);
+
+        for &operation in &[
+            ArpOperation::RarpRequest,
+            ArpOperation::RarpReply,
+            ArpOperation::Unknown(9),
+        ] {
+            let repr = ArpRepr::EthernetIpv4 {
+                operation,
+                source_hardware_addr: remote_hw_addr,
+                source_protocol_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x02]),
+                target_hardware_addr: EthernetAddress::default(),
+                target_protocol_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x01]),
+            };
+            let mut frame = EthernetFrame::new_unchecked(&mut eth_bytes);
+            frame.set_dst_addr(EthernetAddress::BROADCAST);
+            frame.set_src_addr(remote_hw_addr);
+            frame.set_ethertype(EthernetProtocol::Arp);
+            repr.emit(&mut ArpPacket::new_unchecked(frame.payload_mut()));
+
+            assert_eq!(
+                iface
+                    .inner
+                    .process_ethernet(&mut iface.sockets, frame.into_inner()),
+                None
+            );
+        }
+
+        // Nothing was learned from these packets.
+        assert_eq!(
+            iface.inner.lookup_hardware_addr(
+                MockTxToken,
+                &IpAddress::v4(127, 0, 0, 1),
+                &IpAddress::v4(127, 0, 0, 2)
+            ),
+            Err(Error::Unaddressable)
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+    fn test_handle_rarp_request() {
+        let mut iface = create_loopback_ethernet();
+
+        let local_ip_addr = Ipv4Address([0x7f, 0x00, 0x00, 0x01]);
+        let local_hw_addr = EthernetAddress([0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        let remote_hw_addr = EthernetAddress([0x52, 0x54, 0x00, 0x00, 0x00, 0x00]);
+        let known_hw_addr = EthernetAddress([0x52, 0x54, 0x00, 0x00, 0x00, 0x01]);
+        let unknown_hw_addr = EthernetAddress([0x52, 0x54, 0x00, 0x00, 0x00, 0x02]);
+        let known_ip_addr = Ipv4Address([0x7f, 0x00, 0x00, 0x03]);
+
+        let rarp_frame = |operation, target_hardware_addr| {
+            let mut eth_bytes = vec![0u8; 42];
+            let repr = ArpRepr::EthernetIpv4 {
+                operation,
+                source_hardware_addr: remote_hw_addr,
+                source_protocol_addr: Ipv4Address::UNSPECIFIED,
+                target_hardware_addr,
+                target_protocol_addr: Ipv4Address::UNSPECIFIED,
+            };
+            let mut frame = EthernetFrame::new_unchecked(&mut eth_bytes);
+            frame.set_dst_addr(EthernetAddress::BROADCAST);
+            frame.set_src_addr(remote_hw_addr);
+            frame.set_ethertype(EthernetProtocol::Rarp);
+            repr.emit(&mut ArpPacket::new_unchecked(frame.payload_mut()));
+            eth_bytes
+        };
+
+        // Without any mappings, requests are ignored.
+        let frame = rarp_frame(ArpOperation::RarpRequest, known_hw_addr);
+        assert_eq!(
+            iface.inner.process_ethernet(&mut iface.sockets, &frame),
+            None
+        );
+
+        *iface.rarp_table_mut() = BTreeMap::new().into();
+        iface
+            .rarp_table_mut()
+            .insert(known_hw_addr, known_ip_addr)
+            .unwrap();
+
+        assert_eq!(
+            iface.inner.process_ethernet(&mut iface.sockets, &frame),
+            Some(EthernetPacket::Arp(ArpRepr::EthernetIpv4 {
+                operation: ArpOperation::RarpReply,
+                source_hardware_addr: local_hw_addr,
+                source_protocol_addr: local_ip_addr,
+                target_hardware_addr: known_hw_addr,
+                target_protocol_addr: known_ip_addr,
+            }))
+        );
+
+        let frame = rarp_frame(ArpOperation::RarpRequest, unknown_hw_addr);
+        assert_eq!(
+            iface.inner.process_ethernet(&mut iface.sockets, &frame),
+            None
+        );
+
+        for &operation in &[ArpOperation::RarpReply, ArpOperation::Request] {
+            let frame = rarp_frame(operation, known_hw_addr);
+            assert_eq!(
+                iface.inner.process_ethernet(&mut iface.sockets, &frame),
+                None
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+    fn test_dispatch_rarp_reply() {
+        let mut iface = create_loopback_ethernet();
+
+        let known_hw_addr = EthernetAddress([0x52, 0x54, 0x00, 0x00, 0x00, 0x01]);
+        let reply_repr = ArpRepr::EthernetIpv4 {
+            operation: ArpOperation::RarpReply,
+            source_hardware_addr: EthernetAddress::default(),
+            source_protocol_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x01]),
+            target_hardware_addr: known_hw_addr,
+            target_protocol_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x03]),
+        };
+
+        let tx_token = iface.device.transmit().unwrap();
+        iface
+            .inner
+            .dispatch(tx_token, EthernetPacket::Arp(reply_repr))
+            .unwrap();
+
+        let (rx_token, _) = iface.device.receive().unwrap();
+        rx_token
+            .consume(Instant::from_millis(0), |buffer| {
+                let frame = EthernetFrame::new_checked(&*buffer).unwrap();
+                assert_eq!(frame.dst_addr(), known_hw_addr);
+                assert_eq!(frame.ethertype(), EthernetProtocol::Rarp);
+                let packet = ArpPacket::new_checked(frame.payload()).unwrap();
+                assert_eq!(ArpRepr::parse(&packet), Ok(reply_repr));
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv6"))]
+    fn test_handle_valid_ndisc_request() {
+        let mut iface = create_loopback_ethernet();
+
+        let mut eth_bytes = vec![0u8; 86];
+
+        let local_ip_addr = Ipv6Address::new(0xfdbe, 0, 0, 0, 0, 0, 0, 1);
+        let remote_ip_addr = Ipv6Address::new(0xfdbe, 0, 0, 0, 0, 0, 0, 2);
+        let local_hw_addr = EthernetAddress([0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        let remote_hw_addr = EthernetAddress([0x52, 0x54, 0x00, 0x00, 0x00, 0x00]);
+
+        let solicit = Icmpv6Repr::Ndisc(NdiscRepr::NeighborSolicit {
+            target_addr: local_ip_addr,
+            lladdr: Some(remote_hw_addr.into()),
+        });
+        let ip_repr = IpRepr::Ipv6(Ipv6Repr {
+            src_addr: remote_ip_addr,
+            dst_addr: local_ip_addr.solicited_node(),
+            next_header: IpProtocol::Icmpv6,
+            hop_limit: 0xff,
+            payload_len: solicit.buffer_len(),
+        });
+
+        let mut frame = EthernetFrame::new_unchecked(&mut eth_bytes);
+        frame.set_dst_addr(EthernetAddress([0x33, 0x33, 0x00, 0x00, 0x00, 0x00]));
+        frame.set_src_addr(remote_hw_addr);
+        frame.set_ethertype(EthernetProtocol::Ipv6);
+        ip_repr.emit(frame.payload_mut(), &ChecksumCapabilities::default());
+        solicit.emit(
+            &remote_ip_addr.into(),
+            &local_ip_addr.solicited_node().into(),
+            &mut Icmpv6Packet::new_unchecked(&mut frame.payload_mut()[ip_repr.buffer_len()..]),
+            &ChecksumCapabilities::default(),
+        );
+
+        let icmpv6_expected = Icmpv6Repr::Ndisc(NdiscRepr::NeighborAdvert {
+            flags: NdiscNeighborFlags::SOLICITED,
+            target_addr: local_ip_addr,
+            lladdr: Some(local_hw_addr.into()),
+        });
+
+        let ipv6_expected = Ipv6Repr {
+            src_addr: local_ip_addr,
+            dst_addr: remote_ip_addr,
+            next_header: IpProtocol::Icmpv6,
+            hop_limit: 0xff,
+            payload_len: icmpv6_expected.buffer_len(),
+        };
+
+        // Ensure an Neighbor Solicitation triggers a Neighbor Advertisement
+        assert_eq!(
+            iface
+                .inner
+                .process_ethernet(&mut iface.sockets, frame.into_inner()),
+            Some(EthernetPacket::Ip(IpPacket::Icmpv6((
+                ipv6_expected,
+                icmpv6_expected
+            ))))
+        );
+
+        // Ensure the address of the requestor was entered in the cache
+        assert_eq!(
+            iface.inner.lookup_hardware_addr(
+                MockTxToken,
+                &IpAddress::Ipv6(local_ip_addr),
+                &IpAddress::Ipv6(remote_ip_addr)
+            ),
+            Ok((HardwareAddress::Ethernet(remote_hw_addr), MockTxToken))
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv6"))]
+    fn test_ndisc_solicit_rate_limit() {
+        let mut iface = create_loopback_ethernet();
+        iface.inner.neighbor_request_limiter = RateLimiter::new(1, Duration::from_secs(1));
+
+        let local_ip_addr = Ipv6Address::new(0xfdbe, 0, 0, 0, 0, 0, 0, 1);
+        let other_ip_addr = Ipv6Address::new(0xfdbe, 0, 0, 0, 0, 0, 0, 3);
+        let remote_ip_addr = Ipv6Address::new(0xfdbe, 0, 0, 0, 0, 0, 0, 2);
+        let remote_hw_addr = EthernetAddress([0x52, 0x54, 0x00, 0x00, 0x00, 0x00]);
+        let solicit_frame = |target_addr: Ipv6Address| {
+            let solicit = Icmpv6Repr::Ndisc(NdiscRepr::NeighborSolicit {
+                target_addr,
+                lladdr: Some(remote_hw_addr.into()),
+            });
+            let ip_repr = IpRepr::Ipv6(Ipv6Repr {
+                src_addr: remote_ip_addr,
+                dst_addr: target_addr.solicited_node(),
+                next_header: IpProtocol::Icmpv6,
+                hop_limit: 0xff,
+                payload_len: solicit.buffer_len(),
+            });
+            let mut eth_bytes = vec![0u8; 86];
+            let mut frame = EthernetFrame::new_unchecked(&mut eth_bytes);
+            frame.set_dst_addr(EthernetAddress([0x33, 0x33, 0x00, 0x00, 0x00, 0x00]));
+            frame.set_src_addr(remote_hw_addr);
+            frame.set_ethertype(EthernetProtocol::Ipv6);
+            ip_repr.emit(frame.payload_mut(), &ChecksumCapabilities::default());
+            solicit.emit(
+                &remote_ip_addr.into(),
+                &target_addr.solicited_node().into(),
+                &mut Icmpv6Packet::new_unchecked(&mut frame.payload_mut()[ip_repr.buffer_len()..]),
+                &ChecksumCapabilities::default(),
+            );
+            eth_bytes
+        };
+
+        // Solicitations for other addresses don't use up the budget.
+        let other_frame = solicit_frame(other_ip_addr);
+        for _ in 0..3 {
+            assert_eq!(
+                iface
+                    .inner
+                    .process_ethernet(&mut iface.sockets, &other_frame),
+                None
+            );
+        }
+        assert_eq!(iface.neighbor_requests_limited(), 0);
+
+        let local_frame = solicit_frame(local_ip_addr);
+        assert!(iface
+            .inner
+            .process_ethernet(&mut iface.sockets, &local_frame)
+            .is_some());
+        assert_eq!(
+            iface
+                .inner
+                .process_ethernet(&mut iface.sockets, &local_frame),
+            None
+        );
+        assert_eq!(iface.neighbor_requests_limited(), 1);
     }
 
     #[test]
@@ -3605,37 +6863,287 @@ mod test {
     }
 
     #[test]
-    #[cfg(feature = "proto-ipv6")]
-    fn test_solicited_node_addrs() {
-        let mut iface = create_loopback();
-        let mut new_addrs = vec![
-            IpCidr::new(IpAddress::v6(0xfe80, 0, 0, 0, 1, 2, 0, 2), 64),
-            IpCidr::new(IpAddress::v6(0xfe80, 0, 0, 0, 3, 4, 0, 0xffff), 64),
-        ];
-        iface.update_ip_addrs(|addrs| {
-            new_addrs.extend(addrs.to_vec());
-            *addrs = From::from(new_addrs);
-        });
-        assert!(iface
-            .inner
-            .has_solicited_node(Ipv6Address::new(0xff02, 0, 0, 0, 0, 1, 0xff00, 0x0002)));
-        assert!(iface
-            .inner
-            .has_solicited_node(Ipv6Address::new(0xff02, 0, 0, 0, 0, 1, 0xff00, 0xffff)));
-        assert!(!iface
-            .inner
-            .has_solicited_node(Ipv6Address::new(0xff02, 0, 0, 0, 0, 1, 0xff00, 0x0003)));
-    }
+    #[cfg(all(feature = "socket-icmp", feature = "proto-ipv4"))]
+    fn test_icmpv4_socket_echo_reply() {
+        use crate::wire::{Icmpv4Message, Icmpv4Packet};
 
-    #[test]
-    #[cfg(feature = "proto-ipv6")]
-    fn test_icmpv6_nxthdr_unknown() {
         let mut iface = create_loopback();
+        assert!(iface.icmp_echo_reply());
+        iface.set_icmp_echo_reply(false);
+        assert!(!iface.icmp_echo_reply());
+
+        let rx_buffer = icmp::PacketBuffer::new(vec![icmp::PacketMetadata::EMPTY], vec![0; 24]);
+        let tx_buffer = icmp::PacketBuffer::new(vec![icmp::PacketMetadata::EMPTY], vec![0; 24]);
+        let socket_handle = iface.add_socket(icmp::Socket::new(rx_buffer, tx_buffer));
+        let socket = iface.get_socket::<icmp::Socket>(socket_handle);
+        assert_eq!(
+            socket.bind(icmp::Endpoint::Icmpv4Message(
+                Icmpv4Message::EchoRequest,
+                None
+            )),
+            Ok(())
+        );
+
+        let echo_repr = Icmpv4Repr::EchoRequest {
+            ident: 0x1234,
+            seq_no: 0x5432,
+            data: &[0xff; 16],
+        };
+        let mut bytes = [0; 24];
+        echo_repr.emit(
+            &mut Icmpv4Packet::new_unchecked(&mut bytes),
+            &ChecksumCapabilities::default(),
+        );
+        let ipv4_repr = Ipv4Repr {
+            src_addr: Ipv4Address::new(0x7f, 0x00, 0x00, 0x02),
+            dst_addr: Ipv4Address::new(0x7f, 0x00, 0x00, 0x01),
+            next_header: IpProtocol::Icmp,
+            payload_len: 24,
+            hop_limit: 64,
+        };
+
+        // The interface leaves the request to the socket.
+        assert_eq!(
+            iface
+                .inner
+                .process_icmpv4(&mut iface.sockets, IpRepr::Ipv4(ipv4_repr), &bytes),
+            None
+        );
+
+        let socket = iface.get_socket::<icmp::Socket>(socket_handle);
+        let mut request = [0; 24];
+        let (len, remote) = socket.recv_slice(&mut request).unwrap();
+        assert_eq!(remote, IpAddress::Ipv4(ipv4_repr.src_addr));
+        let request_packet = Icmpv4Packet::new_checked(&request[..len]).unwrap();
+        let request_repr =
+            Icmpv4Repr::parse(&request_packet, &ChecksumCapabilities::default()).unwrap();
+        let reply_repr = request_repr.echo_reply().unwrap();
+        let reply_buf = socket.send(reply_repr.buffer_len(), remote).unwrap();
+        reply_repr.emit(
+            &mut Icmpv4Packet::new_unchecked(reply_buf),
+            &ChecksumCapabilities::default(),
+        );
+
+        let (socket, cx) = iface.get_socket_and_context::<icmp::Socket>(socket_handle);
+        let mut sent = false;
+        socket
+            .dispatch(cx, |_, (ip_repr, icmp_repr)| {
+                assert_eq!(ip_repr.dst_addr(), IpAddress::Ipv4(ipv4_repr.src_addr));
+                assert_eq!(
+                    icmp_repr,
+                    IcmpRepr::Ipv4(Icmpv4Repr::EchoReply {
+                        ident: 0x1234,
+                        seq_no: 0x5432,
+                        data: &[0xff; 16],
+                    })
+                );
+                sent = true;
+                Ok::<_, ()>(())
+            })
+            .unwrap();
+        assert!(sent);
+    }
+
+    #[test]
+    #[cfg(feature = "proto-ipv4")]
+    fn test_icmpv4_redirect() {
+        use crate::wire::{Icmpv4Packet, Icmpv4Redirect};
+
+        let mut iface = create_loopback();
+        *iface.routes_mut() = Routes::new(BTreeMap::new());
+        iface
+            .routes_mut()
+            .add_default_ipv4_route(Ipv4Address::new(127, 0, 0, 254))
+            .unwrap();
+
+        fn redirect(
+            iface: &mut Interface<'_, Loopback>,
+            router: Ipv4Address,
+            dst_addr: Ipv4Address,
+            gateway: Ipv4Address,
+        ) {
+            let icmp_repr = Icmpv4Repr::Redirect {
+                reason: Icmpv4Redirect::Host,
+                gateway,
+                header: Ipv4Repr {
+                    src_addr: Ipv4Address::new(127, 0, 0, 1),
+                    dst_addr,
+                    next_header: IpProtocol::Udp,
+                    payload_len: 8,
+                    hop_limit: 64,
+                },
+                data: &[0; 8],
+            };
+            let mut bytes = vec![0; icmp_repr.buffer_len()];
+            icmp_repr.emit(
+                &mut Icmpv4Packet::new_unchecked(&mut bytes),
+                &ChecksumCapabilities::default(),
+            );
+            let ip_repr = IpRepr::Ipv4(Ipv4Repr {
+                src_addr: router,
+                dst_addr: Ipv4Address::new(127, 0, 0, 1),
+                next_header: IpProtocol::Icmp,
+                payload_len: bytes.len(),
+                hop_limit: 64,
+            });
+            assert_eq!(
+                iface
+                    .inner
+                    .process_icmpv4(&mut iface.sockets, ip_repr, &bytes),
+                None
+            );
+        }
+
+        let router = Ipv4Address::new(127, 0, 0, 254);
+        let better_router = Ipv4Address::new(127, 0, 0, 253);
+        let lookup = |iface: &Interface<'_, Loopback>, addr: Ipv4Address, timestamp| {
+            iface.routes().lookup(&addr.into(), timestamp)
+        };
+
+        // Accept a redirect from the first hop to an on-link router.
+        let dst_addr = Ipv4Address::new(10, 0, 0, 1);
+        redirect(&mut iface, router, dst_addr, better_router);
+        assert_eq!(
+            lookup(&iface, dst_addr, Instant::from_secs(0)),
+            Some(better_router.into())
+        );
+        // The redirect applies to this destination only, and only for a while.
+        assert_eq!(
+            lookup(&iface, Ipv4Address::new(10, 0, 0, 2), Instant::from_secs(0)),
+            Some(router.into())
+        );
+        assert_eq!(
+            lookup(
+                &iface,
+                dst_addr,
+                Instant::from_secs(0) + Routes::REDIRECT_LIFETIME * 2
+            ),
+            Some(router.into())
+        );
+
+        // Ignore a redirect that does not come from the first hop.
+        let dst_addr = Ipv4Address::new(10, 0, 0, 3);
+        redirect(
+            &mut iface,
+            Ipv4Address::new(127, 0, 0, 99),
+            dst_addr,
+            better_router,
+        );
+        assert_eq!(
+            lookup(&iface, dst_addr, Instant::from_secs(0)),
+            Some(router.into())
+        );
+
+        // Ignore a redirect to an off-link router.
+        redirect(
+            &mut iface,
+            router,
+            dst_addr,
+            Ipv4Address::new(10, 0, 0, 254),
+        );
+        assert_eq!(
+            lookup(&iface, dst_addr, Instant::from_secs(0)),
+            Some(router.into())
+        );
+
+        // Ignore all redirects when asked to.
+        iface.inner.accept_redirects = false;
+        redirect(&mut iface, router, dst_addr, better_router);
+        assert_eq!(
+            lookup(&iface, dst_addr, Instant::from_secs(0)),
+            Some(router.into())
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv6"))]
+    fn test_ndisc_redirect() {
+        let mut iface = create_loopback_ethernet();
+        *iface.routes_mut() = Routes::new(BTreeMap::new());
+        let router = Ipv6Address::new(0xfe80, 0, 0, 0, 0, 0, 0, 1);
+        let better_router = Ipv6Address::new(0xfe80, 0, 0, 0, 0, 0, 0, 2);
+        let dst_addr = Ipv6Address::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1);
+        iface.routes_mut().add_default_ipv6_route(router).unwrap();
+
+        let ip_repr = Ipv6Repr {
+            src_addr: router,
+            dst_addr: Ipv6Address::new(0xfdbe, 0, 0, 0, 0, 0, 0, 1),
+            next_header: IpProtocol::Icmpv6,
+            payload_len: 40,
+            hop_limit: 0xff,
+        };
+        let lladdr = EthernetAddress([0x52, 0x54, 0x00, 0x00, 0x00, 0x02]);
+        let redirect = NdiscRepr::Redirect {
+            target_addr: better_router,
+            dest_addr: dst_addr,
+            lladdr: Some(lladdr.into()),
+            redirected_hdr: None,
+        };
+        assert_eq!(iface.inner.process_ndisc(ip_repr, redirect), None);
+        assert_eq!(
+            iface
+                .routes()
+                .lookup(&dst_addr.into(), Instant::from_secs(0)),
+            Some(better_router.into())
+        );
+        assert_eq!(
+            iface.inner.lookup_hardware_addr(
+                MockTxToken,
+                &IpAddress::Ipv6(Ipv6Address::new(0xfdbe, 0, 0, 0, 0, 0, 0, 1)),
+                &dst_addr.into()
+            ),
+            Ok((HardwareAddress::Ethernet(lladdr), MockTxToken))
+        );
+
+        // A redirect to a global address other than the destination is invalid.
+        let dst_addr = Ipv6Address::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 2);
+        let redirect = NdiscRepr::Redirect {
+            target_addr: Ipv6Address::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 3),
+            dest_addr: dst_addr,
+            lladdr: None,
+            redirected_hdr: None,
+        };
+        assert_eq!(iface.inner.process_ndisc(ip_repr, redirect), None);
+        assert_eq!(
+            iface
+                .routes()
+                .lookup(&dst_addr.into(), Instant::from_secs(0)),
+            Some(router.into())
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "proto-ipv6")]
+    fn test_solicited_node_addrs() {
+        let mut iface = create_loopback();
+        let mut new_addrs = vec![
+            IpCidr::new(IpAddress::v6(0xfe80, 0, 0, 0, 1, 2, 0, 2), 64),
+            IpCidr::new(IpAddress::v6(0xfe80, 0, 0, 0, 3, 4, 0, 0xffff), 64),
+        ];
+        iface.update_ip_addrs(|addrs| {
+            new_addrs.extend(addrs.to_vec());
+            *addrs = From::from(new_addrs);
+        });
+        assert!(iface
+            .inner
+            .has_solicited_node(Ipv6Address::new(0xff02, 0, 0, 0, 0, 1, 0xff00, 0x0002)));
+        assert!(iface
+            .inner
+            .has_solicited_node(Ipv6Address::new(0xff02, 0, 0, 0, 0, 1, 0xff00, 0xffff)));
+        assert!(!iface
+            .inner
+            .has_solicited_node(Ipv6Address::new(0xff02, 0, 0, 0, 0, 1, 0xff00, 0x0003)));
+    }
+
+    #[test]
+    #[cfg(feature = "proto-ipv6")]
+    fn test_icmpv6_nxthdr_unknown() {
+        let mut iface = create_loopback();
+
+        let remote_ip_addr = Ipv6Address::new(0xfe80, 0, 0, 0, 0, 0, 0, 1);
+
+        let payload = [0x12, 0x34, 0x56, 0x78];
 
-        let remote_ip_addr = Ipv6Address::new(0xfe80, 0, 0, 0, 0, 0, 0, 1);
-
-        let payload = [0x12, 0x34, 0x56, 0x78];
-
         let ipv6_repr = Ipv6Repr {
             src_addr: remote_ip_addr,
             dst_addr: Ipv6Address::LOOPBACK,
@@ -3669,9 +7177,10 @@ mod test {
 
         let reply_icmp_repr = Icmpv6Repr::ParamProblem {
             reason: Icmpv6ParamProblem::UnrecognizedNxtHdr,
+            // The Next Header field of the hop-by-hop header.
             pointer: 40,
             header: ipv6_repr,
-            data: &payload[..],
+            data: frame.payload(),
         };
 
         let reply_ipv6_repr = Ipv6Repr {
@@ -3690,6 +7199,142 @@ mod test {
         );
     }
 
+    #[test]
+    #[cfg(feature = "proto-ipv6")]
+    fn test_icmpv6_nxthdr_unknown_no_ext_header() {
+        let mut iface = create_loopback();
+
+        let remote_ip_addr = Ipv6Address::new(0xfe80, 0, 0, 0, 0, 0, 0, 1);
+        let ipv6_repr = Ipv6Repr {
+            src_addr: remote_ip_addr,
+            dst_addr: Ipv6Address::LOOPBACK,
+            next_header: IpProtocol::Unknown(0x0c),
+            payload_len: 4,
+            hop_limit: 0x40,
+        };
+        let mut bytes = vec![0; 44];
+        IpRepr::Ipv6(ipv6_repr).emit(&mut bytes, &ChecksumCapabilities::default());
+        bytes[40..].copy_from_slice(&[0x12, 0x34, 0x56, 0x78]);
+        let frame = Ipv6Packet::new_unchecked(&bytes);
+
+        let reply_icmp_repr = Icmpv6Repr::ParamProblem {
+            reason: Icmpv6ParamProblem::UnrecognizedNxtHdr,
+            // The Next Header field of the IPv6 header.
+            pointer: 6,
+            header: ipv6_repr,
+            data: &[0x12, 0x34, 0x56, 0x78],
+        };
+        let reply_ipv6_repr = Ipv6Repr {
+            src_addr: Ipv6Address::LOOPBACK,
+            dst_addr: remote_ip_addr,
+            next_header: IpProtocol::Icmpv6,
+            payload_len: reply_icmp_repr.buffer_len(),
+            hop_limit: 0x40,
+        };
+        assert_eq!(
+            iface.inner.process_ipv6(&mut iface.sockets, &frame),
+            Some(IpPacket::Icmpv6((reply_ipv6_repr, reply_icmp_repr)))
+        );
+
+        iface.inner.ipv6_param_problems = false;
+        assert_eq!(iface.inner.process_ipv6(&mut iface.sockets, &frame), None);
+    }
+
+    #[test]
+    #[cfg(feature = "proto-ipv6")]
+    fn test_icmpv6_hbh_unknown_option() {
+        let mut iface = create_loopback();
+
+        let remote_ip_addr = Ipv6Address::new(0xfe80, 0, 0, 0, 0, 0, 0, 1);
+        let build = |dst_addr: Ipv6Address, option_type: u8| {
+            let ipv6_repr = Ipv6Repr {
+                src_addr: remote_ip_addr,
+                dst_addr,
+                next_header: IpProtocol::HopByHop,
+                payload_len: 8,
+                hop_limit: 0x40,
+            };
+            let mut bytes = vec![0; 48];
+            IpRepr::Ipv6(ipv6_repr).emit(&mut bytes, &ChecksumCapabilities::default());
+            let mut hbh_pkt = Ipv6HopByHopHeader::new_unchecked(&mut bytes[40..]);
+            hbh_pkt.set_next_header(IpProtocol::Unknown(0x0c));
+            hbh_pkt.set_header_len(0);
+            // A Pad1 option, then the unknown option with two octets of data.
+            hbh_pkt
+                .options_mut()
+                .copy_from_slice(&[0x00, option_type, 0x02, 0xaa, 0xbb, 0x00]);
+            (ipv6_repr, bytes)
+        };
+        let reply = |ipv6_repr: Ipv6Repr, bytes: &[u8], src_addr: Ipv6Address| -> Vec<u8> {
+            let icmp_repr = Icmpv6Repr::ParamProblem {
+                reason: Icmpv6ParamProblem::UnrecognizedOption,
+                // The option type, after the IPv6 header, the hop-by-hop header fields and
+                // the Pad1 option.
+                pointer: 43,
+                header: ipv6_repr,
+                data: &bytes[40..],
+            };
+            let mut reply = vec![0; icmp_repr.buffer_len()];
+            icmp_repr.emit(
+                &src_addr.into(),
+                &remote_ip_addr.into(),
+                &mut Icmpv6Packet::new_unchecked(&mut reply),
+                &ChecksumCapabilities::default(),
+            );
+            reply
+        };
+        let src_addr = iface.inner.get_source_address_ipv6(remote_ip_addr).unwrap();
+        let mut process = |bytes: &[u8]| -> Option<(Ipv6Address, Vec<u8>)> {
+            match iface
+                .inner
+                .process_ipv6(&mut iface.sockets, &Ipv6Packet::new_unchecked(bytes))?
+            {
+                IpPacket::Icmpv6((ipv6_repr, icmp_repr)) => {
+                    assert_eq!(ipv6_repr.dst_addr, remote_ip_addr);
+                    let mut buffer = vec![0; icmp_repr.buffer_len()];
+                    icmp_repr.emit(
+                        &ipv6_repr.src_addr.into(),
+                        &ipv6_repr.dst_addr.into(),
+                        &mut Icmpv6Packet::new_unchecked(&mut buffer),
+                        &ChecksumCapabilities::default(),
+                    );
+                    Some((ipv6_repr.src_addr, buffer))
+                }
+                _ => panic!("expected an ICMPv6 reply"),
+            }
+        };
+
+        // Skipped and silently discarding options.
+        let (_, bytes) = build(Ipv6Address::LOOPBACK, 0x1e);
+        assert!(matches!(
+            process(&bytes),
+            Some((_, ref reply)) if reply[0] == 4 && reply[1] == 1
+        ));
+        let (_, bytes) = build(Ipv6Address::LOOPBACK, 0x5e);
+        assert_eq!(process(&bytes), None);
+
+        // Options asking for the sender to be notified.
+        for &option_type in &[0x9e, 0xde] {
+            let (ipv6_repr, bytes) = build(Ipv6Address::LOOPBACK, option_type);
+            assert_eq!(
+                process(&bytes),
+                Some((
+                    Ipv6Address::LOOPBACK,
+                    reply(ipv6_repr, &bytes, Ipv6Address::LOOPBACK)
+                ))
+            );
+        }
+
+        // Only the former are answered when sent to a multicast address.
+        let (ipv6_repr, bytes) = build(Ipv6Address::LINK_LOCAL_ALL_NODES, 0x9e);
+        assert_eq!(
+            process(&bytes),
+            Some((src_addr, reply(ipv6_repr, &bytes, src_addr)))
+        );
+        let (_, bytes) = build(Ipv6Address::LINK_LOCAL_ALL_NODES, 0xde);
+        assert_eq!(process(&bytes), None);
+    }
+
     #[test]
     #[cfg(feature = "proto-igmp")]
     fn test_handle_igmp() {
@@ -3788,6 +7433,102 @@ mod test {
         }
     }
 
+    #[test]
+    #[cfg(all(feature = "proto-igmp", feature = "medium-ethernet"))]
+    fn test_igmp_router_alert() {
+        let group_addr = Ipv4Address::new(224, 0, 0, 56);
+        let mut iface = create_loopback_ethernet();
+
+        let timestamp = Instant::from_millis(0);
+        iface.join_multicast_group(group_addr, timestamp).unwrap();
+        iface.leave_multicast_group(group_addr, timestamp).unwrap();
+
+        let frames = recv_all(&mut iface, timestamp);
+        assert_eq!(frames.len(), 2);
+        for frame in &frames {
+            let eth_frame = EthernetFrame::new_checked(&frame[..]).unwrap();
+            let ipv4_packet = Ipv4Packet::new_checked(eth_frame.payload()).unwrap();
+            assert_eq!(ipv4_packet.header_len(), 24);
+            assert_eq!(ipv4_packet.options(), &IPV4_ROUTER_ALERT_OPTION[..]);
+            assert_eq!(ipv4_packet.router_alert(), Some(0));
+            assert!(ipv4_packet.verify_checksum());
+
+            let igmp_packet = IgmpPacket::new_checked(ipv4_packet.payload()).unwrap();
+            assert!(IgmpRepr::parse(&igmp_packet).is_ok());
+        }
+    }
+
+    #[test]
+    #[cfg(all(
+        feature = "medium-ethernet",
+        feature = "proto-ipv4",
+        feature = "socket-raw"
+    ))]
+    fn test_raw_socket_headers() {
+        let mut iface = create_loopback_ethernet();
+
+        let rx_buffer = raw::PacketBuffer::new(vec![raw::PacketMetadata::EMPTY; 1], vec![0; 64]);
+        let tx_buffer = raw::PacketBuffer::new(vec![], vec![]);
+        let raw_socket = raw::Socket::new(
+            IpVersion::Ipv4,
+            IpProtocol::Unknown(0x3f),
+            rx_buffer,
+            tx_buffer,
+        );
+        let handle = iface.add_socket(raw_socket);
+
+        let remote_hw_addr = EthernetAddress([0x52, 0x54, 0x00, 0x00, 0x00, 0x00]);
+        let ipv4_repr = Ipv4Repr {
+            src_addr: Ipv4Address([127, 0, 0, 2]),
+            dst_addr: Ipv4Address([127, 0, 0, 1]),
+            next_header: IpProtocol::Unknown(0x3f),
+            payload_len: 4,
+            hop_limit: 17,
+        };
+        let mut bytes = vec![0; ETHERNET_HEADER_LEN + ipv4_repr.buffer_len() + 4];
+        let mut frame = EthernetFrame::new_unchecked(&mut bytes);
+        frame.set_dst_addr(EthernetAddress::default());
+        frame.set_src_addr(remote_hw_addr);
+        frame.set_ethertype(EthernetProtocol::Ipv4);
+        ipv4_repr.emit(
+            &mut Ipv4Packet::new_unchecked(frame.payload_mut()),
+            &ChecksumCapabilities::default(),
+        );
+
+        assert_eq!(
+            iface.inner.process_ethernet(&mut iface.sockets, &bytes),
+            None
+        );
+
+        let socket = iface.get_socket::<raw::Socket>(handle);
+        assert_eq!(
+            socket.recv_with_headers(),
+            Ok((
+                &bytes[ETHERNET_HEADER_LEN..],
+                raw::Headers {
+                    ethernet: Some(EthernetRepr {
+                        src_addr: remote_hw_addr,
+                        dst_addr: EthernetAddress::default(),
+                        ethertype: EthernetProtocol::Ipv4,
+                    }),
+                    src_addr: IpAddress::v4(127, 0, 0, 2),
+                    dst_addr: IpAddress::v4(127, 0, 0, 1),
+                    next_header: IpProtocol::Unknown(0x3f),
+                    hop_limit: 17,
+                }
+            ))
+        );
+
+        // The header isn't kept past a frame that is ignored.
+        EthernetFrame::new_unchecked(&mut bytes)
+            .set_dst_addr(EthernetAddress([0x52, 0x54, 0x00, 0x00, 0x00, 0x01]));
+        assert_eq!(
+            iface.inner.process_ethernet(&mut iface.sockets, &bytes),
+            None
+        );
+        assert_eq!(iface.inner.rx_ethernet_repr(), None);
+    }
+
     #[test]
     #[cfg(all(feature = "proto-ipv4", feature = "socket-raw"))]
     fn test_raw_socket_no_reply() {
@@ -3942,4 +7683,1107 @@ mod test {
             Ok((&UDP_PAYLOAD[..], IpEndpoint::new(src_addr.into(), 67)))
         );
     }
+
+    #[test]
+    #[cfg(all(feature = "proto-ipv4", feature = "socket-raw"))]
+    fn test_raw_socket_fragment_passthrough() {
+        use crate::wire::{IpVersion, Ipv4Packet};
+
+        let mut iface = create_loopback();
+
+        let rx_buffer = raw::PacketBuffer::new(vec![raw::PacketMetadata::EMPTY; 2], vec![0; 96]);
+        let tx_buffer = raw::PacketBuffer::new(vec![raw::PacketMetadata::EMPTY; 2], vec![0; 96]);
+        let raw_socket = raw::Socket::new(IpVersion::Ipv4, IpProtocol::Udp, rx_buffer, tx_buffer);
+        let handle = iface.add_socket(raw_socket);
+
+        let ipv4_repr = Ipv4Repr {
+            src_addr: Ipv4Address([127, 0, 0, 1]),
+            dst_addr: Ipv4Address([127, 0, 0, 1]),
+            next_header: IpProtocol::Udp,
+            hop_limit: 64,
+            payload_len: 16,
+        };
+        let mut bytes = vec![0x2a; ipv4_repr.buffer_len() + 16];
+        {
+            let mut packet = Ipv4Packet::new_unchecked(&mut bytes[..]);
+            ipv4_repr.emit(&mut packet, &ChecksumCapabilities::default());
+            packet.set_ident(0x1234);
+            packet.set_dont_frag(false);
+            packet.set_more_frags(true);
+            packet.fill_checksum();
+        }
+
+        // Fragments are dropped, unless the raw socket asks for them.
+        let frame = Ipv4Packet::new_unchecked(&bytes[..]);
+        assert_eq!(iface.inner.process_ipv4(&mut iface.sockets, &frame), None);
+        assert!(!iface.get_socket::<raw::Socket>(handle).can_recv());
+
+        let socket = iface.get_socket::<raw::Socket>(handle);
+        socket.set_pass_fragments(true);
+        socket.send_slice(&bytes[..]).unwrap();
+
+        // The fragment goes out and comes back through the loopback device untouched.
+        for _ in 0..4 {
+            let _ = iface.poll(Instant::from_millis(0));
+        }
+        let socket = iface.get_socket::<raw::Socket>(handle);
+        assert_eq!(socket.recv(), Ok(&bytes[..]));
+        assert!(!socket.can_recv());
+    }
+
+    #[test]
+    #[cfg(all(feature = "proto-ipv4", feature = "socket-tcp"))]
+    fn test_tcp_reap_time_wait() {
+        let mut iface = create_loopback();
+
+        let server_handle = iface.add_socket(tcp_socket());
+        let client_handle = iface.add_socket(tcp_socket());
+        iface
+            .get_socket::<tcp::Socket>(server_handle)
+            .listen(1234)
+            .unwrap();
+        let (client, cx) = iface.get_socket_and_context::<tcp::Socket>(client_handle);
+        client
+            .connect(cx, (IpAddress::v4(127, 0, 0, 1), 1234), 65000)
+            .unwrap();
+
+        let poll = |iface: &mut Interface<'_, Loopback>| {
+            for _ in 0..4 {
+                let _ = iface.poll(Instant::from_millis(0));
+            }
+        };
+        poll(&mut iface);
+        assert_eq!(iface.time_wait_count(), 0);
+
+        // The end closing first stays in TIME-WAIT.
+        iface.get_socket::<tcp::Socket>(client_handle).close();
+        poll(&mut iface);
+        iface.get_socket::<tcp::Socket>(server_handle).close();
+        poll(&mut iface);
+        assert_eq!(
+            iface.get_socket::<tcp::Socket>(client_handle).state(),
+            tcp::State::TimeWait
+        );
+        assert_eq!(
+            iface.get_socket::<tcp::Socket>(server_handle).state(),
+            tcp::State::Closed
+        );
+        assert_eq!(iface.time_wait_count(), 1);
+
+        assert_eq!(iface.reap_time_wait(), 1);
+        assert_eq!(iface.time_wait_count(), 0);
+        assert_eq!(
+            iface.get_socket::<tcp::Socket>(client_handle).state(),
+            tcp::State::Closed
+        );
+        assert_eq!(iface.reap_time_wait(), 0);
+    }
+
+    #[test]
+    #[cfg(all(feature = "proto-ipv4", feature = "socket-tcp-ao"))]
+    fn test_tcp_authentication() {
+        use crate::socket::tcp::{AuthAlgorithm, AuthKey};
+
+        let key =
+            |master_key: &[u8]| AuthKey::new(1, 1, AuthAlgorithm::Aes128Cmac, master_key).unwrap();
+        let connect = |server_key: AuthKey, client_key: AuthKey| {
+            let mut iface = create_loopback();
+            let auth_socket = |key: AuthKey| {
+                let mut socket = tcp_socket();
+                socket.set_auth_keys(vec![key]);
+                socket
+            };
+            let server_handle = iface.add_socket(auth_socket(server_key));
+            let client_handle = iface.add_socket(auth_socket(client_key));
+            iface
+                .get_socket::<tcp::Socket>(server_handle)
+                .listen(1234)
+                .unwrap();
+            let (client, cx) = iface.get_socket_and_context::<tcp::Socket>(client_handle);
+            client
+                .connect(cx, (IpAddress::v4(127, 0, 0, 1), 1234), 65000)
+                .unwrap();
+            let poll = |iface: &mut Interface<'_, Loopback>| {
+                for _ in 0..4 {
+                    let _ = iface.poll(Instant::from_millis(0));
+                }
+            };
+            poll(&mut iface);
+            let client = iface.get_socket::<tcp::Socket>(client_handle);
+            if client.may_send() {
+                client.send_slice(b"hello").unwrap();
+                poll(&mut iface);
+            }
+            let mut data = [0; 5];
+            let server = iface.get_socket::<tcp::Socket>(server_handle);
+            let len = server.recv_slice(&mut data).unwrap_or(0);
+            (server.state(), data[..len].to_vec())
+        };
+
+        assert_eq!(
+            connect(key(b"secret"), key(b"secret")),
+            (tcp::State::Established, b"hello".to_vec())
+        );
+        // The SYN is dropped, and no connection is established.
+        assert_eq!(
+            connect(key(b"secret"), key(b"guess")),
+            (tcp::State::Listen, vec![])
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "proto-ipv4", feature = "socket-tcp", feature = "socket-udp"))]
+    fn test_local_endpoint_in_use() {
+        let mut iface = create_loopback();
+
+        let tcp_rx_buffer = tcp::SocketBuffer::new(vec![0; 64]);
+        let tcp_tx_buffer = tcp::SocketBuffer::new(vec![0; 64]);
+        let tcp_handle = iface.add_socket(tcp::Socket::new(tcp_rx_buffer, tcp_tx_buffer));
+        let udp_rx_buffer = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY], vec![0; 15]);
+        let udp_tx_buffer = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY], vec![0; 15]);
+        let udp_handle = iface.add_socket(udp::Socket::new(udp_rx_buffer, udp_tx_buffer));
+
+        let addr = IpAddress::v4(127, 0, 0, 1);
+        let other_addr = IpAddress::v4(127, 0, 0, 2);
+        assert!(!iface.is_local_endpoint_in_use(IpProtocol::Tcp, 80));
+
+        iface
+            .get_socket::<tcp::Socket>(tcp_handle)
+            .listen_range(Some(addr), 80..=89)
+            .unwrap();
+        iface
+            .get_socket::<udp::Socket>(udp_handle)
+            .bind(53)
+            .unwrap();
+
+        assert!(iface.is_local_endpoint_in_use(IpProtocol::Tcp, 80));
+        assert!(iface.is_local_endpoint_in_use(IpProtocol::Tcp, (addr, 89)));
+        assert!(!iface.is_local_endpoint_in_use(IpProtocol::Tcp, (other_addr, 85)));
+        assert!(!iface.is_local_endpoint_in_use(IpProtocol::Tcp, 90));
+        assert!(!iface.is_local_endpoint_in_use(IpProtocol::Tcp, 53));
+        assert!(iface.is_local_endpoint_in_use(IpProtocol::Udp, (other_addr, 53)));
+        assert!(!iface.is_local_endpoint_in_use(IpProtocol::Udp, 80));
+
+        iface.get_socket::<tcp::Socket>(tcp_handle).abort();
+        iface.get_socket::<udp::Socket>(udp_handle).close();
+        assert!(!iface.is_local_endpoint_in_use(IpProtocol::Tcp, 80));
+        assert!(!iface.is_local_endpoint_in_use(IpProtocol::Udp, 53));
+    }
+
+    #[test]
+    #[cfg(all(feature = "proto-ipv4", feature = "socket-udp"))]
+    fn test_egress_limit() {
+        let mut iface = create_loopback();
+        iface.inner.egress_limit = Some(2);
+
+        let sender_handle = iface.add_socket(udp_socket(3));
+        let receiver_handle = iface.add_socket(udp_socket(3));
+        iface
+            .get_socket::<udp::Socket>(sender_handle)
+            .bind(1000)
+            .unwrap();
+        iface
+            .get_socket::<udp::Socket>(receiver_handle)
+            .bind(2000)
+            .unwrap();
+
+        let endpoint = IpEndpoint::new(IpAddress::v4(127, 0, 0, 1), 2000);
+        let sender = iface.get_socket::<udp::Socket>(sender_handle);
+        for data in [b"one", b"two", b"six"] {
+            sender.send_slice(data, endpoint).unwrap();
+        }
+
+        let received = |iface: &mut Interface<'_, Loopback>| {
+            let mut count = 0;
+            let receiver = iface.get_socket::<udp::Socket>(receiver_handle);
+            while receiver.recv().is_ok() {
+                count += 1;
+            }
+            count
+        };
+
+        let timestamp = Instant::from_millis(0);
+        assert_eq!(iface.poll(timestamp), Ok(true));
+        assert_eq!(iface.poll_at(timestamp), Some(Instant::from_millis(0)));
+        assert_eq!(received(&mut iface), 2);
+
+        assert_eq!(iface.poll(timestamp), Ok(true));
+        assert_eq!(iface.poll_at(timestamp), None);
+        assert_eq!(received(&mut iface), 1);
+    }
+
+    #[test]
+    #[cfg(all(feature = "proto-ipv4", feature = "socket-udp"))]
+    fn test_egress_round_robin() {
+        let mut iface = create_loopback();
+        iface.inner.egress_limit = Some(1);
+
+        let bulk_handle = iface.add_socket(udp_socket(4));
+        let interactive_handle = iface.add_socket(udp_socket(1));
+        let receiver_handle = iface.add_socket(udp_socket(8));
+        iface
+            .get_socket::<udp::Socket>(bulk_handle)
+            .bind(1000)
+            .unwrap();
+        iface
+            .get_socket::<udp::Socket>(interactive_handle)
+            .bind(1001)
+            .unwrap();
+        iface
+            .get_socket::<udp::Socket>(receiver_handle)
+            .bind(2000)
+            .unwrap();
+
+        let endpoint = IpEndpoint::new(IpAddress::v4(127, 0, 0, 1), 2000);
+        let bulk = iface.get_socket::<udp::Socket>(bulk_handle);
+        for _ in 0..4 {
+            bulk.send_slice(b"bulk", endpoint).unwrap();
+        }
+        iface
+            .get_socket::<udp::Socket>(interactive_handle)
+            .send_slice(b"key", endpoint)
+            .unwrap();
+
+        let mut senders = vec![];
+        for _ in 0..5 {
+            let _ = iface.poll(Instant::from_millis(0));
+            let receiver = iface.get_socket::<udp::Socket>(receiver_handle);
+            while let Ok((_, endpoint)) = receiver.recv() {
+                senders.push(endpoint.port);
+            }
+        }
+        assert_eq!(senders, vec![1000, 1001, 1000, 1000, 1000]);
+    }
+
+    #[test]
+    #[cfg(all(feature = "proto-ipv4", feature = "socket-tcp"))]
+    fn test_tcp_range_listener_precedence() {
+        use crate::wire::{TcpPacket, TcpSeqNumber};
+
+        let mut iface = create_loopback();
+
+        let range_handle = iface.add_socket(tcp_socket());
+        let single_handle = iface.add_socket(tcp_socket());
+        iface
+            .get_socket::<tcp::Socket>(range_handle)
+            .listen_range(None, 8000..=8009)
+            .unwrap();
+        iface
+            .get_socket::<tcp::Socket>(single_handle)
+            .listen(8005)
+            .unwrap();
+
+        let src_addr = IpAddress::v4(127, 0, 0, 2);
+        let dst_addr = IpAddress::v4(127, 0, 0, 1);
+        let syn = |iface: &mut Interface<'_, Loopback>, dst_port| {
+            let tcp_repr = TcpRepr {
+                src_port: 49500,
+                dst_port,
+                control: TcpControl::Syn,
+                seq_number: TcpSeqNumber(1000),
+                ack_number: None,
+                window_len: 256,
+                window_scale: None,
+                max_seg_size: None,
+                sack_permitted: false,
+                sack_ranges: [None, None, None],
+                auth: None,
+                payload: &[],
+            };
+            let mut bytes = vec![0; tcp_repr.buffer_len()];
+            tcp_repr.emit(
+                &mut TcpPacket::new_unchecked(&mut bytes[..]),
+                &src_addr,
+                &dst_addr,
+                &ChecksumCapabilities::default(),
+            );
+            let ip_repr = IpRepr::Ipv4(Ipv4Repr {
+                src_addr: Ipv4Address([127, 0, 0, 2]),
+                dst_addr: Ipv4Address([127, 0, 0, 1]),
+                next_header: IpProtocol::Tcp,
+                payload_len: bytes.len(),
+                hop_limit: 64,
+            });
+            // Accepted SYNs are answered on egress, unhandled ones with an immediate RST.
+            assert!(iface
+                .inner
+                .process_tcp(&mut iface.sockets, ip_repr, &bytes)
+                .is_none());
+        };
+
+        // The single port listener wins even though the range listener was added first.
+        syn(&mut iface, 8005);
+        let socket = iface.get_socket::<tcp::Socket>(single_handle);
+        assert_eq!(socket.state(), tcp::State::SynReceived);
+        let socket = iface.get_socket::<tcp::Socket>(range_handle);
+        assert_eq!(socket.state(), tcp::State::Listen);
+
+        syn(&mut iface, 8003);
+        let socket = iface.get_socket::<tcp::Socket>(range_handle);
+        assert_eq!(socket.state(), tcp::State::SynReceived);
+        assert_eq!(
+            socket.local_endpoint(),
+            Some(IpEndpoint::new(dst_addr, 8003))
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "proto-ipv4", feature = "socket-tcp"))]
+    fn test_tcp_half_open_limit() {
+        use crate::wire::TcpSeqNumber;
+
+        let mut iface = create_loopback();
+        iface.inner.half_open_limit = Some(1);
+
+        let listener = || {
+            let mut socket = tcp_socket();
+            socket.listen(80).unwrap();
+            socket
+        };
+        let first_handle = iface.add_socket(listener());
+        let second_handle = iface.add_socket(listener());
+
+        // Neither accepted nor dropped SYNs are answered immediately.
+        let syn = |iface: &mut Interface<'_, Loopback>, src_port| {
+            assert_eq!(
+                tcp_segment_ipv4(iface, src_port, TcpControl::Syn, TcpSeqNumber(1000), None),
+                None
+            );
+        };
+
+        syn(&mut iface, 49500);
+        let socket = iface.get_socket::<tcp::Socket>(first_handle);
+        assert_eq!(socket.state(), tcp::State::SynReceived);
+
+        // The second listener is left alone while the first connection is half-open.
+        syn(&mut iface, 49501);
+        let socket = iface.get_socket::<tcp::Socket>(second_handle);
+        assert_eq!(socket.state(), tcp::State::Listen);
+
+        // A retransmitted SYN of the half-open connection is still processed.
+        syn(&mut iface, 49500);
+        let socket = iface.get_socket::<tcp::Socket>(first_handle);
+        assert_eq!(socket.state(), tcp::State::SynReceived);
+
+        iface.inner.half_open_limit = Some(2);
+        syn(&mut iface, 49501);
+        let socket = iface.get_socket::<tcp::Socket>(second_handle);
+        assert_eq!(socket.state(), tcp::State::SynReceived);
+    }
+
+    #[test]
+    #[cfg(all(feature = "socket-tcp", feature = "proto-ipv4"))]
+    fn test_tcp_syn_cookies() {
+        use crate::wire::TcpSeqNumber;
+
+        let mut iface = create_loopback();
+        iface.inner.half_open_limit = Some(1);
+
+        let listener = || {
+            let mut socket = tcp_socket();
+            socket.listen(80).unwrap();
+            socket.set_syn_cookies(true);
+            socket
+        };
+        let first_handle = iface.add_socket(listener());
+        let second_handle = iface.add_socket(listener());
+
+        // The first SYN is accepted normally.
+        let syn = tcp_segment_ipv4(&mut iface, 49500, TcpControl::Syn, TcpSeqNumber(1000), None);
+        assert_eq!(syn, None);
+        let socket = iface.get_socket::<tcp::Socket>(first_handle);
+        assert_eq!(socket.state(), tcp::State::SynReceived);
+
+        // Once the limit is reached, SYNs are answered with a cookie, and no state is kept.
+        let isn = TcpSeqNumber(2000);
+        let syn = tcp_segment_ipv4(&mut iface, 49501, TcpControl::Syn, isn, None);
+        let (control, cookie) = syn.unwrap();
+        assert_eq!(control, TcpControl::Syn);
+        let socket = iface.get_socket::<tcp::Socket>(second_handle);
+        assert_eq!(socket.state(), tcp::State::Listen);
+
+        // An ACK with a wrong cookie is reset, and a valid one establishes the connection.
+        let ack = tcp_segment_ipv4(
+            &mut iface,
+            49501,
+            TcpControl::None,
+            isn + 1,
+            Some(cookie + 2),
+        );
+        assert_eq!(ack, Some((TcpControl::Rst, cookie + 2)));
+        let ack = tcp_segment_ipv4(
+            &mut iface,
+            49501,
+            TcpControl::None,
+            isn + 1,
+            Some(cookie + 1),
+        );
+        assert_eq!(ack, None);
+        let socket = iface.get_socket::<tcp::Socket>(second_handle);
+        assert_eq!(socket.state(), tcp::State::Established);
+        assert_eq!(
+            socket.remote_endpoint(),
+            Some(IpEndpoint::new(IpAddress::v4(127, 0, 0, 2), 49501))
+        );
+        let socket = iface.get_socket::<tcp::Socket>(first_handle);
+        assert_eq!(socket.state(), tcp::State::SynReceived);
+    }
+
+    #[test]
+    #[cfg(all(feature = "proto-ipv4", feature = "socket-tcp", feature = "socket-udp"))]
+    fn test_transparent() {
+        use crate::wire::{Ipv4Packet, TcpSeqNumber};
+
+        let mut iface = create_loopback();
+
+        let tcp_handle = iface.add_socket(tcp_socket());
+        let tcp_proxy_handle = iface.add_socket(tcp_socket());
+        let udp_handle = iface.add_socket(udp_socket(1));
+        let udp_proxy_handle = iface.add_socket(udp_socket(1));
+        iface
+            .get_socket::<tcp::Socket>(tcp_handle)
+            .listen(80)
+            .unwrap();
+        iface
+            .get_socket::<udp::Socket>(udp_handle)
+            .bind(53)
+            .unwrap();
+
+        let src_addr = Ipv4Address::new(127, 0, 0, 2);
+        let dst_addr = Ipv4Address::new(10, 0, 0, 1);
+        let packet = |next_header, payload: &[u8]| {
+            let ipv4_repr = Ipv4Repr {
+                src_addr,
+                dst_addr,
+                next_header,
+                payload_len: payload.len(),
+                hop_limit: 64,
+            };
+            let mut bytes = vec![0; ipv4_repr.buffer_len() + payload.len()];
+            let mut packet = Ipv4Packet::new_unchecked(&mut bytes);
+            ipv4_repr.emit(&mut packet, &ChecksumCapabilities::default());
+            packet.payload_mut().copy_from_slice(payload);
+            bytes
+        };
+
+        let tcp_repr = TcpRepr {
+            src_port: 49500,
+            dst_port: 80,
+            control: TcpControl::Syn,
+            seq_number: TcpSeqNumber(1000),
+            ack_number: None,
+            window_len: 256,
+            window_scale: None,
+            max_seg_size: None,
+            sack_permitted: false,
+            sack_ranges: [None, None, None],
+            auth: None,
+            payload: &[],
+        };
+        let mut tcp_bytes = vec![0; tcp_repr.buffer_len()];
+        tcp_repr.emit(
+            &mut TcpPacket::new_unchecked(&mut tcp_bytes[..]),
+            &src_addr.into(),
+            &dst_addr.into(),
+            &ChecksumCapabilities::default(),
+        );
+        let syn = packet(IpProtocol::Tcp, &tcp_bytes);
+
+        let udp_repr = UdpRepr {
+            src_port: 49500,
+            dst_port: 53,
+        };
+        let mut udp_bytes = vec![0; udp_repr.header_len() + 4];
+        udp_repr.emit(
+            &mut UdpPacket::new_unchecked(&mut udp_bytes[..]),
+            &src_addr.into(),
+            &dst_addr.into(),
+            4,
+            |buf| buf.copy_from_slice(b"ping"),
+            &ChecksumCapabilities::default(),
+        );
+        let datagram = packet(IpProtocol::Udp, &udp_bytes);
+
+        // Without transparent sockets, packets to other hosts are dropped without a reply.
+        for bytes in [&syn, &datagram] {
+            let frame = Ipv4Packet::new_checked(&bytes[..]).unwrap();
+            assert_eq!(iface.inner.process_ipv4(&mut iface.sockets, &frame), None);
+        }
+        assert_eq!(
+            iface.get_socket::<tcp::Socket>(tcp_handle).state(),
+            tcp::State::Listen
+        );
+        assert!(!iface.get_socket::<udp::Socket>(udp_handle).can_recv());
+
+        let tcp_proxy = iface.get_socket::<tcp::Socket>(tcp_proxy_handle);
+        tcp_proxy.set_transparent(true);
+        tcp_proxy.listen_range(None, 1..=65535).unwrap();
+        let udp_proxy = iface.get_socket::<udp::Socket>(udp_proxy_handle);
+        udp_proxy.set_transparent(true);
+        udp_proxy.bind(53).unwrap();
+
+        for bytes in [&syn, &datagram] {
+            let frame = Ipv4Packet::new_checked(&bytes[..]).unwrap();
+            assert_eq!(iface.inner.process_ipv4(&mut iface.sockets, &frame), None);
+        }
+        assert_eq!(
+            iface.get_socket::<tcp::Socket>(tcp_handle).state(),
+            tcp::State::Listen
+        );
+        assert!(!iface.get_socket::<udp::Socket>(udp_handle).can_recv());
+
+        // The original destinations are recorded, and replies are sent from them.
+        let (tcp_proxy, cx) = iface.get_socket_and_context::<tcp::Socket>(tcp_proxy_handle);
+        assert_eq!(tcp_proxy.state(), tcp::State::SynReceived);
+        assert_eq!(
+            tcp_proxy.local_endpoint(),
+            Some(IpEndpoint::new(dst_addr.into(), 80))
+        );
+        let mut sent = false;
+        tcp_proxy
+            .dispatch(cx, |_, (ip_repr, tcp_repr)| {
+                assert_eq!(ip_repr.src_addr(), IpAddress::Ipv4(dst_addr));
+                assert_eq!(tcp_repr.control, TcpControl::Syn);
+                sent = true;
+                Ok::<_, ()>(())
+            })
+            .unwrap();
+        assert!(sent);
+
+        let udp_proxy = iface.get_socket::<udp::Socket>(udp_proxy_handle);
+        let (payload, meta) = udp_proxy.recv_with_metadata().unwrap();
+        assert_eq!(payload, b"ping");
+        assert_eq!(meta.endpoint, IpEndpoint::new(src_addr.into(), 49500));
+        assert_eq!(meta.local_address, Some(dst_addr.into()));
+    }
+
+    #[test]
+    #[cfg(all(feature = "proto-ipv6", feature = "socket-udp"))]
+    fn test_transparent_ipv6() {
+        let mut iface = create_loopback();
+
+        let udp_handle = iface.add_socket(udp_socket(1));
+        let udp_proxy_handle = iface.add_socket(udp_socket(1));
+        iface
+            .get_socket::<udp::Socket>(udp_handle)
+            .bind(53)
+            .unwrap();
+
+        let src_addr = Ipv6Address::new(0xfdbe, 0, 0, 0, 0, 0, 0, 2);
+        let dst_addr = Ipv6Address::new(0xfdbe, 0, 0, 0, 0, 0, 0, 3);
+        let udp_repr = UdpRepr {
+            src_port: 49500,
+            dst_port: 53,
+        };
+        let ip_repr = IpRepr::Ipv6(Ipv6Repr {
+            src_addr,
+            dst_addr,
+            next_header: IpProtocol::Udp,
+            payload_len: udp_repr.header_len() + 4,
+            hop_limit: 64,
+        });
+        let mut bytes = vec![0; ip_repr.total_len()];
+        ip_repr.emit(&mut bytes[..], &ChecksumCapabilities::default());
+        udp_repr.emit(
+            &mut UdpPacket::new_unchecked(&mut bytes[IPV6_HEADER_LEN..]),
+            &src_addr.into(),
+            &dst_addr.into(),
+            4,
+            |buf| buf.copy_from_slice(b"ping"),
+            &ChecksumCapabilities::default(),
+        );
+
+        // Without transparent sockets, datagrams to other hosts are dropped.
+        let frame = Ipv6Packet::new_checked(&bytes[..]).unwrap();
+        assert_eq!(iface.inner.process_ipv6(&mut iface.sockets, &frame), None);
+        assert!(!iface.get_socket::<udp::Socket>(udp_handle).can_recv());
+
+        let udp_proxy = iface.get_socket::<udp::Socket>(udp_proxy_handle);
+        udp_proxy.set_transparent(true);
+        udp_proxy.bind(53).unwrap();
+
+        let frame = Ipv6Packet::new_checked(&bytes[..]).unwrap();
+        assert_eq!(iface.inner.process_ipv6(&mut iface.sockets, &frame), None);
+        assert!(!iface.get_socket::<udp::Socket>(udp_handle).can_recv());
+
+        let udp_proxy = iface.get_socket::<udp::Socket>(udp_proxy_handle);
+        let (payload, meta) = udp_proxy.recv_with_metadata().unwrap();
+        assert_eq!(payload, b"ping");
+        assert_eq!(meta.endpoint, IpEndpoint::new(src_addr.into(), 49500));
+        assert_eq!(meta.local_address, Some(dst_addr.into()));
+    }
+
+    #[test]
+    #[cfg(all(feature = "proto-ipv4", feature = "proto-ipv6", feature = "socket-udp"))]
+    fn test_udp_zero_checksum_emit() {
+        let caps = DeviceCapabilities::default();
+        let udp_repr = UdpRepr {
+            src_port: 67,
+            dst_port: 68,
+        };
+        let payload = [0x2a; 8];
+
+        let ipv4_repr = IpRepr::Ipv4(Ipv4Repr {
+            src_addr: Ipv4Address([127, 0, 0, 1]),
+            dst_addr: Ipv4Address([127, 0, 0, 2]),
+            next_header: IpProtocol::Udp,
+            payload_len: udp_repr.header_len() + payload.len(),
+            hop_limit: 64,
+        });
+        let ipv6_repr = IpRepr::Ipv6(Ipv6Repr {
+            src_addr: Ipv6Address::LOOPBACK,
+            dst_addr: Ipv6Address::LOOPBACK,
+            next_header: IpProtocol::Udp,
+            payload_len: udp_repr.header_len() + payload.len(),
+            hop_limit: 64,
+        });
+
+        let udp_checksum = |ip_repr: &IpRepr, checksum| {
+            let packet = IpPacket::Udp((ip_repr.clone(), udp_repr, &payload[..], checksum));
+            let mut bytes = vec![0; ip_repr.total_len()];
+            packet.emit(ip_repr.clone(), &mut bytes, &caps);
+            UdpPacket::new_unchecked(&bytes[ip_repr.buffer_len()..]).checksum()
+        };
+
+        assert_ne!(udp_checksum(&ipv4_repr, true), 0);
+        assert_eq!(udp_checksum(&ipv4_repr, false), 0);
+        // A zero checksum is never sent over IPv6.
+        assert_ne!(udp_checksum(&ipv6_repr, true), 0);
+        assert_eq!(
+            udp_checksum(&ipv6_repr, false),
+            udp_checksum(&ipv6_repr, true)
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "proto-ipv4", feature = "socket-udp"))]
+    fn test_udp_zero_checksum_accept() {
+        use crate::wire::IpEndpoint;
+
+        let mut iface = create_loopback();
+
+        let rx_buffer = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY], vec![0; 15]);
+        let tx_buffer = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY], vec![0; 15]);
+        let udp_socket = udp::Socket::new(rx_buffer, tx_buffer);
+        let handle = iface.add_socket(udp_socket);
+        iface.get_socket::<udp::Socket>(handle).bind(68).unwrap();
+
+        let src_addr = Ipv4Address([127, 0, 0, 2]);
+        let dst_addr = Ipv4Address([127, 0, 0, 1]);
+        let udp_repr = UdpRepr {
+            src_port: 67,
+            dst_port: 68,
+        };
+        let ipv4_repr = Ipv4Repr {
+            src_addr,
+            dst_addr,
+            next_header: IpProtocol::Udp,
+            payload_len: udp_repr.header_len() + 5,
+            hop_limit: 64,
+        };
+        let mut bytes = vec![0; ipv4_repr.buffer_len() + ipv4_repr.payload_len];
+        ipv4_repr.emit(
+            &mut Ipv4Packet::new_unchecked(&mut bytes[..]),
+            &ChecksumCapabilities::default(),
+        );
+        {
+            let mut udp_packet = UdpPacket::new_unchecked(&mut bytes[ipv4_repr.buffer_len()..]);
+            udp_repr.emit(
+                &mut udp_packet,
+                &src_addr.into(),
+                &dst_addr.into(),
+                5,
+                |buf| buf.copy_from_slice(b"hello"),
+                &ChecksumCapabilities::default(),
+            );
+            udp_packet.set_checksum(0);
+        }
+
+        let frame = Ipv4Packet::new_unchecked(&bytes[..]);
+        assert_eq!(iface.inner.process_ipv4(&mut iface.sockets, &frame), None);
+        assert_eq!(
+            iface.get_socket::<udp::Socket>(handle).recv(),
+            Ok((&b"hello"[..], IpEndpoint::new(src_addr.into(), 67)))
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "medium-ip", feature = "socket-udp"))]
+    fn test_try_add_socket() {
+        // Growable storage, bounded by the builder.
+        let mut iface = InterfaceBuilder::new(Loopback::new(Medium::Ip), vec![])
+            .max_sockets(2)
+            .finalize();
+        assert_eq!(iface.socket_capacity(), Some(2));
+        let handle = iface.try_add_socket(udp_socket(1)).unwrap();
+        assert!(iface.try_add_socket(udp_socket(1)).is_ok());
+        let mut socket = udp_socket(1);
+        socket.bind(68).unwrap();
+        // The socket is handed back, and can be added once there is room for it.
+        let socket = iface.try_add_socket(socket).unwrap_err().into_inner();
+        assert_eq!(socket.endpoint().port, 68);
+        iface.remove_socket(handle);
+        let handle = iface.try_add_socket(socket).unwrap();
+        assert_eq!(iface.get_socket::<udp::Socket>(handle).endpoint().port, 68);
+
+        // Fixed-size storage.
+        let mut storage = [SocketStorage::EMPTY; 1];
+        let mut iface =
+            InterfaceBuilder::new(Loopback::new(Medium::Ip), &mut storage[..]).finalize();
+        assert_eq!(iface.socket_capacity(), Some(1));
+        assert!(iface.try_add_socket(udp_socket(1)).is_ok());
+        assert!(iface.try_add_socket(udp_socket(1)).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "proto-ipv4")]
+    fn test_is_on_link() {
+        use crate::iface::Route;
+        use crate::wire::Ipv4Cidr;
+
+        let mut iface = create_loopback();
+        let on_link = IpAddress::v4(127, 0, 0, 2);
+        let remote = IpAddress::v4(192, 168, 1, 1);
+
+        *iface.routes_mut() = Routes::new(BTreeMap::new());
+        assert!(iface.inner.is_on_link(&on_link));
+        assert!(iface.inner.is_on_link(&IpAddress::v4(255, 255, 255, 255)));
+        assert!(iface.inner.is_on_link(&IpAddress::v4(224, 0, 0, 1)));
+        assert!(!iface.inner.is_on_link(&remote));
+
+        // A destination reached through a router is not on-link.
+        iface
+            .routes_mut()
+            .add_default_ipv4_route(Ipv4Address::new(127, 0, 0, 254))
+            .unwrap();
+        assert!(iface.inner.is_on_link(&on_link));
+        assert!(!iface.inner.is_on_link(&remote));
+
+        // Policy routes can send a directly-connected destination through a router.
+        *iface.policy_routes_mut() = Routes::new(BTreeMap::new());
+        iface.policy_routes_mut().update(|storage| {
+            storage
+                .insert(
+                    Ipv4Cidr::new(Ipv4Address::new(127, 0, 0, 2), 32).into(),
+                    Route::new_ipv4_gateway(Ipv4Address::new(127, 0, 0, 253)),
+                )
+                .unwrap();
+        });
+        assert!(!iface.inner.is_on_link(&on_link));
+        assert!(iface.inner.is_on_link(&IpAddress::v4(127, 0, 0, 3)));
+    }
+
+    #[test]
+    #[cfg(feature = "proto-ipv4")]
+    fn test_next_hop() {
+        use crate::iface::Route;
+        use crate::wire::Ipv4Cidr;
+
+        let mut iface = create_loopback();
+        let on_link = IpAddress::v4(127, 0, 0, 2);
+        let remote = IpAddress::v4(192, 168, 1, 1);
+        let router = Ipv4Address::new(127, 0, 0, 254);
+        let policy_router = Ipv4Address::new(127, 0, 0, 253);
+
+        *iface.routes_mut() = Routes::new(BTreeMap::new());
+        *iface.policy_routes_mut() = Routes::new(BTreeMap::new());
+        assert_eq!(iface.next_hop(on_link), Some((on_link, true)));
+        assert_eq!(iface.next_hop(remote), None);
+        assert_eq!(iface.next_hop(IpAddress::v4(0, 0, 0, 0)), None);
+
+        iface.routes_mut().add_default_ipv4_route(router).unwrap();
+        assert_eq!(iface.next_hop(remote), Some((router.into(), false)));
+        assert_eq!(iface.next_hop(on_link), Some((on_link, true)));
+
+        // Policy routes override both the main table and on-link subnets.
+        iface.policy_routes_mut().update(|storage| {
+            storage
+                .insert(
+                    Ipv4Cidr::new(Ipv4Address::new(192, 168, 1, 0), 24).into(),
+                    Route::new_ipv4_gateway(policy_router),
+                )
+                .unwrap();
+            storage
+                .insert(
+                    Ipv4Cidr::new(Ipv4Address::new(127, 0, 0, 2), 32).into(),
+                    Route::new_ipv4_gateway(policy_router),
+                )
+                .unwrap();
+        });
+        assert_eq!(iface.next_hop(remote), Some((policy_router.into(), false)));
+        assert_eq!(iface.next_hop(on_link), Some((policy_router.into(), false)));
+        assert_eq!(
+            iface.next_hop(IpAddress::v4(192, 168, 2, 1)),
+            Some((router.into(), false))
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+    fn test_vlan_qinq() {
+        let mut iface = create_loopback_ethernet();
+        let vlan_tags = EthernetVlanTags::Double {
+            outer: 100,
+            inner: 42,
+        };
+        iface.set_vlan_tags(vlan_tags);
+        assert_eq!(
+            iface.inner.ip_mtu(),
+            iface.device.capabilities().ip_mtu() - 8
+        );
+
+        let local_ip_addr = Ipv4Address([0x7f, 0x00, 0x00, 0x01]);
+        let remote_ip_addr = Ipv4Address([0x7f, 0x00, 0x00, 0x02]);
+        let remote_hw_addr = EthernetAddress([0x52, 0x54, 0x00, 0x00, 0x00, 0x00]);
+
+        let mut eth_bytes = vec![0u8; 42];
+        let mut frame = EthernetFrame::new_unchecked(&mut eth_bytes);
+        frame.set_dst_addr(EthernetAddress::BROADCAST);
+        frame.set_src_addr(remote_hw_addr);
+        frame.set_ethertype(EthernetProtocol::Arp);
+        let mut packet = ArpPacket::new_unchecked(frame.payload_mut());
+        ArpRepr::EthernetIpv4 {
+            operation: ArpOperation::Request,
+            source_hardware_addr: remote_hw_addr,
+            source_protocol_addr: remote_ip_addr,
+            target_hardware_addr: EthernetAddress::default(),
+            target_protocol_addr: local_ip_addr,
+        }
+        .emit(&mut packet);
+        let tagged = |tags: &[u8]| [&eth_bytes[..12], tags, &eth_bytes[12..]].concat();
+
+        // Untagged frames and frames on other VLANs are ignored.
+        let single = tagged(&[0x81, 0x00, 0x00, 0x64]);
+        let other_vlan = tagged(&[0x88, 0xa8, 0x00, 0x64, 0x81, 0x00, 0x00, 0x2b]);
+        for frame in [&eth_bytes, &single, &other_vlan] {
+            assert_eq!(
+                iface.inner.process_ethernet(&mut iface.sockets, frame),
+                None
+            );
+        }
+
+        let qinq = tagged(&[0x88, 0xa8, 0x00, 0x64, 0x81, 0x00, 0x00, 0x2a]);
+        let reply = iface
+            .inner
+            .process_ethernet(&mut iface.sockets, &qinq)
+            .unwrap();
+        let reply_repr = match reply {
+            EthernetPacket::Arp(repr) => repr,
+            _ => panic!("expected an ARP reply"),
+        };
+
+        // The reply is sent with both tags.
+        let tx_token = iface.device.transmit().unwrap();
+        iface.inner.dispatch(tx_token, reply).unwrap();
+        let (rx_token, _) = iface.device.receive().unwrap();
+        rx_token
+            .consume(Instant::ZERO, |buffer| {
+                let frame = EthernetFrame::new_checked(&*buffer).unwrap();
+                assert_eq!(frame.dst_addr(), remote_hw_addr);
+                assert_eq!(frame.vlan_tags(), Ok(vlan_tags));
+                assert_eq!(frame.tagged_ethertype(), Ok(EthernetProtocol::Arp));
+                let packet = ArpPacket::new_checked(frame.tagged_payload().unwrap()).unwrap();
+                assert_eq!(ArpRepr::parse(&packet), Ok(reply_repr));
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    #[cfg(all(
+        feature = "medium-ip",
+        feature = "socket-udp",
+        feature = "socket-user-data"
+    ))]
+    fn test_socket_user_data() {
+        let mut iface = InterfaceBuilder::new(Loopback::new(Medium::Ip), vec![]).finalize();
+        let first = iface.add_socket(udp_socket(1));
+        let second = iface.add_socket(udp_socket(1));
+        assert_eq!(iface.socket_user_data(first), 0);
+
+        iface.set_socket_user_data(first, 7);
+        iface.set_socket_user_data(second, 9);
+        let (socket, user_data) = iface.get_socket_and_user_data::<udp::Socket>(first);
+        assert!(!socket.is_open());
+        assert_eq!(user_data, 7);
+        let all: Vec<_> = iface
+            .sockets_with_user_data()
+            .map(|(handle, _, user_data)| (handle, user_data))
+            .collect();
+        assert_eq!(all, vec![(first, 7), (second, 9)]);
+
+        // User data does not outlive its socket.
+        iface.remove_socket(first);
+        let third = iface.add_socket(udp_socket(1));
+        assert_eq!(third, first);
+        assert_eq!(iface.socket_user_data(third), 0);
+    }
+
+    #[test]
+    #[cfg(all(feature = "proto-ipv4", feature = "socket-udp"))]
+    fn test_device_tx_queue_len() {
+        let mut iface = create_loopback();
+        assert_eq!(iface.device_tx_queue_len(), Some(0));
+
+        let rx_buffer = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY], vec![0; 15]);
+        let tx_buffer = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY], vec![0; 15]);
+        let handle = iface.add_socket(udp::Socket::new(rx_buffer, tx_buffer));
+        let socket = iface.get_socket::<udp::Socket>(handle);
+        socket.bind(6969).unwrap();
+        socket
+            .send_slice(b"hello", (IpAddress::v4(127, 0, 0, 2), 6969).into())
+            .unwrap();
+
+        // Whatever was emitted (the datagram, or a neighbor request for its destination)
+        // stays queued in the device until it is received back.
+        iface.socket_egress();
+        assert!(iface.device_tx_queue_len().unwrap() > 0);
+        while let Some((rx_token, _)) = iface.device.receive() {
+            rx_token.consume(Instant::ZERO, |_| Ok(())).unwrap();
+        }
+        assert_eq!(iface.device_tx_queue_len(), Some(0));
+    }
+
+    #[test]
+    #[cfg(all(feature = "proto-ipv4", feature = "socket-tcp", feature = "socket-udp"))]
+    fn test_remove_ip_addr() {
+        let mut iface = create_loopback();
+        let local_addr = IpAddress::v4(127, 0, 0, 1);
+
+        let rx_buffer = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY], vec![0; 15]);
+        let tx_buffer = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY], vec![0; 15]);
+        let udp_handle = iface.add_socket(udp::Socket::new(rx_buffer, tx_buffer));
+        iface
+            .get_socket::<udp::Socket>(udp_handle)
+            .bind((local_addr, 6969))
+            .unwrap();
+
+        let rx_buffer = tcp::SocketBuffer::new(vec![0; 64]);
+        let tx_buffer = tcp::SocketBuffer::new(vec![0; 64]);
+        let tcp_handle = iface.add_socket(tcp::Socket::new(rx_buffer, tx_buffer));
+        let (socket, cx) = iface.get_socket_and_context::<tcp::Socket>(tcp_handle);
+        socket
+            .connect(cx, (IpAddress::v4(127, 0, 0, 2), 80), (local_addr, 49500))
+            .unwrap();
+
+        assert!(!iface.remove_ip_addr(IpAddress::v4(127, 0, 0, 3)));
+        assert!(iface.remove_ip_addr(local_addr));
+        assert!(!iface.has_ip_addr(local_addr));
+
+        let socket = iface.get_socket::<tcp::Socket>(tcp_handle);
+        assert_eq!(socket.state(), tcp::State::Closed);
+        assert_eq!(
+            socket.close_reason(),
+            Some(tcp::CloseReason::LocalAddressRemoved)
+        );
+        let socket = iface.get_socket::<udp::Socket>(udp_handle);
+        assert_eq!(
+            socket.send_slice(b"hello", (IpAddress::v4(127, 0, 0, 2), 6969).into()),
+            Err(udp::SendError::Unaddressable)
+        );
+
+        // Nothing is sent from the removed address.
+        iface.socket_egress();
+        assert_eq!(iface.device_tx_queue_len(), Some(0));
+    }
+
+    #[test]
+    #[cfg(feature = "proto-ipv4")]
+    fn test_remove_ip_addr_route() {
+        let mut ip_addrs = [
+            IpCidr::new(IpAddress::v4(127, 0, 0, 1), 8),
+            IpCidr::new(IpAddress::v4(192, 168, 1, 1), 24),
+        ];
+        let mut iface = create_loopback();
+        iface.update_ip_addrs(|addrs| *addrs = ManagedSlice::Borrowed(&mut ip_addrs[..]));
+        *iface.routes_mut() = Routes::new(BTreeMap::new());
+        iface
+            .routes_mut()
+            .add_default_ipv4_route(Ipv4Address::new(192, 168, 1, 254))
+            .unwrap();
+
+        assert!(iface.remove_ip_addr(IpAddress::v4(127, 0, 0, 1)));
+        assert_eq!(
+            iface.ip_addrs(),
+            &[IpCidr::new(IpAddress::v4(192, 168, 1, 1), 24)]
+        );
+        assert_eq!(iface.ipv4_address(), Some(Ipv4Address::new(192, 168, 1, 1)));
+
+        // Off-link destinations still go through the gateway.
+        let remote_addr = IpAddress::v4(10, 0, 0, 1);
+        assert!(!iface.inner.is_on_link(&remote_addr));
+        assert_eq!(
+            iface.inner.route(&remote_addr, Instant::from_secs(0)),
+            Ok(IpAddress::v4(192, 168, 1, 254))
+        );
+        assert_eq!(
+            iface
+                .inner
+                .get_source_address_ipv4(Ipv4Address::new(10, 0, 0, 1)),
+            Some(Ipv4Address::new(192, 168, 1, 1))
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "proto-ipv6")]
+    fn test_ipv6_source_address_scope() {
+        let mut iface = create_loopback();
+        let link_local = Ipv6Address::new(0xfe80, 0, 0, 0, 0, 0, 0, 1);
+        let unique_local = Ipv6Address::new(0xfd00, 0x1234, 0, 1, 0, 0, 0, 1);
+        let global = Ipv6Address::new(0x2001, 0xdb8, 0, 1, 0, 0, 0, 1);
+        iface.update_ip_addrs(|addrs| {
+            *addrs = vec![
+                IpCidr::new(unique_local.into(), 64),
+                IpCidr::new(link_local.into(), 64),
+                IpCidr::new(global.into(), 64),
+            ]
+            .into();
+        });
+
+        let cases = [
+            // Link-local destinations use the link-local address.
+            (Ipv6Address::new(0xfe80, 0, 0, 0, 0, 0, 0, 2), link_local),
+            (Ipv6Address::LINK_LOCAL_ALL_NODES, link_local),
+            // Unique local destinations use the unique local address, even in other networks.
+            (
+                Ipv6Address::new(0xfd00, 0x1234, 0, 1, 0, 0, 0, 2),
+                unique_local,
+            ),
+            (Ipv6Address::new(0xfd99, 0, 0, 0, 0, 0, 0, 1), unique_local),
+            // Global destinations use the global address, even in other networks.
+            (Ipv6Address::new(0x2001, 0xdb8, 0, 1, 0, 0, 0, 2), global),
+            (Ipv6Address::new(0x2a00, 0x1450, 0, 0, 0, 0, 0, 1), global),
+            (Ipv6Address::new(0xff0e, 0, 0, 0, 0, 0, 0, 1), global),
+            // The address itself is always preferred.
+            (unique_local, unique_local),
+        ];
+        for (dst_addr, src_addr) in cases.iter() {
+            assert_eq!(
+                iface.inner.get_source_address_ipv6(*dst_addr),
+                Some(*src_addr),
+                "source address for {}",
+                dst_addr
+            );
+            assert_eq!(
+                iface.inner.get_source_address((*dst_addr).into()),
+                Some((*src_addr).into())
+            );
+        }
+
+        // Without a global address, a unique local address is still a better fit for a global
+        // destination than a link-local one.
+        iface.update_ip_addrs(|addrs| {
+            *addrs = vec![
+                IpCidr::new(link_local.into(), 64),
+                IpCidr::new(unique_local.into(), 64),
+            ]
+            .into();
+        });
+        assert_eq!(
+            iface
+                .inner
+                .get_source_address_ipv6(Ipv6Address::new(0x2a00, 0x1450, 0, 0, 0, 0, 0, 1)),
+            Some(unique_local)
+        );
+    }
 }
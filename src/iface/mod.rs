@@ -4,11 +4,16 @@ The `iface` module deals with the *network interfaces*. It filters incoming fram
 provides lookup and caching of hardware addresses, and handles management packets.
 */
 
-#[cfg(feature = "proto-sixlowpan")]
+#[cfg(any(
+    feature = "proto-sixlowpan",
+    feature = "proto-ipv4-fragmentation",
+    feature = "proto-ipv6-fragmentation"
+))]
 mod fragmentation;
 mod interface;
 #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
 mod neighbor;
+mod rate_limit;
 mod route;
 mod socket_meta;
 mod socket_set;
@@ -20,9 +25,13 @@ pub use self::neighbor::Cache as NeighborCache;
 #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
 pub use self::neighbor::Neighbor;
 pub use self::route::{Route, Routes};
-pub use socket_set::{SocketHandle, SocketStorage};
+pub use socket_set::{SocketHandle, SocketSetFull, SocketStorage};
 
-#[cfg(feature = "proto-sixlowpan")]
+#[cfg(any(
+    feature = "proto-sixlowpan",
+    feature = "proto-ipv4-fragmentation",
+    feature = "proto-ipv6-fragmentation"
+))]
 pub use self::fragmentation::{PacketAssembler, PacketAssemblerSet as FragmentsCache};
 
-pub use self::interface::{Interface, InterfaceBuilder, InterfaceInner as Context};
+pub use self::interface::{DropReason, Interface, InterfaceBuilder, InterfaceInner as Context};
@@ -0,0 +1,106 @@
+use core::cmp;
+
+use crate::time::{Duration, Instant};
+
+/// A token bucket rate limiter.
+///
+/// The bucket holds up to `burst` tokens and is refilled at a rate of `burst` tokens
+/// per `interval`. Every permitted event consumes one token.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub(crate) struct RateLimiter {
+    burst: u32,
+    interval: Duration,
+    tokens: u32,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    /// Create a rate limiter permitting `burst` events per `interval`, starting full.
+    pub(crate) const fn new(burst: u32, interval: Duration) -> RateLimiter {
+        RateLimiter {
+            burst,
+            interval,
+            tokens: burst,
+            last_refill: Instant::ZERO,
+        }
+    }
+
+    /// Consume a token if one is available, and return whether the event is permitted.
+    pub(crate) fn try_acquire(&mut self, timestamp: Instant) -> bool {
+        self.refill(timestamp);
+        if self.tokens > 0 {
+            self.tokens -= 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn refill(&mut self, timestamp: Instant) {
+        if self.tokens == self.burst || timestamp <= self.last_refill {
+            self.last_refill = cmp::max(self.last_refill, timestamp);
+            return;
+        }
+
+        let token_micros = (self.interval / self.burst).total_micros();
+        if token_micros == 0 {
+            self.tokens = self.burst;
+            self.last_refill = timestamp;
+            return;
+        }
+
+        let elapsed_micros = (timestamp - self.last_refill).total_micros();
+        let new_tokens = elapsed_micros / token_micros;
+        if new_tokens >= u64::from(self.burst - self.tokens) {
+            self.tokens = self.burst;
+            self.last_refill = timestamp;
+        } else {
+            // Keep the remainder, so that frequent polling doesn't slow down the refill.
+            self.tokens += new_tokens as u32;
+            self.last_refill += Duration::from_micros(new_tokens * token_micros);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_burst() {
+        let mut limiter = RateLimiter::new(3, Duration::from_secs(1));
+        let now = Instant::from_secs(10);
+        assert!(limiter.try_acquire(now));
+        assert!(limiter.try_acquire(now));
+        assert!(limiter.try_acquire(now));
+        assert!(!limiter.try_acquire(now));
+    }
+
+    #[test]
+    fn test_refill() {
+        let mut limiter = RateLimiter::new(4, Duration::from_secs(1));
+        let now = Instant::from_secs(10);
+        for _ in 0..4 {
+            assert!(limiter.try_acquire(now));
+        }
+        assert!(!limiter.try_acquire(now + Duration::from_millis(200)));
+        // One token every 250ms, accumulated across polls.
+        assert!(limiter.try_acquire(now + Duration::from_millis(250)));
+        assert!(!limiter.try_acquire(now + Duration::from_millis(400)));
+        assert!(limiter.try_acquire(now + Duration::from_millis(500)));
+        // Never more than the burst size.
+        let later = now + Duration::from_secs(60);
+        for _ in 0..4 {
+            assert!(limiter.try_acquire(later));
+        }
+        assert!(!limiter.try_acquire(later));
+    }
+
+    #[test]
+    fn test_zero_burst() {
+        let mut limiter = RateLimiter::new(0, Duration::from_secs(1));
+        assert!(!limiter.try_acquire(Instant::from_secs(0)));
+        assert!(!limiter.try_acquire(Instant::from_secs(100)));
+    }
+}
@@ -1,4 +1,4 @@
-use crate::time::Instant;
+use crate::time::{Duration, Instant};
 use core::ops::Bound;
 use managed::ManagedMap;
 
@@ -126,6 +126,46 @@ impl<'a> Routes<'a> {
         self.storage.remove(&cidr)
     }
 
+    /// How long a host route installed in response to an ICMP redirect stays valid.
+    pub(crate) const REDIRECT_LIFETIME: Duration = Duration::from_secs(600);
+
+    /// Add a host route to `addr` via `router`, as requested by an ICMP redirect.
+    ///
+    /// The route expires after [REDIRECT_LIFETIME](#associatedconstant.REDIRECT_LIFETIME).
+    /// Routes that have already expired are removed first, to make room for it.
+    pub(crate) fn add_redirect(
+        &mut self,
+        addr: IpAddress,
+        router: IpAddress,
+        timestamp: Instant,
+    ) -> Result<()> {
+        let cidr = match addr {
+            #[cfg(feature = "proto-ipv4")]
+            IpAddress::Ipv4(addr) => IpCidr::Ipv4(Ipv4Cidr::new(addr, 32)),
+            #[cfg(feature = "proto-ipv6")]
+            IpAddress::Ipv6(addr) => IpCidr::Ipv6(Ipv6Cidr::new(addr, 128)),
+        };
+        let route = Route {
+            via_router: router,
+            preferred_until: None,
+            expires_at: Some(timestamp + Self::REDIRECT_LIFETIME),
+        };
+        while let Some(expired) = self
+            .storage
+            .iter()
+            .find(
+                |(_, route)| matches!(route.expires_at, Some(expires_at) if timestamp > expires_at),
+            )
+            .map(|(cidr, _)| *cidr)
+        {
+            self.storage.remove(&expired);
+        }
+        match self.storage.insert(cidr, route) {
+            Ok(_) => Ok(()),
+            Err((_cidr, _route)) => Err(Error::Exhausted),
+        }
+    }
+
     pub(crate) fn lookup(&self, addr: &IpAddress, timestamp: Instant) -> Option<IpAddress> {
         assert!(addr.is_unicast());
 
@@ -207,6 +247,31 @@ mod test {
 
     use self::mock::*;
 
+    #[test]
+    fn test_redirect_evicts_expired() {
+        let mut routes_storage = [None];
+        let mut routes = Routes::new(&mut routes_storage[..]);
+        let timestamp = Instant::from_millis(0);
+
+        routes
+            .add_redirect(ADDR_1B.into(), ADDR_1A.into(), timestamp)
+            .unwrap();
+        assert_eq!(
+            routes.add_redirect(ADDR_1C.into(), ADDR_1A.into(), timestamp),
+            Err(Error::Exhausted)
+        );
+
+        let timestamp = timestamp + Routes::REDIRECT_LIFETIME + Duration::from_millis(1);
+        routes
+            .add_redirect(ADDR_1C.into(), ADDR_1A.into(), timestamp)
+            .unwrap();
+        assert_eq!(routes.storage.iter().count(), 1);
+        assert_eq!(
+            routes.lookup(&ADDR_1C.into(), timestamp),
+            Some(ADDR_1A.into())
+        );
+    }
+
     #[test]
     fn test_fill() {
         let mut routes_storage = [None, None, None];
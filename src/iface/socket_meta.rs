@@ -38,6 +38,9 @@ pub(crate) struct Meta {
     pub(crate) handle: SocketHandle,
     /// See [NeighborState](struct.NeighborState.html).
     neighbor_state: NeighborState,
+    /// An arbitrary value the application associates with this socket, zero by default.
+    #[cfg(feature = "socket-user-data")]
+    pub(crate) user_data: usize,
 }
 
 impl Meta {
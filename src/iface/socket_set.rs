@@ -24,11 +24,39 @@ pub(crate) struct Item<'a> {
     pub(crate) socket: Socket<'a>,
 }
 
+/// Error returned by [`Interface::try_add_socket`] when the interface has no room for
+/// another socket. It holds the socket that wasn't added.
+///
+/// [`Interface::try_add_socket`]: struct.Interface.html#method.try_add_socket
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SocketSetFull<T>(pub T);
+
+impl<T> SocketSetFull<T> {
+    /// Return the socket that wasn't added.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> fmt::Display for SocketSetFull<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "no room for another socket")
+    }
+}
+
 /// A handle, identifying a socket in an Interface.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Hash)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct SocketHandle(usize);
 
+impl SocketHandle {
+    /// Return the index of the storage slot this handle refers to.
+    pub(crate) fn index(self) -> usize {
+        self.0
+    }
+}
+
 impl fmt::Display for SocketHandle {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "#{}", self.0)
@@ -41,6 +69,7 @@ impl fmt::Display for SocketHandle {
 #[derive(Debug)]
 pub(crate) struct SocketSet<'a> {
     sockets: ManagedSlice<'a, SocketStorage<'a>>,
+    limit: Option<usize>,
 }
 
 impl<'a> SocketSet<'a> {
@@ -50,14 +79,56 @@ impl<'a> SocketSet<'a> {
         SocketsT: Into<ManagedSlice<'a, SocketStorage<'a>>>,
     {
         let sockets = sockets.into();
-        SocketSet { sockets }
+        SocketSet {
+            sockets,
+            limit: None,
+        }
+    }
+
+    /// Limit the number of sockets the set may hold at once.
+    pub fn set_limit(&mut self, limit: Option<usize>) {
+        self.limit = limit
+    }
+
+    /// Return the maximum number of sockets the set may hold at once, if bounded.
+    ///
+    /// A set backed by fixed-size storage is bounded by that storage.
+    pub fn capacity(&self) -> Option<usize> {
+        let storage_len = match self.sockets {
+            ManagedSlice::Borrowed(ref sockets) => Some(sockets.len()),
+            #[cfg(any(feature = "std", feature = "alloc"))]
+            ManagedSlice::Owned(_) => None,
+        };
+        match (storage_len, self.limit) {
+            (Some(len), Some(limit)) => Some(len.min(limit)),
+            (len, limit) => len.or(limit),
+        }
+    }
+
+    /// Return the number of sockets in the set.
+    pub fn len(&self) -> usize {
+        self.iter().count()
     }
 
     /// Add a socket to the set, and return its handle.
     ///
     /// # Panics
-    /// This function panics if the storage is fixed-size (not a `Vec`) and is full.
+    /// This function panics if the set is full; see [try_add](#method.try_add).
     pub fn add<T: AnySocket<'a>>(&mut self, socket: T) -> SocketHandle {
+        match self.try_add(socket) {
+            Ok(handle) => handle,
+            Err(_) => panic!("adding a socket to a full SocketSet"),
+        }
+    }
+
+    /// Add a socket to the set, and return its handle.
+    ///
+    /// This function returns the socket back as `Err(SocketSetFull(socket))` if the set
+    /// already holds as many sockets as its [capacity](#method.capacity).
+    pub fn try_add<T: AnySocket<'a>>(
+        &mut self,
+        socket: T,
+    ) -> Result<SocketHandle, SocketSetFull<T>> {
         fn put<'a>(index: usize, slot: &mut SocketStorage<'a>, socket: Socket<'a>) -> SocketHandle {
             net_trace!("[{}]: adding", index);
             let handle = SocketHandle(index);
@@ -69,23 +140,28 @@ impl<'a> SocketSet<'a> {
             handle
         }
 
-        let socket = socket.upcast();
-
-        for (index, slot) in self.sockets.iter_mut().enumerate() {
-            if slot.inner.is_none() {
-                return put(index, slot, socket);
+        if let Some(limit) = self.limit {
+            if self.len() >= limit {
+                net_debug!("socket limit of {} reached, not adding socket", limit);
+                return Err(SocketSetFull(socket));
             }
         }
 
-        match self.sockets {
-            ManagedSlice::Borrowed(_) => panic!("adding a socket to a full SocketSet"),
-            #[cfg(any(feature = "std", feature = "alloc"))]
-            ManagedSlice::Owned(ref mut sockets) => {
-                sockets.push(SocketStorage { inner: None });
-                let index = sockets.len() - 1;
-                put(index, &mut sockets[index], socket)
-            }
-        }
+        let index = match self.sockets.iter().position(|slot| slot.inner.is_none()) {
+            Some(index) => index,
+            None => match self.sockets {
+                ManagedSlice::Borrowed(_) => {
+                    net_debug!("socket storage is full, not adding socket");
+                    return Err(SocketSetFull(socket));
+                }
+                #[cfg(any(feature = "std", feature = "alloc"))]
+                ManagedSlice::Owned(ref mut sockets) => {
+                    sockets.push(SocketStorage { inner: None });
+                    sockets.len() - 1
+                }
+            },
+        };
+        Ok(put(index, &mut self.sockets[index], socket.upcast()))
     }
 
     /// Get a socket from the set by its handle, as mutable.
@@ -102,6 +178,30 @@ impl<'a> SocketSet<'a> {
         }
     }
 
+    /// Get the user data attached to a socket.
+    ///
+    /// # Panics
+    /// This function may panic if the handle does not belong to this socket set.
+    #[cfg(feature = "socket-user-data")]
+    pub fn user_data(&self, handle: SocketHandle) -> usize {
+        match self.sockets[handle.0].inner.as_ref() {
+            Some(item) => item.meta.user_data,
+            None => panic!("handle does not refer to a valid socket"),
+        }
+    }
+
+    /// Attach user data to a socket, replacing the previous value.
+    ///
+    /// # Panics
+    /// This function may panic if the handle does not belong to this socket set.
+    #[cfg(feature = "socket-user-data")]
+    pub fn set_user_data(&mut self, handle: SocketHandle, user_data: usize) {
+        match self.sockets[handle.0].inner.as_mut() {
+            Some(item) => item.meta.user_data = user_data,
+            None => panic!("handle does not refer to a valid socket"),
+        }
+    }
+
     /// Remove a socket from the set, without changing its state.
     ///
     /// # Panics
@@ -123,4 +223,14 @@ impl<'a> SocketSet<'a> {
     pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Item<'a>> + '_ {
         self.sockets.iter_mut().filter_map(|x| x.inner.as_mut())
     }
+
+    /// Iterate every socket in this set, starting at the storage slot `start` and wrapping
+    /// around to the beginning.
+    pub fn iter_mut_from(&mut self, start: usize) -> impl Iterator<Item = &mut Item<'a>> + '_ {
+        let start = if start < self.sockets.len() { start } else { 0 };
+        let (head, tail) = self.sockets.split_at_mut(start);
+        tail.iter_mut()
+            .chain(head.iter_mut())
+            .filter_map(|x| x.inner.as_mut())
+    }
 }
@@ -134,6 +134,8 @@ use core::fmt;
 
 #[macro_use]
 mod macros;
+#[cfg(feature = "socket-tcp")]
+mod crypto;
 mod parsers;
 mod rand;
 
@@ -59,6 +59,10 @@ where
         caps
     }
 
+    fn tx_queue_len(&self) -> Option<usize> {
+        self.inner.tx_queue_len()
+    }
+
     fn receive(&'a mut self) -> Option<(Self::RxToken, Self::TxToken)> {
         let &mut Self {
             ref mut inner,
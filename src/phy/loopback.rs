@@ -41,6 +41,11 @@ impl<'a> Device<'a> for Loopback {
         }
     }
 
+    /// Packets are queued until they are received back.
+    fn tx_queue_len(&self) -> Option<usize> {
+        Some(self.queue.iter().map(|packet| packet.len()).sum())
+    }
+
     fn receive(&'a mut self) -> Option<(Self::RxToken, Self::TxToken)> {
         self.queue.pop_front().map(move |buffer| {
             let rx = RxToken { buffer };
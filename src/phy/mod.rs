@@ -325,6 +325,15 @@ pub trait Device<'a> {
 
     /// Get a description of device capabilities.
     fn capabilities(&self) -> DeviceCapabilities;
+
+    /// Get the number of bytes that were handed to the device for transmission, but not yet
+    /// sent on the wire.
+    ///
+    /// This is the depth of the device's own transmit queue, not including any data still
+    /// buffered in sockets. Devices that cannot tell return `None`, which is the default.
+    fn tx_queue_len(&self) -> Option<usize> {
+        None
+    }
 }
 
 /// A token to receive a single network packet.
@@ -174,6 +174,10 @@ where
         self.lower.capabilities()
     }
 
+    fn tx_queue_len(&self) -> Option<usize> {
+        self.lower.tx_queue_len()
+    }
+
     fn receive(&'a mut self) -> Option<(Self::RxToken, Self::TxToken)> {
         let sink = &self.sink;
         let mode = self.mode;
@@ -55,6 +55,10 @@ where
         self.inner.capabilities()
     }
 
+    fn tx_queue_len(&self) -> Option<usize> {
+        self.inner.tx_queue_len()
+    }
+
     fn receive(&'a mut self) -> Option<(Self::RxToken, Self::TxToken)> {
         let &mut Self {
             ref mut inner,
@@ -1,3 +1,4 @@
+use core::fmt;
 #[cfg(feature = "async")]
 use core::task::Waker;
 
@@ -7,7 +8,8 @@ use crate::wire::dhcpv4::field as dhcpv4_field;
 use crate::wire::HardwareAddress;
 use crate::wire::{
     DhcpMessageType, DhcpPacket, DhcpRepr, IpAddress, IpProtocol, Ipv4Address, Ipv4Cidr, Ipv4Repr,
-    UdpRepr, DHCP_CLIENT_PORT, DHCP_MAX_DNS_SERVER_COUNT, DHCP_SERVER_PORT, UDP_HEADER_LEN,
+    UdpRepr, DHCP_CLIENT_PORT, DHCP_MAX_DNS_SERVER_COUNT, DHCP_MAX_NTP_SERVER_COUNT,
+    DHCP_SERVER_PORT, UDP_HEADER_LEN,
 };
 
 #[cfg(feature = "async")]
@@ -30,8 +32,62 @@ const PARAMETER_REQUEST_LIST: &[u8] = &[
     dhcpv4_field::OPT_SUBNET_MASK,
     dhcpv4_field::OPT_ROUTER,
     dhcpv4_field::OPT_DOMAIN_NAME_SERVER,
+    dhcpv4_field::OPT_DOMAIN_NAME,
+    dhcpv4_field::OPT_NTP_SERVERS,
 ];
 
+/// Maximum length of the domain name provided by the DHCP server.
+///
+/// Longer domain names are ignored.
+pub const MAX_DOMAIN_NAME_LEN: usize = 64;
+
+/// A domain name provided by the DHCP server.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct DomainName {
+    buffer: [u8; MAX_DOMAIN_NAME_LEN],
+    len: u8,
+}
+
+impl DomainName {
+    /// Create a domain name, or return `None` if it is longer than [`MAX_DOMAIN_NAME_LEN`].
+    pub fn new(name: &str) -> Option<DomainName> {
+        if name.len() > MAX_DOMAIN_NAME_LEN {
+            return None;
+        }
+        let mut buffer = [0; MAX_DOMAIN_NAME_LEN];
+        buffer[..name.len()].copy_from_slice(name.as_bytes());
+        Some(DomainName {
+            buffer,
+            len: name.len() as u8,
+        })
+    }
+
+    /// Return the domain name as a string slice.
+    pub fn as_str(&self) -> &str {
+        // NOTE(unwrap): the buffer was filled from a string slice.
+        core::str::from_utf8(&self.buffer[..self.len as usize]).unwrap()
+    }
+}
+
+impl fmt::Debug for DomainName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
+
+impl fmt::Display for DomainName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for DomainName {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{=str}", self.as_str())
+    }
+}
+
 /// IPv4 configuration data provided by the DHCP server.
 #[derive(Debug, Eq, PartialEq, Clone, Copy)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -43,6 +99,10 @@ pub struct Config {
     pub router: Option<Ipv4Address>,
     /// DNS servers
     pub dns_servers: [Option<Ipv4Address>; DHCP_MAX_DNS_SERVER_COUNT],
+    /// NTP servers
+    pub ntp_servers: [Option<Ipv4Address>; DHCP_MAX_NTP_SERVER_COUNT],
+    /// Domain name, used as a search suffix when resolving hostnames.
+    pub domain_name: Option<DomainName>,
 }
 
 /// Information on how to reach a DHCP server.
@@ -76,6 +136,17 @@ struct RequestState {
     requested_ip: Ipv4Address,
 }
 
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+struct RebootState {
+    /// When to send next request
+    retry_at: Instant,
+    /// How many retries have been done
+    retry: u16,
+    /// Previously leased IP address that we're trying to reclaim.
+    requested_ip: Ipv4Address,
+}
+
 #[derive(Debug)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 struct RenewState {
@@ -100,6 +171,8 @@ enum ClientState {
     Discovering(DiscoverState),
     /// Requesting an address
     Requesting(RequestState),
+    /// Reclaiming a previously leased address (INIT-REBOOT)
+    Rebooting(RebootState),
     /// Having an address, refresh it periodically.
     Renewing(RenewState),
 }
@@ -196,6 +269,7 @@ impl Socket {
         let t = match &self.state {
             ClientState::Discovering(state) => state.retry_at,
             ClientState::Requesting(state) => state.retry_at,
+            ClientState::Rebooting(state) => state.retry_at,
             ClientState::Renewing(state) => state.renew_at.min(state.expires_at),
         };
         PollAt::Time(t)
@@ -291,6 +365,28 @@ impl Socket {
                     self.reset();
                 }
             }
+            (ClientState::Rebooting(_), DhcpMessageType::Ack) => {
+                if let Some((config, renew_at, expires_at)) =
+                    Self::parse_ack(cx.now(), &dhcp_repr, self.max_lease_duration)
+                {
+                    self.state = ClientState::Renewing(RenewState {
+                        server: ServerInfo {
+                            address: src_ip,
+                            identifier: server_identifier,
+                        },
+                        config,
+                        renew_at,
+                        expires_at,
+                    });
+                    self.config_changed();
+                }
+            }
+            (ClientState::Rebooting(_), DhcpMessageType::Nak) => {
+                // The address may not be reused, e.g. because we moved to another network.
+                // No address is in use yet, so this holds even if NAKs are otherwise ignored.
+                net_debug!("DHCP previous address refused, restarting discovery");
+                self.reset();
+            }
             (ClientState::Renewing(state), DhcpMessageType::Ack) => {
                 if let Some((config, renew_at, expires_at)) =
                     Self::parse_ack(cx.now(), &dhcp_repr, self.max_lease_duration)
@@ -317,6 +413,21 @@ impl Socket {
         }
     }
 
+    /// Cleanup a list of servers, keeping only unicasts.
+    fn unicast_addresses<const N: usize>(
+        received: Option<[Option<Ipv4Address>; N]>,
+    ) -> [Option<Ipv4Address>; N] {
+        // TP-Link TD-W8970 sends 0.0.0.0 as second DNS server if there's only one configured :(
+        let mut addresses = [None; N];
+        if let Some(received) = received {
+            let unicasts = received.iter().flatten().filter(|addr| addr.is_unicast());
+            for (address, addr) in addresses.iter_mut().zip(unicasts) {
+                *address = Some(*addr);
+            }
+        }
+        addresses
+    }
+
     fn parse_ack(
         now: Instant,
         dhcp_repr: &DhcpRepr,
@@ -351,23 +462,23 @@ impl Socket {
             lease_duration = lease_duration.min(max_lease_duration);
         }
 
-        // Cleanup the DNS servers list, keeping only unicasts/
-        // TP-Link TD-W8970 sends 0.0.0.0 as second DNS server if there's only one configured :(
-        let mut dns_servers = [None; DHCP_MAX_DNS_SERVER_COUNT];
-        if let Some(received) = dhcp_repr.dns_servers {
-            let mut i = 0;
-            for addr in received.iter().flatten() {
-                if addr.is_unicast() {
-                    // This can never be out-of-bounds since both arrays have length DHCP_MAX_DNS_SERVER_COUNT
-                    dns_servers[i] = Some(*addr);
-                    i += 1;
+        let domain_name = match dhcp_repr.domain_name {
+            Some("") | None => None,
+            Some(name) => {
+                let domain_name = DomainName::new(name);
+                if domain_name.is_none() {
+                    net_debug!("DHCP ignoring domain name because it is too long");
                 }
+                domain_name
             }
-        }
+        };
+
         let config = Config {
             address: Ipv4Cidr::new(dhcp_repr.your_ip, prefix_len),
             router: dhcp_repr.router,
-            dns_servers: dns_servers,
+            dns_servers: Self::unicast_addresses(dhcp_repr.dns_servers),
+            ntp_servers: Self::unicast_addresses(dhcp_repr.ntp_servers),
+            domain_name,
         };
 
         // RFC 2131 indicates clients should renew a lease halfway through its expiration.
@@ -425,6 +536,8 @@ impl Socket {
             max_size: Some((cx.ip_mtu() - MAX_IPV4_HEADER_LEN - UDP_HEADER_LEN) as u16),
             lease_duration: None,
             dns_servers: None,
+            ntp_servers: None,
+            domain_name: None,
         };
 
         let udp_repr = UdpRepr {
@@ -490,6 +603,36 @@ impl Socket {
                 self.transaction_id = next_transaction_id;
                 Ok(())
             }
+            ClientState::Rebooting(state) => {
+                if cx.now() < state.retry_at {
+                    return Ok(());
+                }
+
+                if state.retry >= REQUEST_RETRIES {
+                    net_debug!("DHCP reboot retries exceeded, restarting discovery");
+                    self.reset();
+                    return Ok(());
+                }
+
+                // Unlike in the REQUESTING state, there is no server identifier: any server
+                // that knows about the lease may answer (RFC 2131 § 4.3.2).
+                dhcp_repr.message_type = DhcpMessageType::Request;
+                dhcp_repr.requested_ip = Some(state.requested_ip);
+
+                net_debug!(
+                    "DHCP send reboot request to {}: {:?}",
+                    ipv4_repr.dst_addr,
+                    dhcp_repr
+                );
+                ipv4_repr.payload_len = udp_repr.header_len() + dhcp_repr.buffer_len();
+                emit(cx, (ipv4_repr, udp_repr, dhcp_repr))?;
+
+                state.retry_at = cx.now() + (REQUEST_TIMEOUT << (state.retry as u32 / 2));
+                state.retry += 1;
+
+                self.transaction_id = next_transaction_id;
+                Ok(())
+            }
             ClientState::Renewing(state) => {
                 if state.expires_at <= cx.now() {
                     net_debug!("DHCP lease expired");
@@ -539,6 +682,24 @@ impl Socket {
         });
     }
 
+    /// Reset state and try to reclaim a previously leased address.
+    ///
+    /// Instead of discovering servers, the socket enters the INIT-REBOOT state and asks
+    /// for `address` right away, e.g. using the address from the last
+    /// [`Event::Configured`] before a restart. If the server refuses, or nobody answers,
+    /// the socket falls back to discovery as after [`Self::reset()`].
+    pub fn reboot(&mut self, address: Ipv4Address) {
+        net_trace!("DHCP reboot with {}", address);
+        if let ClientState::Renewing(_) = &self.state {
+            self.config_changed();
+        }
+        self.state = ClientState::Rebooting(RebootState {
+            retry_at: Instant::from_millis(0),
+            retry: 0,
+            requested_ip: address,
+        });
+    }
+
     /// Query the socket for configuration changes.
     ///
     /// The socket has an internal "configuration changed" flag. If
@@ -693,6 +854,7 @@ mod test {
     const DNS_IP_3: Ipv4Address = Ipv4Address([1, 1, 1, 3]);
     const DNS_IPS: [Option<Ipv4Address>; DHCP_MAX_DNS_SERVER_COUNT] =
         [Some(DNS_IP_1), Some(DNS_IP_2), Some(DNS_IP_3)];
+    const NTP_IP: Ipv4Address = Ipv4Address([1, 1, 1, 4]);
     const MASK_24: Ipv4Address = Ipv4Address([255, 255, 255, 0]);
 
     const MY_MAC: EthernetAddress = EthernetAddress([0x02, 0x02, 0x02, 0x02, 0x02, 0x02]);
@@ -754,6 +916,8 @@ mod test {
         server_identifier: None,
         parameter_request_list: None,
         dns_servers: None,
+        ntp_servers: None,
+        domain_name: None,
         max_size: None,
         lease_duration: None,
     };
@@ -761,7 +925,7 @@ mod test {
     const DHCP_DISCOVER: DhcpRepr = DhcpRepr {
         message_type: DhcpMessageType::Discover,
         client_identifier: Some(MY_MAC),
-        parameter_request_list: Some(&[1, 3, 6]),
+        parameter_request_list: Some(&[1, 3, 6, 15, 42]),
         max_size: Some(1432),
         ..DHCP_DEFAULT
     };
@@ -787,7 +951,7 @@ mod test {
         max_size: Some(1432),
 
         requested_ip: Some(MY_IP),
-        parameter_request_list: Some(&[1, 3, 6]),
+        parameter_request_list: Some(&[1, 3, 6, 15, 42]),
         ..DHCP_DEFAULT
     };
 
@@ -812,6 +976,16 @@ mod test {
         ..DHCP_DEFAULT
     };
 
+    const DHCP_REBOOT: DhcpRepr = DhcpRepr {
+        message_type: DhcpMessageType::Request,
+        client_identifier: Some(MY_MAC),
+        max_size: Some(1432),
+
+        requested_ip: Some(MY_IP),
+        parameter_request_list: Some(&[1, 3, 6, 15, 42]),
+        ..DHCP_DEFAULT
+    };
+
     const DHCP_RENEW: DhcpRepr = DhcpRepr {
         message_type: DhcpMessageType::Request,
         client_identifier: Some(MY_MAC),
@@ -820,7 +994,7 @@ mod test {
         max_size: Some(1432),
 
         requested_ip: None,
-        parameter_request_list: Some(&[1, 3, 6]),
+        parameter_request_list: Some(&[1, 3, 6, 15, 42]),
         ..DHCP_DEFAULT
     };
 
@@ -842,6 +1016,8 @@ mod test {
             config: Config {
                 address: Ipv4Cidr::new(MY_IP, 24),
                 dns_servers: DNS_IPS,
+                ntp_servers: [None; DHCP_MAX_NTP_SERVER_COUNT],
+                domain_name: None,
                 router: Some(SERVER_IP),
             },
             server: ServerInfo {
@@ -872,6 +1048,8 @@ mod test {
             Some(Event::Configured(Config {
                 address: Ipv4Cidr::new(MY_IP, 24),
                 dns_servers: DNS_IPS,
+                ntp_servers: [None; DHCP_MAX_NTP_SERVER_COUNT],
+                domain_name: None,
                 router: Some(SERVER_IP),
             }))
         );
@@ -989,6 +1167,73 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_bind_ntp_domain_name() {
+        let mut s = socket();
+
+        recv!(s, [(IP_BROADCAST, UDP_SEND, DHCP_DISCOVER)]);
+        send!(s, (IP_RECV, UDP_RECV, DHCP_OFFER));
+        recv!(s, [(IP_BROADCAST, UDP_SEND, DHCP_REQUEST)]);
+        send!(
+            s,
+            (
+                IP_RECV,
+                UDP_RECV,
+                DhcpRepr {
+                    ntp_servers: Some([Some(NTP_IP), Some(Ipv4Address::UNSPECIFIED), None]),
+                    domain_name: Some("example.com"),
+                    ..DHCP_ACK
+                }
+            )
+        );
+
+        assert_eq!(
+            s.poll(),
+            Some(Event::Configured(Config {
+                address: Ipv4Cidr::new(MY_IP, 24),
+                dns_servers: DNS_IPS,
+                ntp_servers: [Some(NTP_IP), None, None],
+                domain_name: DomainName::new("example.com"),
+                router: Some(SERVER_IP),
+            }))
+        );
+    }
+
+    #[test]
+    fn test_renew_config_changed() {
+        let mut s = socket_bound();
+
+        recv!(s, time 500_000, [(IP_SEND, UDP_SEND, DHCP_RENEW)]);
+        send!(s, time 500_000, (IP_RECV, UDP_RECV, DhcpRepr {
+            dns_servers: Some([Some(DNS_IP_1), None, None]),
+            domain_name: Some("example.com"),
+            ..DHCP_ACK
+        }));
+        assert_eq!(
+            s.poll(),
+            Some(Event::Configured(Config {
+                address: Ipv4Cidr::new(MY_IP, 24),
+                dns_servers: [Some(DNS_IP_1), None, None],
+                ntp_servers: [None; DHCP_MAX_NTP_SERVER_COUNT],
+                domain_name: DomainName::new("example.com"),
+                router: Some(SERVER_IP),
+            }))
+        );
+
+        // Losing the lease clears all of it.
+        recv!(s, time 1_500_000, [(IP_BROADCAST, UDP_SEND, DHCP_DISCOVER)]);
+        assert_eq!(s.poll(), Some(Event::Deconfigured));
+    }
+
+    #[test]
+    fn test_domain_name() {
+        let name = DomainName::new("example.com").unwrap();
+        assert_eq!(name.as_str(), "example.com");
+        assert_eq!(format!("{:?}", name), "\"example.com\"");
+        assert!(DomainName::new(&"a".repeat(MAX_DOMAIN_NAME_LEN)).is_some());
+        assert!(DomainName::new(&"a".repeat(MAX_DOMAIN_NAME_LEN + 1)).is_none());
+    }
+
     #[test]
     fn test_renew_retransmit() {
         let mut s = socket_bound();
@@ -1034,4 +1279,60 @@ mod test {
         send!(s, time 500_000, (IP_SERVER_BROADCAST, UDP_RECV, DHCP_NAK));
         recv!(s, time 500_000, [(IP_BROADCAST, UDP_SEND, DHCP_DISCOVER)]);
     }
+
+    #[test]
+    fn test_reboot() {
+        let mut s = socket();
+        s.reboot(MY_IP);
+
+        recv!(s, time 0, [(IP_BROADCAST, UDP_SEND, DHCP_REBOOT)]);
+        assert_eq!(s.poll(), None);
+        send!(s, time 0, (IP_SERVER_BROADCAST, UDP_RECV, DHCP_ACK));
+        assert_eq!(
+            s.poll(),
+            Some(Event::Configured(Config {
+                address: Ipv4Cidr::new(MY_IP, 24),
+                dns_servers: DNS_IPS,
+                ntp_servers: [None; DHCP_MAX_NTP_SERVER_COUNT],
+                domain_name: None,
+                router: Some(SERVER_IP),
+            }))
+        );
+
+        // Renewals go to the server that acknowledged the reboot.
+        recv!(s, time 500_000, [(IP_SEND, UDP_SEND, DHCP_RENEW)]);
+    }
+
+    #[test]
+    fn test_reboot_nak() {
+        let mut s = socket();
+        s.set_ignore_naks(true);
+        s.reboot(MY_IP);
+
+        recv!(s, time 0, [(IP_BROADCAST, UDP_SEND, DHCP_REBOOT)]);
+        send!(s, time 0, (IP_SERVER_BROADCAST, UDP_RECV, DHCP_NAK));
+        assert_eq!(s.poll(), None);
+        recv!(s, time 0, [(IP_BROADCAST, UDP_SEND, DHCP_DISCOVER)]);
+    }
+
+    #[test]
+    fn test_reboot_timeout() {
+        let mut s = socket();
+        s.reboot(MY_IP);
+
+        recv!(s, time 0, [(IP_BROADCAST, UDP_SEND, DHCP_REBOOT)]);
+        recv!(s, time 5_000, [(IP_BROADCAST, UDP_SEND, DHCP_REBOOT)]);
+        recv!(s, time 10_000, [(IP_BROADCAST, UDP_SEND, DHCP_REBOOT)]);
+        recv!(s, time 20_000, [(IP_BROADCAST, UDP_SEND, DHCP_REBOOT)]);
+        recv!(s, time 30_000, [(IP_BROADCAST, UDP_SEND, DHCP_REBOOT)]);
+        recv!(s, time 70_000, [(IP_BROADCAST, UDP_SEND, DHCP_DISCOVER)]);
+    }
+
+    #[test]
+    fn test_reboot_while_bound() {
+        let mut s = socket_bound();
+        s.reboot(MY_IP);
+        assert_eq!(s.poll(), Some(Event::Deconfigured));
+        recv!(s, time 0, [(IP_BROADCAST, UDP_SEND, DHCP_REBOOT)]);
+    }
 }
@@ -4,6 +4,8 @@ use core::task::Waker;
 use heapless::Vec;
 use managed::ManagedSlice;
 
+#[cfg(feature = "socket-dhcpv4")]
+use crate::socket::dhcpv4;
 use crate::socket::{Context, PollAt};
 use crate::time::{Duration, Instant};
 use crate::wire::dns::{Flags, Opcode, Packet, Question, Rcode, Record, RecordData, Repr, Type};
@@ -130,6 +132,22 @@ impl<'a> Socket<'a> {
         self.servers = Vec::from_slice(servers).unwrap();
     }
 
+    /// Update the list of DNS servers from an event returned by a DHCPv4 socket.
+    ///
+    /// The servers are replaced with those of the lease whenever it is acquired or changes,
+    /// including on renewal, and cleared when it is lost. Passing every event returned by
+    /// [`dhcpv4::Socket::poll`] to this function keeps the resolver configured by DHCP.
+    #[cfg(feature = "socket-dhcpv4")]
+    pub fn update_servers_from_dhcpv4(&mut self, event: &dhcpv4::Event) {
+        self.servers.clear();
+        if let dhcpv4::Event::Configured(config) = event {
+            for addr in config.dns_servers.iter().flatten() {
+                // NOTE(unwrap): a lease never has more than MAX_SERVER_COUNT DNS servers.
+                self.servers.push((*addr).into()).unwrap();
+            }
+        }
+    }
+
     /// Return the time-to-live (IPv4) or hop limit (IPv6) value used in outgoing packets.
     ///
     /// See also the [set_hop_limit](#method.set_hop_limit) method
@@ -2,17 +2,24 @@ use core::cmp;
 #[cfg(feature = "async")]
 use core::task::Waker;
 
+use crate::iface::DropReason;
 use crate::phy::ChecksumCapabilities;
 #[cfg(feature = "async")]
 use crate::socket::WakerRegistration;
 use crate::socket::{Context, PollAt};
 
 use crate::storage::Empty;
+use crate::time::Instant;
 use crate::wire::IcmpRepr;
+#[cfg(all(
+    feature = "proto-ipv6",
+    any(feature = "medium-ethernet", feature = "medium-ieee802154")
+))]
+use crate::wire::NdiscRepr;
 #[cfg(feature = "proto-ipv4")]
-use crate::wire::{Icmpv4Packet, Icmpv4Repr, Ipv4Repr};
+use crate::wire::{Icmpv4Message, Icmpv4Packet, Icmpv4Repr, Ipv4Repr};
 #[cfg(feature = "proto-ipv6")]
-use crate::wire::{Icmpv6Packet, Icmpv6Repr, Ipv6Repr};
+use crate::wire::{Icmpv6Message, Icmpv6Packet, Icmpv6Repr, Ipv6Repr, MldRepr};
 use crate::wire::{IpAddress, IpListenEndpoint, IpProtocol, IpRepr};
 use crate::wire::{UdpPacket, UdpRepr};
 
@@ -49,6 +56,12 @@ pub enum Endpoint {
     Unspecified,
     Ident(u16),
     Udp(IpListenEndpoint),
+    /// ICMPv4 messages of the given type, and of the given code if any.
+    #[cfg(feature = "proto-ipv4")]
+    Icmpv4Message(Icmpv4Message, Option<u8>),
+    /// ICMPv6 messages of the given type, and of the given code if any.
+    #[cfg(feature = "proto-ipv6")]
+    Icmpv6Message(Icmpv6Message, Option<u8>),
 }
 
 impl Endpoint {
@@ -56,11 +69,50 @@ impl Endpoint {
         match *self {
             Endpoint::Ident(_) => true,
             Endpoint::Udp(endpoint) => endpoint.port != 0,
+            #[cfg(feature = "proto-ipv4")]
+            Endpoint::Icmpv4Message(..) => true,
+            #[cfg(feature = "proto-ipv6")]
+            Endpoint::Icmpv6Message(..) => true,
             Endpoint::Unspecified => false,
         }
     }
 }
 
+/// Return the type and code of an ICMPv4 message.
+#[cfg(feature = "proto-ipv4")]
+fn icmpv4_message(repr: &Icmpv4Repr) -> (Icmpv4Message, u8) {
+    match *repr {
+        Icmpv4Repr::EchoRequest { .. } => (Icmpv4Message::EchoRequest, 0),
+        Icmpv4Repr::EchoReply { .. } => (Icmpv4Message::EchoReply, 0),
+        Icmpv4Repr::DstUnreachable { reason, .. } => (Icmpv4Message::DstUnreachable, reason.into()),
+        Icmpv4Repr::Redirect { reason, .. } => (Icmpv4Message::Redirect, reason.into()),
+        Icmpv4Repr::TimeExceeded { reason, .. } => (Icmpv4Message::TimeExceeded, reason.into()),
+    }
+}
+
+/// Return the type and code of an ICMPv6 message.
+#[cfg(feature = "proto-ipv6")]
+fn icmpv6_message(repr: &Icmpv6Repr) -> (Icmpv6Message, u8) {
+    match *repr {
+        Icmpv6Repr::DstUnreachable { reason, .. } => (Icmpv6Message::DstUnreachable, reason.into()),
+        Icmpv6Repr::PktTooBig { .. } => (Icmpv6Message::PktTooBig, 0),
+        Icmpv6Repr::TimeExceeded { reason, .. } => (Icmpv6Message::TimeExceeded, reason.into()),
+        Icmpv6Repr::ParamProblem { reason, .. } => (Icmpv6Message::ParamProblem, reason.into()),
+        Icmpv6Repr::EchoRequest { .. } => (Icmpv6Message::EchoRequest, 0),
+        Icmpv6Repr::EchoReply { .. } => (Icmpv6Message::EchoReply, 0),
+        #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
+        Icmpv6Repr::Ndisc(ref repr) => match *repr {
+            NdiscRepr::RouterSolicit { .. } => (Icmpv6Message::RouterSolicit, 0),
+            NdiscRepr::RouterAdvert { .. } => (Icmpv6Message::RouterAdvert, 0),
+            NdiscRepr::NeighborSolicit { .. } => (Icmpv6Message::NeighborSolicit, 0),
+            NdiscRepr::NeighborAdvert { .. } => (Icmpv6Message::NeighborAdvert, 0),
+            NdiscRepr::Redirect { .. } => (Icmpv6Message::Redirect, 0),
+        },
+        Icmpv6Repr::Mld(MldRepr::Query { .. }) => (Icmpv6Message::MldQuery, 0),
+        Icmpv6Repr::Mld(MldRepr::Report { .. }) => (Icmpv6Message::MldReport, 0),
+    }
+}
+
 impl Default for Endpoint {
     fn default() -> Endpoint {
         Endpoint::Unspecified
@@ -68,10 +120,13 @@ impl Default for Endpoint {
 }
 
 /// An ICMP packet metadata.
-pub type PacketMetadata = crate::storage::PacketMetadata<IpAddress>;
+///
+/// Along with the remote address, the time a received packet was processed at is recorded;
+/// it is unused for packets to send.
+pub type PacketMetadata = crate::storage::PacketMetadata<(IpAddress, Instant)>;
 
 /// An ICMP packet ring buffer.
-pub type PacketBuffer<'a> = crate::storage::PacketBuffer<'a, IpAddress>;
+pub type PacketBuffer<'a> = crate::storage::PacketBuffer<'a, (IpAddress, Instant)>;
 
 /// A ICMP socket
 ///
@@ -222,9 +277,35 @@ impl<'a> Socket<'a> {
     /// icmp_socket.bind(icmp::Endpoint::Ident(0x1234)).unwrap();
     /// ```
     ///
+    /// ## Bind to a specific ICMP message type:
+    ///
+    /// To [recv] all ICMP messages of a given type, and optionally code, regardless of
+    /// the packets they relate to, the socket may be bound to the message type using
+    /// [IcmpEndpoint::Icmpv4Message] or [IcmpEndpoint::Icmpv6Message]. This is useful for
+    /// watching for path problems, such as unreachable destinations or expired hop limits.
+    /// The messages are received in full, including their header.
+    ///
+    /// ```
+    /// # #[cfg(feature = "proto-ipv4")]
+    /// # {
+    /// use smoltcp::wire::Icmpv4Message;
+    /// use smoltcp::socket::icmp;
+    /// # let rx_buffer = icmp::PacketBuffer::new(vec![icmp::PacketMetadata::EMPTY], vec![0; 20]);
+    /// # let tx_buffer = icmp::PacketBuffer::new(vec![icmp::PacketMetadata::EMPTY], vec![0; 20]);
+    ///
+    /// let mut icmp_socket = // ...
+    /// # icmp::Socket::new(rx_buffer, tx_buffer);
+    ///
+    /// // Bind to all ICMPv4 Time Exceeded messages
+    /// icmp_socket.bind(icmp::Endpoint::Icmpv4Message(Icmpv4Message::TimeExceeded, None)).unwrap();
+    /// # }
+    /// ```
+    ///
     /// [is_specified]: enum.IcmpEndpoint.html#method.is_specified
     /// [IcmpEndpoint::Ident]: enum.IcmpEndpoint.html#variant.Ident
     /// [IcmpEndpoint::Udp]: enum.IcmpEndpoint.html#variant.Udp
+    /// [IcmpEndpoint::Icmpv4Message]: enum.IcmpEndpoint.html#variant.Icmpv4Message
+    /// [IcmpEndpoint::Icmpv6Message]: enum.IcmpEndpoint.html#variant.Icmpv6Message
     /// [send]: #method.send
     /// [recv]: #method.recv
     pub fn bind<T: Into<Endpoint>>(&mut self, endpoint: T) -> Result<(), BindError> {
@@ -303,7 +384,7 @@ impl<'a> Socket<'a> {
 
         let packet_buf = self
             .tx_buffer
-            .enqueue(size, endpoint)
+            .enqueue(size, (endpoint, Instant::ZERO))
             .map_err(|_| SendError::BufferFull)?;
 
         net_trace!("icmp:{}: buffer to send {} octets", endpoint, size);
@@ -324,14 +405,30 @@ impl<'a> Socket<'a> {
     ///
     /// This function returns `Err(Error::Exhausted)` if the receive buffer is empty.
     pub fn recv(&mut self) -> Result<(&[u8], IpAddress), RecvError> {
-        let (endpoint, packet_buf) = self.rx_buffer.dequeue().map_err(|_| RecvError::Exhausted)?;
+        let (packet_buf, endpoint, _) = self.recv_with_timestamp()?;
+        Ok((packet_buf, endpoint))
+    }
+
+    /// Dequeue a packet received from a remote endpoint, and return the `IpAddress`,
+    /// the time the packet was received at, and a pointer to the payload.
+    ///
+    /// The time is the timestamp passed to the [poll] call that processed the packet,
+    /// so the round-trip time of an echo request is the difference between it and the time
+    /// the request was sent at, which can be carried in the echo payload.
+    ///
+    /// This function returns `Err(Error::Exhausted)` if the receive buffer is empty.
+    ///
+    /// [poll]: ../../iface/struct.Interface.html#method.poll
+    pub fn recv_with_timestamp(&mut self) -> Result<(&[u8], IpAddress, Instant), RecvError> {
+        let ((endpoint, timestamp), packet_buf) =
+            self.rx_buffer.dequeue().map_err(|_| RecvError::Exhausted)?;
 
         net_trace!(
             "icmp:{}: receive {} buffered octets",
             endpoint,
             packet_buf.len()
         );
-        Ok((packet_buf, endpoint))
+        Ok((packet_buf, endpoint, timestamp))
     }
 
     /// Dequeue a packet received from a remote endpoint, copy the payload into the given slice,
@@ -339,10 +436,23 @@ impl<'a> Socket<'a> {
     ///
     /// See also [recv](#method.recv).
     pub fn recv_slice(&mut self, data: &mut [u8]) -> Result<(usize, IpAddress), RecvError> {
-        let (buffer, endpoint) = self.recv()?;
+        let (length, endpoint, _) = self.recv_slice_with_timestamp(data)?;
+        Ok((length, endpoint))
+    }
+
+    /// Dequeue a packet received from a remote endpoint, copy the payload into the given slice,
+    /// and return the amount of octets copied, the `IpAddress`, and the time the packet was
+    /// received at.
+    ///
+    /// See also [recv_with_timestamp](#method.recv_with_timestamp).
+    pub fn recv_slice_with_timestamp(
+        &mut self,
+        data: &mut [u8],
+    ) -> Result<(usize, IpAddress, Instant), RecvError> {
+        let (buffer, endpoint, timestamp) = self.recv_with_timestamp()?;
         let length = cmp::min(data.len(), buffer.len());
         data[..length].copy_from_slice(&buffer[..length]);
-        Ok((length, endpoint))
+        Ok((length, endpoint, timestamp))
     }
 
     /// Filter determining which packets received by the interface are appended to
@@ -405,11 +515,23 @@ impl<'a> Socket<'a> {
                 &Endpoint::Ident(bound_ident),
                 &IcmpRepr::Ipv6(Icmpv6Repr::EchoReply { ident, .. }),
             ) => ident == bound_ident,
+            // If we are bound to an ICMP message type, accept any message of that type,
+            // and code if specified.
+            #[cfg(feature = "proto-ipv4")]
+            (Endpoint::Icmpv4Message(bound_type, bound_code), IcmpRepr::Ipv4(icmp_repr)) => {
+                let (msg_type, msg_code) = icmpv4_message(icmp_repr);
+                msg_type == *bound_type && bound_code.unwrap_or(msg_code) == msg_code
+            }
+            #[cfg(feature = "proto-ipv6")]
+            (Endpoint::Icmpv6Message(bound_type, bound_code), IcmpRepr::Ipv6(icmp_repr)) => {
+                let (msg_type, msg_code) = icmpv6_message(icmp_repr);
+                msg_type == *bound_type && bound_code.unwrap_or(msg_code) == msg_code
+            }
             _ => false,
         }
     }
 
-    pub(crate) fn process(&mut self, _cx: &mut Context, ip_repr: &IpRepr, icmp_repr: &IcmpRepr) {
+    pub(crate) fn process(&mut self, cx: &mut Context, ip_repr: &IpRepr, icmp_repr: &IcmpRepr) {
         match *icmp_repr {
             #[cfg(feature = "proto-ipv4")]
             IcmpRepr::Ipv4(ref icmp_repr) => {
@@ -417,7 +539,7 @@ impl<'a> Socket<'a> {
 
                 match self
                     .rx_buffer
-                    .enqueue(icmp_repr.buffer_len(), ip_repr.src_addr())
+                    .enqueue(icmp_repr.buffer_len(), (ip_repr.src_addr(), cx.now()))
                 {
                     Ok(packet_buf) => {
                         icmp_repr.emit(
@@ -425,7 +547,10 @@ impl<'a> Socket<'a> {
                             &ChecksumCapabilities::default(),
                         );
                     }
-                    Err(_) => net_trace!("icmp: buffer full, dropped incoming packet"),
+                    Err(_) => {
+                        net_trace!("icmp: buffer full, dropped incoming packet");
+                        cx.observe_drop(DropReason::BufferFull, ip_repr);
+                    }
                 }
             }
             #[cfg(feature = "proto-ipv6")]
@@ -434,7 +559,7 @@ impl<'a> Socket<'a> {
 
                 match self
                     .rx_buffer
-                    .enqueue(icmp_repr.buffer_len(), ip_repr.src_addr())
+                    .enqueue(icmp_repr.buffer_len(), (ip_repr.src_addr(), cx.now()))
                 {
                     Ok(packet_buf) => icmp_repr.emit(
                         &ip_repr.src_addr(),
@@ -442,7 +567,10 @@ impl<'a> Socket<'a> {
                         &mut Icmpv6Packet::new_unchecked(packet_buf),
                         &ChecksumCapabilities::default(),
                     ),
-                    Err(_) => net_trace!("icmp: buffer full, dropped incoming packet"),
+                    Err(_) => {
+                        net_trace!("icmp: buffer full, dropped incoming packet");
+                        cx.observe_drop(DropReason::BufferFull, ip_repr);
+                    }
                 }
             }
         }
@@ -456,84 +584,87 @@ impl<'a> Socket<'a> {
         F: FnOnce(&mut Context, (IpRepr, IcmpRepr)) -> Result<(), E>,
     {
         let hop_limit = self.hop_limit.unwrap_or(64);
-        let res = self.tx_buffer.dequeue_with(|remote_endpoint, packet_buf| {
-            net_trace!(
-                "icmp:{}: sending {} octets",
-                remote_endpoint,
-                packet_buf.len()
-            );
-            match *remote_endpoint {
-                #[cfg(feature = "proto-ipv4")]
-                IpAddress::Ipv4(dst_addr) => {
-                    let src_addr = match cx.get_source_address_ipv4(dst_addr) {
-                        Some(addr) => addr,
-                        None => {
-                            net_trace!(
-                                "icmp:{}: not find suitable source address, dropping",
-                                remote_endpoint
-                            );
-                            return Ok(());
-                        }
-                    };
-                    let packet = Icmpv4Packet::new_unchecked(&*packet_buf);
-                    let repr = match Icmpv4Repr::parse(&packet, &ChecksumCapabilities::ignored()) {
-                        Ok(x) => x,
-                        Err(_) => {
-                            net_trace!(
-                                "icmp:{}: malformed packet in queue, dropping",
-                                remote_endpoint
-                            );
-                            return Ok(());
-                        }
-                    };
-                    let ip_repr = IpRepr::Ipv4(Ipv4Repr {
-                        src_addr,
-                        dst_addr,
-                        next_header: IpProtocol::Icmp,
-                        payload_len: repr.buffer_len(),
-                        hop_limit: hop_limit,
-                    });
-                    emit(cx, (ip_repr, IcmpRepr::Ipv4(repr)))
-                }
-                #[cfg(feature = "proto-ipv6")]
-                IpAddress::Ipv6(dst_addr) => {
-                    let src_addr = match cx.get_source_address_ipv6(dst_addr) {
-                        Some(addr) => addr,
-                        None => {
-                            net_trace!(
-                                "icmp:{}: not find suitable source address, dropping",
-                                remote_endpoint
-                            );
-                            return Ok(());
-                        }
-                    };
-                    let packet = Icmpv6Packet::new_unchecked(&*packet_buf);
-                    let repr = match Icmpv6Repr::parse(
-                        &src_addr.into(),
-                        &dst_addr.into(),
-                        &packet,
-                        &ChecksumCapabilities::ignored(),
-                    ) {
-                        Ok(x) => x,
-                        Err(_) => {
-                            net_trace!(
-                                "icmp:{}: malformed packet in queue, dropping",
-                                remote_endpoint
-                            );
-                            return Ok(());
-                        }
-                    };
-                    let ip_repr = IpRepr::Ipv6(Ipv6Repr {
-                        src_addr,
-                        dst_addr,
-                        next_header: IpProtocol::Icmpv6,
-                        payload_len: repr.buffer_len(),
-                        hop_limit: hop_limit,
-                    });
-                    emit(cx, (ip_repr, IcmpRepr::Ipv6(repr)))
+        let res = self
+            .tx_buffer
+            .dequeue_with(|&mut (remote_endpoint, _), packet_buf| {
+                net_trace!(
+                    "icmp:{}: sending {} octets",
+                    remote_endpoint,
+                    packet_buf.len()
+                );
+                match remote_endpoint {
+                    #[cfg(feature = "proto-ipv4")]
+                    IpAddress::Ipv4(dst_addr) => {
+                        let src_addr = match cx.get_source_address_ipv4(dst_addr) {
+                            Some(addr) => addr,
+                            None => {
+                                net_trace!(
+                                    "icmp:{}: not find suitable source address, dropping",
+                                    remote_endpoint
+                                );
+                                return Ok(());
+                            }
+                        };
+                        let packet = Icmpv4Packet::new_unchecked(&*packet_buf);
+                        let repr =
+                            match Icmpv4Repr::parse(&packet, &ChecksumCapabilities::ignored()) {
+                                Ok(x) => x,
+                                Err(_) => {
+                                    net_trace!(
+                                        "icmp:{}: malformed packet in queue, dropping",
+                                        remote_endpoint
+                                    );
+                                    return Ok(());
+                                }
+                            };
+                        let ip_repr = IpRepr::Ipv4(Ipv4Repr {
+                            src_addr,
+                            dst_addr,
+                            next_header: IpProtocol::Icmp,
+                            payload_len: repr.buffer_len(),
+                            hop_limit: hop_limit,
+                        });
+                        emit(cx, (ip_repr, IcmpRepr::Ipv4(repr)))
+                    }
+                    #[cfg(feature = "proto-ipv6")]
+                    IpAddress::Ipv6(dst_addr) => {
+                        let src_addr = match cx.get_source_address_ipv6(dst_addr) {
+                            Some(addr) => addr,
+                            None => {
+                                net_trace!(
+                                    "icmp:{}: not find suitable source address, dropping",
+                                    remote_endpoint
+                                );
+                                return Ok(());
+                            }
+                        };
+                        let packet = Icmpv6Packet::new_unchecked(&*packet_buf);
+                        let repr = match Icmpv6Repr::parse(
+                            &src_addr.into(),
+                            &dst_addr.into(),
+                            &packet,
+                            &ChecksumCapabilities::ignored(),
+                        ) {
+                            Ok(x) => x,
+                            Err(_) => {
+                                net_trace!(
+                                    "icmp:{}: malformed packet in queue, dropping",
+                                    remote_endpoint
+                                );
+                                return Ok(());
+                            }
+                        };
+                        let ip_repr = IpRepr::Ipv6(Ipv6Repr {
+                            src_addr,
+                            dst_addr,
+                            next_header: IpProtocol::Icmpv6,
+                            payload_len: repr.buffer_len(),
+                            hop_limit: hop_limit,
+                        });
+                        emit(cx, (ip_repr, IcmpRepr::Ipv6(repr)))
+                    }
                 }
-            }
-        });
+            });
         match res {
             Err(Empty) => Ok(()),
             Ok(Err(e)) => Err(e),
@@ -545,6 +676,13 @@ impl<'a> Socket<'a> {
         }
     }
 
+    /// Panic if the state of the packet buffers is inconsistent.
+    #[cfg(feature = "paranoid")]
+    pub(crate) fn check_invariants(&self) {
+        self.rx_buffer.check_invariants();
+        self.tx_buffer.check_invariants();
+    }
+
     pub(crate) fn poll_at(&self, _cx: &mut Context) -> PollAt {
         if self.tx_buffer.is_empty() {
             PollAt::Ingress
@@ -584,7 +722,8 @@ mod tests_common {
 #[cfg(all(test, feature = "proto-ipv4"))]
 mod test_ipv4 {
     use super::tests_common::*;
-    use crate::wire::{Icmpv4DstUnreachable, IpEndpoint, Ipv4Address};
+    use crate::time::Duration;
+    use crate::wire::{Icmpv4DstUnreachable, Icmpv4TimeExceeded, IpEndpoint, Ipv4Address};
     use crate::Error;
 
     const REMOTE_IPV4: Ipv4Address = Ipv4Address([192, 168, 1, 2]);
@@ -742,6 +881,43 @@ mod test_ipv4 {
         assert!(!socket.can_recv());
     }
 
+    #[test]
+    fn test_recv_timestamp() {
+        let mut socket = socket(buffer(2), buffer(1));
+        let mut cx = Context::mock();
+        assert_eq!(socket.bind(Endpoint::Ident(0x1234)), Ok(()));
+
+        // The application sent the request at 1s, and embedded that time in the payload.
+        let sent_at = Instant::from_millis(1000);
+        let payload = sent_at.total_micros().to_be_bytes();
+        let reply_repr = Icmpv4Repr::EchoReply {
+            ident: 0x1234,
+            seq_no: 0x5678,
+            data: &payload,
+        };
+
+        cx.set_now(Instant::from_millis(1250));
+        socket.process(&mut cx, &REMOTE_IPV4_REPR, &reply_repr.into());
+        cx.set_now(Instant::from_millis(1300));
+        socket.process(&mut cx, &REMOTE_IPV4_REPR, &reply_repr.into());
+
+        let (data, addr, received_at) = socket.recv_with_timestamp().unwrap();
+        assert_eq!(addr, REMOTE_IPV4.into());
+        assert_eq!(received_at, Instant::from_millis(1250));
+        let packet = Icmpv4Packet::new_checked(data).unwrap();
+        let mut embedded = [0; 8];
+        embedded.copy_from_slice(packet.data());
+        let embedded = Instant::from_micros(i64::from_be_bytes(embedded));
+        assert_eq!(received_at - embedded, Duration::from_millis(250));
+
+        let mut data = [0; 16];
+        assert_eq!(
+            socket.recv_slice_with_timestamp(&mut data),
+            Ok((16, REMOTE_IPV4.into(), Instant::from_millis(1300)))
+        );
+        assert!(!socket.can_recv());
+    }
+
     #[test]
     fn test_accept_bad_id() {
         let mut socket = socket(buffer(1), buffer(1));
@@ -820,13 +996,74 @@ mod test_ipv4 {
         );
         assert!(!socket.can_recv());
     }
+
+    #[test]
+    fn test_accepts_message_type() {
+        let mut socket = socket(buffer(1), buffer(1));
+        let mut cx = Context::mock();
+        let endpoint = Endpoint::Icmpv4Message(
+            Icmpv4Message::DstUnreachable,
+            Some(Icmpv4DstUnreachable::PortUnreachable.into()),
+        );
+        assert_eq!(socket.bind(endpoint), Ok(()));
+
+        let header = Ipv4Repr {
+            src_addr: LOCAL_IPV4,
+            dst_addr: REMOTE_IPV4,
+            next_header: IpProtocol::Tcp,
+            payload_len: 8,
+            hop_limit: 0x40,
+        };
+        let icmp_repr = Icmpv4Repr::DstUnreachable {
+            reason: Icmpv4DstUnreachable::PortUnreachable,
+            header,
+            data: &[0xff; 8],
+        };
+        let ip_repr = IpRepr::Ipv4(Ipv4Repr {
+            src_addr: REMOTE_IPV4,
+            dst_addr: LOCAL_IPV4,
+            next_header: IpProtocol::Icmp,
+            payload_len: icmp_repr.buffer_len(),
+            hop_limit: 0x40,
+        });
+        // Messages are matched by type and code, not by the packet they relate to.
+        assert!(socket.accepts(&mut cx, &ip_repr, &icmp_repr.into()));
+        let other_code = Icmpv4Repr::DstUnreachable {
+            reason: Icmpv4DstUnreachable::HostUnreachable,
+            header,
+            data: &[0xff; 8],
+        };
+        assert!(!socket.accepts(&mut cx, &ip_repr, &other_code.into()));
+        assert!(!socket.accepts(&mut cx, &REMOTE_IPV4_REPR, &ECHOV4_REPR.into()));
+
+        socket.process(&mut cx, &ip_repr, &icmp_repr.into());
+        let mut bytes = [0x00; 36];
+        let mut packet = Icmpv4Packet::new_unchecked(&mut bytes[..]);
+        icmp_repr.emit(&mut packet, &ChecksumCapabilities::default());
+        assert_eq!(
+            socket.recv(),
+            Ok((&packet.into_inner()[..], REMOTE_IPV4.into()))
+        );
+
+        // Without a code, any message of the type is accepted.
+        let mut socket2 = Socket::new(buffer(1), buffer(1));
+        let endpoint = Endpoint::Icmpv4Message(Icmpv4Message::TimeExceeded, None);
+        assert_eq!(socket2.bind(endpoint), Ok(()));
+        let time_exceeded = Icmpv4Repr::TimeExceeded {
+            reason: Icmpv4TimeExceeded::FragExpired,
+            header,
+            data: &[0xff; 8],
+        };
+        assert!(socket2.accepts(&mut cx, &ip_repr, &time_exceeded.into()));
+        assert!(!socket2.accepts(&mut cx, &ip_repr, &icmp_repr.into()));
+    }
 }
 
 #[cfg(all(test, feature = "proto-ipv6"))]
 mod test_ipv6 {
     use super::tests_common::*;
 
-    use crate::wire::{Icmpv6DstUnreachable, IpEndpoint, Ipv6Address};
+    use crate::wire::{Icmpv6DstUnreachable, Icmpv6TimeExceeded, IpEndpoint, Ipv6Address};
     use crate::Error;
 
     const REMOTE_IPV6: Ipv6Address =
@@ -1088,4 +1325,63 @@ mod test_ipv6 {
         );
         assert!(!socket.can_recv());
     }
+
+    #[test]
+    fn test_accepts_message_type() {
+        let mut socket = socket(buffer(1), buffer(1));
+        let mut cx = Context::mock();
+        let endpoint = Endpoint::Icmpv6Message(Icmpv6Message::TimeExceeded, None);
+        assert_eq!(socket.bind(endpoint), Ok(()));
+
+        let header = Ipv6Repr {
+            src_addr: LOCAL_IPV6,
+            dst_addr: REMOTE_IPV6,
+            next_header: IpProtocol::Udp,
+            payload_len: 8,
+            hop_limit: 0x40,
+        };
+        let icmp_repr = Icmpv6Repr::TimeExceeded {
+            reason: Icmpv6TimeExceeded::HopLimitExceeded,
+            header,
+            data: &[0xff; 8],
+        };
+        let ip_repr = IpRepr::Ipv6(Ipv6Repr {
+            src_addr: REMOTE_IPV6,
+            dst_addr: LOCAL_IPV6,
+            next_header: IpProtocol::Icmpv6,
+            payload_len: icmp_repr.buffer_len(),
+            hop_limit: 0x40,
+        });
+        assert!(socket.accepts(&mut cx, &ip_repr, &icmp_repr.into()));
+        let unreachable = Icmpv6Repr::DstUnreachable {
+            reason: Icmpv6DstUnreachable::PortUnreachable,
+            header,
+            data: &[0xff; 8],
+        };
+        assert!(!socket.accepts(&mut cx, &ip_repr, &unreachable.into()));
+        assert!(!socket.accepts(&mut cx, &REMOTE_IPV6_REPR, &ECHOV6_REPR.into()));
+
+        socket.process(&mut cx, &ip_repr, &icmp_repr.into());
+        let mut bytes = [0x00; 56];
+        let mut packet = Icmpv6Packet::new_unchecked(&mut bytes[..]);
+        icmp_repr.emit(
+            &REMOTE_IPV6.into(),
+            &LOCAL_IPV6.into(),
+            &mut packet,
+            &ChecksumCapabilities::default(),
+        );
+        assert_eq!(
+            socket.recv(),
+            Ok((&packet.into_inner()[..], REMOTE_IPV6.into()))
+        );
+
+        // A code narrows the messages down.
+        let mut socket2 = Socket::new(buffer(1), buffer(1));
+        let endpoint = Endpoint::Icmpv6Message(
+            Icmpv6Message::TimeExceeded,
+            Some(Icmpv6TimeExceeded::FragReassemExceeded.into()),
+        );
+        assert_eq!(socket2.bind(endpoint), Ok(()));
+        assert!(!socket2.accepts(&mut cx, &ip_repr, &icmp_repr.into()));
+    }
 }
@@ -56,6 +56,9 @@ pub(crate) enum PollAt {
 /// [AnySocket]: trait.AnySocket.html
 /// [SocketSet::get]: struct.SocketSet.html#method.get
 #[derive(Debug)]
+// The TCP socket is much larger than the others, but it can't be boxed without `alloc`,
+// and sockets are stored in a `SocketStorage` slot of this size either way.
+#[cfg_attr(feature = "socket-tcp", allow(clippy::large_enum_variant))]
 pub enum Socket<'a> {
     #[cfg(feature = "socket-raw")]
     Raw(raw::Socket<'a>),
@@ -88,6 +91,25 @@ impl<'a> Socket<'a> {
             Socket::Dns(s) => s.poll_at(cx),
         }
     }
+
+    #[cfg(feature = "paranoid")]
+    #[cfg_attr(not(feature = "socket-tcp"), allow(unused_variables))]
+    pub(crate) fn check_invariants(&mut self, timestamp: Instant) {
+        match self {
+            #[cfg(feature = "socket-raw")]
+            Socket::Raw(s) => s.check_invariants(),
+            #[cfg(feature = "socket-icmp")]
+            Socket::Icmp(s) => s.check_invariants(),
+            #[cfg(feature = "socket-udp")]
+            Socket::Udp(s) => s.check_invariants(),
+            #[cfg(feature = "socket-tcp")]
+            Socket::Tcp(s) => s.check_invariants(timestamp),
+            #[cfg(feature = "socket-dhcpv4")]
+            Socket::Dhcpv4(_) => {}
+            #[cfg(feature = "socket-dns")]
+            Socket::Dns(_) => {}
+        }
+    }
 }
 
 /// A conversion trait for network sockets.
@@ -2,13 +2,15 @@ use core::cmp::min;
 #[cfg(feature = "async")]
 use core::task::Waker;
 
-use crate::iface::Context;
+use crate::iface::{Context, DropReason};
 use crate::socket::PollAt;
 #[cfg(feature = "async")]
 use crate::socket::WakerRegistration;
 
 use crate::storage::Empty;
-use crate::wire::{IpProtocol, IpRepr, IpVersion};
+#[cfg(feature = "medium-ethernet")]
+use crate::wire::EthernetRepr;
+use crate::wire::{IpAddress, IpProtocol, IpRepr, IpVersion};
 #[cfg(feature = "proto-ipv4")]
 use crate::wire::{Ipv4Packet, Ipv4Repr};
 #[cfg(feature = "proto-ipv6")]
@@ -36,11 +38,32 @@ pub enum RecvError {
     Exhausted,
 }
 
-/// A UDP packet metadata.
-pub type PacketMetadata = crate::storage::PacketMetadata<()>;
+/// The headers of a received packet, as parsed by the interface.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Headers {
+    /// The Ethernet header, or `None` if the packet was not received in an Ethernet frame.
+    ///
+    /// The EtherType is the one following any VLAN tags.
+    #[cfg(feature = "medium-ethernet")]
+    pub ethernet: Option<EthernetRepr>,
+    /// The source address of the IP header.
+    pub src_addr: IpAddress,
+    /// The destination address of the IP header.
+    pub dst_addr: IpAddress,
+    /// The protocol of the payload, which is the one the socket is bound to.
+    pub next_header: IpProtocol,
+    /// The time-to-live or hop limit of the IP header.
+    pub hop_limit: u8,
+}
 
-/// A UDP packet ring buffer.
-pub type PacketBuffer<'a> = crate::storage::PacketBuffer<'a, ()>;
+/// A raw IP packet metadata.
+///
+/// Received packets carry their parsed headers, and packets to send carry nothing.
+pub type PacketMetadata = crate::storage::PacketMetadata<Option<Headers>>;
+
+/// A raw IP packet ring buffer.
+pub type PacketBuffer<'a> = crate::storage::PacketBuffer<'a, Option<Headers>>;
 
 /// A raw IP socket.
 ///
@@ -52,6 +75,10 @@ pub struct Socket<'a> {
     ip_protocol: IpProtocol,
     rx_buffer: PacketBuffer<'a>,
     tx_buffer: PacketBuffer<'a>,
+    /// Whether outgoing packets are restricted to directly-connected destinations.
+    dont_route: bool,
+    /// Whether incoming IPv4 fragments are delivered to this socket as-is.
+    pass_fragments: bool,
     #[cfg(feature = "async")]
     rx_waker: WakerRegistration,
     #[cfg(feature = "async")]
@@ -72,6 +99,8 @@ impl<'a> Socket<'a> {
             ip_protocol,
             rx_buffer,
             tx_buffer,
+            dont_route: false,
+            pass_fragments: false,
             #[cfg(feature = "async")]
             rx_waker: WakerRegistration::new(),
             #[cfg(feature = "async")]
@@ -126,6 +155,42 @@ impl<'a> Socket<'a> {
         self.ip_protocol
     }
 
+    /// Return whether outgoing packets are restricted to directly-connected destinations.
+    ///
+    /// See also the [set_dont_route](#method.set_dont_route) method
+    pub fn dont_route(&self) -> bool {
+        self.dont_route
+    }
+
+    /// Restrict outgoing packets to destinations on a directly-connected subnet.
+    ///
+    /// When enabled, packets whose destination would have to be sent through a
+    /// gateway are dropped instead of being routed, similar to `SO_DONTROUTE`.
+    ///
+    /// The check happens when the interface dispatches the packet, not in
+    /// [send](#method.send) or [send_slice](#method.send_slice), so those still
+    /// succeed and the packet is silently dropped later.
+    pub fn set_dont_route(&mut self, dont_route: bool) {
+        self.dont_route = dont_route
+    }
+
+    /// Return whether incoming IPv4 fragments are delivered to this socket.
+    ///
+    /// See also the [set_pass_fragments](#method.set_pass_fragments) method
+    pub fn pass_fragments(&self) -> bool {
+        self.pass_fragments
+    }
+
+    /// Deliver incoming IPv4 fragments to this socket without reassembling them.
+    ///
+    /// The interface does not reassemble IPv4 fragments, and drops them by default.
+    /// When enabled, every fragment matching the socket's IP version and protocol is
+    /// received as a separate packet, with its identification, flags and fragment offset
+    /// intact. Fragments are not processed any further by the interface.
+    pub fn set_pass_fragments(&mut self, pass_fragments: bool) {
+        self.pass_fragments = pass_fragments
+    }
+
     /// Check whether the transmit buffer is full.
     #[inline]
     pub fn can_send(&self) -> bool {
@@ -171,12 +236,14 @@ impl<'a> Socket<'a> {
     /// If the buffer is filled in a way that does not match the socket's
     /// IP version or protocol, the packet will be silently dropped.
     ///
-    /// **Note:** The IP header is parsed and re-serialized, and may not match
-    /// the header actually transmitted bit for bit.
+    /// The packet, including its IP header, is transmitted verbatim, except for the
+    /// IPv4 header checksum, which is filled according to the device checksum capabilities.
+    /// In particular, the IPv4 identification, flags and fragment offset are preserved,
+    /// so that the packet may be a fragment.
     pub fn send(&mut self, size: usize) -> Result<&mut [u8], SendError> {
         let packet_buf = self
             .tx_buffer
-            .enqueue(size, ())
+            .enqueue(size, None)
             .map_err(|_| SendError::BufferFull)?;
 
         net_trace!(
@@ -200,10 +267,18 @@ impl<'a> Socket<'a> {
     ///
     /// This function returns `Err(Error::Exhausted)` if the receive buffer is empty.
     ///
-    /// **Note:** The IP header is parsed and re-serialized, and may not match
-    /// the header actually received bit for bit.
+    /// The packet, including its IP header, is returned exactly as it was received.
     pub fn recv(&mut self) -> Result<&[u8], RecvError> {
-        let ((), packet_buf) = self.rx_buffer.dequeue().map_err(|_| RecvError::Exhausted)?;
+        let (packet_buf, _) = self.recv_with_headers()?;
+        Ok(packet_buf)
+    }
+
+    /// Dequeue a packet, and return a pointer to the payload along with the headers the
+    /// interface parsed on the way to this socket.
+    ///
+    /// See also [recv](#method.recv).
+    pub fn recv_with_headers(&mut self) -> Result<(&[u8], Headers), RecvError> {
+        let (headers, packet_buf) = self.rx_buffer.dequeue().map_err(|_| RecvError::Exhausted)?;
 
         net_trace!(
             "raw:{}:{}: receive {} buffered octets",
@@ -211,17 +286,30 @@ impl<'a> Socket<'a> {
             self.ip_protocol,
             packet_buf.len()
         );
-        Ok(packet_buf)
+        // NOTE(unwrap): every received packet is enqueued with its headers.
+        Ok((packet_buf, headers.unwrap()))
     }
 
     /// Dequeue a packet, and copy the payload into the given slice.
     ///
     /// See also [recv](#method.recv).
     pub fn recv_slice(&mut self, data: &mut [u8]) -> Result<usize, RecvError> {
-        let buffer = self.recv()?;
+        let (length, _) = self.recv_slice_with_headers(data)?;
+        Ok(length)
+    }
+
+    /// Dequeue a packet, copy the payload into the given slice, and return its length along
+    /// with the headers of the packet.
+    ///
+    /// See also [recv_with_headers](#method.recv_with_headers).
+    pub fn recv_slice_with_headers(
+        &mut self,
+        data: &mut [u8],
+    ) -> Result<(usize, Headers), RecvError> {
+        let (buffer, headers) = self.recv_with_headers()?;
         let length = min(data.len(), buffer.len());
         data[..length].copy_from_slice(&buffer[..length]);
-        Ok(length)
+        Ok((length, headers))
     }
 
     pub(crate) fn accepts(&self, ip_repr: &IpRepr) -> bool {
@@ -235,29 +323,35 @@ impl<'a> Socket<'a> {
         true
     }
 
-    pub(crate) fn process(&mut self, cx: &mut Context, ip_repr: &IpRepr, payload: &[u8]) {
+    pub(crate) fn process(&mut self, cx: &mut Context, ip_repr: &IpRepr, packet: &[u8]) {
         debug_assert!(self.accepts(ip_repr));
 
-        let header_len = ip_repr.buffer_len();
-        let total_len = header_len + payload.len();
+        let headers = Headers {
+            #[cfg(feature = "medium-ethernet")]
+            ethernet: cx.rx_ethernet_repr(),
+            src_addr: ip_repr.src_addr(),
+            dst_addr: ip_repr.dst_addr(),
+            next_header: ip_repr.next_header(),
+            hop_limit: ip_repr.hop_limit(),
+        };
 
         net_trace!(
             "raw:{}:{}: receiving {} octets",
             self.ip_version,
             self.ip_protocol,
-            total_len
+            packet.len()
         );
 
-        match self.rx_buffer.enqueue(total_len, ()) {
-            Ok(buf) => {
-                ip_repr.emit(&mut buf[..header_len], &cx.checksum_caps());
-                buf[header_len..].copy_from_slice(payload);
+        match self.rx_buffer.enqueue(packet.len(), Some(headers)) {
+            Ok(buf) => buf.copy_from_slice(packet),
+            Err(_) => {
+                net_trace!(
+                    "raw:{}:{}: buffer full, dropped incoming packet",
+                    self.ip_version,
+                    self.ip_protocol
+                );
+                cx.observe_drop(DropReason::BufferFull, ip_repr);
             }
-            Err(_) => net_trace!(
-                "raw:{}:{}: buffer full, dropped incoming packet",
-                self.ip_version,
-                self.ip_protocol
-            ),
         }
 
         #[cfg(feature = "async")]
@@ -270,8 +364,9 @@ impl<'a> Socket<'a> {
     {
         let ip_protocol = self.ip_protocol;
         let ip_version = self.ip_version;
+        let dont_route = self.dont_route;
         let _checksum_caps = &cx.checksum_caps();
-        let res = self.tx_buffer.dequeue_with(|&mut (), buffer| {
+        let res = self.tx_buffer.dequeue_with(|_, buffer| {
             match IpVersion::of_packet(buffer) {
                 #[cfg(feature = "proto-ipv4")]
                 Ok(IpVersion::Ipv4) => {
@@ -294,16 +389,23 @@ impl<'a> Socket<'a> {
                         packet.set_checksum(0);
                     }
 
-                    let packet = Ipv4Packet::new_unchecked(&*packet.into_inner());
-                    let ipv4_repr = match Ipv4Repr::parse(&packet, _checksum_caps) {
-                        Ok(x) => x,
-                        Err(_) => {
-                            net_trace!("raw: malformed ipv4 packet in queue, dropping.");
-                            return Ok(());
-                        }
+                    let total_len = packet.total_len() as usize;
+                    let packet = Ipv4Packet::new_unchecked(&packet.into_inner()[..total_len]);
+                    // The header is sent as-is, so this may well be a fragment, which
+                    // `Ipv4Repr::parse` would refuse; the representation is only used for routing.
+                    let ipv4_repr = Ipv4Repr {
+                        src_addr: packet.src_addr(),
+                        dst_addr: packet.dst_addr(),
+                        next_header: packet.next_header(),
+                        payload_len: packet.payload().len(),
+                        hop_limit: packet.hop_limit(),
                     };
+                    if dont_route && !cx.is_on_link(&ipv4_repr.dst_addr.into()) {
+                        net_trace!("raw: destination not directly connected, dropping.");
+                        return Ok(());
+                    }
                     net_trace!("raw:{}:{}: sending", ip_version, ip_protocol);
-                    emit(cx, (IpRepr::Ipv4(ipv4_repr), packet.payload()))
+                    emit(cx, (IpRepr::Ipv4(ipv4_repr), packet.into_inner()))
                 }
                 #[cfg(feature = "proto-ipv6")]
                 Ok(IpVersion::Ipv6) => {
@@ -318,7 +420,8 @@ impl<'a> Socket<'a> {
                         net_trace!("raw: sent ipv6 packet with wrong ip protocol, dropping.");
                         return Ok(());
                     }
-                    let packet = Ipv6Packet::new_unchecked(&*packet.into_inner());
+                    let total_len = packet.total_len();
+                    let packet = Ipv6Packet::new_unchecked(&packet.into_inner()[..total_len]);
                     let ipv6_repr = match Ipv6Repr::parse(&packet) {
                         Ok(x) => x,
                         Err(_) => {
@@ -327,8 +430,12 @@ impl<'a> Socket<'a> {
                         }
                     };
 
+                    if dont_route && !cx.is_on_link(&ipv6_repr.dst_addr.into()) {
+                        net_trace!("raw: destination not directly connected, dropping.");
+                        return Ok(());
+                    }
                     net_trace!("raw:{}:{}: sending", ip_version, ip_protocol);
-                    emit(cx, (IpRepr::Ipv6(ipv6_repr), packet.payload()))
+                    emit(cx, (IpRepr::Ipv6(ipv6_repr), packet.into_inner()))
                 }
                 Err(_) => {
                     net_trace!("raw: sent packet with invalid IP version, dropping.");
@@ -347,6 +454,13 @@ impl<'a> Socket<'a> {
         }
     }
 
+    /// Panic if the state of the packet buffers is inconsistent.
+    #[cfg(feature = "paranoid")]
+    pub(crate) fn check_invariants(&self) {
+        self.rx_buffer.check_invariants();
+        self.tx_buffer.check_invariants();
+    }
+
     pub(crate) fn poll_at(&self, _cx: &mut Context) -> PollAt {
         if self.tx_buffer.is_empty() {
             PollAt::Ingress
@@ -395,10 +509,9 @@ mod test {
             hop_limit: 64,
         });
         pub const PACKET_BYTES: [u8; 24] = [
-            0x45, 0x00, 0x00, 0x18, 0x00, 0x00, 0x40, 0x00, 0x40, 0x3f, 0x00, 0x00, 0x0a, 0x00,
+            0x45, 0x00, 0x00, 0x18, 0x00, 0x00, 0x40, 0x00, 0x40, 0x3f, 0x26, 0xa5, 0x0a, 0x00,
             0x00, 0x01, 0x0a, 0x00, 0x00, 0x02, 0xaa, 0x00, 0x00, 0xff,
         ];
-        pub const PACKET_PAYLOAD: [u8; 4] = [0xaa, 0x00, 0x00, 0xff];
     }
 
     #[cfg(feature = "proto-ipv6")]
@@ -438,12 +551,10 @@ mod test {
             0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0xaa, 0x00,
             0x00, 0xff,
         ];
-
-        pub const PACKET_PAYLOAD: [u8; 4] = [0xaa, 0x00, 0x00, 0xff];
     }
 
     macro_rules! reusable_ip_specific_tests {
-        ($module:ident, $socket:path, $hdr:path, $packet:path, $payload:path) => {
+        ($module:ident, $socket:path, $hdr:path, $packet:path) => {
             mod $module {
                 use super::*;
 
@@ -469,9 +580,9 @@ mod test {
                     assert!(!socket.can_send());
 
                     assert_eq!(
-                        socket.dispatch(&mut cx, |_, (ip_repr, ip_payload)| {
+                        socket.dispatch(&mut cx, |_, (ip_repr, ip_packet)| {
                             assert_eq!(ip_repr, $hdr);
-                            assert_eq!(ip_payload, &$payload);
+                            assert_eq!(ip_packet, &$packet);
                             Err(Error::Unaddressable)
                         }),
                         Err(Error::Unaddressable)
@@ -479,9 +590,9 @@ mod test {
                     assert!(!socket.can_send());
 
                     assert_eq!(
-                        socket.dispatch(&mut cx, |_, (ip_repr, ip_payload)| {
+                        socket.dispatch(&mut cx, |_, (ip_repr, ip_packet)| {
                             assert_eq!(ip_repr, $hdr);
-                            assert_eq!(ip_payload, &$payload);
+                            assert_eq!(ip_packet, &$packet);
                             Ok::<_, Error>(())
                         }),
                         Ok(())
@@ -495,7 +606,7 @@ mod test {
                     let mut cx = Context::mock();
 
                     assert!(socket.accepts(&$hdr));
-                    socket.process(&mut cx, &$hdr, &$payload);
+                    socket.process(&mut cx, &$hdr, &$packet);
 
                     let mut slice = [0; 4];
                     assert_eq!(socket.recv_slice(&mut slice[..]), Ok(4));
@@ -522,8 +633,7 @@ mod test {
         ipv4,
         ipv4_locals::socket,
         ipv4_locals::HEADER_REPR,
-        ipv4_locals::PACKET_BYTES,
-        ipv4_locals::PACKET_PAYLOAD
+        ipv4_locals::PACKET_BYTES
     );
 
     #[cfg(feature = "proto-ipv6")]
@@ -531,8 +641,7 @@ mod test {
         ipv6,
         ipv6_locals::socket,
         ipv6_locals::HEADER_REPR,
-        ipv6_locals::PACKET_BYTES,
-        ipv6_locals::PACKET_PAYLOAD
+        ipv6_locals::PACKET_BYTES
     );
 
     #[test]
@@ -586,6 +695,33 @@ mod test {
         }
     }
 
+    #[test]
+    #[cfg(feature = "proto-ipv4")]
+    fn test_send_fragment() {
+        let mut socket = ipv4_locals::socket(buffer(0), buffer(1));
+        let mut cx = Context::mock();
+
+        let mut fragment = [0; 32];
+        fragment[..ipv4_locals::PACKET_BYTES.len()].copy_from_slice(&ipv4_locals::PACKET_BYTES);
+        let mut packet = Ipv4Packet::new_unchecked(&mut fragment);
+        packet.set_ident(0x1234);
+        packet.set_dont_frag(false);
+        packet.set_more_frags(true);
+        packet.set_frag_offset(8);
+        packet.fill_checksum();
+
+        // Trailing bytes beyond the total length are not sent.
+        assert_eq!(socket.send_slice(&fragment[..]), Ok(()));
+        assert_eq!(
+            socket.dispatch(&mut cx, |_, (ip_repr, ip_packet)| {
+                assert_eq!(ip_repr, ipv4_locals::HEADER_REPR);
+                assert_eq!(ip_packet, &fragment[..ipv4_locals::PACKET_BYTES.len()]);
+                Ok::<_, Error>(())
+            }),
+            Ok(())
+        );
+    }
+
     #[test]
     fn test_recv_process() {
         #[cfg(feature = "proto-ipv4")]
@@ -594,15 +730,12 @@ mod test {
             assert!(!socket.can_recv());
             let mut cx = Context::mock();
 
-            let mut cksumd_packet = ipv4_locals::PACKET_BYTES;
-            Ipv4Packet::new_unchecked(&mut cksumd_packet).fill_checksum();
-
             assert_eq!(socket.recv(), Err(RecvError::Exhausted));
             assert!(socket.accepts(&ipv4_locals::HEADER_REPR));
             socket.process(
                 &mut cx,
                 &ipv4_locals::HEADER_REPR,
-                &ipv4_locals::PACKET_PAYLOAD,
+                &ipv4_locals::PACKET_BYTES,
             );
             assert!(socket.can_recv());
 
@@ -610,9 +743,9 @@ mod test {
             socket.process(
                 &mut cx,
                 &ipv4_locals::HEADER_REPR,
-                &ipv4_locals::PACKET_PAYLOAD,
+                &ipv4_locals::PACKET_BYTES,
             );
-            assert_eq!(socket.recv(), Ok(&cksumd_packet[..]));
+            assert_eq!(socket.recv(), Ok(&ipv4_locals::PACKET_BYTES[..]));
             assert!(!socket.can_recv());
         }
         #[cfg(feature = "proto-ipv6")]
@@ -626,7 +759,7 @@ mod test {
             socket.process(
                 &mut cx,
                 &ipv6_locals::HEADER_REPR,
-                &ipv6_locals::PACKET_PAYLOAD,
+                &ipv6_locals::PACKET_BYTES,
             );
             assert!(socket.can_recv());
 
@@ -634,13 +767,43 @@ mod test {
             socket.process(
                 &mut cx,
                 &ipv6_locals::HEADER_REPR,
-                &ipv6_locals::PACKET_PAYLOAD,
+                &ipv6_locals::PACKET_BYTES,
             );
             assert_eq!(socket.recv(), Ok(&ipv6_locals::PACKET_BYTES[..]));
             assert!(!socket.can_recv());
         }
     }
 
+    #[test]
+    #[cfg(feature = "proto-ipv4")]
+    fn test_recv_with_headers() {
+        let mut socket = ipv4_locals::socket(buffer(1), buffer(0));
+        let mut cx = Context::mock();
+
+        socket.process(
+            &mut cx,
+            &ipv4_locals::HEADER_REPR,
+            &ipv4_locals::PACKET_BYTES,
+        );
+        let mut slice = [0; 4];
+        assert_eq!(
+            socket.recv_slice_with_headers(&mut slice[..]),
+            Ok((
+                4,
+                Headers {
+                    // The mock context never processes Ethernet frames.
+                    #[cfg(feature = "medium-ethernet")]
+                    ethernet: None,
+                    src_addr: Ipv4Address([10, 0, 0, 1]).into(),
+                    dst_addr: Ipv4Address([10, 0, 0, 2]).into(),
+                    next_header: IpProtocol::Unknown(ipv4_locals::IP_PROTO),
+                    hop_limit: 64,
+                }
+            ))
+        );
+        assert_eq!(&slice, &ipv4_locals::PACKET_BYTES[..4]);
+    }
+
     #[test]
     fn test_doesnt_accept_wrong_proto() {
         #[cfg(feature = "proto-ipv4")]
@@ -3,10 +3,14 @@
 // a new feature.
 
 use core::fmt::Display;
+use core::ops::RangeInclusive;
 #[cfg(feature = "async")]
 use core::task::Waker;
 use core::{cmp, fmt, mem};
+#[cfg(feature = "socket-tcp-ao")]
+use managed::ManagedSlice;
 
+use crate::crypto::siphash24;
 #[cfg(feature = "async")]
 use crate::socket::WakerRegistration;
 use crate::socket::{Context, PollAt};
@@ -16,6 +20,11 @@ use crate::wire::{
     IpAddress, IpEndpoint, IpListenEndpoint, IpProtocol, IpRepr, TcpControl, TcpRepr, TcpSeqNumber,
     TCP_HEADER_LEN,
 };
+#[cfg(feature = "socket-tcp-ao")]
+use crate::{
+    crypto::{Aes128Cmac, HmacSha1, AES_BLOCK_LEN, SHA1_LEN},
+    wire::{TcpAuthOption, TcpOption, TcpPacket, TCP_AUTH_MAC_LEN},
+};
 
 macro_rules! tcp_trace {
     ($($arg:expr),*) => (net_log!(trace, $($arg),*));
@@ -52,6 +61,35 @@ pub enum RecvError {
     Finished,
 }
 
+/// Error returned by [`Socket::export_connection`]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ExportError {
+    InvalidState,
+    BufferTooSmall,
+}
+
+/// Error returned by [`Socket::import_connection`]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ImportError {
+    InvalidState,
+    Unaddressable,
+    BufferTooSmall,
+    Malformed,
+}
+
+/// Error returned by [`AuthKey::new`] and [`Socket::set_auth_rnext_key`]
+#[cfg(feature = "socket-tcp-ao")]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AuthError {
+    /// The master key is empty or too long.
+    InvalidKey,
+    /// No key has the given receive identifier.
+    UnknownKey,
+}
+
 /// A TCP socket ring buffer.
 pub type SocketBuffer<'a> = RingBuffer<'a, u8>;
 
@@ -92,6 +130,102 @@ impl fmt::Display for State {
     }
 }
 
+/// The reason a connection was closed without an orderly shutdown.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum CloseReason {
+    /// The remote endpoint reset the connection.
+    ///
+    /// The data left in the transmit buffer is discarded: `unacked` octets had been sent
+    /// without being acknowledged, and `unsent` octets had not been sent yet. Data rewound
+    /// by a retransmission timeout counts as unsent until it is sent again.
+    Reset { unacked: usize, unsent: usize },
+    /// Nothing was received from the remote endpoint within the [timeout].
+    ///
+    /// [timeout]: struct.Socket.html#method.set_timeout
+    TimedOut,
+    /// No data was received within the [read timeout] while there was none left to read.
+    ///
+    /// [read timeout]: struct.Socket.html#method.set_read_timeout
+    ReadTimedOut,
+    /// None of the data in the transmit buffer was acknowledged within the [write timeout].
+    ///
+    /// [write timeout]: struct.Socket.html#method.set_write_timeout
+    WriteTimedOut,
+    /// The remote endpoint sent data slower than the [minimum receive rate].
+    ///
+    /// [minimum receive rate]: struct.Socket.html#method.set_min_recv_rate
+    TooSlow,
+    /// The connection was [aborted] locally.
+    ///
+    /// [aborted]: struct.Socket.html#method.abort
+    Aborted,
+    /// The local address of the connection was removed from the interface.
+    LocalAddressRemoved,
+}
+
+impl fmt::Display for CloseReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CloseReason::Reset {
+                unacked: 0,
+                unsent: 0,
+            } => write!(f, "connection reset"),
+            CloseReason::Reset { unacked, unsent } => write!(
+                f,
+                "connection reset with {} unacknowledged and {} unsent octets",
+                unacked, unsent
+            ),
+            CloseReason::TimedOut => write!(f, "connection timed out"),
+            CloseReason::ReadTimedOut => write!(f, "read timed out"),
+            CloseReason::WriteTimedOut => write!(f, "write timed out"),
+            CloseReason::TooSlow => write!(f, "receive rate too low"),
+            CloseReason::Aborted => write!(f, "connection aborted"),
+            CloseReason::LocalAddressRemoved => write!(f, "local address removed"),
+        }
+    }
+}
+
+/// The state of an established connection, as exported by [`Socket::export_connection`]
+/// and imported by [`Socket::import_connection`].
+///
+/// This is everything needed to resume the connection on another socket, except for the data
+/// in the socket buffers, which is exported separately.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ConnectionState {
+    /// The local endpoint.
+    pub local: IpEndpoint,
+    /// The remote endpoint.
+    pub remote: IpEndpoint,
+    /// The state of the connection, either `Established`, or `CloseWait` if the remote
+    /// endpoint has closed its half of the connection.
+    pub state: State,
+    /// The sequence number of the first octet in the transmit buffer, i.e. the oldest octet
+    /// not acknowledged by the remote endpoint.
+    pub send_unacked: TcpSeqNumber,
+    /// The sequence number right after the data received and acknowledged, including the FIN
+    /// if the remote endpoint has closed its half of the connection.
+    pub recv_next: TcpSeqNumber,
+    /// The window advertised by the remote endpoint, relative to `send_unacked`, in octets.
+    pub send_window: usize,
+    /// The window scale of the remote endpoint, or `None` if it doesn't support window scaling.
+    pub send_window_scale: Option<u8>,
+    /// The window scale advertised to the remote endpoint.
+    pub recv_window_scale: u8,
+    /// The last window advertised to the remote endpoint, in octets.
+    pub recv_window: usize,
+    /// Whether the remote endpoint supports selective acknowledgements.
+    pub sack_permitted: bool,
+    /// The maximum segment size of the remote endpoint.
+    pub remote_mss: usize,
+    /// The smoothed round-trip time.
+    pub rtt: Duration,
+    /// The round-trip time variation.
+    pub rtt_deviation: Duration,
+}
+
 // Conservative initial RTT estimate.
 const RTTE_INITIAL_RTT: u32 = 300;
 const RTTE_INITIAL_DEV: u32 = 100;
@@ -200,6 +334,127 @@ impl RttEstimator {
     }
 }
 
+// The bottleneck bandwidth is the maximum delivery rate over this many round trips.
+const RATE_BW_WINDOW_ROUNDS: u32 = 10;
+// The minimum RTT is taken over this period.
+const RATE_MIN_RTT_WINDOW: Duration = Duration::from_secs(10);
+
+/// The state of the connection when the segment sampled by the delivery rate estimator was sent.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+struct RateSample {
+    end_seq: TcpSeqNumber,
+    sent_time: Instant,
+    delivered: u64,
+    delivered_time: Instant,
+    first_sent_time: Instant,
+    app_limited: bool,
+}
+
+/// Delivery rate estimation, as described in draft-cheng-iccrg-delivery-rate-estimation.
+///
+/// Like the RTT estimator, and so that no per-segment state is needed, only a single segment
+/// is sampled at a time, which results in about one sample per round trip.
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+struct RateEstimator {
+    /// Total number of octets acknowledged.
+    delivered: u64,
+    /// The time `delivered` was last updated.
+    delivered_time: Option<Instant>,
+    /// The send time of the segment acknowledged at `delivered_time`.
+    first_sent_time: Option<Instant>,
+    sample: Option<RateSample>,
+    max_seq_sent: Option<TcpSeqNumber>,
+    /// Number of samples taken; a round trip passes between two samples.
+    round: u32,
+    /// The maximum delivery rate in octets per second, and the round it was sampled in.
+    max_bw: Option<(u64, u32)>,
+    /// The minimum RTT, and the time it was sampled at.
+    min_rtt: Option<(Duration, Instant)>,
+}
+
+impl RateEstimator {
+    fn on_send(
+        &mut self,
+        timestamp: Instant,
+        end_seq: TcpSeqNumber,
+        data_in_flight: bool,
+        app_limited: bool,
+    ) {
+        if matches!(self.max_seq_sent, Some(max_seq_sent) if end_seq <= max_seq_sent) {
+            // Acknowledgements of retransmitted data are ambiguous.
+            return;
+        }
+        self.max_seq_sent = Some(end_seq);
+
+        // Time spent idle isn't counted towards the sending interval.
+        if !data_in_flight || self.delivered_time.is_none() {
+            self.first_sent_time = Some(timestamp);
+            self.delivered_time = Some(timestamp);
+        }
+        if self.sample.is_none() {
+            self.sample = Some(RateSample {
+                end_seq,
+                sent_time: timestamp,
+                delivered: self.delivered,
+                delivered_time: self.delivered_time.unwrap_or(timestamp),
+                first_sent_time: self.first_sent_time.unwrap_or(timestamp),
+                app_limited,
+            });
+        }
+    }
+
+    fn on_ack(&mut self, timestamp: Instant, ack_number: TcpSeqNumber, ack_len: usize) {
+        if ack_len == 0 {
+            return;
+        }
+        self.delivered += ack_len as u64;
+        self.delivered_time = Some(timestamp);
+
+        let sample = match self.sample {
+            Some(sample) if ack_number >= sample.end_seq => sample,
+            _ => return,
+        };
+        self.sample = None;
+        self.first_sent_time = Some(sample.sent_time);
+
+        let rtt = timestamp - sample.sent_time;
+        match self.min_rtt {
+            Some((min_rtt, sampled_at))
+                if rtt > min_rtt && timestamp < sampled_at + RATE_MIN_RTT_WINDOW => {}
+            _ => self.min_rtt = Some((rtt, timestamp)),
+        }
+
+        // The acknowledgements can be compressed, or the segments sent in a burst,
+        // so the longest of both intervals is the most conservative.
+        let interval = cmp::max(
+            sample.sent_time - sample.first_sent_time,
+            timestamp - sample.delivered_time,
+        );
+        if interval == Duration::ZERO {
+            return;
+        }
+        let bw = (self.delivered - sample.delivered) * 1_000_000 / interval.total_micros();
+        self.round = self.round.wrapping_add(1);
+        tcp_trace!("rate: sample={:?} rtt={:?}", bw, rtt);
+
+        // A sample taken while the application didn't have enough data to send only shows
+        // a lower bound of the bandwidth.
+        match self.max_bw {
+            Some((max_bw, round))
+                if bw < max_bw
+                    && (sample.app_limited
+                        || self.round.wrapping_sub(round) < RATE_BW_WINDOW_ROUNDS) => {}
+            _ => self.max_bw = Some((bw, self.round)),
+        }
+    }
+
+    fn on_retransmit(&mut self) {
+        self.sample = None;
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 enum Timer {
@@ -217,6 +472,7 @@ enum Timer {
 }
 
 const ACK_DELAY_DEFAULT: Duration = Duration::from_millis(10);
+const QUICKACK_SEGMENTS_DEFAULT: u8 = 16;
 const CLOSE_DELAY: Duration = Duration::from_millis(10_000);
 
 impl Timer {
@@ -336,6 +592,45 @@ enum AckDelayTimer {
     Immediate,
 }
 
+/// Number of duplicate ACKs, or of SACKed segments above a hole, after which that hole
+/// is considered lost.
+const DUP_THRESH: usize = 3;
+/// Number of disjoint SACKed ranges remembered by the sender.
+const SACK_SCOREBOARD_LEN: usize = 4;
+
+/// State of a SACK-based loss recovery, as described in RFC 6675.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+struct SackRecovery {
+    /// The highest sequence number sent when recovery started. Recovery ends once it is ACKed.
+    recovery_point: TcpSeqNumber,
+    /// The highest sequence number retransmitted during this recovery.
+    high_rxt: TcpSeqNumber,
+    /// Whether the segment at the cumulative ACK point is still to be retransmitted,
+    /// regardless of whether the scoreboard considers it lost.
+    rexmit_first: bool,
+    /// The sender maximum segment size, used to determine which holes are lost.
+    mss: usize,
+}
+
+/// A range of unacknowledged octets not covered by any SACK block.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct SackHole {
+    start: TcpSeqNumber,
+    end: TcpSeqNumber,
+    /// Number of SACK blocks above the hole.
+    blocks_above: usize,
+    /// Number of SACKed octets above the hole.
+    sacked_above: usize,
+}
+
+impl SackHole {
+    /// Whether the octets in the hole are presumed lost, per IsLost() from RFC 6675.
+    fn is_lost(&self, mss: usize) -> bool {
+        self.blocks_above >= DUP_THRESH || self.sacked_above > (DUP_THRESH - 1) * mss
+    }
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 struct Tuple {
@@ -349,32 +644,473 @@ impl Display for Tuple {
     }
 }
 
+/// The largest possible TCP header, with 40 octets of options.
+#[cfg(feature = "socket-tcp-ao")]
+const TCP_MAX_HEADER_LEN: usize = 60;
+
+/// A MAC algorithm of the TCP Authentication Option, as specified in [RFC 5926].
+///
+/// [RFC 5926]: https://tools.ietf.org/html/rfc5926
+#[cfg(feature = "socket-tcp-ao")]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AuthAlgorithm {
+    /// HMAC-SHA-1-96, with traffic keys derived by KDF_HMAC_SHA1.
+    HmacSha1,
+    /// AES-128-CMAC-96, with traffic keys derived by KDF_AES_128_CMAC.
+    Aes128Cmac,
+}
+
+#[cfg(feature = "socket-tcp-ao")]
+impl AuthAlgorithm {
+    /// Compute the MAC of the concatenation of the `message` parts, truncated to 96 bits,
+    /// with the traffic key derived from a master key and the connection context.
+    fn mac(self, master_key: &[u8], context: &[u8], message: &[&[u8]]) -> [u8; TCP_AUTH_MAC_LEN] {
+        // RFC 5926 § 3.1.1: the input is i || Label || Context || Output_Length, where the
+        // counter i is always 1, since a single iteration yields enough key material.
+        const LABEL: &[u8] = b"\x01TCP-AO";
+        let mut truncated = [0; TCP_AUTH_MAC_LEN];
+        match self {
+            AuthAlgorithm::HmacSha1 => {
+                let mut kdf = HmacSha1::new(master_key);
+                kdf.update(LABEL);
+                kdf.update(context);
+                kdf.update(&(SHA1_LEN as u16 * 8).to_be_bytes());
+                let mut mac = HmacSha1::new(&kdf.finalize());
+                for part in message {
+                    mac.update(part);
+                }
+                truncated.copy_from_slice(&mac.finalize()[..TCP_AUTH_MAC_LEN]);
+            }
+            AuthAlgorithm::Aes128Cmac => {
+                // Master keys of other lengths are first condensed to an AES key.
+                let mut key = [0; AES_BLOCK_LEN];
+                if master_key.len() == AES_BLOCK_LEN {
+                    key.copy_from_slice(master_key);
+                } else {
+                    let mut cmac = Aes128Cmac::new(&[0; AES_BLOCK_LEN]);
+                    cmac.update(master_key);
+                    key = cmac.finalize();
+                }
+                let mut kdf = Aes128Cmac::new(&key);
+                kdf.update(LABEL);
+                kdf.update(context);
+                kdf.update(&(AES_BLOCK_LEN as u16 * 8).to_be_bytes());
+                let mut mac = Aes128Cmac::new(&kdf.finalize());
+                for part in message {
+                    mac.update(part);
+                }
+                truncated.copy_from_slice(&mac.finalize()[..TCP_AUTH_MAC_LEN]);
+            }
+        }
+        truncated
+    }
+}
+
+/// A master key tuple of the TCP Authentication Option, as described in [RFC 5925].
+///
+/// Segments sent with the key carry its `send_id`, and segments received with it carry its
+/// `recv_id`; the remote endpoint has the same key configured with the two IDs swapped.
+///
+/// [RFC 5925]: https://tools.ietf.org/html/rfc5925
+#[cfg(feature = "socket-tcp-ao")]
+#[derive(Clone, Copy)]
+pub struct AuthKey {
+    send_id: u8,
+    recv_id: u8,
+    algorithm: AuthAlgorithm,
+    key: [u8; AuthKey::MAX_LEN],
+    key_len: u8,
+}
+
+#[cfg(feature = "socket-tcp-ao")]
+impl AuthKey {
+    /// The maximum length of a master key, in octets.
+    pub const MAX_LEN: usize = 80;
+
+    /// Create a key tuple out of a master key.
+    ///
+    /// Returns `Err(AuthError::InvalidKey)` if the master key is empty or longer than
+    /// [MAX_LEN](#associatedconstant.MAX_LEN) octets.
+    pub fn new(
+        send_id: u8,
+        recv_id: u8,
+        algorithm: AuthAlgorithm,
+        master_key: &[u8],
+    ) -> Result<AuthKey, AuthError> {
+        if master_key.is_empty() || master_key.len() > Self::MAX_LEN {
+            return Err(AuthError::InvalidKey);
+        }
+        let mut key = [0; Self::MAX_LEN];
+        key[..master_key.len()].copy_from_slice(master_key);
+        Ok(AuthKey {
+            send_id,
+            recv_id,
+            algorithm,
+            key,
+            key_len: master_key.len() as u8,
+        })
+    }
+
+    /// Return the ID sent in segments authenticated with this key.
+    pub fn send_id(&self) -> u8 {
+        self.send_id
+    }
+
+    /// Return the ID the remote endpoint sends in segments authenticated with this key.
+    pub fn recv_id(&self) -> u8 {
+        self.recv_id
+    }
+
+    /// Return the MAC algorithm.
+    pub fn algorithm(&self) -> AuthAlgorithm {
+        self.algorithm
+    }
+
+    fn master_key(&self) -> &[u8] {
+        &self.key[..self.key_len as usize]
+    }
+
+    /// Compute the MAC of a segment from `src_addr` to `dst_addr`, given its header with the
+    /// checksum and MAC zeroed.
+    #[allow(clippy::too_many_arguments)]
+    fn segment_mac(
+        &self,
+        src_addr: IpAddress,
+        dst_addr: IpAddress,
+        src_isn: TcpSeqNumber,
+        dst_isn: TcpSeqNumber,
+        sne: u32,
+        header: &[u8],
+        payload: &[u8],
+    ) -> [u8; TCP_AUTH_MAC_LEN] {
+        let packet = TcpPacket::new_unchecked(header);
+
+        // RFC 5925 § 5.2: the context is the connection's socket pair and the ISNs, in the
+        // direction of the segment.
+        let mut context = [0; 44];
+        let mut len = 0;
+        for field in [
+            src_addr.as_bytes(),
+            dst_addr.as_bytes(),
+            &packet.src_port().to_be_bytes(),
+            &packet.dst_port().to_be_bytes(),
+            &(src_isn.0 as u32).to_be_bytes(),
+            &(dst_isn.0 as u32).to_be_bytes(),
+        ]
+        .iter()
+        {
+            context[len..len + field.len()].copy_from_slice(field);
+            len += field.len();
+        }
+
+        // RFC 5925 § 5.1: the MAC covers the sequence number extension, the pseudo-header,
+        // the header including options, and the payload.
+        let tcp_len = header.len() + payload.len();
+        let mut pseudo_header = [0; 8];
+        let pseudo_header = if src_addr.as_bytes().len() == 4 {
+            pseudo_header[1] = IpProtocol::Tcp.into();
+            pseudo_header[2..4].copy_from_slice(&(tcp_len as u16).to_be_bytes());
+            &pseudo_header[..4]
+        } else {
+            pseudo_header[..4].copy_from_slice(&(tcp_len as u32).to_be_bytes());
+            pseudo_header[7] = IpProtocol::Tcp.into();
+            &pseudo_header[..]
+        };
+        self.algorithm.mac(
+            self.master_key(),
+            &context[..len],
+            &[
+                &sne.to_be_bytes(),
+                src_addr.as_bytes(),
+                dst_addr.as_bytes(),
+                pseudo_header,
+                header,
+                payload,
+            ],
+        )
+    }
+}
+
+#[cfg(feature = "socket-tcp-ao")]
+impl fmt::Debug for AuthKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The master key is deliberately left out.
+        f.debug_struct("AuthKey")
+            .field("send_id", &self.send_id)
+            .field("recv_id", &self.recv_id)
+            .field("algorithm", &self.algorithm)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The high-order bits extending the sequence numbers of one direction of a connection.
+#[cfg(feature = "socket-tcp-ao")]
+#[derive(Debug, Clone, Copy, Default)]
+struct SeqExtension {
+    last_seq: TcpSeqNumber,
+    sne: u32,
+}
+
+#[cfg(feature = "socket-tcp-ao")]
+impl SeqExtension {
+    fn new(isn: TcpSeqNumber) -> SeqExtension {
+        SeqExtension {
+            last_seq: isn,
+            sne: 0,
+        }
+    }
+
+    /// Return the extension of `seq`, which may be somewhat older than the latest sequence
+    /// number seen.
+    fn get(&self, seq: TcpSeqNumber) -> u32 {
+        let wrapped = (seq.0 as u32) < (self.last_seq.0 as u32);
+        match (seq >= self.last_seq, wrapped) {
+            (true, true) => self.sne.wrapping_add(1),
+            (false, false) => self.sne.wrapping_sub(1),
+            _ => self.sne,
+        }
+    }
+
+    fn update(&mut self, seq: TcpSeqNumber) {
+        if seq > self.last_seq {
+            self.sne = self.get(seq);
+            self.last_seq = seq;
+        }
+    }
+}
+
+/// The TCP Authentication Option state of a socket.
+#[cfg(feature = "socket-tcp-ao")]
+#[derive(Debug)]
+struct AuthState<'a> {
+    keys: ManagedSlice<'a, AuthKey>,
+    /// Index of the key that outgoing segments are authenticated with.
+    current: usize,
+    /// Receive ID of the key the remote endpoint is asked to switch to, if any.
+    rnext: Option<u8>,
+    local_isn: TcpSeqNumber,
+    remote_isn: TcpSeqNumber,
+    send_sne: SeqExtension,
+    recv_sne: SeqExtension,
+}
+
+#[cfg(feature = "socket-tcp-ao")]
+impl<'a> AuthState<'a> {
+    fn new() -> AuthState<'a> {
+        AuthState {
+            keys: ManagedSlice::Borrowed(&mut []),
+            current: 0,
+            rnext: None,
+            local_isn: TcpSeqNumber::default(),
+            remote_isn: TcpSeqNumber::default(),
+            send_sne: SeqExtension::default(),
+            recv_sne: SeqExtension::default(),
+        }
+    }
+
+    /// Forget the state of the connection, but not the keys.
+    fn reset(&mut self) {
+        self.local_isn = TcpSeqNumber::default();
+        self.remote_isn = TcpSeqNumber::default();
+        self.send_sne = SeqExtension::default();
+        self.recv_sne = SeqExtension::default();
+    }
+
+    fn is_enabled(&self) -> bool {
+        !self.keys.is_empty()
+    }
+
+    /// Compute the authentication option of an outgoing segment, or return None if
+    /// authentication is disabled.
+    fn sign(&mut self, ip_repr: &IpRepr, repr: &TcpRepr) -> Option<TcpAuthOption> {
+        let key = *self.keys.get(self.current)?;
+        if repr.control == TcpControl::Syn {
+            self.local_isn = repr.seq_number;
+            self.send_sne = SeqExtension::new(repr.seq_number);
+        }
+        // RFC 5925 § 5.2: the remote ISN is zero in a SYN, since it isn't known yet.
+        let remote_isn = match (repr.control, repr.ack_number) {
+            (TcpControl::Syn, None) => TcpSeqNumber(0),
+            _ => self.remote_isn,
+        };
+        self.send_sne.update(repr.seq_number);
+
+        let mut option = TcpAuthOption {
+            key_id: key.send_id,
+            rnext_key_id: self.rnext.unwrap_or(key.recv_id),
+            mac: [0; TCP_AUTH_MAC_LEN],
+        };
+        let mut header = [0; TCP_MAX_HEADER_LEN];
+        let signed_repr = TcpRepr {
+            auth: Some(option),
+            ..*repr
+        };
+        let header = header.get_mut(..signed_repr.header_len())?;
+        signed_repr.emit_header(&mut TcpPacket::new_unchecked(&mut *header));
+        option.mac = key.segment_mac(
+            ip_repr.src_addr(),
+            ip_repr.dst_addr(),
+            self.local_isn,
+            remote_isn,
+            self.send_sne.get(repr.seq_number),
+            header,
+            repr.payload,
+        );
+        Some(option)
+    }
+
+    /// Check the authentication option of an incoming segment, given the bytes `repr` was
+    /// parsed from.
+    ///
+    /// A valid segment may also ask for a different key to be used for outgoing segments.
+    fn verify(&mut self, ip_repr: &IpRepr, repr: &TcpRepr, segment: &[u8]) -> bool {
+        let option = match repr.auth {
+            Some(option) => option,
+            None => return false,
+        };
+        let key = match self.keys.iter().find(|key| key.recv_id == option.key_id) {
+            Some(key) => key,
+            None => return false,
+        };
+        let (remote_isn, recv_sne) = match repr.control {
+            TcpControl::Syn => (repr.seq_number, SeqExtension::new(repr.seq_number)),
+            _ => (self.remote_isn, self.recv_sne),
+        };
+        let local_isn = match (repr.control, repr.ack_number) {
+            (TcpControl::Syn, None) => TcpSeqNumber(0),
+            _ => self.local_isn,
+        };
+
+        // The MAC is computed with both the checksum and the MAC itself zeroed.
+        let mut header = [0; TCP_MAX_HEADER_LEN];
+        let header_len = TcpPacket::new_unchecked(segment).header_len() as usize;
+        let header = match header.get_mut(..header_len) {
+            Some(header) => header,
+            None => return false,
+        };
+        header.copy_from_slice(&segment[..header_len]);
+        TcpPacket::new_unchecked(&mut *header).set_checksum(0);
+        let mut offset = TCP_HEADER_LEN;
+        while offset < header.len() {
+            let (next, is_auth) = match TcpOption::parse(&header[offset..]) {
+                Ok((_, TcpOption::EndOfList)) => break,
+                Ok((rest, option)) => (
+                    header.len() - rest.len(),
+                    matches!(option, TcpOption::Authentication { .. }),
+                ),
+                Err(_) => return false,
+            };
+            if is_auth {
+                for byte in header[offset + 4..next].iter_mut() {
+                    *byte = 0
+                }
+            }
+            offset = next;
+        }
+
+        let mac = key.segment_mac(
+            ip_repr.src_addr(),
+            ip_repr.dst_addr(),
+            remote_isn,
+            local_isn,
+            recv_sne.get(repr.seq_number),
+            header,
+            repr.payload,
+        );
+        // Compare in constant time.
+        let diff = mac
+            .iter()
+            .zip(option.mac.iter())
+            .fold(0, |diff, (a, b)| diff | (a ^ b));
+        if diff != 0 {
+            return false;
+        }
+
+        self.remote_isn = remote_isn;
+        self.recv_sne = recv_sne;
+        self.recv_sne.update(repr.seq_number);
+        // RFC 5925 § 7.5.2: switch to the key the remote endpoint asks for, if we have it.
+        if let Some(index) = self
+            .keys
+            .iter()
+            .position(|key| key.send_id == option.rnext_key_id)
+        {
+            self.current = index;
+        }
+        true
+    }
+}
+
 /// A Transmission Control Protocol socket.
 ///
 /// A TCP socket may passively listen for connections or actively connect to another endpoint.
 /// Note that, for listening sockets, there is no "backlog"; to be able to simultaneously
 /// accept several connections, as many sockets must be allocated, or any new connection
 /// attempts will be reset.
+///
+/// Once closed, a socket can be reused for another connection by calling [listen] or
+/// [connect] again. This discards all state of the previous connection, including any data
+/// left in the buffers, but keeps the buffers themselves.
+///
+/// The socket never allocates memory. Segments received out of order are held in the free
+/// space of the receive buffer until the hole before them is filled, and the advertised window
+/// is exactly that free space; so the amount of reordering a connection can absorb without
+/// retransmissions is the size of the receive buffer. A socket created with
+/// [with_reassembly_buffer] reassembles the incoming data in a buffer of its own instead,
+/// which decouples that amount from the size of the receive buffer.
+///
+/// [listen]: #method.listen
+/// [connect]: #method.connect
+/// [with_reassembly_buffer]: #method.with_reassembly_buffer
 #[derive(Debug)]
 pub struct Socket<'a> {
     state: State,
     timer: Timer,
     rtte: RttEstimator,
+    rate: RateEstimator,
     assembler: Assembler,
     rx_buffer: SocketBuffer<'a>,
+    /// Holds the received data until it's moved to `rx_buffer`, if not empty.
+    reassembly_buffer: SocketBuffer<'a>,
     rx_fin_received: bool,
     tx_buffer: SocketBuffer<'a>,
     /// Interval after which, if no inbound packets are received, the connection is aborted.
     timeout: Option<Duration>,
     /// Interval at which keep-alive packets will be sent.
     keep_alive: Option<Duration>,
+    /// Interval after which, if no data is received while there is none left to read, the
+    /// connection is aborted.
+    read_timeout: Option<Duration>,
+    /// Interval after which, if none of the data in the transmit buffer is acknowledged, the
+    /// connection is aborted.
+    write_timeout: Option<Duration>,
+    /// Interval after which, if the handshake of a connection accepted in the LISTEN state
+    /// isn't completed, the socket listens again.
+    handshake_timeout: Option<Duration>,
+    /// Number of octets that must be received in every interval, or the connection is reset.
+    min_recv_rate: Option<(usize, Duration)>,
     /// The time-to-live (IPv4) or hop limit (IPv6) value used in outgoing packets.
     hop_limit: Option<u8>,
+    /// Number of octets subtracted from the MTU-derived MSS to leave room for encapsulation.
+    mss_overhead: usize,
+    /// Whether segments to destinations that aren't local are accepted.
+    transparent: bool,
+    /// Whether SYNs are answered with SYN cookies once the half-open limit is reached.
+    syn_cookies: bool,
     /// Address passed to listen(). Listen address is set when listen() is called and
     /// used every time the socket is reset back to the LISTEN state.
     listen_endpoint: IpListenEndpoint,
+    /// Number of ports following the listen port that are listened on as well,
+    /// when the socket listens on a port range.
+    listen_port_span: u16,
     /// Current 4-tuple (local and remote endpoints).
     tuple: Option<Tuple>,
+    /// Why the last connection was closed, if it wasn't shut down in an orderly way.
+    close_reason: Option<CloseReason>,
+    /// Keys and state of the TCP Authentication Option.
+    #[cfg(feature = "socket-tcp-ao")]
+    auth: AuthState<'a>,
     /// The sequence number corresponding to the beginning of the transmit buffer.
     /// I.e. an ACK(local_seq_no+n) packet removes n bytes from the transmit buffer.
     local_seq_no: TcpSeqNumber,
@@ -385,7 +1121,7 @@ pub struct Socket<'a> {
     /// I.e. in an idle socket, local_seq_no+tx_buffer.len().
     remote_last_seq: TcpSeqNumber,
     /// The last acknowledgement number sent.
-    /// I.e. in an idle socket, remote_seq_no+rx_buffer.len().
+    /// I.e. in an idle socket, remote_seq_no+rx_unread().
     remote_last_ack: Option<TcpSeqNumber>,
     /// The last window length sent.
     remote_last_win: u16,
@@ -403,6 +1139,14 @@ pub struct Socket<'a> {
     remote_mss: usize,
     /// The timestamp of the last packet received.
     remote_last_ts: Option<Instant>,
+    /// When the read timeout started counting, or `None` if it was (re)started since.
+    read_idle_since: Option<Instant>,
+    /// When the write timeout started counting, or `None` if it was (re)started since.
+    write_idle_since: Option<Instant>,
+    /// When the SYN that moved the socket into the SYN-RECEIVED state was received.
+    syn_received_at: Option<Instant>,
+    /// When the current minimum receive rate interval started, and the octets received since.
+    recv_rate_interval: Option<(Instant, usize)>,
     /// The sequence number of the last packet received, used for sACK
     local_rx_last_seq: Option<TcpSeqNumber>,
     /// The ACK number of the last packet received.
@@ -410,36 +1154,117 @@ pub struct Socket<'a> {
     /// The number of packets received directly after
     /// each other which have the same ACK number.
     local_rx_dup_acks: u8,
+    /// Ranges above local_seq_no reported as received by the remote through SACK,
+    /// sorted and disjoint.
+    sack_scoreboard: [Option<(TcpSeqNumber, TcpSeqNumber)>; SACK_SCOREBOARD_LEN],
+    /// The SACK-based loss recovery in progress, if any.
+    sack_recovery: Option<SackRecovery>,
 
     /// Duration for Delayed ACK. If None no ACKs will be delayed.
     ack_delay: Option<Duration>,
     /// Delayed ack timer. If set, packets containing exclusively
     /// ACK or window updates (ie, no data) won't be sent until expiry.
     ack_delay_timer: AckDelayTimer,
+    /// Whether ACKs are forced to be always (`Some(true)`) or never (`Some(false)`) sent
+    /// without delay, or follow the quick ACK window (`None`).
+    quickack: Option<bool>,
+    /// Number of segments acknowledged without delay after the connection is established,
+    /// or after a hole in the received data is filled.
+    quickack_segments: u8,
+    /// Number of segments left in the current quick ACK window.
+    quickack_remaining: u8,
 
     /// Used for rate-limiting: No more challenge ACKs will be sent until this instant.
     challenge_ack_timer: Instant,
 
     /// Nagle's Algorithm enabled.
     nagle: bool,
+    /// Whether segments smaller than MSS are held back until flushed.
+    cork: bool,
+    /// The end of the data queued when the socket was last flushed, until it is acknowledged.
+    flush_seq: Option<TcpSeqNumber>,
+
+    /// The congestion window. It only limits sending after the connection was idle, and
+    /// grows with slow start from there.
+    cwnd: usize,
+    /// Whether the congestion window is reset to the restart window after an idle period.
+    slow_start_after_idle: bool,
+    /// Whether a zero window is advertised regardless of the receive buffer occupancy.
+    rx_paused: bool,
+    /// The time data or flags were last sent, not counting keep-alive packets.
+    last_send_at: Option<Instant>,
+    /// The rate data is sent at, at most, in octets per second.
+    max_send_rate: Option<u64>,
+    /// When the next segment with data may be sent without exceeding the maximum send rate.
+    paced_until: Option<Instant>,
+    /// The connection and its sequence numbers when the invariants were last checked.
+    #[cfg(feature = "paranoid")]
+    checked_seq: Option<(Tuple, TcpSeqNumber, TcpSeqNumber)>,
 
     #[cfg(feature = "async")]
     rx_waker: WakerRegistration,
     #[cfg(feature = "async")]
     tx_waker: WakerRegistration,
+    #[cfg(feature = "async")]
+    window_waker: WakerRegistration,
 }
 
 const DEFAULT_MSS: usize = 536;
 
+/// The remote MSS values a SYN cookie can encode. The remote MSS is rounded down to one of them,
+/// and SYNs with a smaller MSS are not answered with a cookie.
+const SYN_COOKIE_MSS: [u16; 8] = [216, 536, 1200, 1220, 1360, 1400, 1440, 1460];
+/// How often the timestamp encoded in SYN cookies advances. A cookie is accepted for one to
+/// two periods after it was sent.
+const SYN_COOKIE_PERIOD: Duration = Duration::from_secs(64);
+/// The bits of a SYN cookie that hold its message authentication code.
+const SYN_COOKIE_MAC_MASK: u32 = 0x3f_ffff;
+
+/// The connection state that a SYN cookie encodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SynCookie {
+    mss: u16,
+    window_scale: Option<u8>,
+    sack_permitted: bool,
+}
+
 impl<'a> Socket<'a> {
-    #[allow(unused_comparisons)] // small usize platforms always pass rx_capacity check
     /// Create a socket using the given buffers.
     pub fn new<T>(rx_buffer: T, tx_buffer: T) -> Socket<'a>
     where
         T: Into<SocketBuffer<'a>>,
+    {
+        Socket::with_reassembly_buffer(rx_buffer, tx_buffer, SocketBuffer::new(&mut [][..]))
+    }
+
+    #[allow(unused_comparisons)] // small usize platforms always pass rx_capacity check
+    /// Create a socket using the given buffers, reassembling the incoming data in
+    /// `reassembly_buffer` rather than in the receive buffer.
+    ///
+    /// Segments are reassembled in the reassembly buffer, and the data received in order is
+    /// moved from there to the receive buffer as the receive buffer is read. That data is
+    /// acknowledged as soon as it is in order, so a small receive buffer can be paired with
+    /// a large reassembly buffer to absorb reordering without retransmissions.
+    ///
+    /// The advertised window is the free space in the reassembly buffer, plus what the receive
+    /// buffer has room for of the data waiting there. With an empty reassembly buffer, this
+    /// is the same as [new](#method.new).
+    pub fn with_reassembly_buffer<T, U>(
+        rx_buffer: T,
+        tx_buffer: T,
+        reassembly_buffer: U,
+    ) -> Socket<'a>
+    where
+        T: Into<SocketBuffer<'a>>,
+        U: Into<SocketBuffer<'a>>,
     {
         let (rx_buffer, tx_buffer) = (rx_buffer.into(), tx_buffer.into());
-        let rx_capacity = rx_buffer.capacity();
+        let reassembly_buffer = reassembly_buffer.into();
+        let rx_capacity = if reassembly_buffer.capacity() > 0 {
+            reassembly_buffer.capacity()
+        } else {
+            rx_buffer.capacity()
+        };
 
         // From RFC 1323:
         // [...] the above constraints imply that 2 * the max window size must be less
@@ -454,15 +1279,28 @@ impl<'a> Socket<'a> {
             state: State::Closed,
             timer: Timer::new(),
             rtte: RttEstimator::default(),
-            assembler: Assembler::new(rx_buffer.capacity()),
+            rate: RateEstimator::default(),
+            assembler: Assembler::new(rx_capacity),
             tx_buffer,
             rx_buffer,
+            reassembly_buffer,
             rx_fin_received: false,
             timeout: None,
             keep_alive: None,
+            read_timeout: None,
+            write_timeout: None,
+            handshake_timeout: None,
+            min_recv_rate: None,
             hop_limit: None,
+            mss_overhead: 0,
+            transparent: false,
+            syn_cookies: false,
             listen_endpoint: IpListenEndpoint::default(),
+            listen_port_span: 0,
             tuple: None,
+            close_reason: None,
+            #[cfg(feature = "socket-tcp-ao")]
+            auth: AuthState::new(),
             local_seq_no: TcpSeqNumber::default(),
             remote_seq_no: TcpSeqNumber::default(),
             remote_last_seq: TcpSeqNumber::default(),
@@ -474,18 +1312,39 @@ impl<'a> Socket<'a> {
             remote_has_sack: false,
             remote_mss: DEFAULT_MSS,
             remote_last_ts: None,
+            read_idle_since: None,
+            write_idle_since: None,
+            syn_received_at: None,
+            recv_rate_interval: None,
             local_rx_last_ack: None,
             local_rx_last_seq: None,
             local_rx_dup_acks: 0,
+            sack_scoreboard: [None; SACK_SCOREBOARD_LEN],
+            sack_recovery: None,
             ack_delay: Some(ACK_DELAY_DEFAULT),
             ack_delay_timer: AckDelayTimer::Idle,
+            quickack: None,
+            quickack_segments: QUICKACK_SEGMENTS_DEFAULT,
+            quickack_remaining: 0,
             challenge_ack_timer: Instant::from_secs(0),
             nagle: true,
+            cork: false,
+            flush_seq: None,
+            cwnd: usize::MAX,
+            slow_start_after_idle: true,
+            rx_paused: false,
+            last_send_at: None,
+            max_send_rate: None,
+            paced_until: None,
+            #[cfg(feature = "paranoid")]
+            checked_seq: None,
 
             #[cfg(feature = "async")]
             rx_waker: WakerRegistration::new(),
             #[cfg(feature = "async")]
             tx_waker: WakerRegistration::new(),
+            #[cfg(feature = "async")]
+            window_waker: WakerRegistration::new(),
         }
     }
 
@@ -524,6 +1383,19 @@ impl<'a> Socket<'a> {
         self.tx_waker.register(waker)
     }
 
+    /// Register a waker for the remote window reopening.
+    ///
+    /// The waker is woken when the remote endpoint advertises a nonzero window after having
+    /// advertised a zero one, or when the socket changes state. Unlike the send waker, it is
+    /// not woken by space becoming available in the transmit buffer; see also
+    /// [send_window](#method.send_window).
+    ///
+    /// The notes on [register_send_waker](#method.register_send_waker) apply here as well.
+    #[cfg(feature = "async")]
+    pub fn register_window_waker(&mut self, waker: &Waker) {
+        self.window_waker.register(waker)
+    }
+
     /// Return the timeout duration.
     ///
     /// See also the [set_timeout](#method.set_timeout) method.
@@ -531,6 +1403,34 @@ impl<'a> Socket<'a> {
         self.timeout
     }
 
+    /// Return the read timeout duration.
+    ///
+    /// See also the [set_read_timeout](#method.set_read_timeout) method.
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.read_timeout
+    }
+
+    /// Return the write timeout duration.
+    ///
+    /// See also the [set_write_timeout](#method.set_write_timeout) method.
+    pub fn write_timeout(&self) -> Option<Duration> {
+        self.write_timeout
+    }
+
+    /// Return the handshake timeout duration.
+    ///
+    /// See also the [set_handshake_timeout](#method.set_handshake_timeout) method.
+    pub fn handshake_timeout(&self) -> Option<Duration> {
+        self.handshake_timeout
+    }
+
+    /// Return the minimum receive rate, as a number of octets per interval.
+    ///
+    /// See also the [set_min_recv_rate](#method.set_min_recv_rate) method.
+    pub fn min_recv_rate(&self) -> Option<(usize, Duration)> {
+        self.min_recv_rate
+    }
+
     /// Return the ACK delay duration.
     ///
     /// See also the [set_ack_delay](#method.set_ack_delay) method.
@@ -538,6 +1438,21 @@ impl<'a> Socket<'a> {
         self.ack_delay
     }
 
+    /// Return whether ACKs are forced to be sent without delay, or never to be.
+    ///
+    /// `None` means that the quick ACK window applies.
+    /// See also the [set_quickack](#method.set_quickack) method.
+    pub fn quickack(&self) -> Option<bool> {
+        self.quickack
+    }
+
+    /// Return how many segments are acknowledged without delay in a quick ACK window.
+    ///
+    /// See also the [set_quickack_segments](#method.set_quickack_segments) method.
+    pub fn quickack_segments(&self) -> u8 {
+        self.quickack_segments
+    }
+
     /// Return whether Nagle's Algorithm is enabled.
     ///
     /// See also the [set_nagle_enabled](#method.set_nagle_enabled) method.
@@ -551,12 +1466,57 @@ impl<'a> Socket<'a> {
     ///
     #[inline]
     fn scaled_window(&self) -> u16 {
+        if self.rx_paused {
+            return 0;
+        }
         cmp::min(
-            self.rx_buffer.window() >> self.remote_win_shift as usize,
+            self.rx_window() >> self.remote_win_shift as usize,
             (1 << 16) - 1,
         ) as u16
     }
 
+    /// Return the buffer the incoming data is reassembled in: the reassembly buffer, unless
+    /// it's empty, and the receive buffer otherwise.
+    fn window_buffer(&self) -> &SocketBuffer<'a> {
+        if self.reassembly_buffer.capacity() > 0 {
+            &self.reassembly_buffer
+        } else {
+            &self.rx_buffer
+        }
+    }
+
+    fn window_buffer_mut(&mut self) -> &mut SocketBuffer<'a> {
+        if self.reassembly_buffer.capacity() > 0 {
+            &mut self.reassembly_buffer
+        } else {
+            &mut self.rx_buffer
+        }
+    }
+
+    /// Return how many more octets can be accepted from the remote endpoint, i.e. the window
+    /// before scaling.
+    fn rx_window(&self) -> usize {
+        let pending = cmp::min(self.reassembly_buffer.len(), self.rx_buffer.window());
+        self.window_buffer().window() + pending
+    }
+
+    /// Return how many octets were received in order but not read yet.
+    fn rx_unread(&self) -> usize {
+        self.rx_buffer.len() + self.reassembly_buffer.len()
+    }
+
+    /// Move the data received in order from the reassembly buffer to the receive buffer,
+    /// as far as there is room for it.
+    fn deliver_reassembled(&mut self) {
+        let rx_buffer = &mut self.rx_buffer;
+        while !self.reassembly_buffer.is_empty() && !rx_buffer.is_full() {
+            self.reassembly_buffer.dequeue_many_with(|data| {
+                let size = rx_buffer.enqueue_slice(data);
+                (size, ())
+            });
+        }
+    }
+
     /// Set the timeout duration.
     ///
     /// A socket with a timeout duration set will abort the connection if either of the following
@@ -572,32 +1532,212 @@ impl<'a> Socket<'a> {
         self.timeout = duration
     }
 
-    /// Set the ACK delay duration.
+    /// Set the read timeout duration.
     ///
-    /// By default, the ACK delay is set to 10ms.
-    pub fn set_ack_delay(&mut self, duration: Option<Duration>) {
-        self.ack_delay = duration
+    /// A socket with a read timeout set will abort the connection, with the
+    /// [ReadTimedOut](enum.CloseReason.html#variant.ReadTimedOut) reason, if the remote endpoint
+    /// sends no data for the specified duration while the receive buffer is empty, i.e. while
+    /// the application is waiting for data. Out of order data, which can't be received yet,
+    /// and segments without data, such as keep-alives, don't count.
+    ///
+    /// The timeout only applies to established connections that the remote endpoint hasn't
+    /// closed yet. Unlike the [timeout](#method.set_timeout), it catches peers that are alive
+    /// but make no progress.
+    pub fn set_read_timeout(&mut self, duration: Option<Duration>) {
+        self.read_timeout = duration
     }
 
-    /// Enable or disable Nagle's Algorithm.
+    /// Set the write timeout duration.
     ///
-    /// Also known as "tinygram prevention". By default, it is enabled.
-    /// Disabling it is equivalent to Linux's TCP_NODELAY flag.
+    /// A socket with a write timeout set will abort the connection, with the
+    /// [WriteTimedOut](enum.CloseReason.html#variant.WriteTimedOut) reason, if the remote
+    /// endpoint acknowledges none of the data in the transmit buffer for the specified
+    /// duration, for example because it keeps its receive window closed.
     ///
-    /// When enabled, Nagle's Algorithm prevents sending segments smaller than MSS if
-    /// there is data in flight (sent but not acknowledged). In other words, it ensures
-    /// at most only one segment smaller than MSS is in flight at a time.
+    /// The timeout only applies to established connections, and doesn't run while the
+    /// transmit buffer is empty.
+    pub fn set_write_timeout(&mut self, duration: Option<Duration>) {
+        self.write_timeout = duration
+    }
+
+    /// Set the handshake timeout duration.
     ///
-    /// It ensures better network utilization by preventing sending many very small packets,
-    /// at the cost of increased latency in some situations, particularly when the remote peer
-    /// has ACK delay enabled.
-    pub fn set_nagle_enabled(&mut self, enabled: bool) {
-        self.nagle = enabled
+    /// A listening socket with a handshake timeout set gives up on a connection that doesn't
+    /// complete the three-way handshake within the specified duration of its SYN, and goes
+    /// back to the LISTEN state without notifying the remote endpoint. This keeps clients
+    /// that never answer the SYN-ACK, or spoof their address, from tying up the socket.
+    ///
+    /// See also [InterfaceBuilder::half_open_limit].
+    ///
+    /// [InterfaceBuilder::half_open_limit]: ../../iface/struct.InterfaceBuilder.html#method.half_open_limit
+    pub fn set_handshake_timeout(&mut self, duration: Option<Duration>) {
+        self.handshake_timeout = duration
     }
 
-    /// Return the keep-alive interval.
+    /// Set the minimum receive rate, as `(octets, interval)`.
     ///
-    /// See also the [set_keep_alive](#method.set_keep_alive) method.
+    /// A socket with a minimum receive rate set resets the connection, with the
+    /// [TooSlow](enum.CloseReason.html#variant.TooSlow) reason, if the remote endpoint sends
+    /// less than `octets` octets of data in any `interval`, counting from when the connection
+    /// is established. An interval in which the receive buffer has no room for the missing
+    /// octets doesn't count against the remote endpoint.
+    ///
+    /// The rate only applies while the remote endpoint may still send data. Its first interval
+    /// also bounds the time a client may take to send a request: requiring 512 octets per 5
+    /// seconds rejects a client that trickles in a request one octet at a time.
+    ///
+    /// # Panics
+    /// This function panics if the interval is zero.
+    pub fn set_min_recv_rate(&mut self, rate: Option<(usize, Duration)>) {
+        if let Some((_, interval)) = rate {
+            assert!(interval != Duration::ZERO, "the interval must not be zero");
+        }
+        self.min_recv_rate = rate;
+        self.recv_rate_interval = None;
+    }
+
+    /// Set the ACK delay duration.
+    ///
+    /// By default, the ACK delay is set to 10ms.
+    pub fn set_ack_delay(&mut self, duration: Option<Duration>) {
+        self.ack_delay = duration
+    }
+
+    /// Force ACKs to be always, or never, sent without delay.
+    ///
+    /// By default, the segments received right after the connection is established, or right
+    /// after a hole in the received data is filled, are acknowledged without delay, so that
+    /// the peer's congestion window grows quickly during slow start; the ACK delay then applies
+    /// again. This is similar to Linux's quick ACK mode. Passing `Some(true)` disables the
+    /// ACK delay altogether, passing `Some(false)` applies it even within the quick ACK window,
+    /// and passing `None` restores the default behavior.
+    pub fn set_quickack(&mut self, quickack: Option<bool>) {
+        self.quickack = quickack
+    }
+
+    /// Set how many segments are acknowledged without delay in a quick ACK window.
+    ///
+    /// By default, 16 segments are. See also [set_quickack](#method.set_quickack).
+    pub fn set_quickack_segments(&mut self, segments: u8) {
+        self.quickack_segments = segments
+    }
+
+    /// Enable or disable Nagle's Algorithm.
+    ///
+    /// Also known as "tinygram prevention". By default, it is enabled.
+    /// Disabling it is equivalent to Linux's TCP_NODELAY flag.
+    ///
+    /// When enabled, Nagle's Algorithm prevents sending segments smaller than MSS if
+    /// there is data in flight (sent but not acknowledged). In other words, it ensures
+    /// at most only one segment smaller than MSS is in flight at a time.
+    ///
+    /// It ensures better network utilization by preventing sending many very small packets,
+    /// at the cost of increased latency in some situations, particularly when the remote peer
+    /// has ACK delay enabled.
+    pub fn set_nagle_enabled(&mut self, enabled: bool) {
+        self.nagle = enabled
+    }
+
+    /// Return whether the socket is corked.
+    ///
+    /// See also the [set_cork](#method.set_cork) method.
+    pub fn cork(&self) -> bool {
+        self.cork
+    }
+
+    /// Cork or uncork the socket.
+    ///
+    /// Equivalent to Linux's TCP_CORK flag. By default, the socket is not corked.
+    ///
+    /// While corked, the socket only sends full segments, whether or not there is data in
+    /// flight, so that e.g. a header and a body queued with separate calls go out together.
+    /// The data left over is sent once the socket is [flushed](#method.flush) or uncorked,
+    /// both of which send it regardless of Nagle's Algorithm, or when the socket is
+    /// [closed](#method.close). Corking takes precedence over disabling Nagle's Algorithm.
+    pub fn set_cork(&mut self, cork: bool) {
+        if self.cork && !cork {
+            self.flush();
+        }
+        self.cork = cork
+    }
+
+    /// Return the maximum send rate, in octets per second.
+    ///
+    /// See also the [set_max_send_rate](#method.set_max_send_rate) method.
+    pub fn max_send_rate(&self) -> Option<u64> {
+        self.max_send_rate
+    }
+
+    /// Set the maximum send rate, in octets per second.
+    ///
+    /// A socket with a maximum send rate set paces its transmission: after sending a segment
+    /// with data, it waits for as long as sending that data takes at the configured rate
+    /// before sending the next one, retransmissions included. The data that doesn't go out
+    /// yet stays in the transmit buffer. Acknowledgements and segments with only flags are
+    /// not held back. By default, the send rate is unlimited.
+    ///
+    /// # Panics
+    /// This function panics if the rate is zero.
+    pub fn set_max_send_rate(&mut self, rate: Option<u64>) {
+        assert!(rate != Some(0), "the rate must not be zero");
+        self.max_send_rate = rate;
+        if rate.is_none() {
+            self.paced_until = None;
+        }
+    }
+
+    /// Send all of the data queued so far right away, even in segments smaller than MSS.
+    ///
+    /// This overrides both corking and Nagle's Algorithm until the data currently in the
+    /// transmit buffer is sent. Data queued afterwards may share its last segment, but is
+    /// otherwise held back again.
+    pub fn flush(&mut self) {
+        if !self.tx_buffer.is_empty() {
+            self.flush_seq = Some(self.local_seq_no + self.tx_buffer.len());
+        }
+    }
+
+    /// Return whether slow start is restarted after an idle period.
+    ///
+    /// See also the [set_slow_start_after_idle](#method.set_slow_start_after_idle) method.
+    pub fn slow_start_after_idle(&self) -> bool {
+        self.slow_start_after_idle
+    }
+
+    /// Enable or disable slow start after an idle period, as described in RFC 5681.
+    ///
+    /// When enabled, if nothing but keep-alive packets was sent for longer than the
+    /// retransmission timeout, the data sent after that is limited to the restart window
+    /// of a few segments, which then doubles every round trip. This avoids sending a whole
+    /// window at once on a path whose conditions may have changed. By default, it is enabled.
+    /// Disabling it is equivalent to clearing Linux's `tcp_slow_start_after_idle` setting.
+    pub fn set_slow_start_after_idle(&mut self, enabled: bool) {
+        self.slow_start_after_idle = enabled
+    }
+
+    /// Return whether the receive window is paused.
+    ///
+    /// See also the [set_recv_window_paused](#method.set_recv_window_paused) method.
+    pub fn recv_window_paused(&self) -> bool {
+        self.rx_paused
+    }
+
+    /// Pause or resume the receive window.
+    ///
+    /// While paused, the socket advertises a zero window no matter how much space is left in
+    /// the receive buffer, which stops the remote endpoint from sending more data; a window
+    /// update announcing this is sent right away. Data the remote endpoint sent before learning
+    /// about the pause is still accepted, as long as it fits into the receive buffer.
+    /// Resuming sends a window update with the actual window, also right away, so that the
+    /// remote endpoint doesn't have to wait for its zero window probe to find out.
+    /// By default, the receive window is not paused.
+    pub fn set_recv_window_paused(&mut self, paused: bool) {
+        self.rx_paused = paused
+    }
+
+    /// Return the keep-alive interval.
+    ///
+    /// See also the [set_keep_alive](#method.set_keep_alive) method.
     pub fn keep_alive(&self) -> Option<Duration> {
         self.keep_alive
     }
@@ -614,6 +1754,10 @@ impl<'a> Socket<'a> {
     ///
     /// The keep-alive functionality together with the timeout functionality allows to react
     /// to these error conditions.
+    ///
+    /// The interval is counted from the last segment sent or received, whether or not it
+    /// carried data, so the probes are sent on connections the application neither reads from
+    /// nor writes to as well.
     pub fn set_keep_alive(&mut self, interval: Option<Duration>) {
         self.keep_alive = interval;
         if self.keep_alive.is_some() {
@@ -650,6 +1794,146 @@ impl<'a> Socket<'a> {
         self.hop_limit = hop_limit
     }
 
+    /// Return the number of octets subtracted from the MSS to account for tunnel overhead.
+    ///
+    /// See also the [set_mss_overhead](#method.set_mss_overhead) method
+    pub fn mss_overhead(&self) -> usize {
+        self.mss_overhead
+    }
+
+    /// Set the number of octets subtracted from the MSS to account for tunnel overhead.
+    ///
+    /// When traffic is encapsulated below the device (e.g. by WireGuard or IPsec), the MSS
+    /// derived from the interface MTU is too large for the inner segments. The overhead set
+    /// here is subtracted from both the MSS advertised in the SYN and the size of emitted
+    /// segments. By default, no overhead is subtracted.
+    ///
+    /// An overhead that would take the MSS below 536 octets, the default MSS, is limited
+    /// so that the MSS stays at 536 octets.
+    pub fn set_mss_overhead(&mut self, overhead: usize) {
+        self.mss_overhead = overhead
+    }
+
+    /// Return whether the socket is in transparent mode.
+    ///
+    /// See also the [set_transparent](#method.set_transparent) method
+    pub fn transparent(&self) -> bool {
+        self.transparent
+    }
+
+    /// Enable or disable transparent mode, similar to `IP_TRANSPARENT`.
+    ///
+    /// In transparent mode, a listening socket also accepts connections to addresses that are
+    /// not assigned to the interface, such as traffic redirected to the interface by a
+    /// transparent proxy setup. Combined with [listen_range](#method.listen_range) and no
+    /// listen address, it intercepts connections to any destination within the port range.
+    /// The original destination of an accepted connection is its
+    /// [local_endpoint](#method.local_endpoint), and the segments of the connection are sent
+    /// from it.
+    ///
+    /// Sockets that are not in transparent mode never see these connections, and segments that
+    /// no transparent socket accepts are dropped silently rather than answered with a reset.
+    pub fn set_transparent(&mut self, transparent: bool) {
+        self.transparent = transparent
+    }
+
+    /// Return whether the socket answers SYNs with SYN cookies.
+    ///
+    /// See also the [set_syn_cookies](#method.set_syn_cookies) method.
+    pub fn syn_cookies(&self) -> bool {
+        self.syn_cookies
+    }
+
+    /// Enable or disable SYN cookies.
+    ///
+    /// Once the [half-open limit] of the interface is reached, a listening socket with SYN
+    /// cookies enabled still answers SYNs, instead of dropping them. The SYN|ACK carries the
+    /// connection state in its initial sequence number, authenticated with a secret of the
+    /// interface, the 4-tuple and a coarse timestamp; the socket keeps listening, and only
+    /// moves to the ESTABLISHED state when it receives an ACK with a valid cookie. This lets
+    /// legitimate clients connect during a SYN flood.
+    ///
+    /// The cookie only has room for an approximation of the remote MSS, and for the window
+    /// scaling and selective acknowledgement options the remote endpoint offered. Any data
+    /// the SYN carries is ignored, and a cookie expires after one to two minutes.
+    ///
+    /// SYN cookies are disabled by default, and are never used for sockets that authenticate
+    /// their connections with the TCP Authentication Option.
+    ///
+    /// [half-open limit]: ../../iface/struct.InterfaceBuilder.html#method.half_open_limit
+    pub fn set_syn_cookies(&mut self, syn_cookies: bool) {
+        self.syn_cookies = syn_cookies
+    }
+
+    /// Return the keys of the TCP Authentication Option.
+    ///
+    /// See also the [set_auth_keys](#method.set_auth_keys) method.
+    #[cfg(feature = "socket-tcp-ao")]
+    pub fn auth_keys(&self) -> &[AuthKey] {
+        &self.auth.keys
+    }
+
+    /// Authenticate the connections of the socket with the TCP Authentication Option
+    /// ([RFC 5925]), using the given keys.
+    ///
+    /// Every segment the socket sends is authenticated with the [current
+    /// key](#method.auth_current_key), which is initially the first one, and every segment it
+    /// receives must be authenticated with one of the keys; any other segment is dropped
+    /// silently. The remote endpoint must be configured with the same keys, so this should be
+    /// done before calling [listen](#method.listen) or [connect](#method.connect). The keys are
+    /// kept when the socket is reused for another connection.
+    ///
+    /// Setting an empty set of keys disables authentication, which is the default.
+    ///
+    /// [RFC 5925]: https://tools.ietf.org/html/rfc5925
+    #[cfg(feature = "socket-tcp-ao")]
+    pub fn set_auth_keys<K>(&mut self, keys: K)
+    where
+        K: Into<ManagedSlice<'a, AuthKey>>,
+    {
+        self.auth.keys = keys.into();
+        self.auth.current = 0;
+        self.auth.rnext = None;
+    }
+
+    /// Return the key outgoing segments are authenticated with, or None if authentication is
+    /// disabled.
+    ///
+    /// The current key changes when the remote endpoint asks for another one of the configured
+    /// keys to be used, as described in the [set_auth_rnext_key](#method.set_auth_rnext_key)
+    /// method.
+    #[cfg(feature = "socket-tcp-ao")]
+    pub fn auth_current_key(&self) -> Option<&AuthKey> {
+        self.auth.keys.get(self.auth.current)
+    }
+
+    /// Ask the remote endpoint to authenticate its segments with the key that has the given
+    /// receive ID.
+    ///
+    /// This is how keys are rolled over: once a new key is configured on both endpoints,
+    /// each of them asks the other one to use it, and switches to it itself as soon as it is
+    /// asked to. By default, the remote endpoint is asked to keep using the current key.
+    ///
+    /// Returns `Err(AuthError::UnknownKey)` if none of the keys has the given receive ID.
+    #[cfg(feature = "socket-tcp-ao")]
+    pub fn set_auth_rnext_key(&mut self, recv_id: u8) -> Result<(), AuthError> {
+        if !self.auth.keys.iter().any(|key| key.recv_id == recv_id) {
+            return Err(AuthError::UnknownKey);
+        }
+        self.auth.rnext = Some(recv_id);
+        Ok(())
+    }
+
+    /// Return the maximum segment size used for outgoing segments, or None if not connected.
+    ///
+    /// This takes into account the interface MTU, the [tunnel overhead](#method.set_mss_overhead),
+    /// the MSS advertised by the remote endpoint and the space taken by segment options.
+    pub fn effective_mss(&self, cx: &Context) -> Option<usize> {
+        let ip_header_len = Self::ip_header_len(self.tuple?.local.addr);
+        let mss = self.local_mss(cx, ip_header_len).min(self.remote_mss);
+        Some(mss.saturating_sub(self.options_len()))
+    }
+
     /// Return the local endpoint, or None if not connected.
     #[inline]
     pub fn local_endpoint(&self) -> Option<IpEndpoint> {
@@ -669,35 +1953,61 @@ impl<'a> Socket<'a> {
     }
 
     fn reset(&mut self) {
-        let rx_cap_log2 =
-            mem::size_of::<usize>() * 8 - self.rx_buffer.capacity().leading_zeros() as usize;
+        let rx_capacity = self.window_buffer().capacity();
+        let rx_cap_log2 = mem::size_of::<usize>() * 8 - rx_capacity.leading_zeros() as usize;
 
         self.state = State::Closed;
         self.timer = Timer::new();
         self.rtte = RttEstimator::default();
-        self.assembler = Assembler::new(self.rx_buffer.capacity());
+        self.rate = RateEstimator::default();
+        self.assembler = Assembler::new(rx_capacity);
         self.tx_buffer.clear();
         self.rx_buffer.clear();
+        self.reassembly_buffer.clear();
         self.rx_fin_received = false;
         self.listen_endpoint = IpListenEndpoint::default();
+        self.listen_port_span = 0;
         self.tuple = None;
+        self.close_reason = None;
+        #[cfg(feature = "socket-tcp-ao")]
+        self.auth.reset();
         self.local_seq_no = TcpSeqNumber::default();
         self.remote_seq_no = TcpSeqNumber::default();
         self.remote_last_seq = TcpSeqNumber::default();
+        self.flush_seq = None;
         self.remote_last_ack = None;
         self.remote_last_win = 0;
         self.remote_win_len = 0;
         self.remote_win_scale = None;
         self.remote_win_shift = rx_cap_log2.saturating_sub(16) as u8;
+        self.remote_has_sack = false;
         self.remote_mss = DEFAULT_MSS;
         self.remote_last_ts = None;
+        self.read_idle_since = None;
+        self.write_idle_since = None;
+        self.syn_received_at = None;
+        self.recv_rate_interval = None;
+        self.local_rx_last_seq = None;
+        self.local_rx_last_ack = None;
+        self.local_rx_dup_acks = 0;
+        self.sack_scoreboard = [None; SACK_SCOREBOARD_LEN];
+        self.sack_recovery = None;
         self.ack_delay_timer = AckDelayTimer::Idle;
+        self.quickack_remaining = 0;
         self.challenge_ack_timer = Instant::from_secs(0);
+        self.cwnd = usize::MAX;
+        self.last_send_at = None;
+        self.paced_until = None;
+        #[cfg(feature = "paranoid")]
+        {
+            self.checked_seq = None;
+        }
 
         #[cfg(feature = "async")]
         {
             self.rx_waker.wake();
             self.tx_waker.wake();
+            self.window_waker.wake();
         }
     }
 
@@ -726,6 +2036,57 @@ impl<'a> Socket<'a> {
         Ok(())
     }
 
+    /// Start listening on a range of ports, with an optional local address.
+    ///
+    /// The socket accepts a connection to any port within `ports`, like a socket listening
+    /// on that single port would; [local_endpoint](#method.local_endpoint) returns the port
+    /// that was actually connected to. A socket listening on a single port takes precedence
+    /// over a range for its port.
+    ///
+    /// This function returns `Err(ListenError::InvalidState)` if the socket was already open
+    /// (see [is_open](#method.is_open)), and `Err(ListenError::Unaddressable)`
+    /// if the range is empty or includes port zero.
+    pub fn listen_range(
+        &mut self,
+        addr: Option<IpAddress>,
+        ports: RangeInclusive<u16>,
+    ) -> Result<(), ListenError> {
+        let (first, last) = (*ports.start(), *ports.end());
+        if first > last {
+            return Err(ListenError::Unaddressable);
+        }
+
+        self.listen(IpListenEndpoint { addr, port: first })?;
+        self.listen_port_span = last - first;
+        Ok(())
+    }
+
+    /// Return whether the socket is listening on a range of ports rather than a single one.
+    pub(crate) fn is_range_listener(&self) -> bool {
+        self.tuple.is_none() && self.listen_port_span != 0
+    }
+
+    /// Return whether the socket is listening on, or connected from, a local endpoint
+    /// overlapping with `endpoint`.
+    ///
+    /// Sockets in the `TIME-WAIT` state still occupy their local endpoint.
+    pub(crate) fn is_bound_to(&self, endpoint: &IpListenEndpoint) -> bool {
+        match self.tuple {
+            _ if self.state == State::Closed => false,
+            Some(tuple) => IpListenEndpoint::from(tuple.local).overlaps(endpoint),
+            None => {
+                let first_port = self.listen_endpoint.port;
+                let last_port = first_port + self.listen_port_span;
+                let local_endpoint = IpListenEndpoint {
+                    addr: self.listen_endpoint.addr,
+                    port: endpoint.port,
+                };
+                (first_port..=last_port).contains(&endpoint.port)
+                    && local_endpoint.overlaps(endpoint)
+            }
+        }
+    }
+
     /// Connect to a given endpoint.
     ///
     /// The local port must be provided explicitly. Assuming `fn get_ephemeral_port() -> u16`
@@ -811,6 +2172,8 @@ impl<'a> Socket<'a> {
     /// Note that there is no corresponding function for the receive half of the full-duplex
     /// connection; only the remote end can close it. If you no longer wish to receive any
     /// data and would like to reuse the socket right away, use [abort](#method.abort).
+    ///
+    /// A corked socket sends the data left in the transmit buffer before the FIN.
     pub fn close(&mut self) {
         match self.state {
             // In the LISTEN state there is no established connection.
@@ -842,9 +2205,195 @@ impl<'a> Socket<'a> {
     /// In terms of the TCP state machine, the socket may be in any state and is moved to
     /// the `CLOSED` state.
     pub fn abort(&mut self) {
+        if self.tuple.is_some() {
+            self.close_reason = Some(CloseReason::Aborted);
+        }
         self.set_state(State::Closed);
     }
 
+    /// Close the socket right away if it is in the `TIME-WAIT` state, instead of waiting for
+    /// the delay to expire, and return whether it was.
+    ///
+    /// # Correctness
+    /// TIME-WAIT exists so that duplicate segments of the old connection still in flight
+    /// cannot be mistaken for a part of a new connection between the same endpoints, and so
+    /// that a lost final ACK can be retransmitted. Reaping the socket early gives up on both.
+    pub fn reap_time_wait(&mut self) -> bool {
+        if self.state != State::TimeWait {
+            return false;
+        }
+        tcp_trace!("TIME-WAIT state reaped");
+        self.reset();
+        true
+    }
+
+    /// Export the state of the connection, so that it can be resumed on another socket with
+    /// [import_connection](#method.import_connection).
+    ///
+    /// The data in the receive and transmit buffers is copied to the start of `rx_data` and
+    /// `tx_data`; they must be at least [recv_queue](#method.recv_queue) and
+    /// [send_queue](#method.send_queue) octets long, or `Err(ExportError::BufferTooSmall)`
+    /// is returned. Segments received out of order are not exported; the remote endpoint
+    /// retransmits them.
+    ///
+    /// This function returns `Err(ExportError::InvalidState)` unless the socket is in
+    /// the `ESTABLISHED` or `CLOSE-WAIT` state. Once the connection is exported, the socket
+    /// should be removed from the interface without closing or aborting it, which would
+    /// shut down the connection.
+    pub fn export_connection(
+        &mut self,
+        rx_data: &mut [u8],
+        tx_data: &mut [u8],
+    ) -> Result<ConnectionState, ExportError> {
+        let tuple = match (self.state, self.tuple) {
+            (State::Established | State::CloseWait, Some(tuple)) => tuple,
+            _ => return Err(ExportError::InvalidState),
+        };
+        if rx_data.len() < self.rx_unread() || tx_data.len() < self.tx_buffer.len() {
+            return Err(ExportError::BufferTooSmall);
+        }
+
+        let mut rx_len = self.rx_buffer.read_allocated(0, rx_data);
+        rx_len += self
+            .reassembly_buffer
+            .read_allocated(0, &mut rx_data[rx_len..]);
+        let _ = self.tx_buffer.read_allocated(0, tx_data);
+
+        Ok(ConnectionState {
+            local: tuple.local,
+            remote: tuple.remote,
+            state: self.state,
+            send_unacked: self.local_seq_no,
+            recv_next: self.remote_seq_no + rx_len,
+            send_window: self.remote_win_len,
+            send_window_scale: self.remote_win_scale,
+            recv_window_scale: self.remote_win_shift,
+            recv_window: (self.remote_last_win as usize) << self.remote_win_shift,
+            sack_permitted: self.remote_has_sack,
+            remote_mss: self.remote_mss,
+            rtt: Duration::from_millis(self.rtte.rtt as u64),
+            rtt_deviation: Duration::from_millis(self.rtte.deviation as u64),
+        })
+    }
+
+    /// Resume a connection exported with [export_connection](#method.export_connection).
+    ///
+    /// The receive and transmit buffers are filled with `rx_data` and `tx_data`. Any data that
+    /// was in flight is sent again right away, since it cannot be known whether it reached
+    /// the remote endpoint; the retransmission timer and congestion control start anew, using
+    /// the exported round-trip time estimate. The socket options, such as the timeout or
+    /// the keep-alive interval, are those of this socket, not of the exported one.
+    ///
+    /// This function returns `Err(ImportError::InvalidState)` if the socket is open, and
+    /// `Err(ImportError::Unaddressable)` if either endpoint is unspecified.
+    /// It returns `Err(ImportError::BufferTooSmall)` if the data doesn't fit into the buffers,
+    /// or if the free space they would leave for incoming data is smaller than the window last
+    /// advertised to the remote endpoint, since the data it is allowed to send would then be dropped.
+    /// It returns `Err(ImportError::Malformed)` if the state is not `ESTABLISHED` or
+    /// `CLOSE-WAIT`, or if the window scales, the window last advertised to the remote endpoint
+    /// or the maximum segment size are invalid.
+    pub fn import_connection(
+        &mut self,
+        state: &ConnectionState,
+        rx_data: &[u8],
+        tx_data: &[u8],
+    ) -> Result<(), ImportError> {
+        if self.is_open() {
+            return Err(ImportError::InvalidState);
+        }
+        if !matches!(state.state, State::Established | State::CloseWait)
+            || matches!(state.send_window_scale, Some(scale) if scale > 14)
+            || state.recv_window_scale > 14
+            || (state.send_window_scale.is_none() && state.recv_window_scale != 0)
+            || state.recv_window >> state.recv_window_scale > u16::MAX as usize
+            || state.remote_mss == 0
+        {
+            return Err(ImportError::Malformed);
+        }
+        for endpoint in [state.local, state.remote].iter() {
+            if endpoint.port == 0 || endpoint.addr.is_unspecified() {
+                return Err(ImportError::Unaddressable);
+            }
+        }
+        // The received data that doesn't fit into the receive buffer waits in the reassembly
+        // buffer, which then holds the window.
+        let rx_window = if self.reassembly_buffer.capacity() > 0 {
+            let overflow = rx_data.len().saturating_sub(self.rx_buffer.capacity());
+            self.reassembly_buffer.capacity().checked_sub(overflow)
+        } else {
+            self.rx_buffer.capacity().checked_sub(rx_data.len())
+        };
+        if tx_data.len() > self.tx_buffer.capacity()
+            || !matches!(rx_window, Some(window) if window >= state.recv_window)
+        {
+            return Err(ImportError::BufferTooSmall);
+        }
+
+        self.reset();
+        let mut rx_len = self.rx_buffer.enqueue_slice(rx_data);
+        rx_len += self.reassembly_buffer.enqueue_slice(&rx_data[rx_len..]);
+        let _ = self.tx_buffer.enqueue_slice(tx_data);
+        self.tuple = Some(Tuple {
+            local: state.local,
+            remote: state.remote,
+        });
+        self.local_seq_no = state.send_unacked;
+        self.remote_last_seq = state.send_unacked;
+        self.remote_seq_no = state.recv_next - rx_len;
+        self.remote_last_ack = Some(state.recv_next);
+        self.remote_last_win = (state.recv_window >> state.recv_window_scale) as u16;
+        self.remote_win_len = state.send_window;
+        self.remote_win_scale = state.send_window_scale;
+        self.remote_win_shift = state.recv_window_scale;
+        self.remote_has_sack = state.sack_permitted;
+        self.remote_mss = state.remote_mss;
+        self.rtte.rtt = state.rtt.total_millis().min(RTTE_MAX_RTO as u64) as u32;
+        self.rtte.deviation = state.rtt_deviation.total_millis().min(RTTE_MAX_RTO as u64) as u32;
+        self.rx_fin_received = state.state == State::CloseWait;
+        self.set_state(state.state);
+        if self.keep_alive.is_some() {
+            self.timer.set_keep_alive();
+        }
+        Ok(())
+    }
+
+    /// Return why the last connection was closed, or `None` if it is still open or was shut
+    /// down in an orderly way.
+    ///
+    /// The reason is cleared when the socket is reused with [listen] or [connect].
+    ///
+    /// [listen]: #method.listen
+    /// [connect]: #method.connect
+    #[inline]
+    pub fn close_reason(&self) -> Option<CloseReason> {
+        self.close_reason
+    }
+
+    /// Return the number of octets in the transmit buffer that were sent but not acknowledged,
+    /// and the number of octets that were not sent yet.
+    fn tx_buffer_split(&self) -> (usize, usize) {
+        let unacked = match self.state {
+            // The sequence space sent so far only holds the SYN.
+            State::Closed | State::Listen | State::SynSent | State::SynReceived => 0,
+            // The FIN occupies the sequence space past the end of the buffer.
+            _ => cmp::min(
+                self.remote_last_seq - self.local_seq_no,
+                self.tx_buffer.len(),
+            ),
+        };
+        (unacked, self.tx_buffer.len() - unacked)
+    }
+
+    /// Reset the connection without sending anything, if it uses the given local address,
+    /// which was removed from the interface.
+    pub(crate) fn local_address_removed(&mut self, addr: IpAddress) {
+        if matches!(self.tuple, Some(tuple) if tuple.local.addr == addr) {
+            net_debug!("local address {} removed, resetting connection", addr);
+            self.reset();
+            self.close_reason = Some(CloseReason::LocalAddressRemoved);
+        }
+    }
+
     /// Return whether the socket is passively listening for incoming connections.
     ///
     /// In terms of the TCP state machine, the socket must be in the `LISTEN` state.
@@ -931,7 +2480,7 @@ impl<'a> Socket<'a> {
             // we still can receive indefinitely.
             State::FinWait1 | State::FinWait2 => true,
             // If we have something in the receive buffer, we can receive that.
-            _ if !self.rx_buffer.is_empty() => true,
+            _ if self.rx_unread() > 0 => true,
             _ => false,
         }
     }
@@ -967,7 +2516,7 @@ impl<'a> Socket<'a> {
             return false;
         }
 
-        !self.rx_buffer.is_empty()
+        self.rx_unread() > 0
     }
 
     fn send_impl<'b, F, R>(&'b mut self, f: F) -> Result<R, SendError>
@@ -982,7 +2531,8 @@ impl<'a> Socket<'a> {
         // would be far in the past. Unless we clear it here, we'll abort the connection
         // down over in dispatch() by erroneously detecting it as timed out.
         if self.tx_buffer.is_empty() {
-            self.remote_last_ts = None
+            self.remote_last_ts = None;
+            self.write_idle_since = None;
         }
 
         let _old_length = self.tx_buffer.len();
@@ -1042,6 +2592,7 @@ impl<'a> Socket<'a> {
         F: FnOnce(&'b mut SocketBuffer<'a>) -> (usize, R),
     {
         self.recv_error_check()?;
+        self.deliver_reassembled();
 
         let _old_length = self.rx_buffer.len();
         let (size, result) = f(&mut self.rx_buffer);
@@ -1093,6 +2644,7 @@ impl<'a> Socket<'a> {
     /// This function otherwise behaves identically to [recv](#method.recv).
     pub fn peek(&mut self, size: usize) -> Result<&[u8], RecvError> {
         self.recv_error_check()?;
+        self.deliver_reassembled();
 
         let buffer = self.rx_buffer.get_allocated(0, size);
         if !buffer.is_empty() {
@@ -1120,6 +2672,14 @@ impl<'a> Socket<'a> {
         self.tx_buffer.len()
     }
 
+    /// Return the size of the window last advertised by the remote endpoint, in octets.
+    ///
+    /// Unlike [can_send](#method.can_send), this does not depend on the space left in
+    /// the transmit buffer.
+    pub fn send_window(&self) -> usize {
+        self.remote_win_len
+    }
+
     /// Return the amount of octets queued in the receive buffer. This value can be larger than
     /// the slice read by the next `recv` or `peek` call because it includes all queued octets,
     /// and not only the octets that may be returned as a contiguous slice.
@@ -1129,6 +2689,44 @@ impl<'a> Socket<'a> {
         self.rx_buffer.len()
     }
 
+    /// Return the estimated bandwidth of the path to the remote endpoint, in octets per second,
+    /// or `None` if it wasn't measured yet.
+    ///
+    /// This is the maximum delivery rate, i.e. the rate at which sent data is acknowledged,
+    /// sampled over the last few round trips, as used by BBR congestion control.
+    pub fn bottleneck_bandwidth(&self) -> Option<u64> {
+        self.rate.max_bw.map(|(bw, _)| bw)
+    }
+
+    /// Return the minimum round-trip time to the remote endpoint sampled over the last
+    /// ten seconds, or `None` if it wasn't measured yet.
+    pub fn min_rtt(&self) -> Option<Duration> {
+        self.rate.min_rtt.map(|(rtt, _)| rtt)
+    }
+
+    /// Return the estimated amount of octets in flight, as defined by the "pipe" variable
+    /// of RFC 6675.
+    ///
+    /// These are the octets that were sent and neither acknowledged, cumulatively or
+    /// selectively, nor presumed lost, plus the octets retransmitted during the current
+    /// loss recovery.
+    pub fn pipe(&self) -> usize {
+        let (high_rxt, mss) = match self.sack_recovery {
+            Some(recovery) => (recovery.high_rxt.max(self.local_seq_no), Some(recovery.mss)),
+            None => (self.local_seq_no, None),
+        };
+
+        let mut pipe = 0;
+        for hole in self.sack_holes() {
+            if !matches!(mss, Some(mss) if hole.is_lost(mss)) {
+                pipe += hole.end - hole.start;
+            } else if high_rxt > hole.start {
+                pipe += high_rxt.min(hole.end) - hole.start;
+            }
+        }
+        pipe
+    }
+
     fn set_state(&mut self, state: State) {
         if self.state != state {
             tcp_trace!("state={}=>{}", self.state, state);
@@ -1143,6 +2741,7 @@ impl<'a> Socket<'a> {
             // For example, a pending read has to fail with an error if the socket is closed.
             self.rx_waker.wake();
             self.tx_waker.wake();
+            self.window_waker.wake();
         }
     }
 
@@ -1158,6 +2757,7 @@ impl<'a> Socket<'a> {
             max_seg_size: None,
             sack_permitted: false,
             sack_ranges: [None, None, None],
+            auth: None,
             payload: &[],
         };
         let ip_reply_repr = IpRepr::new(
@@ -1194,7 +2794,7 @@ impl<'a> Socket<'a> {
         // and an acknowledgment indicating the next sequence number expected
         // to be received.
         reply_repr.seq_number = self.remote_last_seq;
-        reply_repr.ack_number = Some(self.remote_seq_no + self.rx_buffer.len());
+        reply_repr.ack_number = Some(self.remote_seq_no + self.rx_unread());
         self.remote_last_ack = reply_repr.ack_number;
 
         // From RFC 1323:
@@ -1259,48 +2859,285 @@ impl<'a> Socket<'a> {
         return Some(self.ack_reply(ip_repr, repr));
     }
 
-    pub(crate) fn accepts(&self, _cx: &mut Context, ip_repr: &IpRepr, repr: &TcpRepr) -> bool {
-        if self.state == State::Closed {
-            return false;
-        }
+    /// Answer a SYN with a SYN|ACK whose sequence number is a SYN cookie, without leaving
+    /// the LISTEN state.
+    ///
+    /// Returns `None` if the SYN should be dropped instead.
+    pub(crate) fn syn_cookie_reply(
+        &self,
+        cx: &mut Context,
+        ip_repr: &IpRepr,
+        repr: &TcpRepr,
+    ) -> Option<(IpRepr, TcpRepr<'static>)> {
+        debug_assert!(self.state == State::Listen);
+        debug_assert!(repr.control == TcpControl::Syn && repr.ack_number.is_none());
 
-        // If we're still listening for SYNs and the packet has an ACK, it cannot
-        // be destined to this socket, but another one may well listen on the same
-        // local endpoint.
-        if self.state == State::Listen && repr.ack_number.is_some() {
-            return false;
+        #[cfg(feature = "socket-tcp-ao")]
+        if self.auth.is_enabled() {
+            return None;
         }
-
-        if let Some(tuple) = &self.tuple {
-            // Reject packets not matching the 4-tuple
-            ip_repr.dst_addr() == tuple.local.addr
-                && repr.dst_port == tuple.local.port
-                && ip_repr.src_addr() == tuple.remote.addr
-                && repr.src_port == tuple.remote.port
-        } else {
-            // We're listening, reject packets not matching the listen endpoint.
-            let addr_ok = match self.listen_endpoint.addr {
-                Some(addr) => ip_repr.dst_addr() == addr,
-                None => true,
-            };
-            addr_ok && repr.dst_port != 0 && repr.dst_port == self.listen_endpoint.port
+        if repr.max_seg_size == Some(0) {
+            tcp_trace!("received SYN with zero MSS, ignoring");
+            return None;
         }
+
+        let mss = repr.max_seg_size.unwrap_or(DEFAULT_MSS as u16);
+        let mss = match SYN_COOKIE_MSS
+            .iter()
+            .rev()
+            .find(|&&cookie_mss| cookie_mss <= mss)
+        {
+            Some(&mss) => mss,
+            None => {
+                tcp_trace!(
+                    "received SYN with MSS {} too small for a SYN cookie, ignoring",
+                    mss
+                );
+                return None;
+            }
+        };
+        let cookie = SynCookie {
+            mss,
+            window_scale: repr.window_scale.map(|scale| scale.min(14)),
+            sack_permitted: repr.sack_permitted,
+        };
+        let (mut ip_reply_repr, mut reply_repr) = Self::reply(ip_repr, repr);
+        reply_repr.control = TcpControl::Syn;
+        reply_repr.seq_number = Self::syn_cookie(cx, ip_repr, repr, cookie);
+        reply_repr.ack_number = Some(repr.seq_number + 1);
+        // window len must NOT be scaled in SYNs.
+        reply_repr.window_len = self.rx_window().min((1 << 16) - 1) as u16;
+        reply_repr.window_scale = cookie.window_scale.map(|_| self.remote_win_shift);
+        reply_repr.sack_permitted = cookie.sack_permitted;
+        reply_repr.max_seg_size = Some(self.local_mss(cx, ip_reply_repr.buffer_len()) as u16);
+        ip_reply_repr.set_payload_len(reply_repr.buffer_len());
+        Some((ip_reply_repr, reply_repr))
     }
 
-    pub(crate) fn process(
-        &mut self,
+    /// Compute the initial sequence number of a SYN|ACK answering `repr` with a SYN cookie.
+    ///
+    /// From the most significant bit on, the cookie holds the low two bits of the timestamp,
+    /// the index of the MSS, the window scale (or 15 if the remote endpoint doesn't scale its
+    /// window), whether selective acknowledgements are permitted, and a 22-bit MAC over all of
+    /// them. It is offset by the initial sequence number of the remote endpoint.
+    fn syn_cookie(
         cx: &mut Context,
         ip_repr: &IpRepr,
         repr: &TcpRepr,
-    ) -> Option<(IpRepr, TcpRepr<'static>)> {
-        debug_assert!(self.accepts(cx, ip_repr, repr));
+        cookie: SynCookie,
+    ) -> TcpSeqNumber {
+        let timestamp = Self::syn_cookie_timestamp(cx);
+        // NOTE(unwrap): the MSS was picked from the table.
+        let mss_index = SYN_COOKIE_MSS
+            .iter()
+            .position(|&mss| mss == cookie.mss)
+            .unwrap();
+        let fields = ((timestamp & 0b11) << 30)
+            | ((mss_index as u32) << 27)
+            | ((cookie.window_scale.unwrap_or(15) as u32) << 23)
+            | ((cookie.sack_permitted as u32) << 22);
+        let mac = Self::syn_cookie_mac(cx, ip_repr, repr, repr.seq_number, timestamp, fields);
+        TcpSeqNumber(repr.seq_number.0.wrapping_add((fields | mac) as i32))
+    }
+
+    /// Check the SYN cookie acknowledged by `repr`, and return the state it encodes if it
+    /// is valid.
+    fn check_syn_cookie(
+        &self,
+        cx: &mut Context,
+        ip_repr: &IpRepr,
+        repr: &TcpRepr,
+    ) -> Option<SynCookie> {
+        if !self.syn_cookies || matches!(repr.control, TcpControl::Syn | TcpControl::Rst) {
+            return None;
+        }
+        #[cfg(feature = "socket-tcp-ao")]
+        if self.auth.is_enabled() {
+            return None;
+        }
+        if !self.accepts_endpoint(ip_repr, repr) {
+            return None;
+        }
 
-        // Consider how much the sequence number space differs from the transmit buffer space.
-        let (sent_syn, sent_fin) = match self.state {
-            // In SYN-SENT or SYN-RECEIVED, we've just sent a SYN.
-            State::SynSent | State::SynReceived => (true, false),
-            // In FIN-WAIT-1, LAST-ACK, or CLOSING, we've just sent a FIN.
-            State::FinWait1 | State::LastAck | State::Closing => (false, true),
+        let remote_isn = repr.seq_number - 1;
+        let cookie = (repr.ack_number?.0)
+            .wrapping_sub(1)
+            .wrapping_sub(remote_isn.0) as u32;
+        let now = Self::syn_cookie_timestamp(cx);
+        let age = now.wrapping_sub(cookie >> 30) & 0b11;
+        if age > 1 {
+            return None;
+        }
+        let fields = cookie & !SYN_COOKIE_MAC_MASK;
+        let mac =
+            Self::syn_cookie_mac(cx, ip_repr, repr, remote_isn, now.wrapping_sub(age), fields);
+        if mac != cookie & SYN_COOKIE_MAC_MASK {
+            return None;
+        }
+
+        let window_scale = ((cookie >> 23) & 0xf) as u8;
+        Some(SynCookie {
+            mss: SYN_COOKIE_MSS[((cookie >> 27) & 0b111) as usize],
+            window_scale: if window_scale == 15 {
+                None
+            } else {
+                Some(window_scale)
+            },
+            sack_permitted: cookie & (1 << 22) != 0,
+        })
+    }
+
+    fn syn_cookie_timestamp(cx: &Context) -> u32 {
+        (cx.now().total_millis() / SYN_COOKIE_PERIOD.total_millis() as i64) as u32
+    }
+
+    /// Authenticate the fields of a SYN cookie for the connection described by the 4-tuple
+    /// of `repr` and the initial sequence number of the remote endpoint.
+    fn syn_cookie_mac(
+        cx: &mut Context,
+        ip_repr: &IpRepr,
+        repr: &TcpRepr,
+        remote_isn: TcpSeqNumber,
+        timestamp: u32,
+        fields: u32,
+    ) -> u32 {
+        let mut data = [0; 48];
+        let mut len = 0;
+        let mut push = |bytes: &[u8]| {
+            data[len..len + bytes.len()].copy_from_slice(bytes);
+            len += bytes.len();
+        };
+        push(ip_repr.src_addr().as_bytes());
+        push(ip_repr.dst_addr().as_bytes());
+        push(&repr.src_port.to_be_bytes());
+        push(&repr.dst_port.to_be_bytes());
+        push(&remote_isn.0.to_be_bytes());
+        push(&timestamp.to_be_bytes());
+        push(&fields.to_be_bytes());
+        let secret = cx.syn_cookie_secret();
+        siphash24(&secret, &data[..len]) as u32 & SYN_COOKIE_MAC_MASK
+    }
+
+    /// Enter the SYN-RECEIVED state for the connection a valid SYN cookie was acknowledged for,
+    /// as if we had received its SYN and sent our SYN|ACK.
+    fn restore_syn_cookie(
+        &mut self,
+        cx: &mut Context,
+        ip_repr: &IpRepr,
+        repr: &TcpRepr,
+        cookie: SynCookie,
+    ) {
+        tcp_trace!("received ACK with a valid SYN cookie");
+        // NOTE(unwrap): the cookie was acknowledged.
+        let ack_number = repr.ack_number.unwrap();
+        self.tuple = Some(Tuple {
+            local: IpEndpoint::new(ip_repr.dst_addr(), repr.dst_port),
+            remote: IpEndpoint::new(ip_repr.src_addr(), repr.src_port),
+        });
+        self.local_seq_no = ack_number - 1;
+        self.remote_seq_no = repr.seq_number;
+        self.remote_last_seq = ack_number;
+        self.remote_last_ack = Some(repr.seq_number);
+        self.remote_mss = cookie.mss as usize;
+        self.remote_has_sack = cookie.sack_permitted;
+        self.remote_win_scale = cookie.window_scale;
+        // Remote doesn't support window scaling, don't do it.
+        if self.remote_win_scale.is_none() {
+            self.remote_win_shift = 0;
+        }
+        self.set_state(State::SynReceived);
+        self.timer.set_for_idle(cx.now(), self.keep_alive);
+    }
+
+    pub(crate) fn accepts(&self, cx: &mut Context, ip_repr: &IpRepr, repr: &TcpRepr) -> bool {
+        if self.state == State::Closed {
+            return false;
+        }
+
+        // If we're still listening for SYNs and the packet has an ACK, it cannot
+        // be destined to this socket, unless it completes a handshake we started with
+        // a SYN cookie. Another socket may well listen on the same local endpoint.
+        if self.state == State::Listen
+            && repr.ack_number.is_some()
+            && self.check_syn_cookie(cx, ip_repr, repr).is_none()
+        {
+            return false;
+        }
+
+        if let Some(tuple) = &self.tuple {
+            // Reject packets not matching the 4-tuple
+            ip_repr.dst_addr() == tuple.local.addr
+                && repr.dst_port == tuple.local.port
+                && ip_repr.src_addr() == tuple.remote.addr
+                && repr.src_port == tuple.remote.port
+        } else {
+            // We're listening, reject packets not matching the listen endpoint.
+            self.accepts_endpoint(ip_repr, repr)
+        }
+    }
+
+    /// Return whether a segment is addressed to the listen endpoint.
+    fn accepts_endpoint(&self, ip_repr: &IpRepr, repr: &TcpRepr) -> bool {
+        let addr_ok = match self.listen_endpoint.addr {
+            Some(addr) => ip_repr.dst_addr() == addr,
+            None => true,
+        };
+        let first_port = self.listen_endpoint.port;
+        let last_port = first_port + self.listen_port_span;
+        addr_ok && repr.dst_port != 0 && (first_port..=last_port).contains(&repr.dst_port)
+    }
+
+    /// Process a segment like [process](#method.process) does, but check its authentication
+    /// first, and authenticate the reply.
+    ///
+    /// `segment` holds the octets `repr` was parsed from.
+    #[cfg(feature = "socket-tcp-ao")]
+    pub(crate) fn process_authenticated(
+        &mut self,
+        cx: &mut Context,
+        ip_repr: &IpRepr,
+        repr: &TcpRepr,
+        segment: &[u8],
+    ) -> Option<(IpRepr, TcpRepr<'static>)> {
+        if self.auth.is_enabled() && !self.auth.verify(ip_repr, repr, segment) {
+            net_debug!(
+                "{}:{}:{}:{}: dropping segment that failed authentication",
+                ip_repr.dst_addr(),
+                repr.dst_port,
+                ip_repr.src_addr(),
+                repr.src_port
+            );
+            return None;
+        }
+
+        let (mut ip_reply_repr, mut reply_repr) = self.process(cx, ip_repr, repr)?;
+        reply_repr.auth = self.auth.sign(&ip_reply_repr, &reply_repr);
+        ip_reply_repr.set_payload_len(reply_repr.buffer_len());
+        Some((ip_reply_repr, reply_repr))
+    }
+
+    pub(crate) fn process(
+        &mut self,
+        cx: &mut Context,
+        ip_repr: &IpRepr,
+        repr: &TcpRepr,
+    ) -> Option<(IpRepr, TcpRepr<'static>)> {
+        debug_assert!(self.accepts(cx, ip_repr, repr));
+
+        // An ACK in the LISTEN state carries a valid SYN cookie, which `accepts()` has checked.
+        // Pick up the handshake as if we had kept its state after sending our SYN|ACK.
+        if self.state == State::Listen && repr.ack_number.is_some() {
+            if let Some(cookie) = self.check_syn_cookie(cx, ip_repr, repr) {
+                self.restore_syn_cookie(cx, ip_repr, repr, cookie);
+            }
+        }
+
+        // Consider how much the sequence number space differs from the transmit buffer space.
+        let (sent_syn, sent_fin) = match self.state {
+            // In SYN-SENT or SYN-RECEIVED, we've just sent a SYN.
+            State::SynSent | State::SynReceived => (true, false),
+            // In FIN-WAIT-1, LAST-ACK, or CLOSING, we've just sent a FIN.
+            State::FinWait1 | State::LastAck | State::Closing => (false, true),
             // In all other states we've already got acknowledgements for
             // all of the control flags we sent.
             _ => (false, false),
@@ -1404,8 +3241,8 @@ impl<'a> Socket<'a> {
             }
         }
 
-        let window_start = self.remote_seq_no + self.rx_buffer.len();
-        let window_end = self.remote_seq_no + self.rx_buffer.capacity();
+        let window_start = self.remote_seq_no + self.rx_unread();
+        let window_end = window_start + self.rx_window();
         let segment_start = repr.seq_number;
         let segment_end = repr.seq_number + repr.segment_len();
 
@@ -1480,6 +3317,7 @@ impl<'a> Socket<'a> {
                 }
 
                 self.rtte.on_ack(cx.now(), ack_number);
+                self.rate.on_ack(cx.now(), ack_number, ack_len);
             }
         }
 
@@ -1509,8 +3347,10 @@ impl<'a> Socket<'a> {
             // RSTs in any other state close the socket.
             (_, TcpControl::Rst) => {
                 tcp_trace!("received RST");
+                let (unacked, unsent) = self.tx_buffer_split();
                 self.set_state(State::Closed);
                 self.tuple = None;
+                self.close_reason = Some(CloseReason::Reset { unacked, unsent });
                 return None;
             }
 
@@ -1539,6 +3379,7 @@ impl<'a> Socket<'a> {
                     self.remote_win_shift = 0;
                 }
                 self.set_state(State::SynReceived);
+                self.syn_received_at = Some(cx.now());
                 self.timer.set_for_idle(cx.now(), self.keep_alive);
             }
 
@@ -1546,6 +3387,7 @@ impl<'a> Socket<'a> {
             (State::SynReceived, TcpControl::None) => {
                 self.set_state(State::Established);
                 self.timer.set_for_idle(cx.now(), self.keep_alive);
+                self.quickack_remaining = self.quickack_segments;
             }
 
             // FIN packets in the SYN-RECEIVED state change it to CLOSE-WAIT.
@@ -1580,6 +3422,7 @@ impl<'a> Socket<'a> {
 
                 self.set_state(State::Established);
                 self.timer.set_for_idle(cx.now(), self.keep_alive);
+                self.quickack_remaining = self.quickack_segments;
             }
 
             // ACK packets in ESTABLISHED state reset the retransmit timer,
@@ -1675,7 +3518,13 @@ impl<'a> Socket<'a> {
             TcpControl::Syn => 0,
             _ => self.remote_win_scale.unwrap_or(0),
         };
-        self.remote_win_len = (repr.window_len as usize) << (scale as usize);
+        let remote_win_len = (repr.window_len as usize) << (scale as usize);
+        if self.remote_win_len == 0 && remote_win_len > 0 && repr.control != TcpControl::Syn {
+            tcp_trace!("remote window reopened");
+            #[cfg(feature = "async")]
+            self.window_waker.wake();
+        }
+        self.remote_win_len = remote_win_len;
 
         if ack_len > 0 {
             // Dequeue acknowledged octets.
@@ -1686,6 +3535,14 @@ impl<'a> Socket<'a> {
                 self.tx_buffer.len() - ack_len
             );
             self.tx_buffer.dequeue_allocated(ack_len);
+            // Restart the write timeout for the data that is left.
+            self.write_idle_since = None;
+
+            // Grow the congestion window while in slow start, by at most one segment per ACK.
+            if self.cwnd != usize::MAX {
+                let mss = self.effective_mss(cx).unwrap_or(self.remote_mss);
+                self.cwnd = self.cwnd.saturating_add(ack_len.min(mss));
+            }
 
             // There's new room available in tx_buffer, wake the waiting task if any.
             #[cfg(feature = "async")]
@@ -1698,8 +3555,9 @@ impl<'a> Socket<'a> {
 
             // Detect and react to duplicate ACKs by:
             // 1. Check if duplicate ACK and change self.local_rx_dup_acks accordingly
-            // 2. If exactly 3 duplicate ACKs received, set for fast retransmit
+            // 2. If exactly 3 duplicate ACKs received, start loss recovery
             // 3. Update the last received ACK (self.local_rx_last_ack)
+            let mut start_recovery = false;
             match self.local_rx_last_ack {
                 // Duplicate ACK if payload empty and ACK doesn't move send window ->
                 // Increment duplicate ACK count and set for retransmit if we just received
@@ -1723,9 +3581,8 @@ impl<'a> Socket<'a> {
                         }
                     );
 
-                    if self.local_rx_dup_acks == 3 {
-                        self.timer.set_for_fast_retransmit();
-                        net_debug!("started fast retransmit");
+                    if self.local_rx_dup_acks as usize == DUP_THRESH {
+                        start_recovery = true;
                     }
                 }
                 // No duplicate ACK -> Reset state and update last received ACK
@@ -1740,6 +3597,9 @@ impl<'a> Socket<'a> {
             // We've processed everything in the incoming segment, so advance the local
             // sequence number past it.
             self.local_seq_no = ack_number;
+            if matches!(self.flush_seq, Some(flush_seq) if flush_seq <= self.local_seq_no) {
+                self.flush_seq = None;
+            }
             // During retransmission, if an earlier segment got lost but later was
             // successfully received, self.local_seq_no can move past self.remote_last_seq.
             // Do not attempt to retransmit the latter segments; not only this is pointless
@@ -1748,6 +3608,31 @@ impl<'a> Socket<'a> {
             if self.remote_last_seq < self.local_seq_no {
                 self.remote_last_seq = self.local_seq_no
             }
+
+            if self.remote_has_sack {
+                self.update_sack_scoreboard(&repr.sack_ranges);
+            }
+
+            if let Some(recovery) = self.sack_recovery {
+                if self.local_seq_no >= recovery.recovery_point {
+                    self.sack_recovery = None;
+                    net_debug!("finished SACK loss recovery");
+                }
+            } else if start_recovery && self.sack_scoreboard[0].is_some() {
+                // RFC 6675: retransmit the holes reported by the remote rather than
+                // everything sent after the loss.
+                let ip_header_len = Self::ip_header_len(ip_repr.dst_addr());
+                self.sack_recovery = Some(SackRecovery {
+                    recovery_point: self.remote_last_seq,
+                    high_rxt: self.local_seq_no,
+                    rexmit_first: true,
+                    mss: self.local_mss(cx, ip_header_len).min(self.remote_mss),
+                });
+                net_debug!("started SACK loss recovery");
+            } else if start_recovery {
+                self.timer.set_for_fast_retransmit();
+                net_debug!("started fast retransmit");
+            }
         }
 
         let payload_len = repr.payload.len();
@@ -1760,7 +3645,7 @@ impl<'a> Socket<'a> {
         // Try adding payload octets to the assembler.
         match self.assembler.add(payload_offset, payload_len) {
             Ok(_) => {
-                debug_assert!(self.assembler.total_size() == self.rx_buffer.capacity());
+                debug_assert!(self.assembler.total_size() == self.window_buffer().capacity());
                 // Place payload octets into the buffer.
                 tcp_trace!(
                     "rx buffer: receiving {} octets at offset {}",
@@ -1768,7 +3653,7 @@ impl<'a> Socket<'a> {
                     payload_offset
                 );
                 let len_written = self
-                    .rx_buffer
+                    .window_buffer_mut()
                     .write_unallocated(payload_offset, repr.payload);
                 debug_assert!(len_written == payload_len);
             }
@@ -1783,14 +3668,21 @@ impl<'a> Socket<'a> {
         }
 
         if let Some(contig_len) = self.assembler.remove_front() {
-            debug_assert!(self.assembler.total_size() == self.rx_buffer.capacity());
+            debug_assert!(self.assembler.total_size() == self.window_buffer().capacity());
             // Enqueue the contiguous data octets in front of the buffer.
             tcp_trace!(
                 "rx buffer: enqueueing {} octets (now {})",
                 contig_len,
-                self.rx_buffer.len() + contig_len
+                self.rx_unread() + contig_len
             );
-            self.rx_buffer.enqueue_unallocated(contig_len);
+            self.window_buffer_mut().enqueue_unallocated(contig_len);
+            self.deliver_reassembled();
+            // The read timeout restarts once the new data has been read.
+            self.read_idle_since = None;
+            if self.recv_rate_running() {
+                let (_, octets) = self.recv_rate_interval.get_or_insert((cx.now(), 0));
+                *octets += contig_len;
+            }
 
             // There's new data in rx_buffer, notify waiting task if any.
             #[cfg(feature = "async")]
@@ -1802,8 +3694,19 @@ impl<'a> Socket<'a> {
             tcp_trace!("assembler: {}", self.assembler);
         }
 
+        if !assembler_was_empty && self.assembler.is_empty() {
+            // The peer has recovered from a loss, let its congestion window grow quickly again.
+            self.quickack_remaining = self.quickack_segments;
+        }
+
         // Handle delayed acks
-        if let Some(ack_delay) = self.ack_delay {
+        let quickack = self.quickack_segment();
+        if quickack {
+            if let AckDelayTimer::Waiting(_) = self.ack_delay_timer {
+                tcp_trace!("quick ack, forcing delayed ack timer expiry");
+                self.ack_delay_timer = AckDelayTimer::Immediate;
+            }
+        } else if let Some(ack_delay) = self.ack_delay {
             if self.ack_to_transmit() || self.window_to_update() {
                 self.ack_delay_timer = match self.ack_delay_timer {
                     AckDelayTimer::Idle => {
@@ -1847,16 +3750,265 @@ impl<'a> Socket<'a> {
         }
     }
 
-    fn seq_to_transmit(&self, cx: &mut Context) -> bool {
-        let ip_header_len = match self.tuple.unwrap().local.addr {
+    /// Whether the read timeout runs: the connection is established, the remote endpoint may
+    /// still send data, and there is none left to read.
+    fn read_timer_running(&self) -> bool {
+        self.read_timeout.is_some()
+            && matches!(
+                self.state,
+                State::Established | State::FinWait1 | State::FinWait2
+            )
+            && self.rx_unread() == 0
+    }
+
+    /// Whether the write timeout runs: the connection is established, and there is data in the
+    /// transmit buffer.
+    fn write_timer_running(&self) -> bool {
+        self.write_timeout.is_some()
+            && matches!(
+                self.state,
+                State::Established
+                    | State::FinWait1
+                    | State::Closing
+                    | State::CloseWait
+                    | State::LastAck
+            )
+            && !self.tx_buffer.is_empty()
+    }
+
+    /// Return when the handshake timeout expires, if the socket is waiting for the handshake
+    /// of a connection it accepted to complete.
+    fn handshake_deadline(&self) -> Option<Instant> {
+        match (self.state, self.syn_received_at, self.handshake_timeout) {
+            (State::SynReceived, Some(syn_received_at), Some(timeout)) => {
+                Some(syn_received_at + timeout)
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the minimum receive rate applies: the connection is established, and the remote
+    /// endpoint may still send data.
+    fn recv_rate_running(&self) -> bool {
+        self.min_recv_rate.is_some()
+            && matches!(
+                self.state,
+                State::Established | State::FinWait1 | State::FinWait2
+            )
+    }
+
+    /// Return when the current minimum receive rate interval ends.
+    fn recv_rate_deadline(&self) -> Option<Instant> {
+        match (self.recv_rate_interval, self.min_recv_rate) {
+            (Some((start, _)), Some((_, interval))) if self.recv_rate_running() => {
+                Some(start + interval)
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the remote endpoint sent too little data in the current minimum receive rate
+    /// interval, even though there was room for it.
+    fn recv_rate_too_low(&self) -> bool {
+        match (self.recv_rate_interval, self.min_recv_rate) {
+            (Some((_, received)), Some((octets, _))) if received < octets => {
+                self.rx_window() >= octets - received
+            }
+            _ => false,
+        }
+    }
+
+    /// Return when the read or write timeout expires first, and the reason the connection is
+    /// aborted for then.
+    fn progress_deadline(&self) -> Option<(Instant, CloseReason)> {
+        let read = match (self.read_idle_since, self.read_timeout) {
+            (Some(since), Some(timeout)) if self.read_timer_running() => {
+                Some((since + timeout, CloseReason::ReadTimedOut))
+            }
+            _ => None,
+        };
+        let write = match (self.write_idle_since, self.write_timeout) {
+            (Some(since), Some(timeout)) if self.write_timer_running() => {
+                Some((since + timeout, CloseReason::WriteTimedOut))
+            }
+            _ => None,
+        };
+        match (read, write) {
+            (Some(read), Some(write)) if write.0 < read.0 => Some(write),
+            (read, write) => read.or(write),
+        }
+    }
+
+    fn ip_header_len(addr: IpAddress) -> usize {
+        match addr {
             #[cfg(feature = "proto-ipv4")]
             IpAddress::Ipv4(_) => crate::wire::IPV4_HEADER_LEN,
             #[cfg(feature = "proto-ipv6")]
             IpAddress::Ipv6(_) => crate::wire::IPV6_HEADER_LEN,
+        }
+    }
+
+    /// Max segment size we're able to send due to MTU limitations and tunnel overhead.
+    ///
+    /// The tunnel overhead never takes the MSS below the default MSS (or the MTU-derived
+    /// MSS, if that is smaller already).
+    fn local_mss(&self, cx: &Context, ip_header_len: usize) -> usize {
+        let mss = cx.ip_mtu() - ip_header_len - TCP_HEADER_LEN;
+        cmp::max(
+            mss.saturating_sub(self.mss_overhead),
+            cmp::min(mss, DEFAULT_MSS),
+        )
+    }
+
+    /// Length of the options carried by every segment, which the MSS doesn't account for.
+    fn options_len(&self) -> usize {
+        #[cfg(feature = "socket-tcp-ao")]
+        if self.auth.is_enabled() {
+            return 4 + TCP_AUTH_MAC_LEN;
+        }
+        0
+    }
+
+    /// Iterate over the holes in the scoreboard, from the lowest to the highest one.
+    ///
+    /// The octets sent after the highest SACK block form the last hole.
+    fn sack_holes(&self) -> impl Iterator<Item = SackHole> + '_ {
+        let mut blocks = self.sack_scoreboard.iter().flatten();
+        let mut blocks_above = blocks.clone().count();
+        let mut sacked_above = blocks
+            .clone()
+            .map(|&(left, right)| right - left)
+            .sum::<usize>();
+        let mut start = Some(self.local_seq_no);
+
+        core::iter::from_fn(move || loop {
+            let hole_start = start?;
+            match blocks.next() {
+                Some(&(left, right)) => {
+                    blocks_above -= 1;
+                    sacked_above -= right - left;
+                    start = Some(right);
+                    if left > hole_start {
+                        return Some(SackHole {
+                            start: hole_start,
+                            end: left,
+                            blocks_above: blocks_above + 1,
+                            sacked_above: sacked_above + (right - left),
+                        });
+                    }
+                }
+                None => {
+                    start = None;
+                    if self.remote_last_seq > hole_start {
+                        return Some(SackHole {
+                            start: hole_start,
+                            end: self.remote_last_seq,
+                            blocks_above: 0,
+                            sacked_above: 0,
+                        });
+                    }
+                }
+            }
+        })
+    }
+
+    /// Merge the SACK blocks of an incoming segment into the scoreboard, and forget about
+    /// the blocks that have since been cumulatively acknowledged.
+    fn update_sack_scoreboard(&mut self, sack_ranges: &[Option<(u32, u32)>]) {
+        let mut blocks = [None; SACK_SCOREBOARD_LEN + 1];
+        for (slot, block) in blocks.iter_mut().zip(self.sack_scoreboard.iter()) {
+            *slot = block
+                .filter(|&(_, right)| right > self.local_seq_no)
+                .map(|(left, right)| (left.max(self.local_seq_no), right));
+        }
+
+        for &(left, right) in sack_ranges.iter().flatten() {
+            let (mut left, mut right) = (TcpSeqNumber(left as i32), TcpSeqNumber(right as i32));
+            // Ignore blocks that are stale, or that cover octets we've never sent.
+            if right <= left || right <= self.local_seq_no || right > self.remote_last_seq {
+                continue;
+            }
+            left = left.max(self.local_seq_no);
+
+            for slot in blocks.iter_mut() {
+                if let Some((other_left, other_right)) = *slot {
+                    if other_left <= right && left <= other_right {
+                        left = left.min(other_left);
+                        right = right.max(other_right);
+                        *slot = None;
+                    }
+                }
+            }
+            if let Some(slot) = blocks.iter_mut().find(|slot| slot.is_none()) {
+                *slot = Some((left, right));
+            }
+
+            blocks.sort_unstable_by(|a, b| match (a, b) {
+                (Some(a), Some(b)) => a.0.partial_cmp(&b.0).unwrap(),
+                (Some(_), None) => cmp::Ordering::Less,
+                (None, Some(_)) => cmp::Ordering::Greater,
+                (None, None) => cmp::Ordering::Equal,
+            });
+        }
+
+        // If there are too many blocks, forget about the highest one. This only makes
+        // the recovery more conservative, since the octets in it are considered unreceived.
+        self.sack_scoreboard
+            .copy_from_slice(&blocks[..SACK_SCOREBOARD_LEN]);
+    }
+
+    /// Select the next segment to retransmit during a SACK-based loss recovery, using the
+    /// first rule of NextSeg() from RFC 6675. New data, the second rule, is sent as usual.
+    fn sack_rexmit_segment(&self) -> Option<(TcpSeqNumber, usize)> {
+        let recovery = self.sack_recovery?;
+        let high_rxt = recovery.high_rxt.max(self.local_seq_no);
+        let allowed = self.remote_win_len.saturating_sub(self.pipe());
+
+        let hole = self.sack_holes().find(|hole| {
+            hole.blocks_above > 0
+                && hole.end > high_rxt
+                && (hole.is_lost(recovery.mss)
+                    || (recovery.rexmit_first && hole.start == self.local_seq_no))
+        })?;
+        let start = hole.start.max(high_rxt);
+        let size = (hole.end - start).min(recovery.mss).min(allowed);
+        if size == 0 {
+            return None;
+        }
+        Some((start, size))
+    }
+
+    /// Reset the congestion window to the restart window, as described in RFC 5681,
+    /// if nothing is in flight and nothing was sent for longer than the retransmission timeout.
+    fn restart_after_idle(&mut self, cx: &mut Context) {
+        if !self.slow_start_after_idle || self.remote_last_seq != self.local_seq_no {
+            return;
+        }
+        let last_send_at = match self.last_send_at {
+            Some(last_send_at) => last_send_at,
+            None => return,
         };
+        if cx.now() - last_send_at <= self.rtte.retransmission_timeout() {
+            return;
+        }
+
+        let mss = self.effective_mss(cx).unwrap_or(self.remote_mss);
+        let initial_window = match mss {
+            0..=1095 => 4 * mss,
+            1096..=2190 => 3 * mss,
+            _ => 2 * mss,
+        };
+        if self.cwnd > initial_window {
+            tcp_trace!("idle for too long, restarting with cwnd={}", initial_window);
+            self.cwnd = initial_window;
+        }
+    }
+
+    fn seq_to_transmit(&self, cx: &mut Context) -> bool {
+        let ip_header_len = Self::ip_header_len(self.tuple.unwrap().local.addr);
 
         // Max segment size we're able to send due to MTU limitations.
-        let local_mss = cx.ip_mtu() - ip_header_len - TCP_HEADER_LEN;
+        let local_mss = self.local_mss(cx, ip_header_len);
 
         // The effective max segment size, taking into account our and remote's limits.
         let effective_mss = local_mss.min(self.remote_mss);
@@ -1870,15 +4022,19 @@ impl<'a> Socket<'a> {
         }
 
         // max sequence number we can send.
-        let max_send_seq =
-            self.local_seq_no + core::cmp::min(self.remote_win_len, self.tx_buffer.len());
+        let max_send_seq = self.local_seq_no
+            + core::cmp::min(self.remote_win_len.min(self.cwnd), self.tx_buffer.len());
 
         // Max amount of octets we can send.
-        let max_send = if max_send_seq >= self.remote_last_seq {
+        let mut max_send = if max_send_seq >= self.remote_last_seq {
             max_send_seq - self.remote_last_seq
         } else {
             0
         };
+        // During loss recovery, the octets in flight are estimated with the pipe instead.
+        if self.sack_recovery.is_some() {
+            max_send = max_send.min(self.remote_win_len.saturating_sub(self.pipe()));
+        }
 
         // Can we send at least 1 octet?
         let mut can_send = max_send != 0;
@@ -1893,7 +4049,19 @@ impl<'a> Socket<'a> {
             _ => false,
         };
 
-        if self.nagle && data_in_flight && !can_send_full {
+        // Has the data up to the next segment been flushed?
+        let flushed = matches!(self.flush_seq, Some(flush_seq) if self.remote_last_seq < flush_seq);
+
+        if self.nagle && data_in_flight && !can_send_full && !flushed {
+            can_send = false;
+        }
+
+        // Closing the connection flushes a corked socket, or the FIN would never be sent.
+        if self.cork && !can_send_full && !flushed && !want_fin {
+            can_send = false;
+        }
+
+        if self.paced(cx.now()) {
             can_send = false;
         }
 
@@ -1906,6 +4074,11 @@ impl<'a> Socket<'a> {
         can_send || can_fin
     }
 
+    /// Return whether the maximum send rate holds back data at `timestamp`.
+    fn paced(&self, timestamp: Instant) -> bool {
+        matches!(self.paced_until, Some(paced_until) if paced_until > timestamp)
+    }
+
     fn delayed_ack_expired(&self, timestamp: Instant) -> bool {
         match self.ack_delay_timer {
             AckDelayTimer::Idle => true,
@@ -1916,7 +4089,7 @@ impl<'a> Socket<'a> {
 
     fn ack_to_transmit(&self) -> bool {
         if let Some(remote_last_ack) = self.remote_last_ack {
-            remote_last_ack < self.remote_seq_no + self.rx_buffer.len()
+            remote_last_ack < self.remote_seq_no + self.rx_unread()
         } else {
             false
         }
@@ -1933,6 +4106,40 @@ impl<'a> Socket<'a> {
         }
     }
 
+    /// Return whether the ACK for the segment being processed is to be sent without delay,
+    /// and account for that segment in the quick ACK window.
+    fn quickack_segment(&mut self) -> bool {
+        match self.quickack {
+            Some(forced) => forced,
+            None if self.quickack_remaining > 0 => {
+                self.quickack_remaining -= 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Whether the receive window was paused since we last advertised it.
+    ///
+    /// The remote end keeps sending until it learns about the pause, so this update is never
+    /// held back by the delayed ACK timer either.
+    fn window_to_pause(&self) -> bool {
+        match self.state {
+            State::Established | State::FinWait1 | State::FinWait2 => {
+                self.rx_paused && self.remote_last_win != 0
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the last window we advertised was zero and the receive buffer has since drained.
+    ///
+    /// The remote end is stalled until it learns about the new window, so this update is never
+    /// held back by the delayed ACK timer.
+    fn window_to_reopen(&self) -> bool {
+        self.remote_last_win == 0 && self.window_to_update()
+    }
+
     pub(crate) fn dispatch<F, E>(&mut self, cx: &mut Context, emit: F) -> Result<(), E>
     where
         F: FnOnce(&mut Context, (IpRepr, TcpRepr)) -> Result<(), E>,
@@ -1941,6 +4148,9 @@ impl<'a> Socket<'a> {
             return Ok(());
         }
 
+        // Make room in the reassembly buffer for the window to open.
+        self.deliver_reassembled();
+
         if self.remote_last_ts.is_none() {
             // We get here in exactly two cases:
             //  1) This socket just transitioned into SYN-SENT.
@@ -1951,12 +4161,49 @@ impl<'a> Socket<'a> {
             // but from the first transmitted one.
             self.remote_last_ts = Some(cx.now());
         }
+        if self.read_timer_running() && self.read_idle_since.is_none() {
+            self.read_idle_since = Some(cx.now());
+        }
+        if self.write_timer_running() && self.write_idle_since.is_none() {
+            self.write_idle_since = Some(cx.now());
+        }
+        if self.recv_rate_running() && self.recv_rate_interval.is_none() {
+            self.recv_rate_interval = Some((cx.now(), 0));
+        }
+        if matches!(self.recv_rate_deadline(), Some(deadline) if cx.now() >= deadline)
+            && !self.recv_rate_too_low()
+        {
+            // The remote endpoint kept up, measure the next interval.
+            self.recv_rate_interval = Some((cx.now(), 0));
+        }
+
+        if matches!(self.handshake_deadline(), Some(deadline) if cx.now() >= deadline) {
+            // Give up on the half-open connection, and wait for another one.
+            net_debug!("handshake timeout exceeded");
+            self.tuple = None;
+            self.set_state(State::Listen);
+            return Ok(());
+        }
 
         // Check if any state needs to be changed because of a timer.
         if self.timed_out(cx.now()) {
             // If a timeout expires, we should abort the connection.
             net_debug!("timeout exceeded");
             self.set_state(State::Closed);
+            self.close_reason = Some(CloseReason::TimedOut);
+        } else if let Some((_, reason)) = self
+            .progress_deadline()
+            .filter(|&(deadline, _)| cx.now() >= deadline)
+        {
+            // The connection is alive, but not making progress.
+            net_debug!("{}", reason);
+            self.set_state(State::Closed);
+            self.close_reason = Some(reason);
+        } else if matches!(self.recv_rate_deadline(), Some(deadline) if cx.now() >= deadline) {
+            // The remote endpoint didn't keep up with the minimum receive rate.
+            net_debug!("receive rate too low");
+            self.set_state(State::Closed);
+            self.close_reason = Some(CloseReason::TooSlow);
         } else if !self.seq_to_transmit(cx) {
             if let Some(retransmit_delta) = self.timer.should_retransmit(cx.now()) {
                 // If a retransmit timer expired, we should resend data starting at the last ACK.
@@ -1967,6 +4214,11 @@ impl<'a> Socket<'a> {
                 // to be sent again.
                 self.remote_last_seq = self.local_seq_no;
 
+                // The SACK information is not trusted after a timeout, since the remote
+                // may have discarded the data it reported.
+                self.sack_scoreboard = [None; SACK_SCOREBOARD_LEN];
+                self.sack_recovery = None;
+
                 // Clear the `should_retransmit` state. If we can't retransmit right
                 // now for whatever reason (like zero window), this avoids an
                 // infinite polling loop where `poll_at` returns `Now` but `dispatch`
@@ -1975,16 +4227,29 @@ impl<'a> Socket<'a> {
 
                 // Inform RTTE, so that it can avoid bogus measurements.
                 self.rtte.on_retransmit();
+                self.rate.on_retransmit();
             }
         }
 
+        self.restart_after_idle(cx);
+
         // Decide whether we're sending a packet.
-        if self.seq_to_transmit(cx) {
+        let sack_rexmit = self.sack_rexmit_segment().filter(|_| !self.paced(cx.now()));
+        if sack_rexmit.is_some() {
+            // If the scoreboard says a segment was lost, retransmit it.
+            tcp_trace!("outgoing segment will retransmit a lost segment");
+        } else if self.seq_to_transmit(cx) {
             // If we have data to transmit and it fits into partner's window, do it.
             tcp_trace!("outgoing segment will send data or flags");
         } else if self.ack_to_transmit() && self.delayed_ack_expired(cx.now()) {
             // If we have data to acknowledge, do it.
             tcp_trace!("outgoing segment will acknowledge");
+        } else if self.window_to_reopen() {
+            // If the remote end is waiting for our window to open, tell it right away.
+            tcp_trace!("outgoing segment will reopen window");
+        } else if self.window_to_pause() {
+            // If the receive window was paused, tell the remote end right away.
+            tcp_trace!("outgoing segment will pause window");
         } else if self.window_to_update() && self.delayed_ack_expired(cx.now()) {
             // If we have window length increase to advertise, do it.
             tcp_trace!("outgoing segment will update window");
@@ -2023,12 +4288,13 @@ impl<'a> Socket<'a> {
             dst_port: tuple.remote.port,
             control: TcpControl::None,
             seq_number: self.remote_last_seq,
-            ack_number: Some(self.remote_seq_no + self.rx_buffer.len()),
+            ack_number: Some(self.remote_seq_no + self.rx_unread()),
             window_len: self.scaled_window(),
             window_scale: None,
             max_seg_size: None,
             sack_permitted: false,
             sack_ranges: [None, None, None],
+            auth: None,
             payload: &[],
         };
 
@@ -2047,7 +4313,11 @@ impl<'a> Socket<'a> {
             State::SynSent | State::SynReceived => {
                 repr.control = TcpControl::Syn;
                 // window len must NOT be scaled in SYNs.
-                repr.window_len = self.rx_buffer.window().min((1 << 16) - 1) as u16;
+                repr.window_len = if self.rx_paused {
+                    0
+                } else {
+                    self.rx_window().min((1 << 16) - 1) as u16
+                };
                 if self.state == State::SynSent {
                     repr.ack_number = None;
                     repr.window_scale = Some(self.remote_win_shift);
@@ -2069,10 +4339,13 @@ impl<'a> Socket<'a> {
                 // from the transmit buffer.
 
                 // Right edge of window, ie the max sequence number we're allowed to send.
-                let win_right_edge = self.local_seq_no + self.remote_win_len;
+                let win_right_edge = self.local_seq_no + self.remote_win_len.min(self.cwnd);
 
                 // Max amount of octets we're allowed to send according to the remote window.
-                let win_limit = if win_right_edge >= self.remote_last_seq {
+                let mut win_limit = if let Some((seq, size)) = sack_rexmit {
+                    repr.seq_number = seq;
+                    size
+                } else if win_right_edge >= self.remote_last_seq {
                     win_right_edge - self.remote_last_seq
                 } else {
                     // This can happen if we've sent some data and later the remote side
@@ -2082,16 +4355,24 @@ impl<'a> Socket<'a> {
                     // http://www.tcpipguide.com/free/t_TCPWindowManagementIssues.htm
                     0
                 };
+                if sack_rexmit.is_none() && self.sack_recovery.is_some() {
+                    win_limit = win_limit.min(self.remote_win_len.saturating_sub(self.pipe()));
+                }
+                // An ACK sent while the data is paced mustn't carry any of it.
+                if self.paced(cx.now()) {
+                    win_limit = 0;
+                }
 
                 // Maximum size we're allowed to send. This can be limited by 3 factors:
                 // 1. remote window
                 // 2. MSS the remote is willing to accept, probably determined by their MTU
-                // 3. MSS we can send, determined by our MTU.
-                let size = win_limit
-                    .min(self.remote_mss)
-                    .min(cx.ip_mtu() - ip_repr.buffer_len() - TCP_HEADER_LEN);
+                // 3. MSS we can send, determined by our MTU and tunnel overhead.
+                let mss = self
+                    .remote_mss
+                    .min(self.local_mss(cx, ip_repr.buffer_len()));
+                let size = win_limit.min(mss.saturating_sub(self.options_len()));
 
-                let offset = self.remote_last_seq - self.local_seq_no;
+                let offset = repr.seq_number - self.local_seq_no;
                 repr.payload = self.tx_buffer.get_allocated(offset, size);
 
                 // If we've sent everything we had in the buffer, follow it with the PSH or FIN
@@ -2133,7 +4414,7 @@ impl<'a> Socket<'a> {
             tcp_trace!(
                 "tx buffer: sending {} octets at offset {}",
                 repr.payload.len(),
-                self.remote_last_seq - self.local_seq_no
+                repr.seq_number - self.local_seq_no
             );
         }
         if repr.control != TcpControl::None || repr.payload.is_empty() {
@@ -2151,7 +4432,7 @@ impl<'a> Socket<'a> {
 
         if repr.control == TcpControl::Syn {
             // Fill the MSS option. See RFC 6691 for an explanation of this calculation.
-            let max_segment_size = cx.ip_mtu() - ip_repr.buffer_len() - TCP_HEADER_LEN;
+            let max_segment_size = self.local_mss(cx, ip_repr.buffer_len());
             repr.max_seg_size = Some(max_segment_size as u16);
         }
 
@@ -2162,6 +4443,10 @@ impl<'a> Socket<'a> {
         // Bailing out if the packet isn't placed in the device buffer allows us
         // to not waste time waiting for the retransmit timer on packets that we know
         // for sure will not be successfully transmitted.
+        #[cfg(feature = "socket-tcp-ao")]
+        {
+            repr.auth = self.auth.sign(&ip_repr, &repr);
+        }
         ip_repr.set_payload_len(repr.buffer_len());
         emit(cx, (ip_repr, repr))?;
 
@@ -2188,13 +4473,39 @@ impl<'a> Socket<'a> {
         }
 
         // We've sent a packet successfully, so we can update the internal state now.
-        self.remote_last_seq = repr.seq_number + repr.segment_len();
         self.remote_last_ack = repr.ack_number;
         self.remote_last_win = repr.window_len;
-
         if repr.segment_len() > 0 {
-            self.rtte
-                .on_send(cx.now(), repr.seq_number + repr.segment_len());
+            self.last_send_at = Some(cx.now());
+        }
+        if let Some(rate) = self.max_send_rate {
+            if !repr.payload.is_empty() {
+                let micros = repr.payload.len() as u64 * 1_000_000 / rate;
+                self.paced_until = Some(cx.now() + Duration::from_micros(micros));
+            }
+        }
+
+        match (sack_rexmit, self.sack_recovery.as_mut()) {
+            (Some(_), Some(recovery)) => {
+                // A retransmission doesn't advance the last sequence number sent.
+                recovery.high_rxt = repr.seq_number + repr.segment_len();
+                recovery.rexmit_first = false;
+            }
+            _ => {
+                let data_in_flight = self.remote_last_seq != self.local_seq_no;
+                self.remote_last_seq = repr.seq_number + repr.segment_len();
+
+                if repr.segment_len() > 0 {
+                    self.rtte
+                        .on_send(cx.now(), repr.seq_number + repr.segment_len());
+                }
+                if !repr.payload.is_empty() {
+                    let app_limited =
+                        self.remote_last_seq >= self.local_seq_no + self.tx_buffer.len();
+                    self.rate
+                        .on_send(cx.now(), self.remote_last_seq, data_in_flight, app_limited);
+                }
+            }
         }
 
         if !self.seq_to_transmit(cx) && repr.segment_len() > 0 {
@@ -2212,6 +4523,88 @@ impl<'a> Socket<'a> {
         Ok(())
     }
 
+    /// Panic if the state of the socket is inconsistent, or went backwards since the last check.
+    #[cfg(feature = "paranoid")]
+    pub(crate) fn check_invariants(&mut self, timestamp: Instant) {
+        self.rx_buffer.check_invariants();
+        self.reassembly_buffer.check_invariants();
+        self.tx_buffer.check_invariants();
+        self.assembler.check_invariants();
+        assert!(
+            self.assembler.total_size() == self.window_buffer().capacity(),
+            "tcp: assembler covers {} octets, but the reassembling buffer holds {}",
+            self.assembler.total_size(),
+            self.window_buffer().capacity()
+        );
+
+        // Only the SYN and the FIN take up sequence space beyond the transmit buffer.
+        let max_in_flight = match self.state {
+            State::Closed | State::Listen => None,
+            State::SynSent | State::SynReceived => Some(1),
+            _ => Some(self.tx_buffer.len() + 1),
+        };
+        if let Some(max_in_flight) = max_in_flight {
+            assert!(
+                self.remote_last_seq >= self.local_seq_no
+                    && self.remote_last_seq - self.local_seq_no <= max_in_flight,
+                "tcp: last sequence number sent {} is outside of {}+{} in state {}",
+                self.remote_last_seq,
+                self.local_seq_no,
+                max_in_flight,
+                self.state
+            );
+        }
+
+        // Timers are armed relative to the time they are set at, which can't be in the future.
+        match self.timer {
+            Timer::Retransmit { expires_at, delay } => assert!(
+                expires_at <= timestamp + delay,
+                "tcp: retransmit timer expires at {}, over {} from now {}",
+                expires_at,
+                delay,
+                timestamp
+            ),
+            Timer::Close { expires_at } => assert!(
+                expires_at <= timestamp + CLOSE_DELAY,
+                "tcp: close timer expires at {}, over {} from now {}",
+                expires_at,
+                CLOSE_DELAY,
+                timestamp
+            ),
+            _ => {}
+        }
+
+        let synchronized = !matches!(
+            self.state,
+            State::Closed | State::Listen | State::SynSent | State::SynReceived
+        );
+        let checked_seq = match self.tuple {
+            Some(tuple) if synchronized => Some((tuple, self.local_seq_no, self.remote_seq_no)),
+            _ => None,
+        };
+        if let (
+            Some((tuple, local_seq_no, remote_seq_no)),
+            Some((last_tuple, last_local, last_remote)),
+        ) = (checked_seq, self.checked_seq)
+        {
+            if tuple == last_tuple {
+                assert!(
+                    local_seq_no >= last_local,
+                    "tcp: unacknowledged sequence number went back from {} to {}",
+                    last_local,
+                    local_seq_no
+                );
+                assert!(
+                    remote_seq_no >= last_remote,
+                    "tcp: received sequence number went back from {} to {}",
+                    last_remote,
+                    remote_seq_no
+                );
+            }
+        }
+        self.checked_seq = checked_seq;
+    }
+
     #[allow(clippy::if_same_then_else)]
     pub(crate) fn poll_at(&self, cx: &mut Context) -> PollAt {
         // The logic here mirrors the beginning of dispatch() closely.
@@ -2221,12 +4614,22 @@ impl<'a> Socket<'a> {
         } else if self.remote_last_ts.is_none() {
             // Socket stopped being quiet recently, we need to acquire a timestamp.
             PollAt::Now
+        } else if (self.read_timer_running() && self.read_idle_since.is_none())
+            || (self.write_timer_running() && self.write_idle_since.is_none())
+            || (self.recv_rate_running() && self.recv_rate_interval.is_none())
+        {
+            // A read or write timeout, or a receive rate interval, was just (re)started. It
+            // needs a timestamp too.
+            PollAt::Now
         } else if self.state == State::Closed {
             // Socket was aborted, we have an RST packet to transmit.
             PollAt::Now
-        } else if self.seq_to_transmit(cx) {
+        } else if self.seq_to_transmit(cx) || self.sack_rexmit_segment().is_some() {
             // We have a data or flag packet to transmit.
             PollAt::Now
+        } else if self.window_to_reopen() || self.window_to_pause() {
+            // We have a window update for a stalled, or to be stalled, remote end to transmit.
+            PollAt::Now
         } else {
             let want_ack = self.ack_to_transmit() || self.window_to_update();
 
@@ -2245,11 +4648,34 @@ impl<'a> Socket<'a> {
                 (_, _) => PollAt::Ingress,
             };
 
-            // We wait for the earliest of our timers to fire.
-            *[self.timer.poll_at(), timeout_poll_at, delayed_ack_poll_at]
-                .iter()
+            let progress_poll_at = self
+                .progress_deadline()
+                .map(|(deadline, _)| deadline)
+                .into_iter()
+                .chain(self.handshake_deadline())
+                .chain(self.recv_rate_deadline())
                 .min()
-                .unwrap_or(&PollAt::Ingress)
+                .map_or(PollAt::Ingress, PollAt::Time);
+
+            // If the maximum send rate holds back data, send it once it's released.
+            let unsent = self.remote_last_seq < self.local_seq_no + self.tx_buffer.len()
+                || self.sack_recovery.is_some();
+            let pacing_poll_at = match self.paced_until {
+                Some(paced_until) if unsent && self.paced(cx.now()) => PollAt::Time(paced_until),
+                _ => PollAt::Ingress,
+            };
+
+            // We wait for the earliest of our timers to fire.
+            *[
+                self.timer.poll_at(),
+                timeout_poll_at,
+                progress_poll_at,
+                pacing_poll_at,
+                delayed_ack_poll_at,
+            ]
+            .iter()
+            .min()
+            .unwrap_or(&PollAt::Ingress)
         }
     }
 }
@@ -2268,6 +4694,8 @@ impl<'a> fmt::Write for Socket<'a> {
 #[cfg(test)]
 mod test {
     use super::*;
+    #[cfg(feature = "socket-tcp-ao")]
+    use crate::phy::ChecksumCapabilities;
     use crate::wire::IpRepr;
     use crate::Error;
     use core::i32;
@@ -2347,6 +4775,7 @@ mod test {
         max_seg_size: None,
         sack_permitted: false,
         sack_ranges: [None, None, None],
+        auth: None,
         payload: &[],
     };
     const _RECV_IP_TEMPL: IpRepr = IpReprIpvX(IpvXRepr {
@@ -2367,6 +4796,7 @@ mod test {
         max_seg_size: None,
         sack_permitted: false,
         sack_ranges: [None, None, None],
+        auth: None,
         payload: &[],
     };
 
@@ -2770,15 +5200,86 @@ mod test {
     }
 
     #[test]
-    fn test_listen_twice() {
+    fn test_listen_range_validation() {
         let mut s = socket();
-        assert_eq!(s.listen(80), Ok(()));
-        assert_eq!(s.listen(80), Err(ListenError::InvalidState));
+        assert_eq!(
+            s.listen_range(None, 0..=10),
+            Err(ListenError::Unaddressable)
+        );
+        #[allow(clippy::reversed_empty_ranges)]
+        let empty = 90..=80;
+        assert_eq!(s.listen_range(None, empty), Err(ListenError::Unaddressable));
+        assert_eq!(s.state, State::Closed);
+        assert_eq!(s.listen_range(None, 80..=89), Ok(()));
+        assert_eq!(
+            s.listen_range(None, 80..=89),
+            Err(ListenError::InvalidState)
+        );
     }
 
     #[test]
-    fn test_listen_syn() {
-        let mut s = socket_listen();
+    fn test_listen_range_syn() {
+        let mut s = socket();
+        s.listen_range(None, LOCAL_PORT - 5..=LOCAL_PORT + 5)
+            .unwrap();
+        assert!(s.is_range_listener());
+
+        let ip_repr = IpReprIpvX(IpvXRepr {
+            src_addr: REMOTE_ADDR,
+            dst_addr: LOCAL_ADDR,
+            next_header: IpProtocol::Tcp,
+            payload_len: SEND_TEMPL.buffer_len(),
+            hop_limit: 64,
+        });
+        for &port in &[LOCAL_PORT - 6, LOCAL_PORT + 6] {
+            let repr = TcpRepr {
+                control: TcpControl::Syn,
+                dst_port: port,
+                ack_number: None,
+                ..SEND_TEMPL
+            };
+            assert!(!s.socket.accepts(&mut s.cx, &ip_repr, &repr));
+        }
+
+        send!(
+            s,
+            TcpRepr {
+                control: TcpControl::Syn,
+                dst_port: LOCAL_PORT + 5,
+                seq_number: REMOTE_SEQ,
+                ack_number: None,
+                ..SEND_TEMPL
+            }
+        );
+        assert_eq!(s.state, State::SynReceived);
+        assert!(!s.is_range_listener());
+        assert_eq!(
+            s.local_endpoint(),
+            Some(IpEndpoint::new(LOCAL_ADDR.into(), LOCAL_PORT + 5))
+        );
+        recv!(
+            s,
+            [TcpRepr {
+                control: TcpControl::Syn,
+                src_port: LOCAL_PORT + 5,
+                seq_number: LOCAL_SEQ,
+                ack_number: Some(REMOTE_SEQ + 1),
+                max_seg_size: Some(BASE_MSS),
+                ..RECV_TEMPL
+            }]
+        );
+    }
+
+    #[test]
+    fn test_listen_twice() {
+        let mut s = socket();
+        assert_eq!(s.listen(80), Ok(()));
+        assert_eq!(s.listen(80), Err(ListenError::InvalidState));
+    }
+
+    #[test]
+    fn test_listen_syn() {
+        let mut s = socket_listen();
         send!(
             s,
             TcpRepr {
@@ -2828,6 +5329,142 @@ mod test {
         assert_eq!(s.state, State::Closed);
     }
 
+    fn syn_cookie(s: &mut TestSocket, timestamp: Instant) -> TcpSeqNumber {
+        s.cx.set_now(timestamp);
+        let ip_repr = IpReprIpvX(IpvXRepr {
+            src_addr: REMOTE_ADDR,
+            dst_addr: LOCAL_ADDR,
+            next_header: IpProtocol::Tcp,
+            payload_len: 20,
+            hop_limit: 64,
+        });
+        let syn = TcpRepr {
+            control: TcpControl::Syn,
+            seq_number: REMOTE_SEQ,
+            ack_number: None,
+            max_seg_size: Some(1400),
+            window_scale: Some(2),
+            sack_permitted: true,
+            ..SEND_TEMPL
+        };
+        let (_, reply) = s
+            .socket
+            .syn_cookie_reply(&mut s.cx, &ip_repr, &syn)
+            .unwrap();
+        assert_eq!(reply.control, TcpControl::Syn);
+        assert_eq!(reply.ack_number, Some(REMOTE_SEQ + 1));
+        assert_eq!(reply.max_seg_size, Some(BASE_MSS));
+        assert_eq!(reply.window_scale, Some(s.remote_win_shift));
+        assert!(reply.sack_permitted);
+        assert_eq!(s.state, State::Listen);
+        reply.seq_number
+    }
+
+    #[test]
+    fn test_listen_syn_cookie() {
+        let mut s = socket_listen();
+        s.set_syn_cookies(true);
+        let cookie = syn_cookie(&mut s, Instant::from_secs(0));
+        assert_eq!(s.tuple, None);
+
+        send!(
+            s,
+            time 100_000,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(cookie + 1),
+                payload: &b"abcdef"[..],
+                ..SEND_TEMPL
+            }
+        );
+        assert_eq!(s.state, State::Established);
+        assert_eq!(s.local_seq_no, cookie + 1);
+        assert_eq!(s.remote_mss, 1400);
+        assert_eq!(s.remote_win_scale, Some(2));
+        assert!(s.remote_has_sack);
+        assert_eq!(s.rx_buffer.dequeue_many(6), &b"abcdef"[..]);
+    }
+
+    #[test]
+    fn test_listen_syn_cookie_invalid() {
+        let mut s = socket_listen();
+        s.set_syn_cookies(true);
+        let cookie = syn_cookie(&mut s, Instant::from_secs(0));
+        let ip_repr = IpReprIpvX(IpvXRepr {
+            src_addr: REMOTE_ADDR,
+            dst_addr: LOCAL_ADDR,
+            next_header: IpProtocol::Tcp,
+            payload_len: 20,
+            hop_limit: 64,
+        });
+        let ack = TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(cookie + 1),
+            ..SEND_TEMPL
+        };
+
+        // A cookie is bound to the 4-tuple and the initial sequence number of the remote
+        // endpoint.
+        let other_port = TcpRepr {
+            src_port: REMOTE_PORT + 1,
+            ..ack
+        };
+        assert!(!s.socket.accepts(&mut s.cx, &ip_repr, &other_port));
+        let other_seq = TcpRepr {
+            seq_number: REMOTE_SEQ + 2,
+            ack_number: Some(cookie + 2),
+            ..ack
+        };
+        assert!(!s.socket.accepts(&mut s.cx, &ip_repr, &other_seq));
+        let forged = TcpRepr {
+            ack_number: Some(cookie + 2),
+            ..ack
+        };
+        assert!(!s.socket.accepts(&mut s.cx, &ip_repr, &forged));
+
+        // It expires after one to two periods.
+        s.cx.set_now(Instant::from_secs(127));
+        assert!(s.socket.accepts(&mut s.cx, &ip_repr, &ack));
+        s.cx.set_now(Instant::from_secs(128));
+        assert!(!s.socket.accepts(&mut s.cx, &ip_repr, &ack));
+
+        // And is only accepted while SYN cookies are enabled.
+        s.cx.set_now(Instant::from_secs(0));
+        s.set_syn_cookies(false);
+        assert!(!s.socket.accepts(&mut s.cx, &ip_repr, &ack));
+        assert_eq!(s.state, State::Listen);
+    }
+
+    #[test]
+    fn test_listen_syn_cookie_small_mss() {
+        let mut s = socket_listen();
+        s.set_syn_cookies(true);
+        let ip_repr = IpReprIpvX(IpvXRepr {
+            src_addr: REMOTE_ADDR,
+            dst_addr: LOCAL_ADDR,
+            next_header: IpProtocol::Tcp,
+            payload_len: 20,
+            hop_limit: 64,
+        });
+        let syn = TcpRepr {
+            control: TcpControl::Syn,
+            seq_number: REMOTE_SEQ,
+            ack_number: None,
+            max_seg_size: Some(215),
+            ..SEND_TEMPL
+        };
+        // The MSS can't be encoded without rounding it up.
+        assert_eq!(s.socket.syn_cookie_reply(&mut s.cx, &ip_repr, &syn), None);
+        let syn = TcpRepr {
+            max_seg_size: Some(216),
+            ..syn
+        };
+        assert!(s
+            .socket
+            .syn_cookie_reply(&mut s.cx, &ip_repr, &syn)
+            .is_some());
+    }
+
     // =========================================================================================//
     // Tests for the SYN-RECEIVED state.
     // =========================================================================================//
@@ -3134,6 +5771,115 @@ mod test {
         assert_eq!(s.tuple, Some(TUPLE));
     }
 
+    #[test]
+    fn test_connect_reuse_after_close() {
+        let mut s = socket();
+        for _ in 0..3 {
+            s.socket
+                .connect(&mut s.cx, REMOTE_END, LOCAL_END.port)
+                .unwrap();
+            // Nothing from the previous connection is carried over.
+            assert_eq!(s.local_seq_no, LOCAL_SEQ);
+            assert_eq!(s.remote_last_seq, LOCAL_SEQ);
+            assert_eq!(s.remote_last_ack, None);
+            assert_eq!(s.remote_mss, DEFAULT_MSS);
+            assert!(!s.remote_has_sack);
+            assert_eq!(s.local_rx_last_seq, None);
+            assert_eq!(s.local_rx_last_ack, None);
+            assert_eq!(s.local_rx_dup_acks, 0);
+            assert!(s.assembler.is_empty());
+            assert!(s.rx_buffer.is_empty());
+            assert!(s.tx_buffer.is_empty());
+            assert_eq!(s.rx_buffer.capacity(), 64);
+            assert_eq!(s.tx_buffer.capacity(), 64);
+
+            recv!(
+                s,
+                [TcpRepr {
+                    control: TcpControl::Syn,
+                    seq_number: LOCAL_SEQ,
+                    ack_number: None,
+                    max_seg_size: Some(BASE_MSS),
+                    window_scale: Some(0),
+                    sack_permitted: true,
+                    ..RECV_TEMPL
+                }]
+            );
+            send!(
+                s,
+                TcpRepr {
+                    control: TcpControl::Syn,
+                    seq_number: REMOTE_SEQ,
+                    ack_number: Some(LOCAL_SEQ + 1),
+                    max_seg_size: Some(BASE_MSS - 80),
+                    window_scale: Some(0),
+                    sack_permitted: true,
+                    ..SEND_TEMPL
+                }
+            );
+            recv!(
+                s,
+                [TcpRepr {
+                    seq_number: LOCAL_SEQ + 1,
+                    ack_number: Some(REMOTE_SEQ + 1),
+                    ..RECV_TEMPL
+                }]
+            );
+
+            // Leave unread and unacknowledged data behind in both buffers.
+            s.send_slice(b"abcdef").unwrap();
+            recv!(
+                s,
+                [TcpRepr {
+                    seq_number: LOCAL_SEQ + 1,
+                    ack_number: Some(REMOTE_SEQ + 1),
+                    payload: &b"abcdef"[..],
+                    ..RECV_TEMPL
+                }]
+            );
+            send!(
+                s,
+                TcpRepr {
+                    seq_number: REMOTE_SEQ + 1,
+                    ack_number: Some(LOCAL_SEQ + 1),
+                    payload: &b"xyz"[..],
+                    ..SEND_TEMPL
+                }
+            );
+            recv!(
+                s,
+                [TcpRepr {
+                    seq_number: LOCAL_SEQ + 1 + 6,
+                    ack_number: Some(REMOTE_SEQ + 1 + 3),
+                    window_len: 61,
+                    ..RECV_TEMPL
+                }]
+            );
+            send!(
+                s,
+                TcpRepr {
+                    seq_number: REMOTE_SEQ + 1 + 3,
+                    ack_number: Some(LOCAL_SEQ + 1),
+                    ..SEND_TEMPL
+                }
+            );
+            assert_eq!(s.local_rx_dup_acks, 1);
+
+            s.abort();
+            recv!(
+                s,
+                [TcpRepr {
+                    control: TcpControl::Rst,
+                    seq_number: LOCAL_SEQ + 1 + 6,
+                    ack_number: Some(REMOTE_SEQ + 1 + 3),
+                    window_len: 61,
+                    ..RECV_TEMPL
+                }]
+            );
+            assert_eq!(s.state, State::Closed);
+        }
+    }
+
     #[test]
     fn test_connect_unspecified_local() {
         let mut s = socket();
@@ -4023,11 +6769,37 @@ mod test {
             }
         );
         assert_eq!(s.state, State::Closed);
+        assert_eq!(
+            s.close_reason(),
+            Some(CloseReason::Reset {
+                unacked: 0,
+                unsent: 0
+            })
+        );
     }
 
     #[test]
-    fn test_established_rst_no_ack() {
+    fn test_established_rst_with_send_data() {
         let mut s = socket_established();
+        s.send_slice(b"abcdef").unwrap();
+        recv!(
+            s,
+            [TcpRepr {
+                seq_number: LOCAL_SEQ + 1,
+                ack_number: Some(REMOTE_SEQ + 1),
+                payload: &b"abcdef"[..],
+                ..RECV_TEMPL
+            }]
+        );
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1 + 2),
+                ..SEND_TEMPL
+            }
+        );
+        s.send_slice(b"ghi").unwrap();
         send!(
             s,
             TcpRepr {
@@ -4038,47 +6810,293 @@ mod test {
             }
         );
         assert_eq!(s.state, State::Closed);
+        assert_eq!(
+            s.close_reason(),
+            Some(CloseReason::Reset {
+                unacked: 4,
+                unsent: 3
+            })
+        );
     }
 
     #[test]
-    fn test_established_close() {
+    fn test_fin_wait_1_rst_with_send_data() {
         let mut s = socket_established();
+        s.send_slice(b"abc").unwrap();
         s.close();
-        assert_eq!(s.state, State::FinWait1);
-        sanity!(s, socket_fin_wait_1());
-    }
-
-    #[test]
-    fn test_established_abort() {
-        let mut s = socket_established();
-        s.abort();
-        assert_eq!(s.state, State::Closed);
         recv!(
             s,
             [TcpRepr {
-                control: TcpControl::Rst,
+                control: TcpControl::Fin,
                 seq_number: LOCAL_SEQ + 1,
                 ack_number: Some(REMOTE_SEQ + 1),
+                payload: &b"abc"[..],
                 ..RECV_TEMPL
             }]
         );
+        send!(
+            s,
+            TcpRepr {
+                control: TcpControl::Rst,
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: None,
+                ..SEND_TEMPL
+            }
+        );
+        // The FIN is not counted.
+        assert_eq!(
+            s.close_reason(),
+            Some(CloseReason::Reset {
+                unacked: 3,
+                unsent: 0
+            })
+        );
     }
 
     #[test]
-    fn test_established_rst_bad_seq() {
+    fn test_established_rst_no_ack() {
         let mut s = socket_established();
         send!(
             s,
             TcpRepr {
                 control: TcpControl::Rst,
-                seq_number: REMOTE_SEQ, // Wrong seq
+                seq_number: REMOTE_SEQ + 1,
                 ack_number: None,
                 ..SEND_TEMPL
-            },
-            Some(TcpRepr {
-                seq_number: LOCAL_SEQ + 1,
-                ack_number: Some(REMOTE_SEQ + 1),
-                ..RECV_TEMPL
+            }
+        );
+        assert_eq!(s.state, State::Closed);
+    }
+
+    #[test]
+    fn test_established_close() {
+        let mut s = socket_established();
+        s.close();
+        assert_eq!(s.state, State::FinWait1);
+        sanity!(s, socket_fin_wait_1());
+    }
+
+    #[test]
+    fn test_established_abort() {
+        let mut s = socket_established();
+        s.abort();
+        assert_eq!(s.state, State::Closed);
+        assert_eq!(s.close_reason(), Some(CloseReason::Aborted));
+        recv!(
+            s,
+            [TcpRepr {
+                control: TcpControl::Rst,
+                seq_number: LOCAL_SEQ + 1,
+                ack_number: Some(REMOTE_SEQ + 1),
+                ..RECV_TEMPL
+            }]
+        );
+    }
+
+    #[test]
+    fn test_established_local_address_removed() {
+        let mut s = socket_established();
+        s.send_slice(b"abcdef").unwrap();
+        s.local_address_removed(REMOTE_ADDR.into());
+        assert_eq!(s.state, State::Established);
+        assert_eq!(s.close_reason(), None);
+
+        s.local_address_removed(LOCAL_ADDR.into());
+        assert_eq!(s.state, State::Closed);
+        assert_eq!(s.close_reason(), Some(CloseReason::LocalAddressRemoved));
+        // Nothing, not even a RST, is sent from the removed address.
+        recv!(s, []);
+        s.listen(LOCAL_PORT).unwrap();
+        assert_eq!(s.close_reason(), None);
+    }
+
+    #[test]
+    fn test_export_import_connection() {
+        let mut s = socket_established();
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1),
+                payload: &b"abcdef"[..],
+                ..SEND_TEMPL
+            }
+        );
+        s.send_slice(b"012345").unwrap();
+        recv!(
+            s,
+            [TcpRepr {
+                seq_number: LOCAL_SEQ + 1,
+                ack_number: Some(REMOTE_SEQ + 1 + 6),
+                window_len: 58,
+                payload: &b"012345"[..],
+                ..RECV_TEMPL
+            }]
+        );
+        // The first half of the data is acknowledged, the second half is in flight.
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1 + 6,
+                ack_number: Some(LOCAL_SEQ + 1 + 3),
+                ..SEND_TEMPL
+            }
+        );
+        s.send_slice(b"6789").unwrap();
+
+        let (mut rx_data, mut tx_data) = ([0; 6], [0; 7]);
+        let state = s.export_connection(&mut rx_data, &mut tx_data).unwrap();
+        assert_eq!(rx_data, *b"abcdef");
+        assert_eq!(&tx_data, b"3456789");
+        assert_eq!(state.local, LOCAL_END);
+        assert_eq!(state.remote, REMOTE_END);
+        assert_eq!(state.state, State::Established);
+        assert_eq!(state.send_unacked, LOCAL_SEQ + 1 + 3);
+        assert_eq!(state.recv_next, REMOTE_SEQ + 1 + 6);
+        assert_eq!(state.recv_window, 58);
+
+        let mut s2 = socket();
+        s2.import_connection(&state, &rx_data, &tx_data).unwrap();
+        assert_eq!(s2.state, State::Established);
+        assert_eq!(s2.recv_queue(), 6);
+        assert_eq!(s2.send_queue(), 7);
+        {
+            let (mut rx_data2, mut tx_data2) = ([0; 6], [0; 7]);
+            let state2 = s2.export_connection(&mut rx_data2, &mut tx_data2).unwrap();
+            assert_eq!(state2, state);
+        }
+
+        // The data in flight is sent again, followed by the rest.
+        recv!(
+            s2,
+            [TcpRepr {
+                seq_number: LOCAL_SEQ + 1 + 3,
+                ack_number: Some(REMOTE_SEQ + 1 + 6),
+                window_len: 58,
+                payload: &b"3456789"[..],
+                ..RECV_TEMPL
+            }]
+        );
+        assert_eq!(s2.recv_slice(&mut [0; 6]), Ok(6));
+        send!(
+            s2,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1 + 6,
+                ack_number: Some(LOCAL_SEQ + 1 + 10),
+                payload: &b"ghi"[..],
+                ..SEND_TEMPL
+            }
+        );
+        assert_eq!(s2.send_queue(), 0);
+        recv!(
+            s2,
+            [TcpRepr {
+                seq_number: LOCAL_SEQ + 1 + 10,
+                ack_number: Some(REMOTE_SEQ + 1 + 9),
+                window_len: 61,
+                ..RECV_TEMPL
+            }]
+        );
+    }
+
+    #[test]
+    fn test_export_connection_invalid() {
+        let mut s = socket();
+        assert_eq!(
+            s.export_connection(&mut [], &mut []),
+            Err(ExportError::InvalidState)
+        );
+
+        let mut s = socket_established();
+        s.send_slice(b"abc").unwrap();
+        assert_eq!(
+            s.export_connection(&mut [], &mut [0; 2]),
+            Err(ExportError::BufferTooSmall)
+        );
+        assert!(s.export_connection(&mut [], &mut [0; 3]).is_ok());
+    }
+
+    #[test]
+    fn test_import_connection_invalid() {
+        let state = socket_established()
+            .export_connection(&mut [], &mut [])
+            .unwrap();
+
+        let mut s = socket_established();
+        assert_eq!(
+            s.import_connection(&state, &[], &[]),
+            Err(ImportError::InvalidState)
+        );
+
+        let mut s = socket_with_buffer_sizes(4, 64);
+        let malformed = [
+            ConnectionState {
+                state: State::FinWait1,
+                ..state
+            },
+            ConnectionState {
+                send_window_scale: Some(15),
+                ..state
+            },
+            ConnectionState {
+                recv_window_scale: 2,
+                ..state
+            },
+            // The window can't be advertised with the window scale.
+            ConnectionState {
+                recv_window: 0x1_0000 << state.recv_window_scale,
+                ..state
+            },
+            ConnectionState {
+                remote_mss: 0,
+                ..state
+            },
+        ];
+        for state in malformed.iter() {
+            assert_eq!(
+                s.import_connection(state, &[], &[]),
+                Err(ImportError::Malformed)
+            );
+        }
+        assert_eq!(
+            s.import_connection(
+                &ConnectionState {
+                    remote: IpEndpoint::new(REMOTE_ADDR.into(), 0),
+                    ..state
+                },
+                &[],
+                &[]
+            ),
+            Err(ImportError::Unaddressable)
+        );
+        assert_eq!(
+            s.import_connection(&state, &[], &[0; 5]),
+            Err(ImportError::BufferTooSmall)
+        );
+        // Data the remote endpoint is allowed to send would not fit.
+        assert_eq!(
+            s.import_connection(&state, &[0; 1], &[]),
+            Err(ImportError::BufferTooSmall)
+        );
+        assert_eq!(s.state, State::Closed);
+        assert_eq!(s.import_connection(&state, &[], &[0; 4]), Ok(()));
+    }
+
+    #[test]
+    fn test_established_rst_bad_seq() {
+        let mut s = socket_established();
+        send!(
+            s,
+            TcpRepr {
+                control: TcpControl::Rst,
+                seq_number: REMOTE_SEQ, // Wrong seq
+                ack_number: None,
+                ..SEND_TEMPL
+            },
+            Some(TcpRepr {
+                seq_number: LOCAL_SEQ + 1,
+                ack_number: Some(REMOTE_SEQ + 1),
+                ..RECV_TEMPL
             })
         );
 
@@ -4335,6 +7353,22 @@ mod test {
         assert_eq!(s.state, State::TimeWait);
     }
 
+    #[test]
+    fn test_time_wait_reap() {
+        let mut s = socket_established();
+        assert!(!s.reap_time_wait());
+        assert_eq!(s.state, State::Established);
+
+        let mut s = socket_time_wait(false);
+        assert!(s.reap_time_wait());
+        assert_eq!(s.state, State::Closed);
+        assert_eq!(s.close_reason(), None);
+        // Nothing is sent when the state is reaped.
+        recv!(s, []);
+        // The socket can be reused right away.
+        s.listen(LOCAL_PORT).unwrap();
+    }
+
     #[test]
     fn test_time_wait_retransmit() {
         let mut s = socket_time_wait(false);
@@ -5537,83 +8571,612 @@ mod test {
         recv!(s, Err(Error::Exhausted));
     }
 
-    // =========================================================================================//
-    // Tests for window management.
-    // =========================================================================================//
+    fn sack_block(start: usize, end: usize) -> Option<(u32, u32)> {
+        let start = LOCAL_SEQ + 1 + start;
+        let end = LOCAL_SEQ + 1 + end;
+        Some((start.0 as u32, end.0 as u32))
+    }
 
-    #[test]
-    fn test_maximum_segment_size() {
-        let mut s = socket_listen();
-        s.tx_buffer = SocketBuffer::new(vec![0; 32767]);
-        send!(
-            s,
-            TcpRepr {
-                control: TcpControl::Syn,
-                seq_number: REMOTE_SEQ,
-                ack_number: None,
-                max_seg_size: Some(1000),
-                ..SEND_TEMPL
-            }
-        );
-        recv!(
-            s,
-            [TcpRepr {
-                control: TcpControl::Syn,
-                seq_number: LOCAL_SEQ,
-                ack_number: Some(REMOTE_SEQ + 1),
-                max_seg_size: Some(BASE_MSS),
-                ..RECV_TEMPL
-            }]
-        );
-        send!(
-            s,
-            TcpRepr {
-                seq_number: REMOTE_SEQ + 1,
-                ack_number: Some(LOCAL_SEQ + 1),
-                window_len: 32767,
-                ..SEND_TEMPL
-            }
-        );
-        s.send_slice(&[0; 1200][..]).unwrap();
-        recv!(
-            s,
-            Ok(TcpRepr {
-                seq_number: LOCAL_SEQ + 1,
+    fn socket_sack_recovery() -> TestSocket {
+        let mut s = socket_established();
+        s.remote_has_sack = true;
+        s.remote_mss = 6;
+
+        // Eight segments, of which the second and the fifth are lost.
+        let data = b"000000111111222222333333444444555555666666777777";
+        s.send_slice(data).unwrap();
+        for i in 0..8 {
+            recv!(s, time 1000, Ok(TcpRepr {
+                seq_number: LOCAL_SEQ + 1 + i * 6,
                 ack_number: Some(REMOTE_SEQ + 1),
-                payload: &[0; 1000][..],
+                payload:    &data[i * 6..(i + 1) * 6],
                 ..RECV_TEMPL
-            })
-        );
+            }));
+        }
+        send!(s, time 1050, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1 + 6),
+            ..SEND_TEMPL
+        });
+        send!(s, time 1051, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1 + 6),
+            sack_ranges: [sack_block(12, 18), None, None],
+            ..SEND_TEMPL
+        });
+        send!(s, time 1052, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1 + 6),
+            sack_ranges: [sack_block(12, 24), None, None],
+            ..SEND_TEMPL
+        });
+        assert!(s.sack_recovery.is_none());
+        send!(s, time 1053, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1 + 6),
+            sack_ranges: [sack_block(30, 36), sack_block(12, 24), None],
+            ..SEND_TEMPL
+        });
+        assert!(s.sack_recovery.is_some());
+        s
     }
 
     #[test]
-    fn test_close_wait_no_window_update() {
-        let mut s = socket_established();
-        send!(
-            s,
-            TcpRepr {
-                control: TcpControl::Fin,
-                seq_number: REMOTE_SEQ + 1,
-                ack_number: Some(LOCAL_SEQ + 1),
-                payload: &[1, 2, 3, 4],
-                ..SEND_TEMPL
-            }
-        );
-        assert_eq!(s.state, State::CloseWait);
+    fn test_sack_recovery_multiple_losses() {
+        let mut s = socket_sack_recovery();
+        // The second segment is presumed lost; the second hole and the unSACKed
+        // segments at the end are still in flight.
+        assert_eq!(s.pipe(), 6 + 12);
 
-        // we ack the FIN, with the reduced window size.
-        recv!(
-            s,
-            Ok(TcpRepr {
-                seq_number: LOCAL_SEQ + 1,
-                ack_number: Some(REMOTE_SEQ + 6),
-                window_len: 60,
-                ..RECV_TEMPL
-            })
-        );
+        // Retransmit only the first hole, instead of everything after it.
+        recv!(s, time 1053, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1 + 6,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &b"111111"[..],
+            ..RECV_TEMPL
+        }));
+        assert_eq!(s.pipe(), 6 + 12 + 6);
+        recv!(s, time 1053, Err(Error::Exhausted));
 
-        let rx_buf = &mut [0; 32];
-        assert_eq!(s.recv_slice(rx_buf), Ok(4));
+        send!(s, time 1054, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1 + 6),
+            sack_ranges: [sack_block(30, 42), sack_block(12, 24), None],
+            ..SEND_TEMPL
+        });
+        // Not enough octets above the second hole were SACKed to consider it lost yet.
+        assert_eq!(s.pipe(), 6 + 6 + 6);
+        recv!(s, time 1054, Err(Error::Exhausted));
+
+        send!(s, time 1055, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1 + 6),
+            sack_ranges: [sack_block(30, 48), sack_block(12, 24), None],
+            ..SEND_TEMPL
+        });
+        assert_eq!(s.pipe(), 6);
+        recv!(s, time 1055, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1 + 24,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &b"444444"[..],
+            ..RECV_TEMPL
+        }));
+        recv!(s, time 1055, Err(Error::Exhausted));
+
+        // A partial ACK keeps the recovery going.
+        send!(s, time 1100, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1 + 24),
+            sack_ranges: [sack_block(30, 48), None, None],
+            ..SEND_TEMPL
+        });
+        assert!(s.sack_recovery.is_some());
+        assert_eq!(s.pipe(), 6);
+        recv!(s, time 1100, Err(Error::Exhausted));
+
+        // Everything is recovered within one round trip, without a timeout.
+        send!(s, time 1102, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1 + 48),
+            ..SEND_TEMPL
+        });
+        assert!(s.sack_recovery.is_none());
+        assert_eq!(s.sack_scoreboard, [None; SACK_SCOREBOARD_LEN]);
+        assert_eq!(s.pipe(), 0);
+        assert_eq!(s.tx_buffer.len(), 0);
+    }
+
+    #[test]
+    fn test_sack_recovery_new_data() {
+        let mut s = socket_sack_recovery();
+        recv!(s, time 1053, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1 + 6,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &b"111111"[..],
+            ..RECV_TEMPL
+        }));
+
+        // Data queued during recovery is sent while the pipe allows.
+        s.send_slice(b"888888").unwrap();
+        recv!(s, time 1054, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1 + 48,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &b"888888"[..],
+            ..RECV_TEMPL
+        }));
+        assert_eq!(s.pipe(), 6 + 12 + 6 + 6);
+    }
+
+    #[test]
+    fn test_sack_recovery_pipe_limit() {
+        let mut s = socket_sack_recovery();
+        recv!(s, time 1053, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1 + 6,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &b"111111"[..],
+            ..RECV_TEMPL
+        }));
+
+        // The remote window is already filled by the octets in flight.
+        send!(s, time 1054, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1 + 6),
+            sack_ranges: [sack_block(30, 48), sack_block(12, 24), None],
+            ..SEND_TEMPL
+        });
+        assert_eq!(s.pipe(), 6);
+        s.remote_win_len = 6;
+        recv!(s, time 1054, Err(Error::Exhausted));
+        s.remote_win_len = 12;
+        recv!(s, time 1054, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1 + 24,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &b"444444"[..],
+            ..RECV_TEMPL
+        }));
+    }
+
+    #[test]
+    fn test_sack_recovery_timeout() {
+        let mut s = socket_sack_recovery();
+        recv!(s, time 1053, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1 + 6,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &b"111111"[..],
+            ..RECV_TEMPL
+        }));
+
+        // On a timeout, the SACK information is discarded and everything is resent.
+        recv!(s, time 5000, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1 + 6,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &b"111111"[..],
+            ..RECV_TEMPL
+        }));
+        assert!(s.sack_recovery.is_none());
+        assert_eq!(s.sack_scoreboard, [None; SACK_SCOREBOARD_LEN]);
+        recv!(s, time 5000, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1 + 12,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &b"222222"[..],
+            ..RECV_TEMPL
+        }));
+    }
+
+    #[test]
+    fn test_sack_scoreboard_merge() {
+        let mut s = socket_established();
+        s.remote_has_sack = true;
+        s.send_slice(&[0; 48]).unwrap();
+        recv!(s, time 0, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &[0; 48][..],
+            ..RECV_TEMPL
+        }));
+
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1),
+                sack_ranges: [sack_block(30, 36), sack_block(10, 20), sack_block(40, 60)],
+                ..SEND_TEMPL
+            }
+        );
+        // The block beyond what was sent is ignored.
+        assert_eq!(
+            s.sack_scoreboard[..2],
+            [
+                Some((LOCAL_SEQ + 1 + 10, LOCAL_SEQ + 1 + 20)),
+                Some((LOCAL_SEQ + 1 + 30, LOCAL_SEQ + 1 + 36)),
+            ]
+        );
+        assert_eq!(s.pipe(), 48 - 16);
+
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1 + 15),
+                sack_ranges: [sack_block(20, 30), None, None],
+                ..SEND_TEMPL
+            }
+        );
+        assert_eq!(
+            s.sack_scoreboard,
+            [
+                Some((LOCAL_SEQ + 1 + 15, LOCAL_SEQ + 1 + 36)),
+                None,
+                None,
+                None,
+            ]
+        );
+        assert_eq!(s.pipe(), 12);
+    }
+
+    fn socket_idle_after_send() -> TestSocket {
+        let mut s = socket_established();
+        s.remote_mss = 6;
+        s.set_nagle_enabled(false);
+        s.send_slice(b"abcdef").unwrap();
+        recv!(s, time 0, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &b"abcdef"[..],
+            ..RECV_TEMPL
+        }));
+        send!(s, time 10, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1 + 6),
+            ..SEND_TEMPL
+        });
+        s
+    }
+
+    #[test]
+    fn test_slow_start_after_idle() {
+        let mut s = socket_idle_after_send();
+        let data = b"000000111111222222333333444444555555";
+        s.send_slice(data).unwrap();
+
+        // Only the restart window of four segments is sent after the idle period.
+        for i in 0..4 {
+            recv!(s, time 5000, Ok(TcpRepr {
+                seq_number: LOCAL_SEQ + 1 + 6 + i * 6,
+                ack_number: Some(REMOTE_SEQ + 1),
+                payload:    &data[i * 6..(i + 1) * 6],
+                ..RECV_TEMPL
+            }));
+        }
+        recv!(s, time 5000, Err(Error::Exhausted));
+
+        // Every ACK grows the window by one segment.
+        send!(s, time 5010, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1 + 6 + 24),
+            ..SEND_TEMPL
+        });
+        assert_eq!(s.cwnd, 30);
+        recv!(s, time 5010, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1 + 6 + 24,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &data[24..30],
+            ..RECV_TEMPL
+        }));
+        recv!(s, time 5010, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1 + 6 + 30,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &data[30..36],
+            ..RECV_TEMPL
+        }));
+    }
+
+    #[test]
+    fn test_slow_start_after_short_idle() {
+        let mut s = socket_idle_after_send();
+        let data = b"000000111111222222333333444444555555";
+        s.send_slice(data).unwrap();
+        for i in 0..6 {
+            recv!(s, time 100, Ok(TcpRepr {
+                seq_number: LOCAL_SEQ + 1 + 6 + i * 6,
+                ack_number: Some(REMOTE_SEQ + 1),
+                payload:    &data[i * 6..(i + 1) * 6],
+                ..RECV_TEMPL
+            }));
+        }
+    }
+
+    #[test]
+    fn test_slow_start_after_idle_disabled() {
+        let mut s = socket_idle_after_send();
+        s.set_slow_start_after_idle(false);
+        assert!(!s.slow_start_after_idle());
+        let data = b"000000111111222222333333444444555555";
+        s.send_slice(data).unwrap();
+        for i in 0..6 {
+            recv!(s, time 5000, Ok(TcpRepr {
+                seq_number: LOCAL_SEQ + 1 + 6 + i * 6,
+                ack_number: Some(REMOTE_SEQ + 1),
+                payload:    &data[i * 6..(i + 1) * 6],
+                ..RECV_TEMPL
+            }));
+        }
+    }
+
+    #[test]
+    fn test_rate_estimate() {
+        let mut s = socket_established();
+        assert_eq!(s.bottleneck_bandwidth(), None);
+        assert_eq!(s.min_rtt(), None);
+
+        s.send_slice(b"abcdef").unwrap();
+        recv!(s, time 0, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload: &b"abcdef"[..],
+            ..RECV_TEMPL
+        }));
+        send!(s, time 100, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1 + 6),
+            ..SEND_TEMPL
+        });
+        // 6 octets in 100ms.
+        assert_eq!(s.bottleneck_bandwidth(), Some(60));
+        assert_eq!(s.min_rtt(), Some(Duration::from_millis(100)));
+
+        s.send_slice(&[0; 24]).unwrap();
+        recv!(s, time 100, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1 + 6,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload: &[0; 24][..],
+            ..RECV_TEMPL
+        }));
+        send!(s, time 150, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1 + 30),
+            ..SEND_TEMPL
+        });
+        // 24 octets in 50ms.
+        assert_eq!(s.bottleneck_bandwidth(), Some(480));
+        assert_eq!(s.min_rtt(), Some(Duration::from_millis(50)));
+
+        // A slower sample doesn't lower the estimates.
+        s.send_slice(b"ghijkl").unwrap();
+        recv!(s, time 150, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1 + 30,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload: &b"ghijkl"[..],
+            ..RECV_TEMPL
+        }));
+        send!(s, time 350, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1 + 36),
+            ..SEND_TEMPL
+        });
+        assert_eq!(s.bottleneck_bandwidth(), Some(480));
+        assert_eq!(s.min_rtt(), Some(Duration::from_millis(50)));
+    }
+
+    #[test]
+    fn test_rate_estimate_retransmit() {
+        let mut s = socket_established();
+        s.send_slice(b"abcdef").unwrap();
+        recv!(s, time 0, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload: &b"abcdef"[..],
+            ..RECV_TEMPL
+        }));
+        recv!(s, time 1000, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload: &b"abcdef"[..],
+            ..RECV_TEMPL
+        }));
+        // It is unknown which transmission is acknowledged, so nothing is sampled.
+        send!(s, time 1050, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1 + 6),
+            ..SEND_TEMPL
+        });
+        assert_eq!(s.bottleneck_bandwidth(), None);
+        assert_eq!(s.min_rtt(), None);
+    }
+
+    #[test]
+    #[cfg(feature = "paranoid")]
+    fn test_check_invariants() {
+        let mut s = socket_established();
+        s.send_slice(b"abcdef").unwrap();
+        recv!(s, time 1000, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload: &b"abcdef"[..],
+            ..RECV_TEMPL
+        }));
+        s.check_invariants(Instant::from_millis(1000));
+        send!(s, time 1100, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1 + 6),
+            payload: &b"xyz"[..],
+            ..SEND_TEMPL
+        });
+        s.check_invariants(Instant::from_millis(1100));
+    }
+
+    #[test]
+    #[cfg(feature = "paranoid")]
+    #[should_panic(expected = "last sequence number sent")]
+    fn test_check_invariants_seq_beyond_buffer() {
+        let mut s = socket_established();
+        s.send_slice(b"abc").unwrap();
+        s.remote_last_seq = LOCAL_SEQ + 1 + 5;
+        s.check_invariants(Instant::from_millis(0));
+    }
+
+    #[test]
+    #[cfg(feature = "paranoid")]
+    #[should_panic(expected = "received sequence number went back")]
+    fn test_check_invariants_seq_went_back() {
+        let mut s = socket_established();
+        s.check_invariants(Instant::from_millis(0));
+        s.remote_seq_no = REMOTE_SEQ;
+        s.check_invariants(Instant::from_millis(0));
+    }
+
+    // =========================================================================================//
+    // Tests for window management.
+    // =========================================================================================//
+
+    #[test]
+    fn test_maximum_segment_size() {
+        let mut s = socket_listen();
+        s.tx_buffer = SocketBuffer::new(vec![0; 32767]);
+        send!(
+            s,
+            TcpRepr {
+                control: TcpControl::Syn,
+                seq_number: REMOTE_SEQ,
+                ack_number: None,
+                max_seg_size: Some(1000),
+                ..SEND_TEMPL
+            }
+        );
+        recv!(
+            s,
+            [TcpRepr {
+                control: TcpControl::Syn,
+                seq_number: LOCAL_SEQ,
+                ack_number: Some(REMOTE_SEQ + 1),
+                max_seg_size: Some(BASE_MSS),
+                ..RECV_TEMPL
+            }]
+        );
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1),
+                window_len: 32767,
+                ..SEND_TEMPL
+            }
+        );
+        s.send_slice(&[0; 1200][..]).unwrap();
+        recv!(
+            s,
+            Ok(TcpRepr {
+                seq_number: LOCAL_SEQ + 1,
+                ack_number: Some(REMOTE_SEQ + 1),
+                payload: &[0; 1000][..],
+                ..RECV_TEMPL
+            })
+        );
+    }
+
+    #[test]
+    fn test_maximum_segment_size_overhead() {
+        let mut s = socket_listen();
+        s.set_mss_overhead(60);
+        s.tx_buffer = SocketBuffer::new(vec![0; 32767]);
+        send!(
+            s,
+            TcpRepr {
+                control: TcpControl::Syn,
+                seq_number: REMOTE_SEQ,
+                ack_number: None,
+                max_seg_size: Some(1460),
+                ..SEND_TEMPL
+            }
+        );
+        recv!(
+            s,
+            [TcpRepr {
+                control: TcpControl::Syn,
+                seq_number: LOCAL_SEQ,
+                ack_number: Some(REMOTE_SEQ + 1),
+                max_seg_size: Some(BASE_MSS - 60),
+                ..RECV_TEMPL
+            }]
+        );
+        assert_eq!(s.effective_mss(&s.cx), Some(BASE_MSS as usize - 60));
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1),
+                window_len: 32767,
+                ..SEND_TEMPL
+            }
+        );
+        s.send_slice(&[0; 1500][..]).unwrap();
+        recv!(
+            s,
+            Ok(TcpRepr {
+                seq_number: LOCAL_SEQ + 1,
+                ack_number: Some(REMOTE_SEQ + 1),
+                payload: &[0; BASE_MSS as usize - 60][..],
+                ..RECV_TEMPL
+            })
+        );
+    }
+
+    #[test]
+    fn test_maximum_segment_size_overhead_too_large() {
+        let mut s = socket_listen();
+        s.set_mss_overhead(5000);
+        send!(
+            s,
+            TcpRepr {
+                control: TcpControl::Syn,
+                seq_number: REMOTE_SEQ,
+                ack_number: None,
+                max_seg_size: Some(1460),
+                ..SEND_TEMPL
+            }
+        );
+        recv!(
+            s,
+            [TcpRepr {
+                control: TcpControl::Syn,
+                seq_number: LOCAL_SEQ,
+                ack_number: Some(REMOTE_SEQ + 1),
+                max_seg_size: Some(DEFAULT_MSS as u16),
+                ..RECV_TEMPL
+            }]
+        );
+        assert_eq!(s.effective_mss(&s.cx), Some(DEFAULT_MSS));
+    }
+
+    #[test]
+    fn test_close_wait_no_window_update() {
+        let mut s = socket_established();
+        send!(
+            s,
+            TcpRepr {
+                control: TcpControl::Fin,
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1),
+                payload: &[1, 2, 3, 4],
+                ..SEND_TEMPL
+            }
+        );
+        assert_eq!(s.state, State::CloseWait);
+
+        // we ack the FIN, with the reduced window size.
+        recv!(
+            s,
+            Ok(TcpRepr {
+                seq_number: LOCAL_SEQ + 1,
+                ack_number: Some(REMOTE_SEQ + 6),
+                window_len: 60,
+                ..RECV_TEMPL
+            })
+        );
+
+        let rx_buf = &mut [0; 32];
+        assert_eq!(s.recv_slice(rx_buf), Ok(4));
 
         // check that we do NOT send a window update even if it has changed.
         recv!(s, Err(Error::Exhausted));
@@ -5791,6 +9354,101 @@ mod test {
         }));
     }
 
+    #[test]
+    fn test_zero_window_reopen_not_delayed() {
+        let mut s = socket_established();
+        s.set_ack_delay(Some(ACK_DELAY_DEFAULT));
+        s.rx_buffer = SocketBuffer::new(vec![0; 6]);
+        s.assembler = Assembler::new(s.rx_buffer.capacity());
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1),
+                payload: &b"abcdef"[..],
+                ..SEND_TEMPL
+            }
+        );
+        recv!(s, time 11, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1 + 6),
+            window_len: 0,
+            ..RECV_TEMPL
+        }));
+        s.recv(|buffer| (buffer.len(), ())).unwrap();
+        // A window probe arriving before the window update goes out starts the delayed ACK
+        // timer...
+        send!(
+            s,
+            time 11,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1 + 6,
+                ack_number: Some(LOCAL_SEQ + 1),
+                payload: &b"g"[..],
+                ..SEND_TEMPL
+            }
+        );
+        assert_eq!(s.socket.poll_at(&mut s.cx), PollAt::Now);
+        // ...but the remote end is stalled, so the window update is sent without waiting for it.
+        recv!(s, time 11, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1 + 7),
+            window_len: 5,
+            ..RECV_TEMPL
+        }));
+        recv!(s, time 11, Err(Error::Exhausted));
+    }
+
+    #[test]
+    fn test_recv_window_paused() {
+        let mut s = socket_established();
+        s.set_ack_delay(Some(ACK_DELAY_DEFAULT));
+        s.set_recv_window_paused(true);
+        assert!(s.recv_window_paused());
+        assert_eq!(s.socket.poll_at(&mut s.cx), PollAt::Now);
+        recv!(s, time 0, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1),
+            window_len: 0,
+            ..RECV_TEMPL
+        }));
+        recv!(s, time 0, Err(Error::Exhausted));
+        // Data sent before the remote end learned about the pause is still accepted.
+        send!(
+            s,
+            time 0,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1),
+                payload: &b"abc"[..],
+                ..SEND_TEMPL
+            }
+        );
+        recv!(s, time 11, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1 + 3),
+            window_len: 0,
+            ..RECV_TEMPL
+        }));
+        s.recv(|buffer| {
+            assert_eq!(buffer, b"abc");
+            (buffer.len(), ())
+        })
+        .unwrap();
+        // Draining the buffer doesn't reopen the window while paused...
+        recv!(s, time 100, Err(Error::Exhausted));
+        // ...but resuming does, without waiting for the ACK delay.
+        s.set_recv_window_paused(false);
+        assert_eq!(s.socket.poll_at(&mut s.cx), PollAt::Now);
+        recv!(s, time 100, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1 + 3),
+            window_len: 64,
+            ..RECV_TEMPL
+        }));
+        recv!(s, time 100, Err(Error::Exhausted));
+    }
+
     #[test]
     fn test_fill_peer_window() {
         let mut s = socket_established();
@@ -5821,6 +9479,77 @@ mod test {
         );
     }
 
+    #[test]
+    #[cfg(feature = "async")]
+    fn test_window_reopen_waker() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        use std::task::Wake;
+
+        struct CountingWaker(AtomicUsize);
+
+        impl Wake for CountingWaker {
+            fn wake(self: Arc<Self>) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let counter = Arc::new(CountingWaker(AtomicUsize::new(0)));
+        let waker = Waker::from(counter.clone());
+
+        let mut s = socket_established();
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1),
+                window_len: 0,
+                ..SEND_TEMPL
+            }
+        );
+        assert_eq!(s.send_window(), 0);
+        assert!(s.can_send());
+
+        // Neither a closed window, nor space in the transmit buffer, wakes it.
+        s.register_window_waker(&waker);
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1),
+                window_len: 0,
+                ..SEND_TEMPL
+            }
+        );
+        s.send_slice(b"abcdef").unwrap();
+        recv!(s, []);
+        assert_eq!(counter.0.load(Ordering::SeqCst), 0);
+
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1),
+                window_len: 64,
+                ..SEND_TEMPL
+            }
+        );
+        assert_eq!(s.send_window(), 64);
+        assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+
+        // It is woken once per registration.
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1),
+                window_len: 128,
+                ..SEND_TEMPL
+            }
+        );
+        assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+    }
+
     #[test]
     fn test_announce_window_after_read() {
         let mut s = socket_established();
@@ -5975,6 +9704,271 @@ mod test {
             ..RECV_TEMPL
         }));
         assert_eq!(s.state, State::Closed);
+        assert_eq!(s.close_reason(), Some(CloseReason::TimedOut));
+    }
+
+    #[test]
+    fn test_established_read_timeout() {
+        let mut s = socket_established();
+        s.set_read_timeout(Some(Duration::from_millis(1000)));
+        assert_eq!(s.socket.poll_at(&mut s.cx), PollAt::Now);
+        recv!(s, time 0, Err(Error::Exhausted));
+        assert_eq!(
+            s.socket.poll_at(&mut s.cx),
+            PollAt::Time(Instant::from_millis(1000))
+        );
+        // Segments without data don't count as progress.
+        send!(s, time 500, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1),
+            ..SEND_TEMPL
+        });
+        assert_eq!(
+            s.socket.poll_at(&mut s.cx),
+            PollAt::Time(Instant::from_millis(1000))
+        );
+        send!(s, time 900, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1),
+            payload:    &b"abc"[..],
+            ..SEND_TEMPL
+        });
+        recv!(s, time 910, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1 + 3),
+            window_len: 61,
+            ..RECV_TEMPL
+        }));
+        // The timeout doesn't run while there is data left to read.
+        recv!(s, time 5000, Err(Error::Exhausted));
+        assert_eq!(s.state, State::Established);
+
+        assert_eq!(s.recv_slice(&mut [0; 3]), Ok(3));
+        assert_eq!(s.socket.poll_at(&mut s.cx), PollAt::Now);
+        recv!(s, time 5000, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1 + 3),
+            ..RECV_TEMPL
+        }));
+        assert_eq!(
+            s.socket.poll_at(&mut s.cx),
+            PollAt::Time(Instant::from_millis(6000))
+        );
+        recv!(s, time 6000, Ok(TcpRepr {
+            control:    TcpControl::Rst,
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1 + 3),
+            ..RECV_TEMPL
+        }));
+        assert_eq!(s.state, State::Closed);
+        assert_eq!(s.close_reason(), Some(CloseReason::ReadTimedOut));
+    }
+
+    #[test]
+    fn test_close_wait_no_read_timeout() {
+        let mut s = socket_close_wait();
+        s.set_read_timeout(Some(Duration::from_millis(1000)));
+        recv!(s, time 0, Err(Error::Exhausted));
+        assert_eq!(s.socket.poll_at(&mut s.cx), PollAt::Ingress);
+        recv!(s, time 5000, Err(Error::Exhausted));
+        assert_eq!(s.state, State::CloseWait);
+    }
+
+    #[test]
+    fn test_established_write_timeout() {
+        let mut s = socket_established();
+        s.set_write_timeout(Some(Duration::from_millis(1000)));
+        // The timeout doesn't run while there is no data to send.
+        recv!(s, time 0, Err(Error::Exhausted));
+        assert_eq!(s.socket.poll_at(&mut s.cx), PollAt::Ingress);
+
+        s.send_slice(b"abcdef").unwrap();
+        recv!(s, time 1000, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &b"abcdef"[..],
+            ..RECV_TEMPL
+        }));
+        // Acknowledging part of the data restarts the timeout, but then the remote endpoint
+        // keeps its window closed.
+        send!(s, time 1500, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1 + 3),
+            window_len: 0,
+            ..SEND_TEMPL
+        });
+        recv!(s, time 1500, Err(Error::Exhausted));
+        assert_eq!(
+            s.socket.poll_at(&mut s.cx),
+            PollAt::Time(Instant::from_millis(2500))
+        );
+        recv!(s, time 2500, Ok(TcpRepr {
+            control:    TcpControl::Rst,
+            seq_number: LOCAL_SEQ + 1 + 6,
+            ack_number: Some(REMOTE_SEQ + 1),
+            ..RECV_TEMPL
+        }));
+        assert_eq!(s.state, State::Closed);
+        assert_eq!(s.close_reason(), Some(CloseReason::WriteTimedOut));
+    }
+
+    #[test]
+    fn test_established_max_send_rate() {
+        let mut s = socket_established();
+        s.set_nagle_enabled(false);
+        s.set_ack_delay(None);
+        s.set_max_send_rate(Some(1000));
+        recv!(s, time 0, Err(Error::Exhausted));
+
+        s.send_slice(b"abcdef").unwrap();
+        recv!(s, time 0, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &b"abcdef"[..],
+            ..RECV_TEMPL
+        }));
+        // Six octets take 6ms at 1000 octets per second, so the rest of the data waits.
+        s.send_slice(b"ghij").unwrap();
+        recv!(s, time 0, Err(Error::Exhausted));
+        assert_eq!(
+            s.socket.poll_at(&mut s.cx),
+            PollAt::Time(Instant::from_millis(6))
+        );
+        // Nor does it go out with an acknowledgement.
+        send!(s, time 2, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1),
+            payload:    &b"xyz"[..],
+            ..SEND_TEMPL
+        });
+        recv!(s, time 2, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1 + 6,
+            ack_number: Some(REMOTE_SEQ + 1 + 3),
+            window_len: 61,
+            ..RECV_TEMPL
+        }));
+        recv!(s, time 5, Err(Error::Exhausted));
+        recv!(s, time 6, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1 + 6,
+            ack_number: Some(REMOTE_SEQ + 1 + 3),
+            window_len: 61,
+            payload:    &b"ghij"[..],
+            ..RECV_TEMPL
+        }));
+        assert_eq!(s.tx_buffer.len(), 10);
+    }
+
+    #[test]
+    fn test_syn_received_handshake_timeout() {
+        let mut s = socket_listen();
+        s.set_handshake_timeout(Some(Duration::from_millis(500)));
+        send!(s, time 100, TcpRepr {
+            control: TcpControl::Syn,
+            seq_number: REMOTE_SEQ,
+            ack_number: None,
+            ..SEND_TEMPL
+        });
+        assert_eq!(s.state, State::SynReceived);
+        recv!(s, time 100, Ok(TcpRepr {
+            control:    TcpControl::Syn,
+            seq_number: LOCAL_SEQ,
+            ack_number: Some(REMOTE_SEQ + 1),
+            max_seg_size: Some(BASE_MSS),
+            ..RECV_TEMPL
+        }));
+        assert_eq!(
+            s.socket.poll_at(&mut s.cx),
+            PollAt::Time(Instant::from_millis(600))
+        );
+        // The socket listens again, without sending anything.
+        recv!(s, time 600, Err(Error::Exhausted));
+        assert_eq!(s.state, State::Listen);
+        assert_eq!(s.tuple, None);
+        assert_eq!(s.close_reason(), None);
+
+        send!(s, time 700, TcpRepr {
+            control: TcpControl::Syn,
+            seq_number: REMOTE_SEQ,
+            ack_number: None,
+            ..SEND_TEMPL
+        });
+        assert_eq!(s.state, State::SynReceived);
+    }
+
+    #[test]
+    fn test_established_min_recv_rate() {
+        let mut s = socket_established();
+        s.set_min_recv_rate(Some((6, Duration::from_millis(1000))));
+        recv!(s, time 0, Err(Error::Exhausted));
+        assert_eq!(
+            s.socket.poll_at(&mut s.cx),
+            PollAt::Time(Instant::from_millis(1000))
+        );
+
+        // Enough data in the first interval.
+        send!(s, time 500, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1),
+            payload:    &b"abcdef"[..],
+            ..SEND_TEMPL
+        });
+        recv!(s, time 510, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1 + 6),
+            window_len: 58,
+            ..RECV_TEMPL
+        }));
+        recv!(s, time 1000, Err(Error::Exhausted));
+        assert_eq!(s.state, State::Established);
+        assert_eq!(
+            s.socket.poll_at(&mut s.cx),
+            PollAt::Time(Instant::from_millis(2000))
+        );
+
+        // But not in the second one.
+        send!(s, time 1500, TcpRepr {
+            seq_number: REMOTE_SEQ + 1 + 6,
+            ack_number: Some(LOCAL_SEQ + 1),
+            payload:    &b"ghi"[..],
+            ..SEND_TEMPL
+        });
+        recv!(s, time 1510, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1 + 9),
+            window_len: 55,
+            ..RECV_TEMPL
+        }));
+        recv!(s, time 2000, Ok(TcpRepr {
+            control:    TcpControl::Rst,
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1 + 9),
+            window_len: 55,
+            ..RECV_TEMPL
+        }));
+        assert_eq!(s.state, State::Closed);
+        assert_eq!(s.close_reason(), Some(CloseReason::TooSlow));
+    }
+
+    #[test]
+    fn test_established_min_recv_rate_buffer_full() {
+        let mut s = socket_established_with_buffer_sizes(64, 4);
+        s.set_min_recv_rate(Some((6, Duration::from_millis(1000))));
+        recv!(s, time 0, Err(Error::Exhausted));
+        send!(s, time 500, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1),
+            payload:    &b"abc"[..],
+            ..SEND_TEMPL
+        });
+        recv!(s, time 510, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1 + 3),
+            window_len: 1,
+            ..RECV_TEMPL
+        }));
+        // The receive buffer has no room for the missing octets.
+        recv!(s, time 1000, Err(Error::Exhausted));
+        assert_eq!(s.state, State::Established);
     }
 
     #[test]
@@ -6110,50 +10104,122 @@ mod test {
         recv!(s, time 0, Ok(TcpRepr {
             seq_number: LOCAL_SEQ,
             ack_number: Some(REMOTE_SEQ + 1),
-            payload:    &[0],
+            payload:    &[0],
+            ..RECV_TEMPL
+        }));
+
+        assert_eq!(
+            s.socket.poll_at(&mut s.cx),
+            PollAt::Time(Instant::from_millis(100))
+        );
+        recv!(s, time 95, Err(Error::Exhausted));
+        recv!(s, time 100, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &[0],
+            ..RECV_TEMPL
+        }));
+
+        assert_eq!(
+            s.socket.poll_at(&mut s.cx),
+            PollAt::Time(Instant::from_millis(200))
+        );
+        recv!(s, time 195, Err(Error::Exhausted));
+        recv!(s, time 200, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &[0],
+            ..RECV_TEMPL
+        }));
+
+        send!(s, time 250, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1),
+            ..SEND_TEMPL
+        });
+        assert_eq!(
+            s.socket.poll_at(&mut s.cx),
+            PollAt::Time(Instant::from_millis(350))
+        );
+        recv!(s, time 345, Err(Error::Exhausted));
+        recv!(s, time 350, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &b"\x00"[..],
+            ..RECV_TEMPL
+        }));
+    }
+
+    #[test]
+    fn test_keep_alive_without_app_io() {
+        // A connection the application never reads from or writes to, e.g. one that was
+        // accepted by a server which only sends data once there is some to publish.
+        let mut s = socket_listen();
+        s.set_keep_alive(Some(Duration::from_millis(100)));
+        s.set_timeout(Some(Duration::from_millis(250)));
+        send!(s, time 0, TcpRepr {
+            control: TcpControl::Syn,
+            seq_number: REMOTE_SEQ,
+            ack_number: None,
+            ..SEND_TEMPL
+        });
+        recv!(s, time 0, Ok(TcpRepr {
+            control: TcpControl::Syn,
+            seq_number: LOCAL_SEQ,
+            ack_number: Some(REMOTE_SEQ + 1),
+            max_seg_size: Some(BASE_MSS),
             ..RECV_TEMPL
         }));
+        send!(s, time 10, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1),
+            ..SEND_TEMPL
+        });
+        assert_eq!(s.state, State::Established);
 
+        // The interval is counted from the last segment in either direction.
         assert_eq!(
             s.socket.poll_at(&mut s.cx),
-            PollAt::Time(Instant::from_millis(100))
+            PollAt::Time(Instant::from_millis(110))
         );
-        recv!(s, time 95, Err(Error::Exhausted));
-        recv!(s, time 100, Ok(TcpRepr {
+        recv!(s, time 105, Err(Error::Exhausted));
+        recv!(s, time 110, Ok(TcpRepr {
             seq_number: LOCAL_SEQ,
             ack_number: Some(REMOTE_SEQ + 1),
-            payload:    &[0],
+            payload: &[0],
             ..RECV_TEMPL
         }));
-
+        send!(s, time 120, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1),
+            ..SEND_TEMPL
+        });
         assert_eq!(
             s.socket.poll_at(&mut s.cx),
-            PollAt::Time(Instant::from_millis(200))
+            PollAt::Time(Instant::from_millis(220))
         );
-        recv!(s, time 195, Err(Error::Exhausted));
-        recv!(s, time 200, Ok(TcpRepr {
+        recv!(s, time 220, Ok(TcpRepr {
             seq_number: LOCAL_SEQ,
             ack_number: Some(REMOTE_SEQ + 1),
-            payload:    &[0],
+            payload: &[0],
             ..RECV_TEMPL
         }));
 
-        send!(s, time 250, TcpRepr {
-            seq_number: REMOTE_SEQ + 1,
-            ack_number: Some(LOCAL_SEQ + 1),
-            ..SEND_TEMPL
-        });
-        assert_eq!(
-            s.socket.poll_at(&mut s.cx),
-            PollAt::Time(Instant::from_millis(350))
-        );
-        recv!(s, time 345, Err(Error::Exhausted));
-        recv!(s, time 350, Ok(TcpRepr {
+        // Unanswered probes keep being sent until the remote endpoint is considered dead.
+        recv!(s, time 320, Ok(TcpRepr {
             seq_number: LOCAL_SEQ,
             ack_number: Some(REMOTE_SEQ + 1),
-            payload:    &b"\x00"[..],
+            payload: &[0],
+            ..RECV_TEMPL
+        }));
+        recv!(s, time 370, Ok(TcpRepr {
+            control: TcpControl::Rst,
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1),
             ..RECV_TEMPL
         }));
+        assert_eq!(s.state, State::Closed);
+        assert_eq!(s.close_reason(), Some(CloseReason::TimedOut));
     }
 
     // =========================================================================================//
@@ -6234,6 +10300,60 @@ mod test {
         .unwrap();
     }
 
+    #[test]
+    fn test_reassembly_buffer() {
+        let mut s = socket_established_with_buffer_sizes(64, 6);
+        s.reassembly_buffer = SocketBuffer::new(vec![0; 64]);
+        s.assembler = Assembler::new(s.reassembly_buffer.capacity());
+        // Out-of-order data is held past the end of the receive buffer.
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1 + 6,
+                ack_number: Some(LOCAL_SEQ + 1),
+                payload: &b"ghij"[..],
+                ..SEND_TEMPL
+            },
+            Some(TcpRepr {
+                seq_number: LOCAL_SEQ + 1,
+                ack_number: Some(REMOTE_SEQ + 1),
+                ..RECV_TEMPL
+            })
+        );
+        // Once the hole is filled, all of the data is acknowledged, and what doesn't fit into
+        // the receive buffer stays in the reassembly buffer.
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1),
+                payload: &b"abcdef"[..],
+                ..SEND_TEMPL
+            },
+            Some(TcpRepr {
+                seq_number: LOCAL_SEQ + 1,
+                ack_number: Some(REMOTE_SEQ + 1 + 10),
+                window_len: 60,
+                ..RECV_TEMPL
+            })
+        );
+        assert_eq!(s.recv_queue(), 6);
+        s.recv(|buffer| {
+            assert_eq!(buffer, b"abcdef");
+            (buffer.len(), ())
+        })
+        .unwrap();
+        // The window counts the data the receive buffer now has room for.
+        assert_eq!(s.scaled_window(), 64);
+        assert!(s.can_recv());
+        s.recv(|buffer| {
+            assert_eq!(buffer, b"ghij");
+            (buffer.len(), ())
+        })
+        .unwrap();
+        assert!(!s.can_recv());
+    }
+
     #[test]
     fn test_buffer_wraparound_rx() {
         let mut s = socket_established();
@@ -6441,17 +10561,207 @@ mod test {
                 ..SEND_TEMPL
             }
         );
-        s.recv(|data| {
-            assert_eq!(data, b"abc");
-            (3, ())
-        })
-        .unwrap();
-        assert_eq!(s.recv(|_| (0, ())), Err(RecvError::InvalidState));
+        s.recv(|data| {
+            assert_eq!(data, b"abc");
+            (3, ())
+        })
+        .unwrap();
+        assert_eq!(s.recv(|_| (0, ())), Err(RecvError::InvalidState));
+    }
+
+    #[test]
+    fn test_rx_close_rst_with_hole() {
+        let mut s = socket_established();
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1),
+                payload: &b"abc"[..],
+                ..SEND_TEMPL
+            }
+        );
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1 + 6,
+                ack_number: Some(LOCAL_SEQ + 1),
+                payload: &b"ghi"[..],
+                ..SEND_TEMPL
+            },
+            Some(TcpRepr {
+                seq_number: LOCAL_SEQ + 1,
+                ack_number: Some(REMOTE_SEQ + 1 + 3),
+                window_len: 61,
+                ..RECV_TEMPL
+            })
+        );
+        send!(
+            s,
+            TcpRepr {
+                control: TcpControl::Rst,
+                seq_number: REMOTE_SEQ + 1 + 9,
+                ack_number: Some(LOCAL_SEQ + 1),
+                ..SEND_TEMPL
+            }
+        );
+        s.recv(|data| {
+            assert_eq!(data, b"abc");
+            (3, ())
+        })
+        .unwrap();
+        assert_eq!(s.recv(|_| (0, ())), Err(RecvError::InvalidState));
+    }
+
+    // =========================================================================================//
+    // Tests for delayed ACK
+    // =========================================================================================//
+
+    #[test]
+    fn test_delayed_ack() {
+        let mut s = socket_established();
+        s.set_ack_delay(Some(ACK_DELAY_DEFAULT));
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1),
+                payload: &b"abc"[..],
+                ..SEND_TEMPL
+            }
+        );
+
+        // No ACK is immediately sent.
+        recv!(s, Err(Error::Exhausted));
+
+        // After 10ms, it is sent.
+        recv!(s, time 11, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1 + 3),
+            window_len: 61,
+            ..RECV_TEMPL
+        }));
+    }
+
+    #[test]
+    fn test_quickack_after_establishment() {
+        let mut s = socket_syn_received();
+        s.set_ack_delay(Some(ACK_DELAY_DEFAULT));
+        s.set_quickack_segments(2);
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1),
+                ..SEND_TEMPL
+            }
+        );
+        assert_eq!(s.state, State::Established);
+
+        // The first two segments are acknowledged right away...
+        for i in 0..2 {
+            send!(
+                s,
+                TcpRepr {
+                    seq_number: REMOTE_SEQ + 1 + i * 3,
+                    ack_number: Some(LOCAL_SEQ + 1),
+                    payload: &b"abc"[..],
+                    ..SEND_TEMPL
+                }
+            );
+            recv!(
+                s,
+                Ok(TcpRepr {
+                    seq_number: LOCAL_SEQ + 1,
+                    ack_number: Some(REMOTE_SEQ + 1 + (i + 1) * 3),
+                    window_len: 64 - (i as u16 + 1) * 3,
+                    ..RECV_TEMPL
+                })
+            );
+        }
+
+        // ... and the next one isn't.
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1 + 6,
+                ack_number: Some(LOCAL_SEQ + 1),
+                payload: &b"abc"[..],
+                ..SEND_TEMPL
+            }
+        );
+        recv!(s, Err(Error::Exhausted));
+        recv!(s, time 11, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1 + 9),
+            window_len: 55,
+            ..RECV_TEMPL
+        }));
+    }
+
+    #[test]
+    fn test_quickack_after_hole_filled() {
+        let mut s = socket_established();
+        s.set_ack_delay(Some(ACK_DELAY_DEFAULT));
+        s.set_quickack_segments(1);
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1 + 3,
+                ack_number: Some(LOCAL_SEQ + 1),
+                payload: &b"def"[..],
+                ..SEND_TEMPL
+            },
+            Some(TcpRepr {
+                seq_number: LOCAL_SEQ + 1,
+                ack_number: Some(REMOTE_SEQ + 1),
+                window_len: 64,
+                sack_ranges: [None, None, None],
+                ..RECV_TEMPL
+            })
+        );
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1),
+                payload: &b"abc"[..],
+                ..SEND_TEMPL
+            },
+            Some(TcpRepr {
+                seq_number: LOCAL_SEQ + 1,
+                ack_number: Some(REMOTE_SEQ + 1 + 6),
+                window_len: 58,
+                ..RECV_TEMPL
+            })
+        );
+
+        // The segment after the recovery is acknowledged right away.
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1 + 6,
+                ack_number: Some(LOCAL_SEQ + 1),
+                payload: &b"ghi"[..],
+                ..SEND_TEMPL
+            }
+        );
+        recv!(
+            s,
+            Ok(TcpRepr {
+                seq_number: LOCAL_SEQ + 1,
+                ack_number: Some(REMOTE_SEQ + 1 + 9),
+                window_len: 55,
+                ..RECV_TEMPL
+            })
+        );
     }
 
     #[test]
-    fn test_rx_close_rst_with_hole() {
+    fn test_quickack_forced() {
         let mut s = socket_established();
+        s.set_ack_delay(Some(ACK_DELAY_DEFAULT));
+        s.set_quickack(Some(true));
         send!(
             s,
             TcpRepr {
@@ -6461,66 +10771,51 @@ mod test {
                 ..SEND_TEMPL
             }
         );
-        send!(
+        recv!(
             s,
-            TcpRepr {
-                seq_number: REMOTE_SEQ + 1 + 6,
-                ack_number: Some(LOCAL_SEQ + 1),
-                payload: &b"ghi"[..],
-                ..SEND_TEMPL
-            },
-            Some(TcpRepr {
+            Ok(TcpRepr {
                 seq_number: LOCAL_SEQ + 1,
                 ack_number: Some(REMOTE_SEQ + 1 + 3),
                 window_len: 61,
                 ..RECV_TEMPL
             })
         );
+
+        // Forcing it off overrides the quick ACK window.
+        s.set_quickack(Some(false));
+        s.quickack_remaining = 16;
         send!(
             s,
             TcpRepr {
-                control: TcpControl::Rst,
-                seq_number: REMOTE_SEQ + 1 + 9,
+                seq_number: REMOTE_SEQ + 1 + 3,
                 ack_number: Some(LOCAL_SEQ + 1),
+                payload: &b"def"[..],
                 ..SEND_TEMPL
             }
         );
-        s.recv(|data| {
-            assert_eq!(data, b"abc");
-            (3, ())
-        })
-        .unwrap();
-        assert_eq!(s.recv(|_| (0, ())), Err(RecvError::InvalidState));
-    }
-
-    // =========================================================================================//
-    // Tests for delayed ACK
-    // =========================================================================================//
+        recv!(s, Err(Error::Exhausted));
 
-    #[test]
-    fn test_delayed_ack() {
-        let mut s = socket_established();
-        s.set_ack_delay(Some(ACK_DELAY_DEFAULT));
+        // Without forcing, the quick ACK window applies again.
+        s.set_quickack(None);
+        assert_eq!(s.quickack(), None);
         send!(
             s,
             TcpRepr {
-                seq_number: REMOTE_SEQ + 1,
+                seq_number: REMOTE_SEQ + 1 + 6,
                 ack_number: Some(LOCAL_SEQ + 1),
-                payload: &b"abc"[..],
+                payload: &b"ghi"[..],
                 ..SEND_TEMPL
             }
         );
-
-        // No ACK is immediately sent.
-        recv!(s, Err(Error::Exhausted));
-
-        // After 10ms, it is sent.
-        recv!(s, time 11, Ok(TcpRepr {
-            seq_number: LOCAL_SEQ + 1,
-            ack_number: Some(REMOTE_SEQ + 1 + 3),
-            window_len: 61,
-            ..RECV_TEMPL
-        }));
+        recv!(
+            s,
+            Ok(TcpRepr {
+                seq_number: LOCAL_SEQ + 1,
+                ack_number: Some(REMOTE_SEQ + 1 + 9),
+                window_len: 55,
+                ..RECV_TEMPL
+            })
+        );
     }
 
     #[test]
@@ -6744,6 +11039,109 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_cork() {
+        let mut s = socket_established();
+        s.remote_mss = 6;
+        s.set_nagle_enabled(false);
+        s.set_cork(true);
+
+        // Nothing is in flight, but partial segments are held back anyway.
+        s.send_slice(b"abc").unwrap();
+        recv!(s, []);
+        s.send_slice(b"def123").unwrap();
+        recv!(
+            s,
+            [TcpRepr {
+                seq_number: LOCAL_SEQ + 1,
+                ack_number: Some(REMOTE_SEQ + 1),
+                payload: &b"abcdef"[..],
+                ..RECV_TEMPL
+            }]
+        );
+        recv!(s, []);
+
+        // Uncorking sends the rest.
+        s.set_cork(false);
+        recv!(
+            s,
+            [TcpRepr {
+                seq_number: LOCAL_SEQ + 1 + 6,
+                ack_number: Some(REMOTE_SEQ + 1),
+                payload: &b"123"[..],
+                ..RECV_TEMPL
+            }]
+        );
+    }
+
+    #[test]
+    fn test_cork_flush() {
+        let mut s = socket_established();
+        s.remote_mss = 6;
+        s.set_cork(true);
+
+        s.send_slice(b"abc").unwrap();
+        recv!(s, []);
+        s.flush();
+        recv!(
+            s,
+            [TcpRepr {
+                seq_number: LOCAL_SEQ + 1,
+                ack_number: Some(REMOTE_SEQ + 1),
+                payload: &b"abc"[..],
+                ..RECV_TEMPL
+            }]
+        );
+
+        // The flush overrides Nagle's Algorithm as well, but only for the data queued before it.
+        s.send_slice(b"def").unwrap();
+        s.flush();
+        recv!(
+            s,
+            [TcpRepr {
+                seq_number: LOCAL_SEQ + 1 + 3,
+                ack_number: Some(REMOTE_SEQ + 1),
+                payload: &b"def"[..],
+                ..RECV_TEMPL
+            }]
+        );
+        s.send_slice(b"gh").unwrap();
+        recv!(s, []);
+
+        // Even once acknowledged, the data queued after the flush stays corked.
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1 + 6),
+                ..SEND_TEMPL
+            }
+        );
+        recv!(s, []);
+        assert_eq!(s.flush_seq, None);
+    }
+
+    #[test]
+    fn test_cork_close() {
+        let mut s = socket_established();
+        s.remote_mss = 6;
+        s.set_cork(true);
+
+        s.send_slice(b"abc").unwrap();
+        recv!(s, []);
+        s.close();
+        recv!(
+            s,
+            [TcpRepr {
+                control: TcpControl::Fin,
+                seq_number: LOCAL_SEQ + 1,
+                ack_number: Some(REMOTE_SEQ + 1),
+                payload: &b"abc"[..],
+                ..RECV_TEMPL
+            }]
+        );
+    }
+
     // =========================================================================================//
     // Tests for packet filtering.
     // =========================================================================================//
@@ -6810,6 +11208,287 @@ mod test {
         assert!(!s.socket.accepts(&mut s.cx, &ip_repr_wrong_dst, &tcp_repr));
     }
 
+    // =========================================================================================//
+    // Tests for the TCP Authentication Option.
+    // =========================================================================================//
+
+    #[cfg(feature = "socket-tcp-ao")]
+    fn auth_key(id: u8, master_key: &[u8]) -> AuthKey {
+        AuthKey::new(id, id, AuthAlgorithm::HmacSha1, master_key).unwrap()
+    }
+
+    /// Emit a segment sent by the remote endpoint, and process what was emitted.
+    #[cfg(feature = "socket-tcp-ao")]
+    fn send_authenticated(socket: &mut TestSocket, repr: &TcpRepr) -> Option<TcpRepr<'static>> {
+        let ip_repr = IpReprIpvX(IpvXRepr {
+            src_addr: REMOTE_ADDR,
+            dst_addr: LOCAL_ADDR,
+            next_header: IpProtocol::Tcp,
+            payload_len: repr.buffer_len(),
+            hop_limit: 64,
+        });
+        let (src_addr, dst_addr) = (ip_repr.src_addr(), ip_repr.dst_addr());
+        let caps = ChecksumCapabilities::default();
+        let mut bytes = vec![0; repr.buffer_len()];
+        repr.emit(
+            &mut TcpPacket::new_unchecked(&mut bytes[..]),
+            &src_addr,
+            &dst_addr,
+            &caps,
+        );
+        let packet = TcpPacket::new_checked(&bytes[..]).unwrap();
+        let repr = TcpRepr::parse(&packet, &src_addr, &dst_addr, &caps).unwrap();
+
+        assert!(socket.socket.accepts(&mut socket.cx, &ip_repr, &repr));
+        socket
+            .socket
+            .process_authenticated(&mut socket.cx, &ip_repr, &repr, &bytes)
+            .map(|(_, repr)| repr)
+    }
+
+    /// Return the authentication option the remote endpoint would send with `repr`.
+    #[cfg(feature = "socket-tcp-ao")]
+    fn remote_sign(remote: &mut AuthState, repr: &TcpRepr) -> Option<TcpAuthOption> {
+        let ip_repr = IpReprIpvX(IpvXRepr {
+            src_addr: REMOTE_ADDR,
+            dst_addr: LOCAL_ADDR,
+            next_header: IpProtocol::Tcp,
+            payload_len: repr.buffer_len(),
+            hop_limit: 64,
+        });
+        remote.sign(&ip_repr, repr)
+    }
+
+    /// Check a segment sent by the socket as the remote endpoint would.
+    #[cfg(feature = "socket-tcp-ao")]
+    fn remote_verify(remote: &mut AuthState, repr: &TcpRepr) -> bool {
+        let ip_repr = IpReprIpvX(IpvXRepr {
+            src_addr: LOCAL_ADDR,
+            dst_addr: REMOTE_ADDR,
+            next_header: IpProtocol::Tcp,
+            payload_len: repr.buffer_len(),
+            hop_limit: 64,
+        });
+        let mut bytes = vec![0; repr.buffer_len()];
+        repr.emit(
+            &mut TcpPacket::new_unchecked(&mut bytes[..]),
+            &ip_repr.src_addr(),
+            &ip_repr.dst_addr(),
+            &ChecksumCapabilities::default(),
+        );
+        remote.verify(&ip_repr, repr, &bytes)
+    }
+
+    #[test]
+    #[cfg(feature = "socket-tcp-ao")]
+    fn test_auth_key_validation() {
+        assert_eq!(
+            AuthKey::new(1, 1, AuthAlgorithm::HmacSha1, b"").err(),
+            Some(AuthError::InvalidKey)
+        );
+        assert_eq!(
+            AuthKey::new(1, 1, AuthAlgorithm::Aes128Cmac, &[0; 81]).err(),
+            Some(AuthError::InvalidKey)
+        );
+        assert!(AuthKey::new(1, 1, AuthAlgorithm::Aes128Cmac, &[0; 80]).is_ok());
+
+        let mut s = socket();
+        assert_eq!(s.set_auth_rnext_key(1), Err(AuthError::UnknownKey));
+        s.set_auth_keys(vec![auth_key(1, b"secret"), auth_key(2, b"other")]);
+        assert_eq!(s.auth_current_key().map(|key| key.send_id()), Some(1));
+        assert_eq!(s.set_auth_rnext_key(3), Err(AuthError::UnknownKey));
+        assert_eq!(s.set_auth_rnext_key(2), Ok(()));
+    }
+
+    #[cfg(feature = "socket-tcp-ao")]
+    fn socket_established_authenticated(keys: &[AuthKey]) -> (TestSocket, AuthState<'static>) {
+        let mut s = socket_listen();
+        s.set_auth_keys(keys.to_vec());
+        let mut remote = AuthState::new();
+        remote.keys = keys.to_vec().into();
+
+        let mut syn = TcpRepr {
+            control: TcpControl::Syn,
+            seq_number: REMOTE_SEQ,
+            ack_number: None,
+            ..SEND_TEMPL
+        };
+        syn.auth = remote_sign(&mut remote, &syn);
+        assert_eq!(send_authenticated(&mut s, &syn), None);
+        recv(&mut s, Instant::from_millis(0), |result| {
+            assert!(remote_verify(&mut remote, &result.unwrap()))
+        });
+        let mut ack = TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1),
+            ..SEND_TEMPL
+        };
+        ack.auth = remote_sign(&mut remote, &ack);
+        assert_eq!(send_authenticated(&mut s, &ack), None);
+        assert_eq!(s.state, State::Established);
+        (s, remote)
+    }
+
+    #[test]
+    #[cfg(feature = "socket-tcp-ao")]
+    fn test_auth_listen_syn() {
+        let mut s = socket_listen();
+        s.set_auth_keys(vec![auth_key(1, b"secret")]);
+        let mut remote = AuthState::new();
+        remote.keys = vec![auth_key(1, b"secret")].into();
+        let mut impostor = AuthState::new();
+        impostor.keys = vec![auth_key(1, b"guess")].into();
+
+        let mut syn = TcpRepr {
+            control: TcpControl::Syn,
+            seq_number: REMOTE_SEQ,
+            ack_number: None,
+            ..SEND_TEMPL
+        };
+        // Unauthenticated segments, or ones authenticated with a wrong key, are dropped.
+        assert_eq!(send_authenticated(&mut s, &syn), None);
+        assert_eq!(s.state, State::Listen);
+        syn.auth = remote_sign(&mut impostor, &syn);
+        assert_eq!(send_authenticated(&mut s, &syn), None);
+        assert_eq!(s.state, State::Listen);
+
+        syn.auth = remote_sign(&mut remote, &syn);
+        assert_eq!(send_authenticated(&mut s, &syn), None);
+        assert_eq!(s.state, State::SynReceived);
+        recv(&mut s, Instant::from_millis(0), |result| {
+            let syn_ack = result.unwrap();
+            assert_eq!(syn_ack.control, TcpControl::Syn);
+            assert_eq!(syn_ack.auth.map(|auth| auth.key_id), Some(1));
+            assert!(!remote_verify(&mut impostor, &syn_ack));
+            assert!(remote_verify(&mut remote, &syn_ack));
+        });
+    }
+
+    #[test]
+    #[cfg(feature = "socket-tcp-ao")]
+    fn test_auth_established() {
+        let (mut s, mut remote) = socket_established_authenticated(&[
+            auth_key(1, b"secret"),
+            AuthKey::new(2, 2, AuthAlgorithm::Aes128Cmac, b"also a secret").unwrap(),
+        ]);
+
+        let mut data = TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1),
+            payload: &b"abcdef"[..],
+            ..SEND_TEMPL
+        };
+        data.auth = remote_sign(&mut remote, &data);
+        let mut tampered = data;
+        tampered.payload = &b"abcdeg"[..];
+        assert_eq!(send_authenticated(&mut s, &tampered), None);
+        assert_eq!(s.rx_buffer.len(), 0);
+
+        assert_eq!(send_authenticated(&mut s, &data), None);
+        assert_eq!(s.rx_buffer.len(), 6);
+        recv(&mut s, Instant::from_millis(0), |result| {
+            let ack = result.unwrap();
+            assert_eq!(ack.ack_number, Some(REMOTE_SEQ + 1 + 6));
+            assert!(remote_verify(&mut remote, &ack));
+        });
+    }
+
+    #[test]
+    #[cfg(feature = "socket-tcp-ao")]
+    fn test_auth_rollover() {
+        let (mut s, mut remote) = socket_established_authenticated(&[
+            auth_key(1, b"secret"),
+            AuthKey::new(2, 2, AuthAlgorithm::Aes128Cmac, b"new secret").unwrap(),
+        ]);
+        assert_eq!(s.auth_current_key().map(|key| key.send_id()), Some(1));
+
+        // The remote endpoint asks for the second key, and gets it.
+        remote.rnext = Some(2);
+        let mut ack = TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1),
+            ..SEND_TEMPL
+        };
+        ack.auth = remote_sign(&mut remote, &ack);
+        assert_eq!(ack.auth.map(|auth| auth.key_id), Some(1));
+        assert_eq!(send_authenticated(&mut s, &ack), None);
+        assert_eq!(s.auth_current_key().map(|key| key.send_id()), Some(2));
+
+        s.send_slice(b"abcdef").unwrap();
+        recv(&mut s, Instant::from_millis(0), |result| {
+            let data = result.unwrap();
+            assert_eq!(data.auth.map(|auth| auth.key_id), Some(2));
+            assert!(remote_verify(&mut remote, &data));
+        });
+        // Unless told otherwise, the socket asks for the key it uses itself, so the remote
+        // endpoint switches as well.
+        assert_eq!(remote.current, 1);
+    }
+
+    #[test]
+    #[cfg(all(feature = "socket-tcp-ao", feature = "proto-ipv4"))]
+    fn test_auth_mac() {
+        // Computed with an independent implementation of RFC 5925 and RFC 5926.
+        let expected = [
+            (
+                AuthAlgorithm::HmacSha1,
+                [
+                    0x3c, 0x12, 0x8c, 0x84, 0xf8, 0x56, 0xb3, 0xeb, 0x67, 0x13, 0xde, 0x06,
+                ],
+            ),
+            (
+                AuthAlgorithm::Aes128Cmac,
+                [
+                    0xe1, 0x97, 0x52, 0xe5, 0xff, 0xd5, 0x78, 0x4e, 0x92, 0x7e, 0x6a, 0x3f,
+                ],
+            ),
+        ];
+        for &(algorithm, mac) in expected.iter() {
+            let mut auth = AuthState::new();
+            auth.keys = vec![AuthKey::new(7, 9, algorithm, b"testvector").unwrap()].into();
+            auth.local_isn = TcpSeqNumber(0x55667788);
+            auth.remote_isn = TcpSeqNumber(0x11223344);
+            let repr = TcpRepr {
+                seq_number: TcpSeqNumber(0x55667789),
+                ack_number: Some(TcpSeqNumber(0x11223345)),
+                payload: &b"hello"[..],
+                ..RECV_TEMPL
+            };
+            assert_eq!(
+                auth.sign(&_RECV_IP_TEMPL, &repr),
+                Some(TcpAuthOption {
+                    key_id: 7,
+                    rnext_key_id: 9,
+                    mac
+                })
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "socket-tcp-ao")]
+    fn test_auth_mss() {
+        let mut s = socket_established();
+        let mss = s.effective_mss(&s.cx).unwrap();
+        s.set_auth_keys(vec![auth_key(1, b"secret")]);
+        assert_eq!(s.effective_mss(&s.cx), Some(mss - 16));
+    }
+
+    #[test]
+    #[cfg(feature = "socket-tcp-ao")]
+    fn test_auth_seq_extension() {
+        let mut sne = SeqExtension::new(TcpSeqNumber(-100));
+        assert_eq!(sne.get(TcpSeqNumber(-50)), 0);
+        // Wrapping around increments the extension, for this and later sequence numbers.
+        assert_eq!(sne.get(TcpSeqNumber(50)), 1);
+        sne.update(TcpSeqNumber(50));
+        assert_eq!(sne.get(TcpSeqNumber(100)), 1);
+        // Segments from before the wrap still use the old extension.
+        assert_eq!(sne.get(TcpSeqNumber(-50)), 0);
+        sne.update(TcpSeqNumber(-50));
+        assert_eq!(sne.get(TcpSeqNumber(100)), 1);
+    }
+
     // =========================================================================================//
     // Timer tests
     // =========================================================================================//
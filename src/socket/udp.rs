@@ -2,18 +2,40 @@ use core::cmp::min;
 #[cfg(feature = "async")]
 use core::task::Waker;
 
-use crate::iface::Context;
+use crate::iface::{Context, DropReason};
 use crate::socket::PollAt;
 #[cfg(feature = "async")]
 use crate::socket::WakerRegistration;
 use crate::storage::Empty;
-use crate::wire::{IpEndpoint, IpListenEndpoint, IpProtocol, IpRepr, UdpRepr};
+use crate::wire::{IpAddress, IpEndpoint, IpListenEndpoint, IpProtocol, IpRepr, UdpRepr};
+
+/// Metadata for a sent or received UDP packet.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct UdpMetadata {
+    /// The remote endpoint the packet was received from, or is sent to.
+    pub endpoint: IpEndpoint,
+    /// The local address the packet was received on, or is sent from.
+    ///
+    /// When sending, `None` selects the address the socket is bound to, or else a suitable
+    /// address of the interface.
+    pub local_address: Option<IpAddress>,
+}
+
+impl From<IpEndpoint> for UdpMetadata {
+    fn from(endpoint: IpEndpoint) -> UdpMetadata {
+        UdpMetadata {
+            endpoint,
+            local_address: None,
+        }
+    }
+}
 
 /// A UDP packet metadata.
-pub type PacketMetadata = crate::storage::PacketMetadata<IpEndpoint>;
+pub type PacketMetadata = crate::storage::PacketMetadata<UdpMetadata>;
 
 /// A UDP packet ring buffer.
-pub type PacketBuffer<'a> = crate::storage::PacketBuffer<'a, IpEndpoint>;
+pub type PacketBuffer<'a> = crate::storage::PacketBuffer<'a, UdpMetadata>;
 
 /// Error returned by [`Socket::bind`]
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -49,6 +71,14 @@ pub struct Socket<'a> {
     tx_buffer: PacketBuffer<'a>,
     /// The time-to-live (IPv4) or hop limit (IPv6) value used in outgoing packets.
     hop_limit: Option<u8>,
+    /// Whether outgoing packets are restricted to directly-connected destinations.
+    dont_route: bool,
+    /// Whether the checksum of outgoing IPv4 datagrams is computed.
+    udp_checksum: bool,
+    /// Whether the address the socket is bound to was removed from the interface.
+    addr_removed: bool,
+    /// Whether packets to destinations that aren't local are accepted.
+    transparent: bool,
     #[cfg(feature = "async")]
     rx_waker: WakerRegistration,
     #[cfg(feature = "async")]
@@ -63,6 +93,10 @@ impl<'a> Socket<'a> {
             rx_buffer,
             tx_buffer,
             hop_limit: None,
+            dont_route: false,
+            udp_checksum: true,
+            addr_removed: false,
+            transparent: false,
             #[cfg(feature = "async")]
             rx_waker: WakerRegistration::new(),
             #[cfg(feature = "async")]
@@ -138,6 +172,66 @@ impl<'a> Socket<'a> {
         self.hop_limit = hop_limit
     }
 
+    /// Return whether outgoing packets are restricted to directly-connected destinations.
+    ///
+    /// See also the [set_dont_route](#method.set_dont_route) method
+    pub fn dont_route(&self) -> bool {
+        self.dont_route
+    }
+
+    /// Restrict outgoing packets to destinations on a directly-connected subnet.
+    ///
+    /// When enabled, packets whose destination would have to be sent through a
+    /// gateway are dropped instead of being routed, similar to `SO_DONTROUTE`.
+    /// This is useful for link-local discovery protocols that must never leak
+    /// off-subnet.
+    ///
+    /// The check happens when the interface dispatches the packet, not in
+    /// [send](#method.send) or [send_slice](#method.send_slice), so those still
+    /// succeed and the packet is silently dropped later.
+    pub fn set_dont_route(&mut self, dont_route: bool) {
+        self.dont_route = dont_route
+    }
+
+    /// Return whether the checksum of outgoing IPv4 datagrams is computed.
+    ///
+    /// See also the [set_udp_checksum](#method.set_udp_checksum) method
+    pub fn udp_checksum(&self) -> bool {
+        self.udp_checksum
+    }
+
+    /// Enable or disable the checksum of outgoing IPv4 datagrams.
+    ///
+    /// When disabled, datagrams sent over IPv4 carry a zero checksum, which RFC 768 defines
+    /// as "not computed"; this is only appropriate on links that are otherwise protected
+    /// against corruption. Datagrams sent over IPv6, where a checksum is mandatory, are not
+    /// affected. Incoming IPv4 datagrams with a zero checksum are always accepted.
+    pub fn set_udp_checksum(&mut self, enabled: bool) {
+        self.udp_checksum = enabled
+    }
+
+    /// Return whether the socket is in transparent mode.
+    ///
+    /// See also the [set_transparent](#method.set_transparent) method
+    pub fn transparent(&self) -> bool {
+        self.transparent
+    }
+
+    /// Enable or disable transparent mode, similar to `IP_TRANSPARENT`.
+    ///
+    /// In transparent mode, the socket also accepts datagrams sent to its port at addresses
+    /// that are not assigned to the interface, such as traffic redirected to the interface
+    /// by a transparent proxy setup. The original destination address of each datagram is
+    /// returned by [recv_with_metadata], and a reply can be sent from it with
+    /// [send_with_metadata]. Sockets that are not in transparent mode never see these
+    /// datagrams, and datagrams no transparent socket accepts are dropped silently.
+    ///
+    /// [recv_with_metadata]: #method.recv_with_metadata
+    /// [send_with_metadata]: #method.send_with_metadata
+    pub fn set_transparent(&mut self, transparent: bool) {
+        self.transparent = transparent
+    }
+
     /// Bind the socket to the given endpoint.
     ///
     /// This function returns `Err(Error::Illegal)` if the socket was open
@@ -168,6 +262,7 @@ impl<'a> Socket<'a> {
     pub fn close(&mut self) {
         // Clear the bound endpoint of the socket.
         self.endpoint = IpListenEndpoint::default();
+        self.addr_removed = false;
 
         // Reset the RX and TX buffers of the socket.
         self.tx_buffer.reset();
@@ -180,12 +275,32 @@ impl<'a> Socket<'a> {
         }
     }
 
+    /// Discard the queued packets and refuse to send any more, if the socket is bound to
+    /// the given local address, which was removed from the interface.
+    ///
+    /// The socket can be used again after it is closed and bound anew.
+    pub(crate) fn local_address_removed(&mut self, addr: IpAddress) {
+        if self.endpoint.addr == Some(addr) {
+            net_debug!("udp:{}: local address removed", self.endpoint);
+            self.addr_removed = true;
+            self.tx_buffer.reset();
+
+            #[cfg(feature = "async")]
+            self.tx_waker.wake();
+        }
+    }
+
     /// Check whether the socket is open.
     #[inline]
     pub fn is_open(&self) -> bool {
         self.endpoint.port != 0
     }
 
+    /// Check whether the socket is bound to an endpoint overlapping with `endpoint`.
+    pub(crate) fn is_bound_to(&self, endpoint: &IpListenEndpoint) -> bool {
+        self.is_open() && self.endpoint.overlaps(endpoint)
+    }
+
     /// Check whether the transmit buffer is full.
     #[inline]
     pub fn can_send(&self) -> bool {
@@ -227,6 +342,7 @@ impl<'a> Socket<'a> {
     ///
     /// This function returns `Err(Error::Exhausted)` if the transmit buffer is full,
     /// `Err(Error::Unaddressable)` if local or remote port, or remote address are unspecified,
+    /// or if the local address was removed from the interface,
     /// and `Err(Error::Truncated)` if there is not enough transmit buffer capacity
     /// to ever send this packet.
     pub fn send(
@@ -234,7 +350,26 @@ impl<'a> Socket<'a> {
         size: usize,
         remote_endpoint: IpEndpoint,
     ) -> Result<&mut [u8], SendError> {
-        if self.endpoint.port == 0 {
+        self.send_with_metadata(size, remote_endpoint.into())
+    }
+
+    /// Enqueue a packet to be sent to the remote endpoint given in `meta`, from the local
+    /// address given in it, and return a pointer to its payload.
+    ///
+    /// The local address only needs to be specified if the socket is not bound to one, and
+    /// should differ from the one the interface selects, e.g. to reply from the original
+    /// destination of a datagram received in [transparent mode](#method.set_transparent).
+    /// Unless the socket is in transparent mode, a packet from an address that is not
+    /// assigned to the interface is dropped when it would be sent.
+    ///
+    /// See also [send](#method.send).
+    pub fn send_with_metadata(
+        &mut self,
+        size: usize,
+        meta: UdpMetadata,
+    ) -> Result<&mut [u8], SendError> {
+        let remote_endpoint = meta.endpoint;
+        if self.endpoint.port == 0 || self.addr_removed {
             return Err(SendError::Unaddressable);
         }
         if remote_endpoint.addr.is_unspecified() {
@@ -246,7 +381,7 @@ impl<'a> Socket<'a> {
 
         let payload_buf = self
             .tx_buffer
-            .enqueue(size, remote_endpoint)
+            .enqueue(size, meta)
             .map_err(|_| SendError::BufferFull)?;
 
         net_trace!(
@@ -276,16 +411,24 @@ impl<'a> Socket<'a> {
     ///
     /// This function returns `Err(Error::Exhausted)` if the receive buffer is empty.
     pub fn recv(&mut self) -> Result<(&[u8], IpEndpoint), RecvError> {
-        let (remote_endpoint, payload_buf) =
-            self.rx_buffer.dequeue().map_err(|_| RecvError::Exhausted)?;
+        let (payload_buf, meta) = self.recv_with_metadata()?;
+        Ok((payload_buf, meta.endpoint))
+    }
+
+    /// Dequeue a packet received from a remote endpoint, and return its metadata, including
+    /// the local address it was sent to, as well as a pointer to the payload.
+    ///
+    /// See also [recv](#method.recv).
+    pub fn recv_with_metadata(&mut self) -> Result<(&[u8], UdpMetadata), RecvError> {
+        let (meta, payload_buf) = self.rx_buffer.dequeue().map_err(|_| RecvError::Exhausted)?;
 
         net_trace!(
             "udp:{}:{}: receive {} buffered octets",
             self.endpoint,
-            remote_endpoint,
+            meta.endpoint,
             payload_buf.len()
         );
-        Ok((payload_buf, remote_endpoint))
+        Ok((payload_buf, meta))
     }
 
     /// Dequeue a packet received from a remote endpoint, copy the payload into the given slice,
@@ -306,17 +449,18 @@ impl<'a> Socket<'a> {
     /// It returns `Err(Error::Exhausted)` if the receive buffer is empty.
     pub fn peek(&mut self) -> Result<(&[u8], &IpEndpoint), RecvError> {
         let endpoint = self.endpoint;
-        self.rx_buffer.peek().map_err(|_| RecvError::Exhausted).map(
-            |(remote_endpoint, payload_buf)| {
+        self.rx_buffer
+            .peek()
+            .map_err(|_| RecvError::Exhausted)
+            .map(|(meta, payload_buf)| {
                 net_trace!(
                     "udp:{}:{}: peek {} buffered octets",
                     endpoint,
-                    remote_endpoint,
+                    meta.endpoint,
                     payload_buf.len()
                 );
-                (payload_buf, remote_endpoint)
-            },
-        )
+                (payload_buf, &meta.endpoint)
+            })
     }
 
     /// Peek at a packet received from a remote endpoint, copy the payload into the given slice,
@@ -370,13 +514,20 @@ impl<'a> Socket<'a> {
             size
         );
 
-        match self.rx_buffer.enqueue(size, remote_endpoint) {
+        let meta = UdpMetadata {
+            endpoint: remote_endpoint,
+            local_address: Some(ip_repr.dst_addr()),
+        };
+        match self.rx_buffer.enqueue(size, meta) {
             Ok(buf) => buf.copy_from_slice(payload),
-            Err(_) => net_trace!(
-                "udp:{}:{}: buffer full, dropped incoming packet",
-                self.endpoint,
-                remote_endpoint
-            ),
+            Err(_) => {
+                net_trace!(
+                    "udp:{}:{}: buffer full, dropped incoming packet",
+                    self.endpoint,
+                    remote_endpoint
+                );
+                cx.observe_drop(DropReason::BufferFull, ip_repr);
+            }
         }
 
         #[cfg(feature = "async")]
@@ -389,9 +540,34 @@ impl<'a> Socket<'a> {
     {
         let endpoint = self.endpoint;
         let hop_limit = self.hop_limit.unwrap_or(64);
+        let dont_route = self.dont_route;
+        let transparent = self.transparent;
+
+        let res = self.tx_buffer.dequeue_with(|meta, payload_buf| {
+            let remote_endpoint = meta.endpoint;
+            if dont_route && !cx.is_on_link(&remote_endpoint.addr) {
+                net_trace!(
+                    "udp:{}:{}: destination not directly connected, dropping.",
+                    endpoint,
+                    remote_endpoint
+                );
+                return Ok(());
+            }
+
+            if let Some(local_address) = meta.local_address {
+                // Only transparent sockets may send from addresses that aren't ours.
+                if !transparent && !cx.has_ip_addr(local_address) {
+                    net_trace!(
+                        "udp:{}:{}: local address {} not assigned to the interface, dropping.",
+                        endpoint,
+                        remote_endpoint,
+                        local_address
+                    );
+                    return Ok(());
+                }
+            }
 
-        let res = self.tx_buffer.dequeue_with(|remote_endpoint, payload_buf| {
-            let src_addr = match endpoint.addr {
+            let src_addr = match meta.local_address.or(endpoint.addr) {
                 Some(addr) => addr,
                 None => match cx.get_source_address(remote_endpoint.addr) {
                     Some(addr) => addr,
@@ -437,6 +613,13 @@ impl<'a> Socket<'a> {
         }
     }
 
+    /// Panic if the state of the packet buffers is inconsistent.
+    #[cfg(feature = "paranoid")]
+    pub(crate) fn check_invariants(&self) {
+        self.rx_buffer.check_invariants();
+        self.tx_buffer.check_invariants();
+    }
+
     pub(crate) fn poll_at(&self, _cx: &mut Context) -> PollAt {
         if self.tx_buffer.is_empty() {
             PollAt::Ingress
@@ -589,6 +772,24 @@ mod test {
         assert_eq!(socket.send_slice(b"abcdef", REMOTE_END), Ok(()));
     }
 
+    #[test]
+    fn test_send_local_address_removed() {
+        let mut socket = socket(buffer(0), buffer(1));
+        assert_eq!(socket.bind(LOCAL_END), Ok(()));
+        assert_eq!(socket.send_slice(b"abcdef", REMOTE_END), Ok(()));
+
+        socket.local_address_removed(LOCAL_END.addr);
+        assert!(socket.can_send());
+        assert_eq!(
+            socket.send_slice(b"abcdef", REMOTE_END),
+            Err(SendError::Unaddressable)
+        );
+
+        socket.close();
+        assert_eq!(socket.bind(LOCAL_END), Ok(()));
+        assert_eq!(socket.send_slice(b"abcdef", REMOTE_END), Ok(()));
+    }
+
     #[test]
     fn test_send_dispatch() {
         let mut socket = socket(buffer(0), buffer(1));
@@ -653,6 +854,52 @@ mod test {
         assert!(!socket.can_recv());
     }
 
+    #[test]
+    fn test_metadata() {
+        let mut socket = socket(buffer(1), buffer(1));
+        let mut cx = Context::mock();
+
+        assert_eq!(socket.bind(LOCAL_PORT), Ok(()));
+
+        // The destination address of received packets is recorded.
+        socket.process(&mut cx, &BAD_IP_REPR, &REMOTE_UDP_REPR, PAYLOAD);
+        let (payload, meta) = socket.recv_with_metadata().unwrap();
+        assert_eq!(payload, PAYLOAD);
+        assert_eq!(meta.endpoint, REMOTE_END);
+        assert_eq!(meta.local_address, Some(OTHER_ADDR.into()));
+
+        // Only transparent sockets may send from addresses that aren't ours.
+        let meta = UdpMetadata {
+            endpoint: REMOTE_END,
+            local_address: Some(OTHER_ADDR.into()),
+        };
+        socket
+            .send_with_metadata(PAYLOAD.len(), meta)
+            .unwrap()
+            .copy_from_slice(PAYLOAD);
+        assert_eq!(
+            socket.dispatch(&mut cx, |_, _| unreachable!()),
+            Ok::<_, Error>(())
+        );
+        assert!(socket.can_send());
+
+        socket.set_transparent(true);
+        socket
+            .send_with_metadata(PAYLOAD.len(), meta)
+            .unwrap()
+            .copy_from_slice(PAYLOAD);
+        assert_eq!(
+            socket.dispatch(&mut cx, |_, (ip_repr, udp_repr, payload)| {
+                assert_eq!(ip_repr.src_addr(), OTHER_ADDR.into());
+                assert_eq!(ip_repr.dst_addr(), REMOTE_ADDR.into());
+                assert_eq!(udp_repr, LOCAL_UDP_REPR);
+                assert_eq!(payload, PAYLOAD);
+                Ok::<_, Error>(())
+            }),
+            Ok(())
+        );
+    }
+
     #[test]
     fn test_peek_process() {
         let mut socket = socket(buffer(1), buffer(0));
@@ -727,6 +974,38 @@ mod test {
         );
     }
 
+    #[test]
+    #[cfg(feature = "proto-ipv4")]
+    fn test_dont_route() {
+        let mut s = socket(buffer(0), buffer(2));
+        let mut cx = Context::mock();
+
+        assert_eq!(s.bind(LOCAL_END), Ok(()));
+        assert!(!s.dont_route());
+        s.set_dont_route(true);
+        assert!(s.dont_route());
+
+        let off_link = IpEndpoint {
+            addr: crate::wire::IpAddress::v4(10, 0, 0, 1),
+            port: REMOTE_PORT,
+        };
+        assert_eq!(s.send_slice(b"abcdef", off_link), Ok(()));
+        assert_eq!(
+            s.dispatch(&mut cx, |_, _| unreachable!()),
+            Ok::<_, Error>(())
+        );
+        assert!(s.can_send());
+
+        assert_eq!(s.send_slice(b"abcdef", REMOTE_END), Ok(()));
+        assert_eq!(
+            s.dispatch(&mut cx, |_, (ip_repr, _, _)| {
+                assert_eq!(ip_repr, LOCAL_IP_REPR);
+                Ok::<_, Error>(())
+            }),
+            Ok(())
+        );
+    }
+
     #[test]
     fn test_doesnt_accept_wrong_port() {
         let mut socket = socket(buffer(1), buffer(0));
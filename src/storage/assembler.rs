@@ -207,7 +207,7 @@ impl Assembler {
                 // The range being added covers the entire hole in this contig, merge it
                 // into the previous config.
                 self.contigs[index - 1].expand_data_by(contig.total_size());
-                overlap -= contig.total_size();
+                overlap -= contig.hole_size;
                 self.remove_contig_at(index);
                 index += 0;
             } else if offset == 0 && size < contig.hole_size && index > 0 {
@@ -282,6 +282,44 @@ impl Assembler {
     pub fn iter_data(&self, first_offset: usize) -> AssemblerIter {
         AssemblerIter::new(self, first_offset)
     }
+
+    /// Panic if the contigs don't describe alternating holes and data, which would mean
+    /// that adjacent ranges were not merged.
+    #[cfg(all(feature = "paranoid", feature = "socket-tcp"))]
+    pub(crate) fn check_invariants(&self) {
+        let used = self
+            .contigs
+            .iter()
+            .position(|contig| contig.is_empty())
+            .unwrap_or(self.contigs.len());
+        for (index, contig) in self.contigs.iter().enumerate() {
+            if index >= used {
+                assert!(
+                    contig.is_empty(),
+                    "assembler: contig {} follows the last one in {}",
+                    index,
+                    self
+                );
+                continue;
+            }
+            if index > 0 {
+                assert!(
+                    contig.has_hole(),
+                    "assembler: contig {} is not separated from the previous one in {}",
+                    index,
+                    self
+                );
+            }
+            if index + 1 < used {
+                assert!(
+                    contig.has_data(),
+                    "assembler: contig {} is followed by another hole in {}",
+                    index,
+                    self
+                );
+            }
+        }
+    }
 }
 
 pub struct AssemblerIter<'a> {
@@ -402,6 +440,13 @@ mod test {
         assert_eq!(assr, contigs![(4, 12)]);
     }
 
+    #[test]
+    fn test_partial_add_hole() {
+        let mut assr = contigs![(0, 4), (4, 4), (4, 0)];
+        assert_eq!(assr.add(4, 4), Ok(false));
+        assert_eq!(assr, contigs![(0, 12), (4, 0)]);
+    }
+
     #[test]
     fn test_partial_add_front_overlap() {
         let mut assr = contigs![(4, 8), (4, 0)];
@@ -543,4 +588,25 @@ mod test {
         let segments: Vec<_> = assr.iter_data(100).collect();
         assert_eq!(segments, vec![(102, 108), (110, 111), (113, 115)]);
     }
+
+    #[test]
+    #[cfg(all(feature = "paranoid", feature = "socket-tcp"))]
+    fn test_check_invariants() {
+        let mut assr = Assembler::new(16);
+        assr.check_invariants();
+        assert_eq!(assr.add(4, 2), Ok(false));
+        assert_eq!(assr.add(10, 2), Ok(false));
+        assr.check_invariants();
+        assert_eq!(assr.add(0, 4), Ok(false));
+        assert_eq!(assr.remove_front(), Some(6));
+        assr.check_invariants();
+    }
+
+    #[test]
+    #[cfg(all(feature = "paranoid", feature = "socket-tcp"))]
+    #[should_panic(expected = "followed by another hole")]
+    fn test_check_invariants_adjacent_holes() {
+        let assr = contigs![(4, 0), (2, 2)];
+        assr.check_invariants();
+    }
 }
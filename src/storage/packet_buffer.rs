@@ -208,6 +208,32 @@ impl<'a, H> PacketBuffer<'a, H> {
         self.payload_ring.clear();
         self.metadata_ring.clear();
     }
+
+    /// Panic if the metadata does not account for exactly the octets in the payload ring.
+    #[cfg(all(
+        feature = "paranoid",
+        any(
+            feature = "socket-raw",
+            feature = "socket-icmp",
+            feature = "socket-udp"
+        )
+    ))]
+    pub(crate) fn check_invariants(&self) {
+        self.metadata_ring.check_invariants();
+        self.payload_ring.check_invariants();
+
+        let size: usize = self
+            .metadata_ring
+            .iter_allocated()
+            .map(|metadata| metadata.size)
+            .sum();
+        assert!(
+            size == self.payload_ring.len(),
+            "packet buffer: metadata describes {} octets, but {} are queued",
+            size,
+            self.payload_ring.len()
+        );
+    }
 }
 
 #[cfg(test)]
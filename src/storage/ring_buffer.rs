@@ -109,6 +109,44 @@ impl<'a, T: 'a> RingBuffer<'a, T> {
     fn get_idx_unchecked(&self, idx: usize) -> usize {
         (self.read_at + idx) % self.capacity()
     }
+
+    /// Panic if the read pointer or the length are out of bounds.
+    #[cfg(all(
+        feature = "paranoid",
+        any(
+            feature = "socket-raw",
+            feature = "socket-icmp",
+            feature = "socket-udp",
+            feature = "socket-tcp"
+        )
+    ))]
+    pub(crate) fn check_invariants(&self) {
+        assert!(
+            self.length <= self.capacity(),
+            "ring buffer: length {} exceeds capacity {}",
+            self.length,
+            self.capacity()
+        );
+        assert!(
+            self.read_at < self.capacity() || self.read_at == 0,
+            "ring buffer: read pointer {} is out of bounds of capacity {}",
+            self.read_at,
+            self.capacity()
+        );
+    }
+
+    /// Iterate over the allocated elements, from the oldest to the newest.
+    #[cfg(all(
+        feature = "paranoid",
+        any(
+            feature = "socket-raw",
+            feature = "socket-icmp",
+            feature = "socket-udp"
+        )
+    ))]
+    pub(crate) fn iter_allocated(&self) -> impl Iterator<Item = &T> + '_ {
+        (0..self.length).map(move |idx| &self.storage[self.get_idx_unchecked(idx)])
+    }
 }
 
 /// This is the "discrete" ring buffer interface: it operates with single elements,
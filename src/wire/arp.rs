@@ -14,9 +14,14 @@ enum_with_unknown! {
 
 enum_with_unknown! {
     /// ARP operation type.
+    ///
+    /// The RARP operations (RFC 903) share the ARP packet format, but are carried in
+    /// frames with the RARP EtherType.
     pub enum Operation(u16) {
         Request = 1,
-        Reply = 2
+        Reply = 2,
+        RarpRequest = 3,
+        RarpReply = 4
     }
 }
 
@@ -460,4 +465,26 @@ mod test {
         packet_repr().emit(&mut packet);
         assert_eq!(&packet.into_inner()[..], &PACKET_BYTES[..]);
     }
+
+    #[test]
+    fn test_rarp_operation() {
+        let mut bytes = PACKET_BYTES;
+        bytes[7] = 0x03;
+        let packet = Packet::new_unchecked(&bytes[..]);
+        assert_eq!(packet.operation(), Operation::RarpRequest);
+        match Repr::parse(&packet) {
+            Ok(Repr::EthernetIpv4 { operation, .. }) => {
+                assert_eq!(operation, Operation::RarpRequest)
+            }
+            repr => panic!("unexpected {:?}", repr),
+        }
+
+        bytes[7] = 0x04;
+        let packet = Packet::new_unchecked(&bytes[..]);
+        assert_eq!(packet.operation(), Operation::RarpReply);
+
+        bytes[7] = 0x09;
+        let packet = Packet::new_unchecked(&bytes[..]);
+        assert_eq!(packet.operation(), Operation::Unknown(9));
+    }
 }
@@ -10,6 +10,7 @@ use crate::wire::{EthernetAddress, Ipv4Address};
 pub const SERVER_PORT: u16 = 67;
 pub const CLIENT_PORT: u16 = 68;
 pub const MAX_DNS_SERVER_COUNT: usize = 3;
+pub const MAX_NTP_SERVER_COUNT: usize = 3;
 
 const DHCP_MAGIC_NUMBER: u32 = 0x63825363;
 
@@ -700,6 +701,10 @@ pub struct Repr<'a> {
     pub parameter_request_list: Option<&'a [u8]>,
     /// DNS servers
     pub dns_servers: Option<[Option<Ipv4Address>; MAX_DNS_SERVER_COUNT]>,
+    /// NTP servers
+    pub ntp_servers: Option<[Option<Ipv4Address>; MAX_NTP_SERVER_COUNT]>,
+    /// The domain name the client should use when resolving hostnames.
+    pub domain_name: Option<&'a str>,
     /// The maximum size dhcp packet the interface can receive
     pub max_size: Option<u16>,
     /// The DHCP IP lease duration, specified in seconds.
@@ -737,6 +742,13 @@ impl<'a> Repr<'a> {
             len += 2;
             len += dns_servers.iter().flatten().count() * core::mem::size_of::<u32>();
         }
+        if let Some(ntp_servers) = self.ntp_servers {
+            len += 2;
+            len += ntp_servers.iter().flatten().count() * core::mem::size_of::<u32>();
+        }
+        if let Some(domain_name) = self.domain_name {
+            len += domain_name.len() + 2;
+        }
         if let Some(list) = self.parameter_request_list {
             len += list.len() + 2;
         }
@@ -778,6 +790,8 @@ impl<'a> Repr<'a> {
         let mut subnet_mask = None;
         let mut parameter_request_list = None;
         let mut dns_servers = None;
+        let mut ntp_servers = None;
+        let mut domain_name = None;
         let mut max_size = None;
         let mut lease_duration = None;
 
@@ -823,15 +837,22 @@ impl<'a> Repr<'a> {
                     kind: field::OPT_DOMAIN_NAME_SERVER,
                     data,
                 } => {
-                    let mut servers = [None; MAX_DNS_SERVER_COUNT];
-                    let chunk_size = 4;
-                    for (server, chunk) in servers.iter_mut().zip(data.chunks(chunk_size)) {
-                        if chunk.len() != chunk_size {
-                            return Err(Error);
-                        }
-                        *server = Some(Ipv4Address::from_bytes(chunk));
-                    }
-                    dns_servers = Some(servers);
+                    dns_servers = Some(Self::parse_addresses(data)?);
+                }
+                DhcpOption::Other {
+                    kind: field::OPT_NTP_SERVERS,
+                    data,
+                } => {
+                    ntp_servers = Some(Self::parse_addresses(data)?);
+                }
+                DhcpOption::Other {
+                    kind: field::OPT_DOMAIN_NAME,
+                    data,
+                } => {
+                    // Some servers include the NUL terminator.
+                    domain_name = core::str::from_utf8(data)
+                        .ok()
+                        .map(|name| name.trim_end_matches('\0'));
                 }
                 DhcpOption::Other { .. } => {}
             }
@@ -855,12 +876,56 @@ impl<'a> Repr<'a> {
             client_identifier,
             parameter_request_list,
             dns_servers,
+            ntp_servers,
+            domain_name,
             max_size,
             lease_duration,
             message_type: message_type?,
         })
     }
 
+    /// Parse a list of addresses, ignoring the addresses that don't fit.
+    fn parse_addresses<const N: usize>(data: &[u8]) -> Result<[Option<Ipv4Address>; N]> {
+        let mut addresses = [None; N];
+        let chunk_size = 4;
+        for (address, chunk) in addresses.iter_mut().zip(data.chunks(chunk_size)) {
+            if chunk.len() != chunk_size {
+                return Err(Error);
+            }
+            *address = Some(Ipv4Address::from_bytes(chunk));
+        }
+        Ok(addresses)
+    }
+
+    fn emit_addresses<'b>(
+        options: &'b mut [u8],
+        kind: u8,
+        addresses: &[Option<Ipv4Address>],
+    ) -> &'b mut [u8] {
+        const IP_SIZE: usize = core::mem::size_of::<u32>();
+        const MAX_COUNT: usize = if MAX_DNS_SERVER_COUNT > MAX_NTP_SERVER_COUNT {
+            MAX_DNS_SERVER_COUNT
+        } else {
+            MAX_NTP_SERVER_COUNT
+        };
+        let mut data = [0; MAX_COUNT * IP_SIZE];
+
+        let data_len = addresses
+            .iter()
+            .flatten()
+            .enumerate()
+            .inspect(|(i, ip)| {
+                data[(i * IP_SIZE)..((i + 1) * IP_SIZE)].copy_from_slice(ip.as_bytes());
+            })
+            .count()
+            * IP_SIZE;
+        DhcpOption::Other {
+            kind,
+            data: &data[..data_len],
+        }
+        .emit(options)
+    }
+
     /// Emit a high-level representation into a Dynamic Host
     /// Configuration Protocol packet.
     pub fn emit<T>(&self, packet: &mut Packet<&mut T>) -> Result<()>
@@ -912,23 +977,18 @@ impl<'a> Repr<'a> {
                 options = DhcpOption::IpLeaseTime(duration).emit(options);
             }
             if let Some(dns_servers) = self.dns_servers {
-                const IP_SIZE: usize = core::mem::size_of::<u32>();
-                let mut servers = [0; MAX_DNS_SERVER_COUNT * IP_SIZE];
-
-                let data_len = dns_servers
-                    .iter()
-                    .flatten()
-                    .enumerate()
-                    .inspect(|(i, ip)| {
-                        servers[(i * IP_SIZE)..((i + 1) * IP_SIZE)].copy_from_slice(ip.as_bytes());
-                    })
-                    .count()
-                    * IP_SIZE;
-                let option = DhcpOption::Other {
-                    kind: field::OPT_DOMAIN_NAME_SERVER,
-                    data: &servers[..data_len],
-                };
-                options = option.emit(options);
+                options =
+                    Self::emit_addresses(options, field::OPT_DOMAIN_NAME_SERVER, &dns_servers);
+            }
+            if let Some(ntp_servers) = self.ntp_servers {
+                options = Self::emit_addresses(options, field::OPT_NTP_SERVERS, &ntp_servers);
+            }
+            if let Some(domain_name) = self.domain_name {
+                options = DhcpOption::Other {
+                    kind: field::OPT_DOMAIN_NAME,
+                    data: domain_name.as_bytes(),
+                }
+                .emit(options);
             }
             if let Some(list) = self.parameter_request_list {
                 options = DhcpOption::Other {
@@ -1133,6 +1193,8 @@ mod test {
             server_identifier: None,
             parameter_request_list: None,
             dns_servers: None,
+            ntp_servers: None,
+            domain_name: None,
             max_size: None,
             lease_duration: Some(0xffff_ffff), // Infinite lease
         }
@@ -1157,6 +1219,8 @@ mod test {
             server_identifier: None,
             parameter_request_list: Some(&[1, 3, 6, 42]),
             dns_servers: None,
+            ntp_servers: None,
+            domain_name: None,
         }
     }
 
@@ -1217,6 +1281,36 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_emit_offer_ntp_domain_name() {
+        let repr = {
+            let mut repr = offer_repr();
+            repr.ntp_servers = Some([Some(Ipv4Address([163, 1, 74, 8])), None, None]);
+            repr.domain_name = Some("example.com");
+            repr
+        };
+        let mut bytes = vec![0xa5; repr.buffer_len()];
+        let mut packet = Packet::new_unchecked(&mut bytes);
+        repr.emit(&mut packet).unwrap();
+
+        let packet = Packet::new_unchecked(&bytes);
+        let repr_parsed = Repr::parse(&packet).unwrap();
+        assert_eq!(repr_parsed, repr);
+    }
+
+    #[test]
+    fn test_parse_domain_name_nul() {
+        let mut repr = offer_repr();
+        repr.domain_name = Some("example.com\0");
+        let mut bytes = vec![0xa5; repr.buffer_len()];
+        let mut packet = Packet::new_unchecked(&mut bytes);
+        repr.emit(&mut packet).unwrap();
+
+        let packet = Packet::new_unchecked(&bytes);
+        let repr_parsed = Repr::parse(&packet).unwrap();
+        assert_eq!(repr_parsed.domain_name, Some("example.com"));
+    }
+
     #[test]
     fn test_emit_dhcp_option() {
         static DATA: &[u8] = &[1, 3, 6];
@@ -8,7 +8,10 @@ enum_with_unknown! {
     pub enum EtherType(u16) {
         Ipv4 = 0x0800,
         Arp  = 0x0806,
-        Ipv6 = 0x86DD
+        Rarp = 0x8035,
+        Vlan = 0x8100,
+        Ipv6 = 0x86DD,
+        QinQ = 0x88A8
     }
 }
 
@@ -18,6 +21,9 @@ impl fmt::Display for EtherType {
             EtherType::Ipv4 => write!(f, "IPv4"),
             EtherType::Ipv6 => write!(f, "IPv6"),
             EtherType::Arp => write!(f, "ARP"),
+            EtherType::Rarp => write!(f, "RARP"),
+            EtherType::Vlan => write!(f, "802.1Q"),
+            EtherType::QinQ => write!(f, "802.1ad"),
             EtherType::Unknown(id) => write!(f, "0x{:04x}", id),
         }
     }
@@ -79,6 +85,60 @@ impl fmt::Display for Address {
     }
 }
 
+/// The IEEE 802.1Q VLAN tags of an Ethernet II frame.
+///
+/// Only the 12-bit VLAN identifiers are represented; priority bits are ignored when parsing
+/// and emitted as zero.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum VlanTags {
+    /// The frame is not tagged.
+    Untagged,
+    /// A single customer tag (C-tag, EtherType 0x8100).
+    Single(u16),
+    /// A service tag (S-tag, EtherType 0x88a8) followed by a customer tag, as used
+    /// by IEEE 802.1ad "QinQ".
+    Double { outer: u16, inner: u16 },
+}
+
+impl VlanTags {
+    /// Return the outermost VLAN identifier, if the frame is tagged.
+    pub fn outer_id(&self) -> Option<u16> {
+        match *self {
+            VlanTags::Untagged => None,
+            VlanTags::Single(id) => Some(id),
+            VlanTags::Double { outer, .. } => Some(outer),
+        }
+    }
+
+    /// Return the inner VLAN identifier, if the frame is double tagged.
+    pub fn inner_id(&self) -> Option<u16> {
+        match *self {
+            VlanTags::Double { inner, .. } => Some(inner),
+            _ => None,
+        }
+    }
+
+    /// Return the number of octets the tags occupy in a frame.
+    pub fn header_len(&self) -> usize {
+        match *self {
+            VlanTags::Untagged => 0,
+            VlanTags::Single(_) => VLAN_TAG_LEN,
+            VlanTags::Double { .. } => 2 * VLAN_TAG_LEN,
+        }
+    }
+}
+
+impl fmt::Display for VlanTags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            VlanTags::Untagged => write!(f, "untagged"),
+            VlanTags::Single(id) => write!(f, "vlan={}", id),
+            VlanTags::Double { outer, inner } => write!(f, "vlan={}.{}", outer, inner),
+        }
+    }
+}
+
 /// A read/write wrapper around an Ethernet II frame buffer.
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -98,6 +158,11 @@ mod field {
 /// The Ethernet header length
 pub const HEADER_LEN: usize = field::PAYLOAD.start;
 
+/// The length of a single 802.1Q tag.
+const VLAN_TAG_LEN: usize = 4;
+
+const VLAN_ID_MASK: u16 = 0x0fff;
+
 impl<T: AsRef<[u8]>> Frame<T> {
     /// Imbue a raw octet buffer with Ethernet frame structure.
     pub fn new_unchecked(buffer: T) -> Frame<T> {
@@ -162,6 +227,45 @@ impl<T: AsRef<[u8]>> Frame<T> {
         let raw = NetworkEndian::read_u16(&data[field::ETHERTYPE]);
         EtherType::from(raw)
     }
+
+    /// Return the 802.1Q tags of the frame.
+    ///
+    /// Returns `Err(Error)` if the buffer is too short to hold the tags and the EtherType
+    /// that follows them, or if a service tag is not followed by a customer tag.
+    pub fn vlan_tags(&self) -> Result<VlanTags> {
+        let data = self.buffer.as_ref();
+        let ethertype = self.ethertype();
+        match ethertype {
+            EtherType::Vlan | EtherType::QinQ => (),
+            _ => return Ok(VlanTags::Untagged),
+        }
+        if data.len() < HEADER_LEN + VLAN_TAG_LEN {
+            return Err(Error);
+        }
+
+        let tag_start = field::ETHERTYPE.end;
+        let outer = NetworkEndian::read_u16(&data[tag_start..]) & VLAN_ID_MASK;
+        let tags = match EtherType::from(NetworkEndian::read_u16(&data[tag_start + 2..])) {
+            EtherType::Vlan => {
+                if data.len() < HEADER_LEN + 2 * VLAN_TAG_LEN {
+                    return Err(Error);
+                }
+                let inner = NetworkEndian::read_u16(&data[tag_start + 4..]) & VLAN_ID_MASK;
+                VlanTags::Double { outer, inner }
+            }
+            // Frames carrying only an S-tag can't be represented.
+            _ if ethertype == EtherType::QinQ => return Err(Error),
+            _ => VlanTags::Single(outer),
+        };
+        Ok(tags)
+    }
+
+    /// Return the EtherType of the payload, following any 802.1Q tags.
+    pub fn tagged_ethertype(&self) -> Result<EtherType> {
+        let data = self.buffer.as_ref();
+        let offset = field::ETHERTYPE.start + self.vlan_tags()?.header_len();
+        Ok(EtherType::from(NetworkEndian::read_u16(&data[offset..])))
+    }
 }
 
 impl<'a, T: AsRef<[u8]> + ?Sized> Frame<&'a T> {
@@ -171,6 +275,12 @@ impl<'a, T: AsRef<[u8]> + ?Sized> Frame<&'a T> {
         let data = self.buffer.as_ref();
         &data[field::PAYLOAD]
     }
+
+    /// Return a pointer to the payload, following any 802.1Q tags.
+    pub fn tagged_payload(&self) -> Result<&'a [u8]> {
+        let data = self.buffer.as_ref();
+        Ok(&data[field::PAYLOAD.start + self.vlan_tags()?.header_len()..])
+    }
 }
 
 impl<T: AsRef<[u8]> + AsMut<[u8]>> Frame<T> {
@@ -195,6 +305,29 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Frame<T> {
         NetworkEndian::write_u16(&mut data[field::ETHERTYPE], value.into())
     }
 
+    /// Set the 802.1Q tags, which are placed right after the source address. The EtherType
+    /// of the payload follows the tags, and is left unchanged.
+    ///
+    /// # Panics
+    /// This function panics if the buffer is too short to hold the tags.
+    pub fn set_vlan_tags(&mut self, tags: VlanTags) {
+        let data = self.buffer.as_mut();
+        let tag_start = field::ETHERTYPE.start;
+        match tags {
+            VlanTags::Untagged => (),
+            VlanTags::Single(id) => {
+                NetworkEndian::write_u16(&mut data[tag_start..], EtherType::Vlan.into());
+                NetworkEndian::write_u16(&mut data[tag_start + 2..], id & VLAN_ID_MASK);
+            }
+            VlanTags::Double { outer, inner } => {
+                NetworkEndian::write_u16(&mut data[tag_start..], EtherType::QinQ.into());
+                NetworkEndian::write_u16(&mut data[tag_start + 2..], outer & VLAN_ID_MASK);
+                NetworkEndian::write_u16(&mut data[tag_start + 4..], EtherType::Vlan.into());
+                NetworkEndian::write_u16(&mut data[tag_start + 6..], inner & VLAN_ID_MASK);
+            }
+        }
+    }
+
     /// Return a mutable pointer to the payload.
     #[inline]
     pub fn payload_mut(&mut self) -> &mut [u8] {
@@ -235,21 +368,30 @@ impl<T: AsRef<[u8]>> PrettyPrint for Frame<T> {
         };
         write!(f, "{}{}", indent, frame)?;
 
-        match frame.ethertype() {
+        let (ethertype, payload) = match (frame.tagged_ethertype(), frame.tagged_payload()) {
+            (Ok(ethertype), Ok(payload)) => (ethertype, payload),
+            _ => return write!(f, " (truncated 802.1Q tags)"),
+        };
+        match frame.vlan_tags() {
+            Ok(VlanTags::Untagged) | Err(_) => (),
+            Ok(tags) => write!(f, " {} inner type={}", tags, ethertype)?,
+        }
+
+        match ethertype {
             #[cfg(feature = "proto-ipv4")]
-            EtherType::Arp => {
+            EtherType::Arp | EtherType::Rarp => {
                 indent.increase(f)?;
-                super::ArpPacket::<&[u8]>::pretty_print(&frame.payload(), f, indent)
+                super::ArpPacket::<&[u8]>::pretty_print(&payload, f, indent)
             }
             #[cfg(feature = "proto-ipv4")]
             EtherType::Ipv4 => {
                 indent.increase(f)?;
-                super::Ipv4Packet::<&[u8]>::pretty_print(&frame.payload(), f, indent)
+                super::Ipv4Packet::<&[u8]>::pretty_print(&payload, f, indent)
             }
             #[cfg(feature = "proto-ipv6")]
             EtherType::Ipv6 => {
                 indent.increase(f)?;
-                super::Ipv6Packet::<&[u8]>::pretty_print(&frame.payload(), f, indent)
+                super::Ipv6Packet::<&[u8]>::pretty_print(&payload, f, indent)
             }
             _ => Ok(()),
         }
@@ -302,6 +444,81 @@ mod test {
         assert!(Address::BROADCAST.is_multicast());
         assert!(Address::BROADCAST.is_local());
     }
+
+    static QINQ_FRAME_BYTES: [u8; 23] = [
+        0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x88, 0xa8, 0x00,
+        0x64, 0x81, 0x00, 0x00, 0x2a, 0x08, 0x06, 0xaa,
+    ];
+
+    #[test]
+    fn test_vlan_deconstruct() {
+        let frame = Frame::new_unchecked(&QINQ_FRAME_BYTES[..]);
+        assert_eq!(frame.ethertype(), EtherType::QinQ);
+        let tags = frame.vlan_tags().unwrap();
+        assert_eq!(
+            tags,
+            VlanTags::Double {
+                outer: 100,
+                inner: 42
+            }
+        );
+        assert_eq!((tags.outer_id(), tags.inner_id()), (Some(100), Some(42)));
+        assert_eq!(frame.tagged_ethertype(), Ok(EtherType::Arp));
+        assert_eq!(frame.tagged_payload(), Ok(&[0xaa][..]));
+
+        let mut bytes = QINQ_FRAME_BYTES;
+        // Priority bits are not part of the VLAN ID.
+        bytes[18] = 0xe0;
+        let frame = Frame::new_unchecked(&bytes[..]);
+        assert_eq!(frame.vlan_tags().unwrap().inner_id(), Some(42));
+
+        // A single C-tag.
+        let bytes = [&QINQ_FRAME_BYTES[..12], &QINQ_FRAME_BYTES[16..]].concat();
+        let frame = Frame::new_unchecked(&bytes[..]);
+        assert_eq!(frame.vlan_tags(), Ok(VlanTags::Single(42)));
+        assert_eq!(frame.tagged_ethertype(), Ok(EtherType::Arp));
+        assert_eq!(frame.tagged_payload(), Ok(&[0xaa][..]));
+
+        // Untagged.
+        let frame = Frame::new_unchecked(&QINQ_FRAME_BYTES[6..]);
+        assert_eq!(frame.vlan_tags(), Ok(VlanTags::Untagged));
+        assert_eq!(frame.tagged_payload(), Ok(frame.payload()));
+    }
+
+    #[test]
+    fn test_vlan_truncated() {
+        let frame = Frame::new_unchecked(&QINQ_FRAME_BYTES[..20]);
+        assert_eq!(frame.vlan_tags(), Err(Error));
+        assert_eq!(frame.tagged_ethertype(), Err(Error));
+        assert_eq!(frame.tagged_payload(), Err(Error));
+        let frame = Frame::new_unchecked(&QINQ_FRAME_BYTES[..17]);
+        assert_eq!(frame.vlan_tags(), Err(Error));
+    }
+
+    #[test]
+    fn test_vlan_service_tag_only() {
+        let bytes = [&QINQ_FRAME_BYTES[..16], &QINQ_FRAME_BYTES[20..]].concat();
+        let frame = Frame::new_unchecked(&bytes[..]);
+        assert_eq!(frame.ethertype(), EtherType::QinQ);
+        assert_eq!(frame.vlan_tags(), Err(Error));
+        assert_eq!(frame.tagged_ethertype(), Err(Error));
+        assert_eq!(frame.tagged_payload(), Err(Error));
+    }
+
+    #[test]
+    fn test_vlan_construct() {
+        let mut bytes = vec![0xa5; 23];
+        let mut frame = Frame::new_unchecked(&mut bytes);
+        frame.set_dst_addr(Address([0x01, 0x02, 0x03, 0x04, 0x05, 0x06]));
+        frame.set_src_addr(Address([0x11, 0x12, 0x13, 0x14, 0x15, 0x16]));
+        frame.set_vlan_tags(VlanTags::Double {
+            outer: 100,
+            inner: 42,
+        });
+        let payload = &mut frame.payload_mut()[6..];
+        payload.copy_from_slice(&[0x08, 0x06, 0xaa]);
+        assert_eq!(&frame.into_inner()[..], &QINQ_FRAME_BYTES[..]);
+    }
 }
 
 #[cfg(test)]
@@ -4,7 +4,7 @@ use core::{cmp, fmt};
 use super::{Error, Result};
 use crate::phy::ChecksumCapabilities;
 use crate::wire::ip::checksum;
-use crate::wire::{Ipv4Packet, Ipv4Repr};
+use crate::wire::{Ipv4Address, Ipv4Packet, Ipv4Repr};
 
 enum_with_unknown! {
     /// Internet protocol control message type.
@@ -128,6 +128,18 @@ enum_with_unknown! {
     }
 }
 
+impl fmt::Display for Redirect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Redirect::Net => write!(f, "redirect for network"),
+            Redirect::Host => write!(f, "redirect for host"),
+            Redirect::NetToS => write!(f, "redirect for ToS and network"),
+            Redirect::HostToS => write!(f, "redirect for ToS and host"),
+            Redirect::Unknown(id) => write!(f, "{}", id),
+        }
+    }
+}
+
 enum_with_unknown! {
     /// Internet protocol control message subtype for type "Time Exceeded".
     pub enum TimeExceeded(u8) {
@@ -138,6 +150,16 @@ enum_with_unknown! {
     }
 }
 
+impl fmt::Display for TimeExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TimeExceeded::TtlExpired => write!(f, "time-to-live exceeded in transit"),
+            TimeExceeded::FragExpired => write!(f, "fragment reassembly time exceeded"),
+            TimeExceeded::Unknown(id) => write!(f, "{}", id),
+        }
+    }
+}
+
 enum_with_unknown! {
     /// Internet protocol control message subtype for type "Parameter Problem".
     pub enum ParamProblem(u8) {
@@ -169,6 +191,8 @@ mod field {
     pub const ECHO_IDENT: Field = 4..6;
     pub const ECHO_SEQNO: Field = 6..8;
 
+    pub const REDIRECT_GATEWAY: Field = 4..8;
+
     pub const HEADER_END: usize = 8;
 }
 
@@ -249,6 +273,16 @@ impl<T: AsRef<[u8]>> Packet<T> {
         NetworkEndian::read_u16(&data[field::ECHO_SEQNO])
     }
 
+    /// Return the gateway address field (for redirect packets).
+    ///
+    /// # Panics
+    /// This function may panic if this packet is not a redirect packet.
+    #[inline]
+    pub fn redirect_gateway(&self) -> Ipv4Address {
+        let data = self.buffer.as_ref();
+        Ipv4Address::from_bytes(&data[field::REDIRECT_GATEWAY])
+    }
+
     /// Return the header length.
     /// The result depends on the value of the message type field.
     pub fn header_len(&self) -> usize {
@@ -256,6 +290,7 @@ impl<T: AsRef<[u8]>> Packet<T> {
             Message::EchoRequest => field::ECHO_SEQNO.end,
             Message::EchoReply => field::ECHO_SEQNO.end,
             Message::DstUnreachable => field::UNUSED.end,
+            Message::Redirect => field::REDIRECT_GATEWAY.end,
             _ => field::UNUSED.end, // make a conservative assumption
         }
     }
@@ -325,6 +360,16 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
         NetworkEndian::write_u16(&mut data[field::ECHO_SEQNO], value)
     }
 
+    /// Set the gateway address field (for redirect packets).
+    ///
+    /// # Panics
+    /// This function may panic if this packet is not a redirect packet.
+    #[inline]
+    pub fn set_redirect_gateway(&mut self, value: Ipv4Address) {
+        let data = self.buffer.as_mut();
+        data[field::REDIRECT_GATEWAY].copy_from_slice(value.as_bytes())
+    }
+
     /// Compute and fill in the header checksum.
     pub fn fill_checksum(&mut self) {
         self.set_checksum(0);
@@ -372,6 +417,17 @@ pub enum Repr<'a> {
         header: Ipv4Repr,
         data: &'a [u8],
     },
+    Redirect {
+        reason: Redirect,
+        gateway: Ipv4Address,
+        header: Ipv4Repr,
+        data: &'a [u8],
+    },
+    TimeExceeded {
+        reason: TimeExceeded,
+        header: Ipv4Repr,
+        data: &'a [u8],
+    },
 }
 
 impl<'a> Repr<'a> {
@@ -424,19 +480,87 @@ impl<'a> Repr<'a> {
                     data: payload,
                 })
             }
+
+            (Message::Redirect, code) => {
+                let ip_packet = Ipv4Packet::new_checked(packet.data())?;
+
+                let payload = &packet.data()[ip_packet.header_len() as usize..];
+                // RFC 792 requires exactly eight bytes to be returned, as for
+                // destination unreachable messages.
+                if payload.len() < 8 {
+                    return Err(Error);
+                }
+
+                Ok(Repr::Redirect {
+                    reason: Redirect::from(code),
+                    gateway: packet.redirect_gateway(),
+                    header: Ipv4Repr {
+                        src_addr: ip_packet.src_addr(),
+                        dst_addr: ip_packet.dst_addr(),
+                        next_header: ip_packet.next_header(),
+                        payload_len: payload.len(),
+                        hop_limit: ip_packet.hop_limit(),
+                    },
+                    data: payload,
+                })
+            }
+
+            (Message::TimeExceeded, code) => {
+                let ip_packet = Ipv4Packet::new_checked(packet.data())?;
+
+                let payload = &packet.data()[ip_packet.header_len() as usize..];
+                // Same layout and requirements as destination unreachable messages.
+                if payload.len() < 8 {
+                    return Err(Error);
+                }
+
+                Ok(Repr::TimeExceeded {
+                    reason: TimeExceeded::from(code),
+                    header: Ipv4Repr {
+                        src_addr: ip_packet.src_addr(),
+                        dst_addr: ip_packet.dst_addr(),
+                        next_header: ip_packet.next_header(),
+                        payload_len: payload.len(),
+                        hop_limit: ip_packet.hop_limit(),
+                    },
+                    data: payload,
+                })
+            }
             _ => Err(Error),
         }
     }
 
+    /// Return the Echo Reply answering this message, if it is an Echo Request.
+    ///
+    /// The reply carries the identifier, sequence number and data of the request.
+    pub fn echo_reply(&self) -> Option<Repr<'a>> {
+        match *self {
+            Repr::EchoRequest {
+                ident,
+                seq_no,
+                data,
+            } => Some(Repr::EchoReply {
+                ident,
+                seq_no,
+                data,
+            }),
+            _ => None,
+        }
+    }
+
     /// Return the length of a packet that will be emitted from this high-level representation.
     pub fn buffer_len(&self) -> usize {
         match self {
             &Repr::EchoRequest { data, .. } | &Repr::EchoReply { data, .. } => {
                 field::ECHO_SEQNO.end + data.len()
             }
-            &Repr::DstUnreachable { header, data, .. } => {
+            &Repr::DstUnreachable { header, data, .. }
+            | &Repr::TimeExceeded { header, data, .. } => {
                 field::UNUSED.end + header.buffer_len() + data.len()
             }
+            &Repr::Redirect { header, data, .. } => {
+                field::REDIRECT_GATEWAY.end + header.buffer_len() + data.len()
+            }
         }
     }
 
@@ -487,6 +611,36 @@ impl<'a> Repr<'a> {
                 let payload = &mut ip_packet.into_inner()[header.buffer_len()..];
                 payload.copy_from_slice(data)
             }
+
+            Repr::Redirect {
+                reason,
+                gateway,
+                header,
+                data,
+            } => {
+                packet.set_msg_type(Message::Redirect);
+                packet.set_msg_code(reason.into());
+                packet.set_redirect_gateway(gateway);
+
+                let mut ip_packet = Ipv4Packet::new_unchecked(packet.data_mut());
+                header.emit(&mut ip_packet, checksum_caps);
+                let payload = &mut ip_packet.into_inner()[header.buffer_len()..];
+                payload.copy_from_slice(data)
+            }
+
+            Repr::TimeExceeded {
+                reason,
+                header,
+                data,
+            } => {
+                packet.set_msg_type(Message::TimeExceeded);
+                packet.set_msg_code(reason.into());
+
+                let mut ip_packet = Ipv4Packet::new_unchecked(packet.data_mut());
+                header.emit(&mut ip_packet, checksum_caps);
+                let payload = &mut ip_packet.into_inner()[header.buffer_len()..];
+                payload.copy_from_slice(data)
+            }
         }
 
         if checksum_caps.icmpv4.tx() {
@@ -510,6 +664,12 @@ impl<'a, T: AsRef<[u8]> + ?Sized> fmt::Display for Packet<&'a T> {
                     Message::DstUnreachable => {
                         write!(f, " code={:?}", DstUnreachable::from(self.msg_code()))
                     }
+                    Message::Redirect => {
+                        write!(f, " code={:?}", Redirect::from(self.msg_code()))
+                    }
+                    Message::TimeExceeded => {
+                        write!(f, " code={:?}", TimeExceeded::from(self.msg_code()))
+                    }
                     _ => write!(f, " code={}", self.msg_code()),
                 }
             }
@@ -545,6 +705,14 @@ impl<'a> fmt::Display for Repr<'a> {
             Repr::DstUnreachable { reason, .. } => {
                 write!(f, "ICMPv4 destination unreachable ({})", reason)
             }
+            Repr::Redirect {
+                reason, gateway, ..
+            } => {
+                write!(f, "ICMPv4 redirect ({}) gateway={}", reason, gateway)
+            }
+            Repr::TimeExceeded { reason, .. } => {
+                write!(f, "ICMPv4 time exceeded ({})", reason)
+            }
         }
     }
 }
@@ -564,7 +732,7 @@ impl<T: AsRef<[u8]>> PrettyPrint for Packet<T> {
         write!(f, "{}{}", indent, packet)?;
 
         match packet.msg_type() {
-            Message::DstUnreachable => {
+            Message::DstUnreachable | Message::Redirect | Message::TimeExceeded => {
                 indent.increase(f)?;
                 super::Ipv4Packet::<&[u8]>::pretty_print(&packet.data(), f, indent)
             }
@@ -632,6 +800,95 @@ mod test {
         assert_eq!(&packet.into_inner()[..], &ECHO_PACKET_BYTES[..]);
     }
 
+    #[test]
+    fn test_echo_reply() {
+        assert_eq!(
+            echo_packet_repr().echo_reply(),
+            Some(Repr::EchoReply {
+                ident: 0x1234,
+                seq_no: 0xabcd,
+                data: &ECHO_DATA_BYTES,
+            })
+        );
+        let reply = echo_packet_repr().echo_reply().unwrap();
+        assert_eq!(reply.echo_reply(), None);
+    }
+
+    static REDIRECT_PACKET_BYTES: [u8; 36] = [
+        0x05, 0x01, 0x33, 0x49, 0xc0, 0xa8, 0x01, 0xfe, 0x45, 0x00, 0x00, 0x1c, 0x00, 0x00, 0x40,
+        0x00, 0x40, 0x11, 0x6f, 0x27, 0xc0, 0xa8, 0x01, 0x01, 0x0a, 0x00, 0x00, 0x01, 0x04, 0xd2,
+        0x00, 0x35, 0x00, 0x08, 0x00, 0x00,
+    ];
+
+    static REDIRECT_DATA_BYTES: [u8; 8] = [0x04, 0xd2, 0x00, 0x35, 0x00, 0x08, 0x00, 0x00];
+
+    fn redirect_packet_repr() -> Repr<'static> {
+        Repr::Redirect {
+            reason: Redirect::Host,
+            gateway: Ipv4Address([192, 168, 1, 254]),
+            header: Ipv4Repr {
+                src_addr: Ipv4Address([192, 168, 1, 1]),
+                dst_addr: Ipv4Address([10, 0, 0, 1]),
+                next_header: crate::wire::IpProtocol::Udp,
+                payload_len: 8,
+                hop_limit: 64,
+            },
+            data: &REDIRECT_DATA_BYTES,
+        }
+    }
+
+    #[test]
+    fn test_redirect_deconstruct() {
+        let packet = Packet::new_unchecked(&REDIRECT_PACKET_BYTES[..]);
+        assert_eq!(packet.msg_type(), Message::Redirect);
+        assert_eq!(packet.msg_code(), 1);
+        assert_eq!(packet.redirect_gateway(), Ipv4Address([192, 168, 1, 254]));
+        assert_eq!(packet.data().len(), 28);
+        assert!(packet.verify_checksum());
+    }
+
+    #[test]
+    fn test_redirect_parse() {
+        let packet = Packet::new_unchecked(&REDIRECT_PACKET_BYTES[..]);
+        let repr = Repr::parse(&packet, &ChecksumCapabilities::default()).unwrap();
+        assert_eq!(repr, redirect_packet_repr());
+    }
+
+    #[test]
+    fn test_redirect_emit() {
+        let repr = redirect_packet_repr();
+        let mut bytes = vec![0xa5; repr.buffer_len()];
+        let mut packet = Packet::new_unchecked(&mut bytes);
+        repr.emit(&mut packet, &ChecksumCapabilities::default());
+        assert_eq!(&packet.into_inner()[..], &REDIRECT_PACKET_BYTES[..]);
+    }
+
+    #[test]
+    fn test_time_exceeded_parse_emit() {
+        let repr = Repr::TimeExceeded {
+            reason: TimeExceeded::TtlExpired,
+            header: Ipv4Repr {
+                src_addr: Ipv4Address([192, 168, 1, 1]),
+                dst_addr: Ipv4Address([10, 0, 0, 1]),
+                next_header: crate::wire::IpProtocol::Udp,
+                payload_len: 8,
+                hop_limit: 1,
+            },
+            data: &REDIRECT_DATA_BYTES,
+        };
+        let mut bytes = vec![0xa5; repr.buffer_len()];
+        let mut packet = Packet::new_unchecked(&mut bytes);
+        repr.emit(&mut packet, &ChecksumCapabilities::default());
+        assert_eq!(packet.msg_type(), Message::TimeExceeded);
+        assert_eq!(packet.msg_code(), 0);
+
+        let packet = Packet::new_checked(&bytes[..]).unwrap();
+        assert_eq!(
+            Repr::parse(&packet, &ChecksumCapabilities::default()),
+            Ok(repr)
+        );
+    }
+
     #[test]
     fn test_check_len() {
         let bytes = [0x0b, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
@@ -624,6 +624,24 @@ impl<'a> Repr<'a> {
         }
     }
 
+    /// Return the Echo Reply answering this message, if it is an Echo Request.
+    ///
+    /// The reply carries the identifier, sequence number and data of the request.
+    pub fn echo_reply(&self) -> Option<Repr<'a>> {
+        match *self {
+            Repr::EchoRequest {
+                ident,
+                seq_no,
+                data,
+            } => Some(Repr::EchoReply {
+                ident,
+                seq_no,
+                data,
+            }),
+            _ => None,
+        }
+    }
+
     /// Return the length of a packet that will be emitted from this high-level representation.
     pub fn buffer_len(&self) -> usize {
         match self {
@@ -460,6 +460,16 @@ impl ListenEndpoint {
     pub fn is_specified(&self) -> bool {
         self.addr.is_some() && self.port != 0
     }
+
+    /// Query whether a packet sent to `endpoint` could be accepted by both endpoints.
+    #[cfg(any(feature = "socket-tcp", feature = "socket-udp"))]
+    pub(crate) fn overlaps(&self, endpoint: &ListenEndpoint) -> bool {
+        let addr_overlaps = match (self.addr, endpoint.addr) {
+            (Some(addr), Some(other_addr)) => addr == other_addr,
+            _ => true,
+        };
+        addr_overlaps && self.port == endpoint.port
+    }
 }
 
 #[cfg(all(feature = "std", feature = "proto-ipv4", feature = "proto-ipv6"))]
@@ -992,4 +1002,17 @@ pub(crate) mod test {
             .prefix_len()
         );
     }
+
+    #[test]
+    #[cfg(any(feature = "socket-tcp", feature = "socket-udp"))]
+    fn test_listen_endpoint_overlaps() {
+        let any = ListenEndpoint::from(80);
+        let addr_1 = ListenEndpoint::from((MOCK_IP_ADDR_1, 80));
+        let addr_2 = ListenEndpoint::from((MOCK_IP_ADDR_2, 80));
+        assert!(any.overlaps(&addr_1));
+        assert!(addr_1.overlaps(&any));
+        assert!(addr_1.overlaps(&addr_1));
+        assert!(!addr_1.overlaps(&addr_2));
+        assert!(!any.overlaps(&ListenEndpoint::from(81)));
+    }
 }
@@ -264,6 +264,17 @@ impl defmt::Format for Cidr {
     }
 }
 
+/// The identity of the packet an IPv4 fragment belongs to, used to reassemble it.
+#[cfg(feature = "proto-ipv4-fragmentation")]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Key {
+    id: u16,
+    src_addr: Address,
+    dst_addr: Address,
+    protocol: Protocol,
+}
+
 /// A read/write wrapper around an Internet Protocol version 4 packet buffer.
 #[derive(Debug, PartialEq, Clone)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -284,10 +295,20 @@ mod field {
     pub const CHECKSUM: Field = 10..12;
     pub const SRC_ADDR: Field = 12..16;
     pub const DST_ADDR: Field = 16..20;
+
+    pub const OPT_END: u8 = 0x00;
+    pub const OPT_NOP: u8 = 0x01;
+    pub const OPT_ROUTER_ALERT: u8 = 0x94;
 }
 
 pub const HEADER_LEN: usize = field::DST_ADDR.end;
 
+/// The Router Alert option defined in [RFC 2113], asking every router on the path
+/// to examine the packet.
+///
+/// [RFC 2113]: https://tools.ietf.org/html/rfc2113
+pub const ROUTER_ALERT_OPTION: [u8; 4] = [field::OPT_ROUTER_ALERT, 4, 0, 0];
+
 impl<T: AsRef<[u8]>> Packet<T> {
     /// Imbue a raw octet buffer with IPv4 packet structure.
     pub fn new_unchecked(buffer: T) -> Packet<T> {
@@ -306,8 +327,8 @@ impl<T: AsRef<[u8]>> Packet<T> {
 
     /// Ensure that no accessor method will panic if called.
     /// Returns `Err(Error)` if the buffer is too short.
-    /// Returns `Err(Error)` if the header length is greater
-    /// than total length.
+    /// Returns `Err(Error)` if the header length is shorter than the fixed part
+    /// of the header, or greater than total length.
     ///
     /// The result of this check is invalidated by calling [set_header_len]
     /// and [set_total_len].
@@ -321,6 +342,8 @@ impl<T: AsRef<[u8]>> Packet<T> {
             Err(Error)
         } else if len < self.header_len() as usize {
             Err(Error)
+        } else if (self.header_len() as usize) < field::DST_ADDR.end {
+            Err(Error)
         } else if self.header_len() as u16 > self.total_len() {
             Err(Error)
         } else if len < self.total_len() as usize {
@@ -431,6 +454,38 @@ impl<T: AsRef<[u8]>> Packet<T> {
         Address::from_bytes(&data[field::DST_ADDR])
     }
 
+    /// Return the options, i.e. the part of the header after the destination address.
+    #[inline]
+    pub fn options(&self) -> &[u8] {
+        let data = self.buffer.as_ref();
+        data.get(field::DST_ADDR.end..self.header_len() as usize)
+            .unwrap_or(&[])
+    }
+
+    /// Return the value of the Router Alert option, if the header carries one.
+    ///
+    /// Malformed options end the search, as does the End of Options List option.
+    pub fn router_alert(&self) -> Option<u16> {
+        let mut options = self.options();
+        while let Some(&kind) = options.first() {
+            match kind {
+                field::OPT_END => break,
+                field::OPT_NOP => options = &options[1..],
+                _ => {
+                    let len = *options.get(1)? as usize;
+                    if len < 2 || len > options.len() {
+                        return None;
+                    }
+                    if kind == field::OPT_ROUTER_ALERT && len == ROUTER_ALERT_OPTION.len() {
+                        return Some(NetworkEndian::read_u16(&options[2..4]));
+                    }
+                    options = &options[len..];
+                }
+            }
+        }
+        None
+    }
+
     /// Validate the header checksum.
     ///
     /// # Fuzzing
@@ -453,6 +508,17 @@ impl<'a, T: AsRef<[u8]> + ?Sized> Packet<&'a T> {
         let data = self.buffer.as_ref();
         &data[range]
     }
+
+    /// Return the key identifying the packet this fragment belongs to.
+    #[cfg(feature = "proto-ipv4-fragmentation")]
+    pub(crate) fn get_key(&self) -> Key {
+        Key {
+            id: self.ident(),
+            src_addr: self.src_addr(),
+            dst_addr: self.dst_addr(),
+            protocol: self.next_header(),
+        }
+    }
 }
 
 impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
@@ -605,6 +671,10 @@ pub struct Repr {
 
 impl Repr {
     /// Parse an Internet Protocol version 4 packet and return a high-level representation.
+    ///
+    /// Any options are skipped; they are available through [Packet::options].
+    ///
+    /// [Packet::options]: struct.Packet.html#method.options
     pub fn parse<T: AsRef<[u8]> + ?Sized>(
         packet: &Packet<&T>,
         checksum_caps: &ChecksumCapabilities,
@@ -651,8 +721,28 @@ impl Repr {
         packet: &mut Packet<T>,
         checksum_caps: &ChecksumCapabilities,
     ) {
+        self.emit_with_options(packet, &[], checksum_caps)
+    }
+
+    /// Emit a high-level representation into an Internet Protocol version 4 packet,
+    /// with `options` placed after the destination address.
+    ///
+    /// The packet must have room for `self.buffer_len() + options.len()` octets of header.
+    ///
+    /// # Panics
+    /// This function panics if the length of `options` is not a multiple of four octets,
+    /// or if it exceeds the 40 octets that fit into the header.
+    pub fn emit_with_options<T: AsRef<[u8]> + AsMut<[u8]>>(
+        &self,
+        packet: &mut Packet<T>,
+        options: &[u8],
+        checksum_caps: &ChecksumCapabilities,
+    ) {
+        assert!(options.len() & 0x3 == 0 && options.len() <= 40);
+
+        let header_len = field::DST_ADDR.end + options.len();
         packet.set_version(4);
-        packet.set_header_len(field::DST_ADDR.end as u8);
+        packet.set_header_len(header_len as u8);
         packet.set_dscp(0);
         packet.set_ecn(0);
         let total_len = packet.header_len() as u16 + self.payload_len as u16;
@@ -666,6 +756,7 @@ impl Repr {
         packet.set_next_header(self.next_header);
         packet.set_src_addr(self.src_addr);
         packet.set_dst_addr(self.dst_addr);
+        packet.buffer.as_mut()[field::DST_ADDR.end..header_len].copy_from_slice(options);
 
         if checksum_caps.ipv4.tx() {
             packet.fill_checksum();
@@ -885,6 +976,14 @@ mod test {
         assert_eq!(Packet::new_checked(&mut bytes), Err(Error));
     }
 
+    #[test]
+    fn test_parse_header_len_less_than_fixed_header() {
+        let mut bytes = vec![];
+        bytes.extend(&REPR_PACKET_BYTES[..]);
+        Packet::new_unchecked(&mut bytes).set_header_len(16);
+        assert_eq!(Packet::new_checked(&bytes), Err(Error));
+    }
+
     #[test]
     fn test_emit() {
         let repr = packet_repr();
@@ -895,6 +994,58 @@ mod test {
         assert_eq!(&packet.into_inner()[..], &REPR_PACKET_BYTES[..]);
     }
 
+    #[test]
+    fn test_emit_with_options() {
+        let repr = packet_repr();
+        let mut bytes = vec![0xa5; repr.buffer_len() + 4 + REPR_PAYLOAD_BYTES.len()];
+        let mut packet = Packet::new_unchecked(&mut bytes);
+        repr.emit_with_options(
+            &mut packet,
+            &ROUTER_ALERT_OPTION,
+            &ChecksumCapabilities::default(),
+        );
+        packet.payload_mut().copy_from_slice(&REPR_PAYLOAD_BYTES);
+
+        let packet = Packet::new_checked(&bytes).unwrap();
+        assert_eq!(packet.header_len(), 24);
+        assert_eq!(packet.total_len(), 28);
+        assert!(packet.verify_checksum());
+        assert_eq!(packet.options(), &[0x94, 0x04, 0x00, 0x00]);
+        assert_eq!(packet.router_alert(), Some(0));
+        assert_eq!(packet.payload(), &REPR_PAYLOAD_BYTES[..]);
+        assert_eq!(
+            Repr::parse(&packet, &ChecksumCapabilities::default()),
+            Ok(packet_repr())
+        );
+    }
+
+    #[test]
+    fn test_router_alert() {
+        fn with_options(options: &[u8]) -> Vec<u8> {
+            let repr = packet_repr();
+            let mut bytes = vec![0; repr.buffer_len() + options.len() + 4];
+            repr.emit_with_options(
+                &mut Packet::new_unchecked(&mut bytes),
+                options,
+                &ChecksumCapabilities::default(),
+            );
+            bytes
+        }
+
+        let bytes = with_options(&[]);
+        assert_eq!(Packet::new_unchecked(&bytes).router_alert(), None);
+        // Skipped over other options.
+        let bytes = with_options(&[0x01, 0x07, 0x03, 0x00, 0x94, 0x04, 0x00, 0x01]);
+        assert_eq!(Packet::new_unchecked(&bytes).router_alert(), Some(1));
+        // Not looked for past the end of the list, or in malformed options.
+        let bytes = with_options(&[0x00, 0x94, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        assert_eq!(Packet::new_unchecked(&bytes).router_alert(), None);
+        let bytes = with_options(&[0x94, 0x05, 0x00, 0x00]);
+        assert_eq!(Packet::new_unchecked(&bytes).router_alert(), None);
+        let bytes = with_options(&[0x07, 0x00, 0x94, 0x04]);
+        assert_eq!(Packet::new_unchecked(&bytes).router_alert(), None);
+    }
+
     #[test]
     fn test_unspecified() {
         assert!(Address::UNSPECIFIED.is_unspecified());
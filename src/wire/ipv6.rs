@@ -132,6 +132,13 @@ impl Address {
         self.0[0..8] == [0xfe, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]
     }
 
+    /// Query whether the IPv6 address is an [unique local address].
+    ///
+    /// [unique local address]: https://tools.ietf.org/html/rfc4193
+    pub fn is_unique_local(&self) -> bool {
+        self.0[0] & 0xfe == 0xfc
+    }
+
     /// Query whether the IPv6 address is the [loopback address].
     ///
     /// [loopback address]: https://tools.ietf.org/html/rfc4291#section-2.5.3
@@ -737,9 +744,22 @@ mod test {
         assert!(!LINK_LOCAL_ADDR.is_unspecified());
         assert!(!LINK_LOCAL_ADDR.is_multicast());
         assert!(LINK_LOCAL_ADDR.is_link_local());
+        assert!(!LINK_LOCAL_ADDR.is_unique_local());
         assert!(!LINK_LOCAL_ADDR.is_loopback());
     }
 
+    #[test]
+    fn test_basic_unique_local() {
+        let addr = Address::new(0xfd12, 0x3456, 0x789a, 0, 0, 0, 0, 1);
+        assert!(addr.is_unicast());
+        assert!(!addr.is_link_local());
+        assert!(addr.is_unique_local());
+        assert!(Address::new(0xfc00, 0, 0, 0, 0, 0, 0, 1).is_unique_local());
+        assert!(!Address::new(0xfe00, 0, 0, 0, 0, 0, 0, 1).is_unique_local());
+        assert!(!Address::LOOPBACK.is_unique_local());
+        assert!(!Address::LINK_LOCAL_ALL_NODES.is_unique_local());
+    }
+
     #[test]
     fn test_basic_loopback() {
         assert!(!Address::LOOPBACK.is_unspecified());
@@ -1,4 +1,6 @@
 use super::{Error, Result};
+#[cfg(feature = "proto-ipv6-fragmentation")]
+use super::{Ipv6Address, Ipv6Repr};
 use core::fmt;
 
 use byteorder::{ByteOrder, NetworkEndian};
@@ -157,6 +159,16 @@ impl<'a, T: AsRef<[u8]> + ?Sized> fmt::Display for Header<&'a T> {
     }
 }
 
+/// The identity of the packet an IPv6 fragment belongs to, used to reassemble it.
+#[cfg(feature = "proto-ipv6-fragmentation")]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Key {
+    id: u32,
+    src_addr: Ipv6Address,
+    dst_addr: Ipv6Address,
+}
+
 /// A high-level representation of an IPv6 Fragment header.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -200,6 +212,16 @@ impl Repr {
         header.set_more_frags(self.more_frags);
         header.set_ident(self.ident);
     }
+
+    /// Return the key identifying the packet this fragment of `ipv6_repr` belongs to.
+    #[cfg(feature = "proto-ipv6-fragmentation")]
+    pub(crate) fn get_key(&self, ipv6_repr: &Ipv6Repr) -> Key {
+        Key {
+            id: self.ident,
+            src_addr: ipv6_repr.src_addr,
+            dst_addr: ipv6_repr.dst_addr,
+        }
+    }
 }
 
 impl<'a> fmt::Display for Repr {
@@ -134,7 +134,7 @@ pub use self::pretty_print::PrettyPrinter;
 #[cfg(feature = "medium-ethernet")]
 pub use self::ethernet::{
     Address as EthernetAddress, EtherType as EthernetProtocol, Frame as EthernetFrame,
-    Repr as EthernetRepr, HEADER_LEN as ETHERNET_HEADER_LEN,
+    Repr as EthernetRepr, VlanTags as EthernetVlanTags, HEADER_LEN as ETHERNET_HEADER_LEN,
 };
 
 #[cfg(all(feature = "proto-ipv4", feature = "medium-ethernet"))]
@@ -170,8 +170,12 @@ pub use self::ip::{
 pub use self::ipv4::{
     Address as Ipv4Address, Cidr as Ipv4Cidr, Packet as Ipv4Packet, Repr as Ipv4Repr,
     HEADER_LEN as IPV4_HEADER_LEN, MIN_MTU as IPV4_MIN_MTU,
+    ROUTER_ALERT_OPTION as IPV4_ROUTER_ALERT_OPTION,
 };
 
+#[cfg(feature = "proto-ipv4-fragmentation")]
+pub use self::ipv4::Key as Ipv4FragKey;
+
 #[cfg(feature = "proto-ipv6")]
 pub use self::ipv6::{
     Address as Ipv6Address, Cidr as Ipv6Cidr, Packet as Ipv6Packet, Repr as Ipv6Repr,
@@ -190,6 +194,9 @@ pub use self::ipv6hopbyhop::{Header as Ipv6HopByHopHeader, Repr as Ipv6HopByHopR
 #[cfg(feature = "proto-ipv6")]
 pub use self::ipv6fragment::{Header as Ipv6FragmentHeader, Repr as Ipv6FragmentRepr};
 
+#[cfg(feature = "proto-ipv6-fragmentation")]
+pub use self::ipv6fragment::Key as Ipv6FragKey;
+
 #[cfg(feature = "proto-ipv6")]
 pub use self::ipv6routing::{Header as Ipv6RoutingHeader, Repr as Ipv6RoutingRepr};
 
@@ -236,15 +243,16 @@ pub use self::mld::{AddressRecord as MldAddressRecord, Repr as MldRepr};
 pub use self::udp::{Packet as UdpPacket, Repr as UdpRepr, HEADER_LEN as UDP_HEADER_LEN};
 
 pub use self::tcp::{
-    Control as TcpControl, Packet as TcpPacket, Repr as TcpRepr, SeqNumber as TcpSeqNumber,
-    TcpOption, HEADER_LEN as TCP_HEADER_LEN,
+    AuthOption as TcpAuthOption, Control as TcpControl, Packet as TcpPacket, Repr as TcpRepr,
+    SeqNumber as TcpSeqNumber, TcpOption, AUTH_MAC_LEN as TCP_AUTH_MAC_LEN,
+    HEADER_LEN as TCP_HEADER_LEN,
 };
 
 #[cfg(feature = "proto-dhcpv4")]
 pub use self::dhcpv4::{
     MessageType as DhcpMessageType, Packet as DhcpPacket, Repr as DhcpRepr,
     CLIENT_PORT as DHCP_CLIENT_PORT, MAX_DNS_SERVER_COUNT as DHCP_MAX_DNS_SERVER_COUNT,
-    SERVER_PORT as DHCP_SERVER_PORT,
+    MAX_NTP_SERVER_COUNT as DHCP_MAX_NTP_SERVER_COUNT, SERVER_PORT as DHCP_SERVER_PORT,
 };
 
 /// Parsing a packet failed.
@@ -14,6 +14,26 @@ use crate::wire::{IpAddress, IpProtocol};
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct SeqNumber(pub i32);
 
+impl SeqNumber {
+    /// Return the later of two sequence numbers.
+    pub fn max(self, rhs: Self) -> Self {
+        if self > rhs {
+            self
+        } else {
+            rhs
+        }
+    }
+
+    /// Return the earlier of two sequence numbers.
+    pub fn min(self, rhs: Self) -> Self {
+        if self < rhs {
+            self
+        } else {
+            rhs
+        }
+    }
+}
+
 impl fmt::Display for SeqNumber {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", self.0 as u32)
@@ -107,6 +127,7 @@ mod field {
     pub const OPT_WS: u8 = 0x03;
     pub const OPT_SACKPERM: u8 = 0x04;
     pub const OPT_SACKRNG: u8 = 0x05;
+    pub const OPT_AUTH: u8 = 0x1d;
 }
 
 pub const HEADER_LEN: usize = field::URGENT.end;
@@ -600,7 +621,15 @@ pub enum TcpOption<'a> {
     WindowScale(u8),
     SackPermitted,
     SackRange([Option<(u32, u32)>; 3]),
-    Unknown { kind: u8, data: &'a [u8] },
+    Authentication {
+        key_id: u8,
+        rnext_key_id: u8,
+        mac: &'a [u8],
+    },
+    Unknown {
+        kind: u8,
+        data: &'a [u8],
+    },
 }
 
 impl<'a> TcpOption<'a> {
@@ -663,6 +692,14 @@ impl<'a> TcpOption<'a> {
                         });
                         option = TcpOption::SackRange(sack_ranges);
                     }
+                    (field::OPT_AUTH, n) if n >= 4 => {
+                        option = TcpOption::Authentication {
+                            key_id: data[0],
+                            rnext_key_id: data[1],
+                            mac: &data[2..],
+                        }
+                    }
+                    (field::OPT_AUTH, _) => return Err(Error),
                     (_, _) => option = TcpOption::Unknown { kind, data },
                 }
             }
@@ -678,6 +715,7 @@ impl<'a> TcpOption<'a> {
             TcpOption::WindowScale(_) => 3,
             TcpOption::SackPermitted => 2,
             TcpOption::SackRange(s) => s.iter().filter(|s| s.is_some()).count() * 8 + 2,
+            TcpOption::Authentication { mac, .. } => 4 + mac.len(),
             TcpOption::Unknown { data, .. } => 2 + data.len(),
         }
     }
@@ -725,6 +763,16 @@ impl<'a> TcpOption<'a> {
                                 NetworkEndian::write_u32(&mut buffer[pos + 4..], second);
                             });
                     }
+                    &TcpOption::Authentication {
+                        key_id,
+                        rnext_key_id,
+                        mac,
+                    } => {
+                        buffer[0] = field::OPT_AUTH;
+                        buffer[2] = key_id;
+                        buffer[3] = rnext_key_id;
+                        buffer[4..length].copy_from_slice(mac)
+                    }
                     &TcpOption::Unknown {
                         kind,
                         data: provided,
@@ -769,6 +817,35 @@ impl Control {
     }
 }
 
+/// The length of the message authentication code carried by [AuthOption].
+pub const AUTH_MAC_LEN: usize = 12;
+
+/// A high-level representation of the TCP Authentication Option, as described in [RFC 5925].
+///
+/// Only MACs of [AUTH_MAC_LEN] octets are represented, which is the length used by both
+/// mandatory-to-implement algorithms of [RFC 5926]. Options with a MAC of a different length
+/// are ignored when parsing a [Repr].
+///
+/// [RFC 5925]: https://tools.ietf.org/html/rfc5925
+/// [RFC 5926]: https://tools.ietf.org/html/rfc5926
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct AuthOption {
+    /// The identifier of the key the segment is authenticated with.
+    pub key_id: u8,
+    /// The identifier of the key the sender would like to receive segments with.
+    pub rnext_key_id: u8,
+    /// The message authentication code of the segment.
+    pub mac: [u8; AUTH_MAC_LEN],
+}
+
+impl AuthOption {
+    /// Return the length of the option, in octets.
+    pub const fn buffer_len(&self) -> usize {
+        4 + AUTH_MAC_LEN
+    }
+}
+
 /// A high-level representation of a Transmission Control Protocol packet.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -783,6 +860,7 @@ pub struct Repr<'a> {
     pub max_seg_size: Option<u16>,
     pub sack_permitted: bool,
     pub sack_ranges: [Option<(u32, u32)>; 3],
+    pub auth: Option<AuthOption>,
     pub payload: &'a [u8],
 }
 
@@ -831,6 +909,7 @@ impl<'a> Repr<'a> {
         let mut options = packet.options();
         let mut sack_permitted = false;
         let mut sack_ranges = [None, None, None];
+        let mut auth = None;
         while !options.is_empty() {
             let (next_options, option) = TcpOption::parse(options)?;
             match option {
@@ -857,6 +936,19 @@ impl<'a> Repr<'a> {
                 }
                 TcpOption::SackPermitted => sack_permitted = true,
                 TcpOption::SackRange(slice) => sack_ranges = slice,
+                TcpOption::Authentication {
+                    key_id,
+                    rnext_key_id,
+                    mac,
+                } if mac.len() == AUTH_MAC_LEN => {
+                    let mut option = AuthOption {
+                        key_id,
+                        rnext_key_id,
+                        mac: [0; AUTH_MAC_LEN],
+                    };
+                    option.mac.copy_from_slice(mac);
+                    auth = Some(option)
+                }
                 _ => (),
             }
             options = next_options;
@@ -873,6 +965,7 @@ impl<'a> Repr<'a> {
             max_seg_size: max_seg_size,
             sack_permitted: sack_permitted,
             sack_ranges: sack_ranges,
+            auth,
             payload: packet.payload(),
         })
     }
@@ -900,6 +993,9 @@ impl<'a> Repr<'a> {
         if sack_range_len > 0 {
             length += sack_range_len + 2;
         }
+        if let Some(auth) = self.auth {
+            length += auth.buffer_len();
+        }
         if length % 4 != 0 {
             length += 4 - length % 4;
         }
@@ -920,6 +1016,25 @@ impl<'a> Repr<'a> {
         checksum_caps: &ChecksumCapabilities,
     ) where
         T: AsRef<[u8]> + AsMut<[u8]> + ?Sized,
+    {
+        self.emit_header(packet);
+        packet.payload_mut()[..self.payload.len()].copy_from_slice(self.payload);
+
+        if checksum_caps.tcp.tx() {
+            packet.fill_checksum(src_addr, dst_addr)
+        } else {
+            // make sure we get a consistently zeroed checksum,
+            // since implementations might rely on it
+            packet.set_checksum(0);
+        }
+    }
+
+    /// Emit everything but the payload and the checksum, which is left untouched.
+    ///
+    /// The buffer of `packet` only needs to be large enough for the header.
+    pub(crate) fn emit_header<T>(&self, packet: &mut Packet<&mut T>)
+    where
+        T: AsRef<[u8]> + AsMut<[u8]> + ?Sized,
     {
         packet.set_src_port(self.src_port);
         packet.set_dst_port(self.dst_port);
@@ -953,21 +1068,21 @@ impl<'a> Repr<'a> {
                 let tmp = options;
                 options = TcpOption::SackRange(self.sack_ranges).emit(tmp);
             }
+            if let Some(auth) = &self.auth {
+                let tmp = options;
+                options = TcpOption::Authentication {
+                    key_id: auth.key_id,
+                    rnext_key_id: auth.rnext_key_id,
+                    mac: &auth.mac,
+                }
+                .emit(tmp);
+            }
 
             if !options.is_empty() {
                 TcpOption::EndOfList.emit(options);
             }
         }
         packet.set_urgent_at(0);
-        packet.payload_mut()[..self.payload.len()].copy_from_slice(self.payload);
-
-        if checksum_caps.tcp.tx() {
-            packet.fill_checksum(src_addr, dst_addr)
-        } else {
-            // make sure we get a consistently zeroed checksum,
-            // since implementations might rely on it
-            packet.set_checksum(0);
-        }
     }
 
     /// Return the length of the segment, in terms of sequence space.
@@ -1033,6 +1148,7 @@ impl<'a, T: AsRef<[u8]> + ?Sized> fmt::Display for Packet<&'a T> {
                 TcpOption::WindowScale(value) => write!(f, " ws={}", value)?,
                 TcpOption::SackPermitted => write!(f, " sACK")?,
                 TcpOption::SackRange(slice) => write!(f, " sACKr{:?}", slice)?, // debug print conveniently includes the []s
+                TcpOption::Authentication { key_id, .. } => write!(f, " ao={}", key_id)?,
                 TcpOption::Unknown { kind, .. } => write!(f, " opt({})", kind)?,
             }
             options = next_options;
@@ -1185,6 +1301,7 @@ mod test {
             max_seg_size: None,
             sack_permitted: false,
             sack_ranges: [None, None, None],
+            auth: None,
             payload: &PAYLOAD_BYTES,
         }
     }
@@ -1264,6 +1381,17 @@ mod test {
                 0x00, 0x26, 0x25, 0xa0, 0x34, 0x3e, 0xfc, 0xea, 0x34, 0x40, 0xae, 0xf0
             ]
         );
+        assert_option_parses!(
+            TcpOption::Authentication {
+                key_id: 3,
+                rnext_key_id: 4,
+                mac: &[0xaa; 12][..]
+            },
+            &[
+                0x1d, 0x10, 0x03, 0x04, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa,
+                0xaa, 0xaa
+            ]
+        );
         assert_option_parses!(
             TcpOption::Unknown {
                 kind: 12,
@@ -1281,5 +1409,6 @@ mod test {
         assert_eq!(TcpOption::parse(&[0xc, 0x01]), Err(Error));
         assert_eq!(TcpOption::parse(&[0x2, 0x02]), Err(Error));
         assert_eq!(TcpOption::parse(&[0x3, 0x02]), Err(Error));
+        assert_eq!(TcpOption::parse(&[0x1d, 0x03, 0x01]), Err(Error));
     }
 }